@@ -0,0 +1,41 @@
+#![no_main]
+#[macro_use] extern crate libfuzzer_sys;
+extern crate xc2bit;
+
+use xc2bit::*;
+
+// The first byte picks a device so that the fuzzer does not have to discover a valid device name and fuse count. The
+// rest of the input is used as fuses, repeated as needed.
+const DEVICES: [&str; 8] = [
+    "XC2C32-4-VQ44",
+    "XC2C32A-6-VQ44",
+    "XC2C64-7-VQ44",
+    "XC2C64A-5-VQ44",
+    "XC2C128-6-VQ100",
+    "XC2C256-7-TQ144",
+    "XC2C384-10-TQ144",
+    "XC2C512-10-PQ208",
+];
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 2 {
+        return;
+    }
+
+    let device = DEVICES[data[0] as usize % DEVICES.len()];
+    let fuse_count = match process_jed(&[], device) {
+        Err(XC2BitError::WrongFuseCount{expected, ..}) => expected,
+        _ => unreachable!(),
+    };
+    let fuse_bytes = &data[1..];
+    let fuses = (0..fuse_count)
+        .map(|i| fuse_bytes[(i / 8) % fuse_bytes.len()] & (1 << (i % 8)) != 0)
+        .collect::<Vec<_>>();
+
+    if let Ok(bitstream) = process_jed(&fuses, device) {
+        let mut out = Vec::new();
+        let _ = bitstream.dump_human_readable(&mut out);
+        let _ = bitstream.write_jed(&mut out);
+        let _ = bitstream.to_crbit();
+    }
+});
@@ -0,0 +1,11 @@
+#![no_main]
+#[macro_use] extern crate libfuzzer_sys;
+extern crate xc2bit;
+
+use xc2bit::*;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(fuse_array) = FuseArray::from_file_contents(data) {
+        let _ = XC2Bitstream::from_crbit(&fuse_array);
+    }
+});
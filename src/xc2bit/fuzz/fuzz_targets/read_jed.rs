@@ -0,0 +1,13 @@
+#![no_main]
+#[macro_use] extern crate libfuzzer_sys;
+extern crate xc2bit;
+
+use xc2bit::*;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(jed_file) = JedFile::from_file_contents(data) {
+        let mut out = Vec::new();
+        jed_file.write_to_writer(&mut out).unwrap();
+    }
+    let _ = read_jed(data);
+});
@@ -23,7 +23,8 @@ OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
 OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 */
 
-//! Testing tool that converts a .jed to the internal .crbit format
+//! Testing tool that converts a .jed to the internal .crbit format. Designs that enable the clock divider cannot be
+//! converted, because the locations of its fuses in the crbit format are not known.
 
 use std::fs::File;
 use std::io::Read;
@@ -51,7 +52,7 @@ fn main() {
     let bitstream_result = process_jed(&bits, &device_name);
     let bitstream = bitstream_result.expect("failed to process jed");
 
-    let fuse_array = bitstream.to_crbit();
+    let fuse_array = bitstream.to_crbit().expect("failed to convert to crbit");
 
     fuse_array.write_to_writer(&mut ::std::io::stdout()).expect("failed to print crbit");
 }
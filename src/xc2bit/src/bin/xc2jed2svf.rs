@@ -0,0 +1,85 @@
+/*
+Copyright (c) 2016-2017, Robert Ou <rqou@robertou.com> and contributors
+All rights reserved.
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice,
+   this list of conditions and the following disclaimer.
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+//! Tool that converts a .jed to an SVF file for programming over JTAG
+//!
+//! The instruction register lengths of other devices in the JTAG chain can be given as comma-separated lists, for
+//! example `--before 8 --after 6,6` for one device between TDI and the CPLD and two devices between the CPLD and TDO.
+
+use std::fs::File;
+use std::io::Read;
+
+extern crate xc2bit;
+use xc2bit::*;
+
+fn usage(prog: &str) -> ! {
+    println!("Usage: {} [--before ir_len,ir_len,...] [--after ir_len,ir_len,...] file.jed", prog);
+    ::std::process::exit(1);
+}
+
+fn parse_ir_lengths(prog: &str, arg: Option<&String>) -> Vec<u32> {
+    if arg.is_none() {
+        usage(prog);
+    }
+
+    arg.unwrap().split(',').map(|x| u32::from_str_radix(x, 10).expect("invalid IR length")).collect()
+}
+
+fn main() {
+    let args = ::std::env::args().collect::<Vec<_>>();
+
+    let mut chain = SVFChain::default();
+    let mut jed_path = None;
+    let mut args_iter = args.iter().skip(1);
+    while let Some(arg) = args_iter.next() {
+        if arg == "--before" {
+            chain.before = parse_ir_lengths(&args[0], args_iter.next());
+        } else if arg == "--after" {
+            chain.after = parse_ir_lengths(&args[0], args_iter.next());
+        } else if jed_path.is_none() {
+            jed_path = Some(arg);
+        } else {
+            usage(&args[0]);
+        }
+    }
+
+    if jed_path.is_none() {
+        usage(&args[0]);
+    }
+
+    // Read the entire file
+    let mut f = File::open(jed_path.unwrap()).expect("failed to open file");
+    let mut data = Vec::new();
+    f.read_to_end(&mut data).expect("failed to read data");
+
+    let bits_result = read_jed(&data);
+    let (bits, device_name_option) = bits_result.expect("failed to read jed");
+    let device_name = device_name_option.expect("missing device name in jed");
+
+    let bitstream_result = process_jed(&bits, &device_name);
+    let bitstream = bitstream_result.expect("failed to process jed");
+
+    bitstream.write_svf(&chain, &mut ::std::io::stdout()).expect("failed to write svf");
+}
@@ -28,19 +28,38 @@ OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 use std::fs::File;
 use std::io::Read;
 
+#[cfg(feature = "serde")]
+extern crate serde_json;
+
 extern crate xc2bit;
 use xc2bit::*;
 
+#[cfg(feature = "serde")]
+fn dump_json(bitstream: &XC2Bitstream) {
+    serde_json::to_writer_pretty(::std::io::stdout(), bitstream).expect("failed to print json");
+    println!("");
+}
+
+#[cfg(not(feature = "serde"))]
+fn dump_json(_: &XC2Bitstream) {
+    println!("JSON output requires the \"serde\" feature");
+    ::std::process::exit(1);
+}
+
 fn main() {
     let args = ::std::env::args().collect::<Vec<_>>();
 
-    if args.len() != 2 {
-        println!("Usage: {} file.jed", args[0]);
+    let (json, jed_path) = if args.len() == 2 {
+        (false, &args[1])
+    } else if args.len() == 3 && args[1] == "--json" {
+        (true, &args[2])
+    } else {
+        println!("Usage: {} [--json] file.jed", args[0]);
         ::std::process::exit(1);
-    }
+    };
 
     // Read the entire file
-    let mut f = File::open(&args[1]).expect("failed to open file");
+    let mut f = File::open(jed_path).expect("failed to open file");
     let mut data = Vec::new();
     f.read_to_end(&mut data).expect("failed to read data");
 
@@ -51,5 +70,9 @@ fn main() {
     let bitstream_result = process_jed(&bits, &device_name);
     let bitstream = bitstream_result.expect("failed to process jed");
 
-    bitstream.dump_human_readable(&mut ::std::io::stdout()).expect("failed to print jed");
+    if json {
+        dump_json(&bitstream);
+    } else {
+        bitstream.dump_human_readable(&mut ::std::io::stdout()).expect("failed to print jed");
+    }
 }
@@ -0,0 +1,78 @@
+/*
+Copyright (c) 2017, Robert Ou <rqou@robertou.com> and contributors
+All rights reserved.
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice,
+   this list of conditions and the following disclaimer.
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+//! Tool that prints a power estimate for a .jed file. The current coefficients of the device are read from a JSON file
+//! with the fields of `XC2PowerParams`.
+
+use std::fs::File;
+use std::io::Read;
+
+extern crate serde_json;
+
+extern crate xc2bit;
+use xc2bit::*;
+
+fn main() {
+    let args = ::std::env::args().collect::<Vec<_>>();
+
+    if args.len() < 4 {
+        println!("Usage: {} file.jed power_params.json input_toggle_mhz [GCKn=freq_mhz,toggle_rate]...", args[0]);
+        ::std::process::exit(1);
+    }
+
+    let mut conditions = XC2PowerConditions::default();
+    conditions.input_toggle_mhz = args[3].parse().expect("invalid input toggle frequency");
+    for arg in &args[4..] {
+        let (clock, activity) = arg.split_at(arg.find('=').expect("missing = in clock activity"));
+        let clock = match clock {
+            "GCK0" => XC2ClockDomain::GCK(0),
+            "GCK1" => XC2ClockDomain::GCK(1),
+            "GCK2" => XC2ClockDomain::GCK(2),
+            _ => panic!("invalid clock name"),
+        };
+        let mut activity = activity[1..].split(',');
+        let freq_mhz = activity.next().unwrap().parse().expect("invalid clock frequency");
+        let toggle_rate = activity.next().expect("missing toggle rate").parse().expect("invalid toggle rate");
+        conditions.clocks.push(XC2ClockActivity{clock, freq_mhz, toggle_rate});
+    }
+
+    // Read the entire file
+    let mut f = File::open(&args[1]).expect("failed to open file");
+    let mut data = Vec::new();
+    f.read_to_end(&mut data).expect("failed to read data");
+
+    let (bits, device_name_option) = read_jed(&data).expect("failed to read jed");
+    let device_name = device_name_option.expect("missing device name in jed");
+
+    let bitstream = process_jed(&bits, &device_name).expect("failed to process jed");
+
+    let f = File::open(&args[2]).expect("failed to open power parameters");
+    let params: XC2PowerParams = serde_json::from_reader(f).expect("failed to parse power parameters");
+
+    println!("Power estimate for {}-{}-{}", bitstream.bits.device_type(), bitstream.speed_grade, bitstream.package);
+    println!("Coefficients from {}\n", args[2]);
+    estimate_power(&bitstream, &params, &conditions).write_report(&mut ::std::io::stdout())
+        .expect("failed to write report");
+}
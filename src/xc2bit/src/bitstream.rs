@@ -38,6 +38,7 @@ use mc::{write_small_mc_to_jed, write_large_mc_to_jed};
 use zia::{zia_get_row_width};
 
 /// Toplevel struct representing an entire Coolrunner-II bitstream
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct XC2Bitstream {
     pub speed_grade: XC2Speed,
     pub package: XC2Package,
@@ -59,36 +60,109 @@ impl XC2Bitstream {
     pub fn write_jed(&self, writer: &mut Write) -> Result<(), io::Error> {
         write!(writer, ".JED fuse map written by xc2bit\n")?;
         write!(writer, "https://github.com/azonenberg/openfpga\n\n")?;
-        write!(writer, "\x02")?;
 
-        write!(writer, "QF{}*\n", total_logical_fuse_count(self.bits.device_type()))?;
-        write!(writer, "N DEVICE {}-{}-{}*\n\n", self.bits.device_type(), self.speed_grade, self.package)?;
+        // Everything from the STX to the ETX is buffered so that the checksums can be computed
+        let mut jed_body = Vec::new();
+        write!(jed_body, "\x02")?;
 
-        self.bits.write_jed(writer)?;
+        let num_fuses = total_logical_fuse_count(self.bits.device_type());
+        write!(jed_body, "QF{}*\n", num_fuses)?;
+        write!(jed_body, "N DEVICE {}-{}-{}*\n\n", self.bits.device_type(), self.speed_grade, self.package)?;
 
-        write!(writer, "\x030000\n")?;
+        // The fuse checksum is computed over the logical fuse vector. Every fuse is written explicitly, so there is no
+        // default fuse state to fill in.
+        let fuses = {
+            let mut fuse_writer = JedFuseWriter::new(&mut jed_body, num_fuses);
+            self.bits.write_jed(&mut fuse_writer)?;
+            fuse_writer.into_fuses()
+        };
+        write!(jed_body, "C{:04X}*\n", jed_fuse_checksum(&fuses))?;
+
+        write!(jed_body, "\x03")?;
+        let file_csum = jed_file_checksum(&jed_body);
+
+        writer.write_all(&jed_body)?;
+        write!(writer, "{:04X}\n", file_csum)?;
 
         Ok(())
     }
 
-    /// Converts the bitstream into a FuseArray object so that it can be written to the native "crbit" format
-    pub fn to_crbit(&self) -> FuseArray {
+    /// Write an SVF file that programs the bitstream into a device to the given `writer` object. `chain` describes
+    /// any other devices in the JTAG chain. Fails in the same cases as `to_crbit`.
+    pub fn write_svf(&self, chain: &SVFChain, writer: &mut Write) -> Result<(), io::Error> {
+        write_svf(self.bits.device_type(), &self.to_crbit()?, chain, writer)
+    }
+
+    /// Write a structural Verilog netlist of the bitstream as a module called `module_name` to the given `writer`
+    /// object.
+    pub fn write_verilog(&self, module_name: &str, writer: &mut Write) -> Result<(), io::Error> {
+        write_verilog(self, module_name, writer)
+    }
+
+    /// Write a BLIF model of the logic in the bitstream as a model called `model_name` to the given `writer` object.
+    pub fn write_blif(&self, model_name: &str, writer: &mut Write) -> Result<(), io::Error> {
+        write_blif(self, model_name, writer)
+    }
+
+    /// Checks the bitstream for illegal or suspicious settings. See `XC2Diagnostic` for what is checked.
+    pub fn validate(&self) -> Vec<XC2Diagnostic> {
+        validate_bitstream(self)
+    }
+
+    /// Converts the bitstream into a FuseArray object so that it can be written to the native "crbit" format. Fails if
+    /// the clock divider is enabled, because the crbit format cannot store it.
+    ///
+    /// Earlier versions converted such a bitstream anyway and silently dropped the clock divider, so a design that
+    /// uses it (and the crbit, SVF, or XSVF generated from it) no longer converts. Use the JED file to program these
+    /// designs until the physical locations of the clock divider fuses are known.
+    pub fn to_crbit(&self) -> Result<FuseArray, XC2BitError> {
         let (w, h) = fuse_array_dims(self.bits.device_type());
         let mut fuse_array = FuseArray::from_dim(w, h);
 
         fuse_array.dev_name_str = Some(format!("{}-{}-{}", self.bits.device_type(), self.speed_grade, self.package));
 
-        self.bits.to_crbit(&mut fuse_array);
+        self.bits.to_crbit(&mut fuse_array)?;
 
-        fuse_array
+        Ok(fuse_array)
+    }
+
+    /// Converts a FuseArray object (for example, one read from the native "crbit" format) back into a bitstream. The
+    /// `dev_name_str` of the fuse array must contain the device, speed grade, and package.
+    pub fn from_crbit(fuse_array: &FuseArray) -> Result<XC2Bitstream, XC2BitError> {
+        if fuse_array.dev_name_str.is_none() {
+            return Err(XC2BitError::MissingDeviceName);
+        }
+
+        let dev_name_str = fuse_array.dev_name_str.as_ref().unwrap();
+        let device_combination = parse_part_name_string(dev_name_str);
+        if device_combination.is_none() {
+            return Err(XC2BitError::BadDeviceName(dev_name_str.to_owned()));
+        }
+
+        let (part, spd, pkg) = device_combination.unwrap();
+
+        if fuse_array.dim() != fuse_array_dims(part) {
+            return Err(XC2BitError::WrongFuseArrayDims {
+                expected: fuse_array_dims(part),
+                found: fuse_array.dim(),
+            });
+        }
+
+        let bits = XC2BitstreamBits::from_crbit(part, fuse_array)?;
+
+        Ok(XC2Bitstream {
+            speed_grade: spd,
+            package: pkg,
+            bits: bits,
+        })
     }
 
     /// Construct a new blank bitstream of the given part
     pub fn blank_bitstream(device: XC2Device, speed_grade: XC2Speed, package: XC2Package)
-        -> Result<XC2Bitstream, &'static str> {
+        -> Result<XC2Bitstream, XC2BitError> {
 
         if !is_valid_part_combination(device, speed_grade, package) {
-            return Err("requested device/speed/package is not a legal combination")
+            return Err(XC2BitError::BadPartCombination(device, speed_grade, package));
         }
 
         match device {
@@ -220,6 +294,7 @@ impl XC2Bitstream {
 
 /// Represents the configuration of the global nets. Coolrunner-II parts have various global control signals that have
 /// dedicated low-skew paths.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct XC2GlobalNets {
     /// Controls whether the three global clock nets are enabled or not
     pub gck_enable: [bool; 3],
@@ -311,6 +386,42 @@ impl XC2GlobalNets {
         let (term_x, term_y) = global_term_fuse_coord(device);
         fuse_array.set(term_x, term_y, self.global_pu);
     }
+
+    /// Read the crbit representation of the global net settings from the given `fuse_array`.
+    pub fn from_crbit(device: XC2Device, fuse_array: &FuseArray) -> XC2GlobalNets {
+        let ((gck0x, gck0y), (gck1x, gck1y), (gck2x, gck2y)) = gck_fuse_coords(device);
+
+        let ((gsren_x, gsren_y), (gsrinv_x, gsrinv_y)) = gsr_fuse_coords(device);
+
+        let (((gts0en_x, gts0en_y), (gts0inv_x, gts0inv_y)), ((gts1en_x, gts1en_y), (gts1inv_x, gts1inv_y)),
+             ((gts2en_x, gts2en_y), (gts2inv_x, gts2inv_y)), ((gts3en_x, gts3en_y), (gts3inv_x, gts3inv_y))) =
+                gts_fuse_coords(device);
+
+        let (term_x, term_y) = global_term_fuse_coord(device);
+
+        XC2GlobalNets {
+            gck_enable: [
+                fuse_array.get(gck0x, gck0y),
+                fuse_array.get(gck1x, gck1y),
+                fuse_array.get(gck2x, gck2y),
+            ],
+            gsr_enable: fuse_array.get(gsren_x, gsren_y),
+            gsr_invert: fuse_array.get(gsrinv_x, gsrinv_y),
+            gts_enable: [
+                !fuse_array.get(gts0en_x, gts0en_y),
+                !fuse_array.get(gts1en_x, gts1en_y),
+                !fuse_array.get(gts2en_x, gts2en_y),
+                !fuse_array.get(gts3en_x, gts3en_y),
+            ],
+            gts_invert: [
+                fuse_array.get(gts0inv_x, gts0inv_y),
+                fuse_array.get(gts1inv_x, gts1inv_y),
+                fuse_array.get(gts2inv_x, gts2inv_y),
+                fuse_array.get(gts3inv_x, gts3inv_y),
+            ],
+            global_pu: fuse_array.get(term_x, term_y),
+        }
+    }
 }
 
 /// Internal function to read the global nets
@@ -341,6 +452,7 @@ fn read_global_nets_logical(device: XC2Device, fuses: &[bool]) -> XC2GlobalNets
 
 /// Possible clock divide ratios for the programmable clock divider
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum XC2ClockDivRatio {
     Div2,
     Div4,
@@ -355,6 +467,7 @@ pub enum XC2ClockDivRatio {
 /// Represents the configuration of the programmable clock divider in devices with 128 macrocells or more. This is
 /// hard-wired onto the GCK2 clock pin.
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct XC2ClockDiv {
     /// Ratio that input clock is divided by
     pub div_ratio: XC2ClockDivRatio,
@@ -418,6 +531,7 @@ fn read_clock_div_logical(device: XC2Device, fuses: &[bool]) -> XC2ClockDiv {
 }
 
 /// The actual bitstream bits for each possible Coolrunner-II part
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum XC2BitstreamBits {
     XC2C32 {
         fb: [XC2BitstreamFB; 2],
@@ -457,6 +571,7 @@ pub enum XC2BitstreamBits {
     },
     XC2C64 {
         fb: [XC2BitstreamFB; 4],
+        #[cfg_attr(feature = "serde", serde(with = "serde_array"))]
         iobs: [XC2MCSmallIOB; 64],
         global_nets: XC2GlobalNets,
         /// Voltage level control
@@ -470,6 +585,7 @@ pub enum XC2BitstreamBits {
     },
     XC2C64A {
         fb: [XC2BitstreamFB; 4],
+        #[cfg_attr(feature = "serde", serde(with = "serde_array"))]
         iobs: [XC2MCSmallIOB; 64],
         global_nets: XC2GlobalNets,
         /// Legacy voltage level control, should almost always be set to `false`
@@ -491,6 +607,7 @@ pub enum XC2BitstreamBits {
     },
     XC2C128 {
         fb: [XC2BitstreamFB; 8],
+        #[cfg_attr(feature = "serde", serde(with = "serde_array"))]
         iobs: [XC2MCLargeIOB; 100],
         global_nets: XC2GlobalNets,
         clock_div: XC2ClockDiv,
@@ -509,6 +626,7 @@ pub enum XC2BitstreamBits {
     },
     XC2C256 {
         fb: [XC2BitstreamFB; 16],
+        #[cfg_attr(feature = "serde", serde(with = "serde_array"))]
         iobs: [XC2MCLargeIOB; 184],
         global_nets: XC2GlobalNets,
         clock_div: XC2ClockDiv,
@@ -527,6 +645,7 @@ pub enum XC2BitstreamBits {
     },
     XC2C384 {
         fb: [XC2BitstreamFB; 24],
+        #[cfg_attr(feature = "serde", serde(with = "serde_array"))]
         iobs: [XC2MCLargeIOB; 240],
         global_nets: XC2GlobalNets,
         clock_div: XC2ClockDiv,
@@ -545,6 +664,7 @@ pub enum XC2BitstreamBits {
     },
     XC2C512 {
         fb: [XC2BitstreamFB; 32],
+        #[cfg_attr(feature = "serde", serde(with = "serde_array"))]
         iobs: [XC2MCLargeIOB; 270],
         global_nets: XC2GlobalNets,
         clock_div: XC2ClockDiv,
@@ -619,13 +739,165 @@ impl XC2BitstreamBits {
         }
     }
 
+    /// Helper to extract the I/O pin settings on "small" devices without having to perform an explicit `match`.
+    /// Returns `None` on "large" devices or if `iob` is out of range.
+    pub fn get_small_iob(&self, iob: usize) -> Option<&XC2MCSmallIOB> {
+        match self {
+            &XC2BitstreamBits::XC2C32{ref iobs, ..} => iobs.get(iob),
+            &XC2BitstreamBits::XC2C32A{ref iobs, ..} => iobs.get(iob),
+            &XC2BitstreamBits::XC2C64{ref iobs, ..} => iobs.get(iob),
+            &XC2BitstreamBits::XC2C64A{ref iobs, ..} => iobs.get(iob),
+            _ => None,
+        }
+    }
+
+    /// Helper to extract the I/O pin settings on "large" devices without having to perform an explicit `match`.
+    /// Returns `None` on "small" devices or if `iob` is out of range.
+    pub fn get_large_iob(&self, iob: usize) -> Option<&XC2MCLargeIOB> {
+        match self {
+            &XC2BitstreamBits::XC2C128{ref iobs, ..} => iobs.get(iob),
+            &XC2BitstreamBits::XC2C256{ref iobs, ..} => iobs.get(iob),
+            &XC2BitstreamBits::XC2C384{ref iobs, ..} => iobs.get(iob),
+            &XC2BitstreamBits::XC2C512{ref iobs, ..} => iobs.get(iob),
+            _ => None,
+        }
+    }
+
+    /// Helper to extract the input-only pin settings, which only exist on 32-macrocell devices
+    pub fn get_inpin(&self) -> Option<&XC2ExtraIBuf> {
+        match self {
+            &XC2BitstreamBits::XC2C32{ref inpin, ..} => Some(inpin),
+            &XC2BitstreamBits::XC2C32A{ref inpin, ..} => Some(inpin),
+            _ => None,
+        }
+    }
+
+    /// Helper to extract only the function block data without having to perform an explicit `match`
+    pub fn get_fb_mut(&mut self) -> &mut [XC2BitstreamFB] {
+        match self {
+            &mut XC2BitstreamBits::XC2C32{ref mut fb, ..} => fb,
+            &mut XC2BitstreamBits::XC2C32A{ref mut fb, ..} => fb,
+            &mut XC2BitstreamBits::XC2C64{ref mut fb, ..} => fb,
+            &mut XC2BitstreamBits::XC2C64A{ref mut fb, ..} => fb,
+            &mut XC2BitstreamBits::XC2C128{ref mut fb, ..} => fb,
+            &mut XC2BitstreamBits::XC2C256{ref mut fb, ..} => fb,
+            &mut XC2BitstreamBits::XC2C384{ref mut fb, ..} => fb,
+            &mut XC2BitstreamBits::XC2C512{ref mut fb, ..} => fb,
+        }
+    }
+
+    /// Helper to extract only the global net data without having to perform an explicit `match`
+    pub fn get_global_nets_mut(&mut self) -> &mut XC2GlobalNets {
+        match self {
+            &mut XC2BitstreamBits::XC2C32{ref mut global_nets, ..} => global_nets,
+            &mut XC2BitstreamBits::XC2C32A{ref mut global_nets, ..} => global_nets,
+            &mut XC2BitstreamBits::XC2C64{ref mut global_nets, ..} => global_nets,
+            &mut XC2BitstreamBits::XC2C64A{ref mut global_nets, ..} => global_nets,
+            &mut XC2BitstreamBits::XC2C128{ref mut global_nets, ..} => global_nets,
+            &mut XC2BitstreamBits::XC2C256{ref mut global_nets, ..} => global_nets,
+            &mut XC2BitstreamBits::XC2C384{ref mut global_nets, ..} => global_nets,
+            &mut XC2BitstreamBits::XC2C512{ref mut global_nets, ..} => global_nets,
+        }
+    }
+
+    pub fn get_clock_div_mut(&mut self) -> Option<&mut XC2ClockDiv> {
+        match self {
+            &mut XC2BitstreamBits::XC2C32{..} => None,
+            &mut XC2BitstreamBits::XC2C32A{..} => None,
+            &mut XC2BitstreamBits::XC2C64{..} => None,
+            &mut XC2BitstreamBits::XC2C64A{..} => None,
+            &mut XC2BitstreamBits::XC2C128{ref mut clock_div, ..} => Some(clock_div),
+            &mut XC2BitstreamBits::XC2C256{ref mut clock_div, ..} => Some(clock_div),
+            &mut XC2BitstreamBits::XC2C384{ref mut clock_div, ..} => Some(clock_div),
+            &mut XC2BitstreamBits::XC2C512{ref mut clock_div, ..} => Some(clock_div),
+        }
+    }
+
+    /// Helper to extract mutable I/O pin settings on "small" devices. Returns `None` on "large" devices or if `iob` is
+    /// out of range.
+    pub fn get_small_iob_mut(&mut self, iob: usize) -> Option<&mut XC2MCSmallIOB> {
+        match self {
+            &mut XC2BitstreamBits::XC2C32{ref mut iobs, ..} => iobs.get_mut(iob),
+            &mut XC2BitstreamBits::XC2C32A{ref mut iobs, ..} => iobs.get_mut(iob),
+            &mut XC2BitstreamBits::XC2C64{ref mut iobs, ..} => iobs.get_mut(iob),
+            &mut XC2BitstreamBits::XC2C64A{ref mut iobs, ..} => iobs.get_mut(iob),
+            _ => None,
+        }
+    }
+
+    /// Helper to extract mutable I/O pin settings on "large" devices. Returns `None` on "small" devices or if `iob` is
+    /// out of range.
+    pub fn get_large_iob_mut(&mut self, iob: usize) -> Option<&mut XC2MCLargeIOB> {
+        match self {
+            &mut XC2BitstreamBits::XC2C128{ref mut iobs, ..} => iobs.get_mut(iob),
+            &mut XC2BitstreamBits::XC2C256{ref mut iobs, ..} => iobs.get_mut(iob),
+            &mut XC2BitstreamBits::XC2C384{ref mut iobs, ..} => iobs.get_mut(iob),
+            &mut XC2BitstreamBits::XC2C512{ref mut iobs, ..} => iobs.get_mut(iob),
+            _ => None,
+        }
+    }
+
+    /// Returns the input and output voltage range settings (`true` = high) of an I/O bank, or `None` if the bank does
+    /// not exist. On the XC2C32A and XC2C64A this does not include the legacy settings.
+    pub fn get_bank_voltage(&self, bank: usize) -> Option<(bool, bool)> {
+        match self {
+            &XC2BitstreamBits::XC2C32{ivoltage, ovoltage, ..} |
+            &XC2BitstreamBits::XC2C64{ivoltage, ovoltage, ..} => {
+                if bank == 0 {Some((ivoltage, ovoltage))} else {None}
+            },
+            &XC2BitstreamBits::XC2C32A{ref ivoltage, ref ovoltage, ..} |
+            &XC2BitstreamBits::XC2C64A{ref ivoltage, ref ovoltage, ..} |
+            &XC2BitstreamBits::XC2C128{ref ivoltage, ref ovoltage, ..} |
+            &XC2BitstreamBits::XC2C256{ref ivoltage, ref ovoltage, ..} => {
+                ivoltage.get(bank).map(|&x| (x, ovoltage[bank]))
+            },
+            &XC2BitstreamBits::XC2C384{ref ivoltage, ref ovoltage, ..} |
+            &XC2BitstreamBits::XC2C512{ref ivoltage, ref ovoltage, ..} => {
+                ivoltage.get(bank).map(|&x| (x, ovoltage[bank]))
+            },
+        }
+    }
+
+    /// Returns mutable references to the input and output voltage range settings of an I/O bank, or `None` if the bank
+    /// does not exist
+    pub fn get_bank_voltage_mut(&mut self, bank: usize) -> Option<(&mut bool, &mut bool)> {
+        match self {
+            &mut XC2BitstreamBits::XC2C32{ref mut ivoltage, ref mut ovoltage, ..} |
+            &mut XC2BitstreamBits::XC2C64{ref mut ivoltage, ref mut ovoltage, ..} => {
+                if bank == 0 {Some((ivoltage, ovoltage))} else {None}
+            },
+            &mut XC2BitstreamBits::XC2C32A{ref mut ivoltage, ref mut ovoltage, ..} |
+            &mut XC2BitstreamBits::XC2C64A{ref mut ivoltage, ref mut ovoltage, ..} |
+            &mut XC2BitstreamBits::XC2C128{ref mut ivoltage, ref mut ovoltage, ..} |
+            &mut XC2BitstreamBits::XC2C256{ref mut ivoltage, ref mut ovoltage, ..} => {
+                match (ivoltage.get_mut(bank), ovoltage.get_mut(bank)) {
+                    (Some(i), Some(o)) => Some((i, o)),
+                    _ => None,
+                }
+            },
+            &mut XC2BitstreamBits::XC2C384{ref mut ivoltage, ref mut ovoltage, ..} |
+            &mut XC2BitstreamBits::XC2C512{ref mut ivoltage, ref mut ovoltage, ..} => {
+                match (ivoltage.get_mut(bank), ovoltage.get_mut(bank)) {
+                    (Some(i), Some(o)) => Some((i, o)),
+                    _ => None,
+                }
+            },
+        }
+    }
+
     /// Convert the actual bitstream bits to crbit format
-    pub fn to_crbit(&self, fuse_array: &mut FuseArray) {
-        // TODO
+    pub fn to_crbit(&self, fuse_array: &mut FuseArray) -> Result<(), XC2BitError> {
+        // The locations of the clock divider fuses in the physical fuse array are not known, so a bitstream that uses
+        // the clock divider cannot be converted. The other clock divider settings are ignored while it is disabled.
+        if let Some(clock_div) = self.get_clock_div() {
+            if clock_div.enabled {
+                return Err(XC2BitError::ClockDivNotInCrbit);
+            }
+        }
 
         // FBs
         for i in 0..self.device_type().num_fbs() {
-            self.get_fb()[i].to_crbit(self.device_type(), i as u32, fuse_array);
+            self.get_fb()[i].to_crbit(self.device_type(), i as u32, fuse_array)?;
         }
 
         // IOBs
@@ -757,6 +1029,191 @@ impl XC2BitstreamBits {
             },
             _ => {}
         }
+
+        Ok(())
+    }
+
+    /// Read the actual bitstream bits for the given `device` from crbit format
+    pub fn from_crbit(device: XC2Device, fuse_array: &FuseArray) -> Result<XC2BitstreamBits, XC2BitError> {
+        // The clock divider fuses are not stored in crbit format (see to_crbit), so the clock divider is always
+        // disabled in the result
+
+        match device {
+            XC2Device::XC2C32 => {
+                let mut fb = [XC2BitstreamFB::default(); 2];
+                let mut iobs = [XC2MCSmallIOB::default(); 32];
+
+                read_bitstream_physical_common_small(fuse_array, device, &mut fb, &mut iobs)?;
+
+                Ok(XC2BitstreamBits::XC2C32 {
+                    fb: fb,
+                    iobs: iobs,
+                    inpin: read_32_extra_ibuf_physical(fuse_array),
+                    global_nets: XC2GlobalNets::from_crbit(device, fuse_array),
+                    ovoltage: !fuse_array.get(130, 24),
+                    ivoltage: !fuse_array.get(130, 25),
+                })
+            },
+            XC2Device::XC2C32A => {
+                let mut fb = [XC2BitstreamFB::default(); 2];
+                let mut iobs = [XC2MCSmallIOB::default(); 32];
+
+                read_bitstream_physical_common_small(fuse_array, device, &mut fb, &mut iobs)?;
+
+                Ok(XC2BitstreamBits::XC2C32A {
+                    fb: fb,
+                    iobs: iobs,
+                    inpin: read_32_extra_ibuf_physical(fuse_array),
+                    global_nets: XC2GlobalNets::from_crbit(device, fuse_array),
+                    legacy_ovoltage: !fuse_array.get(130, 24),
+                    legacy_ivoltage: !fuse_array.get(130, 25),
+                    ivoltage: [
+                        !fuse_array.get(131, 25),
+                        !fuse_array.get(133, 25),
+                    ],
+                    ovoltage: [
+                        !fuse_array.get(132, 25),
+                        !fuse_array.get(134, 25),
+                    ]
+                })
+            },
+            XC2Device::XC2C64 => {
+                let mut fb = [XC2BitstreamFB::default(); 4];
+                let mut iobs = [XC2MCSmallIOB::default(); 64];
+
+                read_bitstream_physical_common_small(fuse_array, device, &mut fb, &mut iobs)?;
+
+                Ok(XC2BitstreamBits::XC2C64 {
+                    fb: fb,
+                    iobs: iobs,
+                    global_nets: XC2GlobalNets::from_crbit(device, fuse_array),
+                    ovoltage: !fuse_array.get(137, 23),
+                    ivoltage: !fuse_array.get(138, 23),
+                })
+            },
+            XC2Device::XC2C64A => {
+                let mut fb = [XC2BitstreamFB::default(); 4];
+                let mut iobs = [XC2MCSmallIOB::default(); 64];
+
+                read_bitstream_physical_common_small(fuse_array, device, &mut fb, &mut iobs)?;
+
+                Ok(XC2BitstreamBits::XC2C64A {
+                    fb: fb,
+                    iobs: iobs,
+                    global_nets: XC2GlobalNets::from_crbit(device, fuse_array),
+                    legacy_ovoltage: !fuse_array.get(137, 23),
+                    legacy_ivoltage: !fuse_array.get(138, 23),
+                    ivoltage: [
+                        !fuse_array.get(139, 23),
+                        !fuse_array.get(141, 23),
+                    ],
+                    ovoltage: [
+                        !fuse_array.get(140, 23),
+                        !fuse_array.get(142, 23),
+                    ]
+                })
+            },
+            XC2Device::XC2C128 => {
+                let mut fb = [XC2BitstreamFB::default(); 8];
+                let mut iobs = [XC2MCLargeIOB::default(); 100];
+
+                read_bitstream_physical_common_large(fuse_array, device, &mut fb, &mut iobs)?;
+
+                Ok(XC2BitstreamBits::XC2C128 {
+                    fb: fb,
+                    iobs: iobs,
+                    global_nets: XC2GlobalNets::from_crbit(device, fuse_array),
+                    clock_div: XC2ClockDiv::default(),
+                    data_gate: !fuse_array.get(371, 67),
+                    use_vref: !fuse_array.get(10, 67),
+                    ivoltage: [
+                        !fuse_array.get(8, 67),
+                        !fuse_array.get(368, 67),
+                    ],
+                    ovoltage: [
+                        !fuse_array.get(9, 67),
+                        !fuse_array.get(369, 67),
+                    ]
+                })
+            },
+            XC2Device::XC2C256 => {
+                let mut fb = [XC2BitstreamFB::default(); 16];
+                let mut iobs = [XC2MCLargeIOB::default(); 184];
+
+                read_bitstream_physical_common_large(fuse_array, device, &mut fb, &mut iobs)?;
+
+                Ok(XC2BitstreamBits::XC2C256 {
+                    fb: fb,
+                    iobs: iobs,
+                    global_nets: XC2GlobalNets::from_crbit(device, fuse_array),
+                    clock_div: XC2ClockDiv::default(),
+                    data_gate: !fuse_array.get(518, 23),
+                    use_vref: !fuse_array.get(177, 23),
+                    ivoltage: [
+                        !fuse_array.get(175, 23),
+                        !fuse_array.get(515, 23),
+                    ],
+                    ovoltage: [
+                        !fuse_array.get(176, 23),
+                        !fuse_array.get(516, 23),
+                    ]
+                })
+            },
+            XC2Device::XC2C384 => {
+                let mut fb = [XC2BitstreamFB::default(); 24];
+                let mut iobs = [XC2MCLargeIOB::default(); 240];
+
+                read_bitstream_physical_common_large(fuse_array, device, &mut fb, &mut iobs)?;
+
+                Ok(XC2BitstreamBits::XC2C384 {
+                    fb: fb,
+                    iobs: iobs,
+                    global_nets: XC2GlobalNets::from_crbit(device, fuse_array),
+                    clock_div: XC2ClockDiv::default(),
+                    data_gate: !fuse_array.get(932, 17),
+                    use_vref: !fuse_array.get(3, 17),
+                    ivoltage: [
+                        !fuse_array.get(936, 17),
+                        !fuse_array.get(1864, 17),
+                        !fuse_array.get(1, 17),
+                        !fuse_array.get(929, 17),
+                    ],
+                    ovoltage: [
+                        !fuse_array.get(937, 17),
+                        !fuse_array.get(1865, 17),
+                        !fuse_array.get(2, 17),
+                        !fuse_array.get(930, 17),
+                    ]
+                })
+            },
+            XC2Device::XC2C512 => {
+                let mut fb = [XC2BitstreamFB::default(); 32];
+                let mut iobs = [XC2MCLargeIOB::default(); 270];
+
+                read_bitstream_physical_common_large(fuse_array, device, &mut fb, &mut iobs)?;
+
+                Ok(XC2BitstreamBits::XC2C512 {
+                    fb: fb,
+                    iobs: iobs,
+                    global_nets: XC2GlobalNets::from_crbit(device, fuse_array),
+                    clock_div: XC2ClockDiv::default(),
+                    data_gate: !fuse_array.get(982, 147),
+                    use_vref: !fuse_array.get(1, 147),
+                    ivoltage: [
+                        fuse_array.get(992, 147),
+                        fuse_array.get(1965, 147),
+                        fuse_array.get(3, 147),
+                        fuse_array.get(985, 147),
+                    ],
+                    ovoltage: [
+                        fuse_array.get(991, 147),
+                        fuse_array.get(1964, 147),
+                        fuse_array.get(2, 147),
+                        fuse_array.get(984, 147),
+                    ]
+                })
+            },
+        }
     }
 
     /// Dump a human-readable explanation of the bitstream to the given `writer` object.
@@ -865,8 +1322,8 @@ impl XC2BitstreamBits {
         Ok(())
     }
 
-    /// Write a .jed representation of the bitstream to the given `writer` object.
-    pub fn write_jed(&self, writer: &mut Write) -> Result<(), io::Error> {
+    /// Write the fuse fields of the .jed representation of the bitstream to the given `writer` object.
+    pub fn write_jed(&self, writer: &mut JedFuseWriter) -> Result<(), io::Error> {
         // FBs
         match self {
             &XC2BitstreamBits::XC2C32 {ref fb, ref iobs, ..} |
@@ -875,7 +1332,7 @@ impl XC2BitstreamBits {
                 for fb_i in 0..2 {
                     let fuse_base = fb_fuse_idx(XC2Device::XC2C32, fb_i as u32);
 
-                    fb[fb_i].write_to_jed(XC2Device::XC2C32, fuse_base, writer)?;
+                    fb[fb_i].write_to_jed(XC2Device::XC2C32, fb_i as u32, fuse_base, writer)?;
 
                     // Macrocells
                     write_small_mc_to_jed(writer, XC2Device::XC2C32, &fb[fb_i], iobs, fb_i, fuse_base)?;
@@ -887,7 +1344,7 @@ impl XC2BitstreamBits {
                 for fb_i in 0..4 {
                     let fuse_base = fb_fuse_idx(XC2Device::XC2C64, fb_i as u32);
 
-                    fb[fb_i].write_to_jed(XC2Device::XC2C64, fuse_base, writer)?;
+                    fb[fb_i].write_to_jed(XC2Device::XC2C64, fb_i as u32, fuse_base, writer)?;
 
                     // Macrocells
                     write_small_mc_to_jed(writer, XC2Device::XC2C64, &fb[fb_i], iobs, fb_i, fuse_base)?;
@@ -898,7 +1355,7 @@ impl XC2BitstreamBits {
                 for fb_i in 0..8 {
                     let fuse_base = fb_fuse_idx(XC2Device::XC2C128, fb_i as u32);
 
-                    fb[fb_i].write_to_jed(XC2Device::XC2C128, fuse_base, writer)?;
+                    fb[fb_i].write_to_jed(XC2Device::XC2C128, fb_i as u32, fuse_base, writer)?;
 
                     // Macrocells
                     write_large_mc_to_jed(writer, XC2Device::XC2C128, &fb[fb_i], iobs, fb_i, fuse_base)?;
@@ -909,7 +1366,7 @@ impl XC2BitstreamBits {
                 for fb_i in 0..16 {
                     let fuse_base = fb_fuse_idx(XC2Device::XC2C256, fb_i as u32);
 
-                    fb[fb_i].write_to_jed(XC2Device::XC2C256, fuse_base, writer)?;
+                    fb[fb_i].write_to_jed(XC2Device::XC2C256, fb_i as u32, fuse_base, writer)?;
 
                     // Macrocells
                     write_large_mc_to_jed(writer, XC2Device::XC2C256, &fb[fb_i], iobs, fb_i, fuse_base)?;
@@ -920,7 +1377,7 @@ impl XC2BitstreamBits {
                 for fb_i in 0..24 {
                     let fuse_base = fb_fuse_idx(XC2Device::XC2C384, fb_i as u32);
 
-                    fb[fb_i].write_to_jed(XC2Device::XC2C384, fuse_base, writer)?;
+                    fb[fb_i].write_to_jed(XC2Device::XC2C384, fb_i as u32, fuse_base, writer)?;
 
                     // Macrocells
                     write_large_mc_to_jed(writer, XC2Device::XC2C384, &fb[fb_i], iobs, fb_i, fuse_base)?;
@@ -931,7 +1388,7 @@ impl XC2BitstreamBits {
                 for fb_i in 0..32 {
                     let fuse_base = fb_fuse_idx(XC2Device::XC2C512, fb_i as u32);
 
-                    fb[fb_i].write_to_jed(XC2Device::XC2C512, fuse_base, writer)?;
+                    fb[fb_i].write_to_jed(XC2Device::XC2C512, fb_i as u32, fuse_base, writer)?;
 
                     // Macrocells
                     write_large_mc_to_jed(writer, XC2Device::XC2C512, &fb[fb_i], iobs, fb_i, fuse_base)?;
@@ -940,56 +1397,59 @@ impl XC2BitstreamBits {
         }
 
         // GCK
-        write!(writer, "L{:06} {}{}{}*\n",
-            gck_fuse_idx(self.device_type()),
-            if self.get_global_nets().gck_enable[0] {"1"} else {"0"},
-            if self.get_global_nets().gck_enable[1] {"1"} else {"0"},
-            if self.get_global_nets().gck_enable[2] {"1"} else {"0"})?;
+        writer.start_field(gck_fuse_idx(self.device_type()))?;
+        writer.fuse(self.get_global_nets().gck_enable[0])?;
+        writer.fuse(self.get_global_nets().gck_enable[1])?;
+        writer.fuse(self.get_global_nets().gck_enable[2])?;
+        writer.end_field()?;
 
         // Clock divider
         if let Some(clock_div) = self.get_clock_div() {
             let clock_fuse_block = clock_div_fuse_idx(self.device_type());
 
-            write!(writer, "L{:06} {}{}*\n",
-                clock_fuse_block,
-                if clock_div.enabled {"0"} else {"1"},
-                match clock_div.div_ratio {
-                    XC2ClockDivRatio::Div2  => "000",
-                    XC2ClockDivRatio::Div4  => "001",
-                    XC2ClockDivRatio::Div6  => "010",
-                    XC2ClockDivRatio::Div8  => "011",
-                    XC2ClockDivRatio::Div10 => "100",
-                    XC2ClockDivRatio::Div12 => "101",
-                    XC2ClockDivRatio::Div14 => "110",
-                    XC2ClockDivRatio::Div16 => "111",
-                })?;
-            write!(writer, "L{:06} {}*\n",
-                clock_fuse_block + 4,
-                if clock_div.delay {"0"} else {"1"})?;
+            let div_ratio = match clock_div.div_ratio {
+                XC2ClockDivRatio::Div2  => (false, false, false),
+                XC2ClockDivRatio::Div4  => (false, false, true),
+                XC2ClockDivRatio::Div6  => (false, true, false),
+                XC2ClockDivRatio::Div8  => (false, true, true),
+                XC2ClockDivRatio::Div10 => (true, false, false),
+                XC2ClockDivRatio::Div12 => (true, false, true),
+                XC2ClockDivRatio::Div14 => (true, true, false),
+                XC2ClockDivRatio::Div16 => (true, true, true),
+            };
+            writer.start_field(clock_fuse_block)?;
+            writer.fuse(!clock_div.enabled)?;
+            writer.fuse(div_ratio.0)?;
+            writer.fuse(div_ratio.1)?;
+            writer.fuse(div_ratio.2)?;
+            writer.end_field()?;
+            writer.start_field(clock_fuse_block + 4)?;
+            writer.fuse(!clock_div.delay)?;
+            writer.end_field()?;
         }
 
         // GSR
-        write!(writer, "L{:06} {}{}*\n",
-            gsr_fuse_idx(self.device_type()),
-            if self.get_global_nets().gsr_invert {"1"} else {"0"},
-            if self.get_global_nets().gsr_enable {"1"} else {"0"})?;
+        writer.start_field(gsr_fuse_idx(self.device_type()))?;
+        writer.fuse(self.get_global_nets().gsr_invert)?;
+        writer.fuse(self.get_global_nets().gsr_enable)?;
+        writer.end_field()?;
 
         // GTS
-        write!(writer, "L{:06} {}{}{}{}{}{}{}{}*\n",
-            gts_fuse_idx(self.device_type()),
-            if self.get_global_nets().gts_invert[0] {"1"} else {"0"},
-            if self.get_global_nets().gts_enable[0] {"0"} else {"1"},
-            if self.get_global_nets().gts_invert[1] {"1"} else {"0"},
-            if self.get_global_nets().gts_enable[1] {"0"} else {"1"},
-            if self.get_global_nets().gts_invert[2] {"1"} else {"0"},
-            if self.get_global_nets().gts_enable[2] {"0"} else {"1"},
-            if self.get_global_nets().gts_invert[3] {"1"} else {"0"},
-            if self.get_global_nets().gts_enable[3] {"0"} else {"1"})?;
+        writer.start_field(gts_fuse_idx(self.device_type()))?;
+        writer.fuse(self.get_global_nets().gts_invert[0])?;
+        writer.fuse(!self.get_global_nets().gts_enable[0])?;
+        writer.fuse(self.get_global_nets().gts_invert[1])?;
+        writer.fuse(!self.get_global_nets().gts_enable[1])?;
+        writer.fuse(self.get_global_nets().gts_invert[2])?;
+        writer.fuse(!self.get_global_nets().gts_enable[2])?;
+        writer.fuse(self.get_global_nets().gts_invert[3])?;
+        writer.fuse(!self.get_global_nets().gts_enable[3])?;
+        writer.end_field()?;
 
         // Global termination
-        write!(writer, "L{:06} {}*\n",
-            global_term_fuse_idx(self.device_type()),
-            if self.get_global_nets().global_pu {"1"} else {"0"})?;
+        writer.start_field(global_term_fuse_idx(self.device_type()))?;
+        writer.fuse(self.get_global_nets().global_pu)?;
+        writer.end_field()?;
 
         // Bank voltages and miscellaneous
         match self {
@@ -997,73 +1457,138 @@ impl XC2BitstreamBits {
             &XC2BitstreamBits::XC2C32A {ref inpin, legacy_ivoltage: ref ivoltage,
                 legacy_ovoltage: ref ovoltage, ..} => {
 
-                write!(writer, "L012270 {}*\n", if *ovoltage {"0"} else {"1"})?;
-                write!(writer, "L012271 {}*\n", if *ivoltage {"0"} else {"1"})?;
-
-                write!(writer, "L012272 {}{}*\n",
-                    if inpin.schmitt_trigger {"1"} else {"0"},
-                    if inpin.termination_enabled {"1"} else {"0"})?;
+                writer.start_field(12270)?;
+                writer.fuse(!*ovoltage)?;
+                writer.end_field()?;
+                writer.start_field(12271)?;
+                writer.fuse(!*ivoltage)?;
+                writer.end_field()?;
+
+                writer.start_field(12272)?;
+                writer.fuse(inpin.schmitt_trigger)?;
+                writer.fuse(inpin.termination_enabled)?;
+                writer.end_field()?;
             }
             &XC2BitstreamBits::XC2C64 {ref ivoltage, ref ovoltage, ..} |
             &XC2BitstreamBits::XC2C64A {legacy_ivoltage: ref ivoltage, legacy_ovoltage: ref ovoltage, ..} => {
-                write!(writer, "L025806 {}*\n", if *ovoltage {"0"} else {"1"})?;
-                write!(writer, "L025807 {}*\n", if *ivoltage {"0"} else {"1"})?;
+                writer.start_field(25806)?;
+                writer.fuse(!*ovoltage)?;
+                writer.end_field()?;
+                writer.start_field(25807)?;
+                writer.fuse(!*ivoltage)?;
+                writer.end_field()?;
             }
             &XC2BitstreamBits::XC2C128 {ref ivoltage, ref ovoltage, ref data_gate, ref use_vref, ..}  => {
-                write!(writer, "L055335 {}*\n", if *data_gate {"0"} else {"1"})?;
-
-                write!(writer, "L055336 {}{}*\n", if ivoltage[0] {"0"} else {"1"}, if ivoltage[1] {"0"} else {"1"})?;
-                write!(writer, "L055338 {}{}*\n", if ovoltage[0] {"0"} else {"1"}, if ovoltage[1] {"0"} else {"1"})?;
-
-                write!(writer, "L055340 {}*\n", if *use_vref {"0"} else {"1"})?;
+                writer.start_field(55335)?;
+                writer.fuse(!*data_gate)?;
+                writer.end_field()?;
+
+                writer.start_field(55336)?;
+                writer.fuse(!ivoltage[0])?;
+                writer.fuse(!ivoltage[1])?;
+                writer.end_field()?;
+                writer.start_field(55338)?;
+                writer.fuse(!ovoltage[0])?;
+                writer.fuse(!ovoltage[1])?;
+                writer.end_field()?;
+
+                writer.start_field(55340)?;
+                writer.fuse(!*use_vref)?;
+                writer.end_field()?;
             }
             &XC2BitstreamBits::XC2C256 {ref ivoltage, ref ovoltage, ref data_gate, ref use_vref, ..}  => {
-                write!(writer, "L123243 {}*\n", if *data_gate {"0"} else {"1"})?;
-
-                write!(writer, "L123244 {}{}*\n", if ivoltage[0] {"0"} else {"1"}, if ivoltage[1] {"0"} else {"1"})?;
-                write!(writer, "L123246 {}{}*\n", if ovoltage[0] {"0"} else {"1"}, if ovoltage[1] {"0"} else {"1"})?;
-
-                write!(writer, "L123248 {}*\n", if *use_vref {"0"} else {"1"})?;
+                writer.start_field(123243)?;
+                writer.fuse(!*data_gate)?;
+                writer.end_field()?;
+
+                writer.start_field(123244)?;
+                writer.fuse(!ivoltage[0])?;
+                writer.fuse(!ivoltage[1])?;
+                writer.end_field()?;
+                writer.start_field(123246)?;
+                writer.fuse(!ovoltage[0])?;
+                writer.fuse(!ovoltage[1])?;
+                writer.end_field()?;
+
+                writer.start_field(123248)?;
+                writer.fuse(!*use_vref)?;
+                writer.end_field()?;
             }
             &XC2BitstreamBits::XC2C384 {ref ivoltage, ref ovoltage, ref data_gate, ref use_vref, ..}  => {
-                write!(writer, "L209347 {}*\n", if *data_gate {"0"} else {"1"})?;
-
-                write!(writer, "L209348 {}{}{}{}*\n",
-                    if ivoltage[0] {"0"} else {"1"}, if ivoltage[1] {"0"} else {"1"},
-                    if ivoltage[2] {"0"} else {"1"}, if ivoltage[3] {"0"} else {"1"})?;
-                write!(writer, "L209352 {}{}{}{}*\n",
-                    if ovoltage[0] {"0"} else {"1"}, if ovoltage[1] {"0"} else {"1"},
-                    if ovoltage[2] {"0"} else {"1"}, if ovoltage[3] {"0"} else {"1"})?;
-
-                write!(writer, "L209356 {}*\n", if *use_vref {"0"} else {"1"})?;
+                writer.start_field(209347)?;
+                writer.fuse(!*data_gate)?;
+                writer.end_field()?;
+
+                writer.start_field(209348)?;
+                writer.fuse(!ivoltage[0])?;
+                writer.fuse(!ivoltage[1])?;
+                writer.fuse(!ivoltage[2])?;
+                writer.fuse(!ivoltage[3])?;
+                writer.end_field()?;
+                writer.start_field(209352)?;
+                writer.fuse(!ovoltage[0])?;
+                writer.fuse(!ovoltage[1])?;
+                writer.fuse(!ovoltage[2])?;
+                writer.fuse(!ovoltage[3])?;
+                writer.end_field()?;
+
+                writer.start_field(209356)?;
+                writer.fuse(!*use_vref)?;
+                writer.end_field()?;
             }
             &XC2BitstreamBits::XC2C512 {ref ivoltage, ref ovoltage, ref data_gate, ref use_vref, ..}  => {
-                write!(writer, "L296393 {}*\n", if *data_gate {"0"} else {"1"})?;
-
-                write!(writer, "L296394 {}{}{}{}*\n",
-                    if ivoltage[0] {"1"} else {"0"}, if ivoltage[1] {"1"} else {"0"},
-                    if ivoltage[2] {"1"} else {"0"}, if ivoltage[3] {"1"} else {"0"})?;
-                write!(writer, "L296398 {}{}{}{}*\n",
-                    if ovoltage[0] {"1"} else {"0"}, if ovoltage[1] {"1"} else {"0"},
-                    if ovoltage[2] {"1"} else {"0"}, if ovoltage[3] {"1"} else {"0"})?;
-
-                write!(writer, "L296402 {}*\n", if *use_vref {"0"} else {"1"})?;
+                writer.start_field(296393)?;
+                writer.fuse(!*data_gate)?;
+                writer.end_field()?;
+
+                writer.start_field(296394)?;
+                writer.fuse(ivoltage[0])?;
+                writer.fuse(ivoltage[1])?;
+                writer.fuse(ivoltage[2])?;
+                writer.fuse(ivoltage[3])?;
+                writer.end_field()?;
+                writer.start_field(296398)?;
+                writer.fuse(ovoltage[0])?;
+                writer.fuse(ovoltage[1])?;
+                writer.fuse(ovoltage[2])?;
+                writer.fuse(ovoltage[3])?;
+                writer.end_field()?;
+
+                writer.start_field(296402)?;
+                writer.fuse(!*use_vref)?;
+                writer.end_field()?;
             }
         }
 
         // A-variant bank voltages
         match self {
             &XC2BitstreamBits::XC2C32A {ref ivoltage, ref ovoltage, ..} => {
-                write!(writer, "L012274 {}*\n", if ivoltage[0] {"0"} else {"1"})?;
-                write!(writer, "L012275 {}*\n", if ovoltage[0] {"0"} else {"1"})?;
-                write!(writer, "L012276 {}*\n", if ivoltage[1] {"0"} else {"1"})?;
-                write!(writer, "L012277 {}*\n", if ovoltage[1] {"0"} else {"1"})?;
+                writer.start_field(12274)?;
+                writer.fuse(!ivoltage[0])?;
+                writer.end_field()?;
+                writer.start_field(12275)?;
+                writer.fuse(!ovoltage[0])?;
+                writer.end_field()?;
+                writer.start_field(12276)?;
+                writer.fuse(!ivoltage[1])?;
+                writer.end_field()?;
+                writer.start_field(12277)?;
+                writer.fuse(!ovoltage[1])?;
+                writer.end_field()?;
             },
             &XC2BitstreamBits::XC2C64A {ref ivoltage, ref ovoltage, ..} => {
-                write!(writer, "L025808 {}*\n", if ivoltage[0] {"0"} else {"1"})?;
-                write!(writer, "L025809 {}*\n", if ovoltage[0] {"0"} else {"1"})?;
-                write!(writer, "L025810 {}*\n", if ivoltage[1] {"0"} else {"1"})?;
-                write!(writer, "L025811 {}*\n", if ovoltage[1] {"0"} else {"1"})?;
+                writer.start_field(25808)?;
+                writer.fuse(!ivoltage[0])?;
+                writer.end_field()?;
+                writer.start_field(25809)?;
+                writer.fuse(!ovoltage[0])?;
+                writer.end_field()?;
+                writer.start_field(25810)?;
+                writer.fuse(!ivoltage[1])?;
+                writer.end_field()?;
+                writer.start_field(25811)?;
+                writer.fuse(!ovoltage[1])?;
+                writer.end_field()?;
             },
             _ => {}
         }
@@ -1072,9 +1597,47 @@ impl XC2BitstreamBits {
     }
 }
 
+/// Common logic for reading crbit bitstreams on "small" devices
+fn read_bitstream_physical_common_small(fuse_array: &FuseArray, device: XC2Device,
+    fb: &mut [XC2BitstreamFB], iobs: &mut [XC2MCSmallIOB]) -> Result<(), XC2BitError> {
+
+    for i in 0..fb.len() {
+        fb[i] = XC2BitstreamFB::from_crbit(device, i as u32, fuse_array)?;
+    }
+
+    for i in 0..iobs.len() {
+        iobs[i] = XC2MCSmallIOB::from_crbit(device, i as u32, fuse_array)?;
+    }
+
+    Ok(())
+}
+
+/// Common logic for reading crbit bitstreams on "large" devices
+fn read_bitstream_physical_common_large(fuse_array: &FuseArray, device: XC2Device,
+    fb: &mut [XC2BitstreamFB], iobs: &mut [XC2MCLargeIOB]) -> Result<(), XC2BitError> {
+
+    for i in 0..fb.len() {
+        fb[i] = XC2BitstreamFB::from_crbit(device, i as u32, fuse_array)?;
+    }
+
+    for i in 0..iobs.len() {
+        iobs[i] = XC2MCLargeIOB::from_crbit(device, i as u32, fuse_array)?;
+    }
+
+    Ok(())
+}
+
+/// Internal function that reads the input-only pin configuration from crbit format
+fn read_32_extra_ibuf_physical(fuse_array: &FuseArray) -> XC2ExtraIBuf {
+    XC2ExtraIBuf {
+        schmitt_trigger: fuse_array.get(131, 24),
+        termination_enabled: fuse_array.get(132, 24),
+    }
+}
+
 /// Common logic for reading bitstreams on "small" devices
 pub fn read_bitstream_logical_common_small(fuses: &[bool], device: XC2Device,
-    fb: &mut [XC2BitstreamFB], iobs: &mut [XC2MCSmallIOB]) -> Result<(), &'static str> {
+    fb: &mut [XC2BitstreamFB], iobs: &mut [XC2MCSmallIOB]) -> Result<(), XC2BitError> {
 
     for i in 0..fb.len() {
         let base_fuse = fb_fuse_idx(device, i as u32);
@@ -1088,7 +1651,7 @@ pub fn read_bitstream_logical_common_small(fuses: &[bool], device: XC2Device,
         let mut iob_fuse = base_fuse + size_of_zia + size_of_and + size_of_or;
         for ff in 0..MCS_PER_FB {
             let iob = fb_ff_num_to_iob_num(device, i as u32, ff as u32);
-            let res = read_small_iob_logical(fuses, iob_fuse)?;
+            let res = read_small_iob_logical(fuses, iob.unwrap(), iob_fuse)?;
             iobs[iob.unwrap() as usize] = res;
             iob_fuse += 27;
         }
@@ -1099,7 +1662,7 @@ pub fn read_bitstream_logical_common_small(fuses: &[bool], device: XC2Device,
 
 /// Common logic for reading bitstreams on "large" devices
 pub fn read_bitstream_logical_common_large(fuses: &[bool], device: XC2Device,
-    fb: &mut [XC2BitstreamFB], iobs: &mut [XC2MCLargeIOB]) -> Result<(), &'static str> {
+    fb: &mut [XC2BitstreamFB], iobs: &mut [XC2MCLargeIOB]) -> Result<(), XC2BitError> {
 
     for i in 0..fb.len() {
         let base_fuse = fb_fuse_idx(device, i as u32);
@@ -1114,7 +1677,7 @@ pub fn read_bitstream_logical_common_large(fuses: &[bool], device: XC2Device,
         for ff in 0..MCS_PER_FB {
             let iob = fb_ff_num_to_iob_num(device, i as u32, ff as u32);
             if iob.is_some() {
-                let res = read_large_iob_logical(fuses, iob_fuse)?;
+                let res = read_large_iob_logical(fuses, iob.unwrap(), iob_fuse)?;
                 iobs[iob.unwrap() as usize] = res;
                 // Must be not a buried macrocell
                 iob_fuse += 29;
@@ -1128,7 +1691,7 @@ pub fn read_bitstream_logical_common_large(fuses: &[bool], device: XC2Device,
     Ok(())
 }
 /// Internal function for parsing an XC2C32 bitstream
-pub fn read_32_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, &'static str> {
+pub fn read_32_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, XC2BitError> {
     let mut fb = [XC2BitstreamFB::default(); 2];
     let mut iobs = [XC2MCSmallIOB::default(); 32];
     
@@ -1149,7 +1712,7 @@ pub fn read_32_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, &'s
 }
 
 /// Internal function for parsing an XC2C32A bitstream
-pub fn read_32a_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, &'static str> {
+pub fn read_32a_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, XC2BitError> {
     let mut fb = [XC2BitstreamFB::default(); 2];
     let mut iobs = [XC2MCSmallIOB::default(); 32];
     
@@ -1178,7 +1741,7 @@ pub fn read_32a_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, &'
 }
 
 /// Internal function for parsing an XC2C64 bitstream
-pub fn read_64_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, &'static str> {
+pub fn read_64_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, XC2BitError> {
     let mut fb = [XC2BitstreamFB::default(); 4];
     let mut iobs = [XC2MCSmallIOB::default(); 64];
     
@@ -1196,7 +1759,7 @@ pub fn read_64_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, &'s
 }
 
 /// Internal function for parsing an XC2C64A bitstream
-pub fn read_64a_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, &'static str> {
+pub fn read_64a_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, XC2BitError> {
     let mut fb = [XC2BitstreamFB::default(); 4];
     let mut iobs = [XC2MCSmallIOB::default(); 64];
     
@@ -1222,7 +1785,7 @@ pub fn read_64a_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, &'
 }
 
 /// Internal function for parsing an XC2C128 bitstream
-pub fn read_128_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, &'static str> {
+pub fn read_128_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, XC2BitError> {
     let mut fb = [XC2BitstreamFB::default(); 8];
     let mut iobs = [XC2MCLargeIOB::default(); 100];
     
@@ -1249,7 +1812,7 @@ pub fn read_128_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, &'
 }
 
 /// Internal function for parsing an XC2C256 bitstream
-pub fn read_256_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, &'static str> {
+pub fn read_256_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, XC2BitError> {
     let mut fb = [XC2BitstreamFB::default(); 16];
     let mut iobs = [XC2MCLargeIOB::default(); 184];
     
@@ -1276,7 +1839,7 @@ pub fn read_256_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, &'
 }
 
 /// Internal function for parsing an XC2C384 bitstream
-pub fn read_384_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, &'static str> {
+pub fn read_384_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, XC2BitError> {
     let mut fb = [XC2BitstreamFB::default(); 24];
     let mut iobs = [XC2MCLargeIOB::default(); 240];
     
@@ -1307,7 +1870,7 @@ pub fn read_384_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, &'
 }
 
 /// Internal function for parsing an XC2C512 bitstream
-pub fn read_512_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, &'static str> {
+pub fn read_512_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, XC2BitError> {
     let mut fb = [XC2BitstreamFB::default(); 32];
     let mut iobs = [XC2MCLargeIOB::default(); 270];
     
@@ -1338,16 +1901,19 @@ pub fn read_512_bitstream_logical(fuses: &[bool]) -> Result<XC2BitstreamBits, &'
 }
 
 /// Processes a fuse array into a bitstream object
-pub fn process_jed(fuses: &[bool], device: &str) -> Result<XC2Bitstream, &'static str> {
+pub fn process_jed(fuses: &[bool], device: &str) -> Result<XC2Bitstream, XC2BitError> {
     let device_combination = parse_part_name_string(device);
     if device_combination.is_none() {
-        return Err("malformed device name");
+        return Err(XC2BitError::BadDeviceName(device.to_owned()));
     }
 
     let (part, spd, pkg) = device_combination.unwrap();
 
     if fuses.len() != total_logical_fuse_count(part) {
-        return Err("wrong number of fuses");
+        return Err(XC2BitError::WrongFuseCount {
+            expected: total_logical_fuse_count(part),
+            found: fuses.len(),
+        });
     }
 
     match part {
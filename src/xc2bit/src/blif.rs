@@ -0,0 +1,340 @@
+/*
+Copyright (c) 2017, Robert Ou <rqou@robertou.com> and contributors
+All rights reserved.
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice,
+   this list of conditions and the following disclaimer.
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+//! Contains routines for exporting the logic configured in a bitstream as a Berkeley BLIF model.
+//!
+//! Every ZIA row becomes a net called `fbN_ziaM`, and each macrocell's OR term becomes a single `.names` cover over
+//! the ZIA rows of its function block. I/O pins are named after the macrocell they belong to, with the suffix `_in`
+//! for the input and `_out` for the output. Outputs that can be tri-stated get an additional `_oe` output.
+//!
+//! BLIF cannot describe everything that the hardware can do. Asynchronous set/reset and DDR registers are written as
+//! comments, and open-drain and tri-state outputs are written as a separate data and output enable. The same
+//! assumptions as the Verilog export are made for empty AND and OR terms and for disabled nets.
+
+use std::collections::BTreeSet;
+use std::io;
+use std::io::Write;
+
+use *;
+
+/// Returns the name of the net driven by the given ZIA input
+fn zia_input_net(device: XC2Device, input: XC2ZIAInput) -> String {
+    match input {
+        XC2ZIAInput::Zero => String::from("const0"),
+        XC2ZIAInput::One => String::from("const1"),
+        XC2ZIAInput::Macrocell{fb, ff} => format!("FB{}_{}_fb", fb + 1, ff + 1),
+        XC2ZIAInput::IBuf{ibuf} => {
+            let (fb, ff) = iob_num_to_fb_ff_num(device, ibuf).unwrap();
+            format!("FB{}_{}_pad_zia", fb + 1, ff + 1)
+        },
+        XC2ZIAInput::DedicatedInput => String::from("dedicated_input"),
+    }
+}
+
+/// Returns the cube for an AND term, or `None` if the term can never be true because it contains both an input and
+/// its complement
+fn and_term_cube(and_term: &XC2PLAAndTerm) -> Option<Vec<char>> {
+    let mut cube = Vec::with_capacity(INPUTS_PER_ANDTERM);
+    for i in 0..INPUTS_PER_ANDTERM {
+        cube.push(match (and_term.input[i], and_term.input_b[i]) {
+            (false, false) => '-',
+            (true, false) => '1',
+            (false, true) => '0',
+            (true, true) => return None,
+        });
+    }
+    Some(cube)
+}
+
+/// Writes a `.names` cover for `output` from the given cubes over the ZIA rows of FB `fb`. Only the ZIA rows that
+/// are actually used become inputs of the cover.
+fn write_cover(writer: &mut Write, fb: usize, cubes: &[Vec<char>], output: &str) -> Result<(), io::Error> {
+    let used_rows = (0..INPUTS_PER_ANDTERM).filter(|&i| cubes.iter().any(|c| c[i] != '-')).collect::<Vec<_>>();
+
+    write!(writer, ".names")?;
+    for &i in &used_rows {
+        write!(writer, " fb{}_zia{}", fb + 1, i)?;
+    }
+    write!(writer, " {}\n", output)?;
+
+    for cube in cubes {
+        let row = used_rows.iter().map(|&i| cube[i]).collect::<String>();
+        if row.is_empty() {
+            // Constant one
+            write!(writer, "1\n")?;
+        } else {
+            write!(writer, "{} 1\n", row)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Write a BLIF model of the logic configured in the given bitstream as a model called `model_name` to the given
+/// `writer` object.
+pub fn write_blif(bitstream: &XC2Bitstream, model_name: &str, writer: &mut Write) -> Result<(), io::Error> {
+    let bits = &bitstream.bits;
+    let device = bits.device_type();
+    let global_nets = bits.get_global_nets();
+
+    // Special product terms that need their own nets, as (fb, pt)
+    let mut pt_nets = BTreeSet::new();
+    let pt_net = |pt_nets: &mut BTreeSet<(usize, u32)>, fb: usize, pt: u32| {
+        pt_nets.insert((fb, pt));
+        format!("fb{}_pt{}", fb + 1, pt)
+    };
+
+    let mut inputs = Vec::new();
+    let mut outputs = Vec::new();
+    let mut body = Vec::new();
+
+    // Constants and global nets
+    write!(body, ".names const0\n")?;
+    write!(body, ".names const1\n1\n")?;
+    for i in 0..3 {
+        if global_nets.gck_enable[i] {
+            inputs.push(format!("GCK{}", i));
+        }
+    }
+    if global_nets.gsr_enable {
+        inputs.push(String::from("GSR"));
+    }
+    for i in 0..4 {
+        if global_nets.gts_enable[i] {
+            inputs.push(format!("GTS{}", i));
+            // This is the output enable, so it is the complement of the T input
+            write!(body, ".names GTS{} gts{}_oe\n{} 1\n", i, i, if global_nets.gts_invert[i] {"1"} else {"0"})?;
+        } else {
+            write!(body, ".names gts{}_oe\n1\n", i)?;
+        }
+    }
+    if bits.get_inpin().is_some() {
+        inputs.push(String::from("dedicated_input"));
+    }
+
+    // I/O pins
+    let mut has_iob = vec![[false; MCS_PER_FB]; device.num_fbs()];
+    for i in 0..device.num_iobs() {
+        let (fb, ff) = iob_num_to_fb_ff_num(device, i as u32).unwrap();
+        let (fb, ff) = (fb as usize, ff as usize);
+        has_iob[fb][ff] = true;
+        let name = format!("FB{}_{}", fb + 1, ff + 1);
+
+        let (zia_mode, obuf_uses_ff, obuf_mode) = if let Some(iob) = bits.get_small_iob(i) {
+            (iob.zia_mode, iob.obuf_uses_ff, iob.obuf_mode)
+        } else {
+            let iob = bits.get_large_iob(i).unwrap();
+            (iob.zia_mode, iob.obuf_uses_ff, iob.obuf_mode)
+        };
+
+        if zia_mode == XC2IOBZIAMode::PAD || bits.get_fb()[fb].ffs[ff].ff_in_ibuf {
+            inputs.push(format!("{}_in", name));
+        }
+        write!(body, ".names {} {}_pad_zia\n", match zia_mode {
+            XC2IOBZIAMode::Disabled => String::from("const0"),
+            XC2IOBZIAMode::PAD => format!("{}_in", name),
+            XC2IOBZIAMode::REG => format!("{}_q", name),
+        }, name)?;
+        write!(body, "1 1\n")?;
+
+        let data = format!("{}_{}", name, if obuf_uses_ff {"q"} else {"xor"});
+        let oe = match obuf_mode {
+            XC2IOBOBufMode::Disabled => continue,
+            XC2IOBOBufMode::PushPull => None,
+            XC2IOBOBufMode::OpenDrain => {
+                write!(body, "# {} is open-drain\n", name)?;
+                None
+            },
+            XC2IOBOBufMode::TriStateGTS0 => Some(String::from("gts0_oe")),
+            XC2IOBOBufMode::TriStateGTS1 => Some(String::from("gts1_oe")),
+            XC2IOBOBufMode::TriStateGTS2 => Some(String::from("gts2_oe")),
+            XC2IOBOBufMode::TriStateGTS3 => Some(String::from("gts3_oe")),
+            XC2IOBOBufMode::TriStatePTB => Some(pt_net(&mut pt_nets, fb, get_ptb(ff as u32))),
+            XC2IOBOBufMode::TriStateCTE => Some(pt_net(&mut pt_nets, fb, CTE)),
+            XC2IOBOBufMode::CGND => {
+                outputs.push(format!("{}_out", name));
+                write!(body, ".names const0 {}_out\n1 1\n", name)?;
+                continue;
+            },
+        };
+
+        outputs.push(format!("{}_out", name));
+        write!(body, ".names {} {}_out\n1 1\n", data, name)?;
+        if let Some(oe) = oe {
+            outputs.push(format!("{}_oe", name));
+            write!(body, ".names {} {}_oe\n1 1\n", oe, name)?;
+        }
+    }
+
+    // FBs
+    for (fb, fb_bits) in bits.get_fb().iter().enumerate() {
+        // ZIA
+        for i in 0..INPUTS_PER_ANDTERM {
+            write!(body, ".names {} fb{}_zia{}\n1 1\n",
+                zia_input_net(device, fb_bits.zia_bits[i].selected), fb + 1, i)?;
+        }
+
+        for i in 0..MCS_PER_FB {
+            let name = format!("FB{}_{}", fb + 1, i + 1);
+            let mc = &fb_bits.ffs[i];
+
+            // Sum of products
+            let cubes = (0..ANDTERMS_PER_FB)
+                .filter(|&j| fb_bits.or_terms[i].input[j])
+                .filter_map(|j| and_term_cube(&fb_bits.and_terms[j]))
+                .collect::<Vec<_>>();
+            write_cover(&mut body, fb, &cubes, &format!("{}_or", name))?;
+
+            // XOR gate
+            match mc.xor_mode {
+                XC2MCXorMode::ZERO => write!(body, ".names {}_or {}_xor\n1 1\n", name, name)?,
+                XC2MCXorMode::ONE => write!(body, ".names {}_or {}_xor\n0 1\n", name, name)?,
+                XC2MCXorMode::PTC => write!(body, ".names {}_or {} {}_xor\n10 1\n01 1\n",
+                    name, pt_net(&mut pt_nets, fb, get_ptc(i as u32)), name)?,
+                XC2MCXorMode::PTCB => write!(body, ".names {}_or {} {}_xor\n00 1\n11 1\n",
+                    name, pt_net(&mut pt_nets, fb, get_ptc(i as u32)), name)?,
+            }
+
+            // Register
+            let d = if mc.ff_in_ibuf {format!("{}_in", name)} else {format!("{}_xor", name)};
+            match mc.reg_mode {
+                XC2MCRegMode::DFF | XC2MCRegMode::LATCH => {
+                    write!(body, ".names {} {}_d\n1 1\n", d, name)?;
+                },
+                XC2MCRegMode::TFF => {
+                    write!(body, ".names {} {}_q {}_d\n10 1\n01 1\n", d, name, name)?;
+                },
+                XC2MCRegMode::DFFCE => {
+                    write!(body, ".names {} {} {}_q {}_d\n11- 1\n0-1 1\n",
+                        pt_net(&mut pt_nets, fb, get_ptc(i as u32)), d, name, name)?;
+                },
+            }
+
+            let gck = |n: usize| if global_nets.gck_enable[n] {format!("GCK{}", n)} else {String::from("const0")};
+            let clk = match mc.clk_src {
+                XC2MCRegClkSrc::GCK0 => gck(0),
+                XC2MCRegClkSrc::GCK1 => gck(1),
+                XC2MCRegClkSrc::GCK2 => gck(2),
+                XC2MCRegClkSrc::PTC => pt_net(&mut pt_nets, fb, get_ptc(i as u32)),
+                XC2MCRegClkSrc::CTC => pt_net(&mut pt_nets, fb, CTC),
+            };
+            let latch_type = match (mc.reg_mode == XC2MCRegMode::LATCH, mc.clk_invert_pol) {
+                (false, false) => "re",
+                (false, true) => "fe",
+                (true, false) => "ah",
+                (true, true) => "al",
+            };
+
+            if mc.is_ddr {
+                write!(body, "# {} is a DDR register\n", name)?;
+            }
+            if mc.s_src != XC2MCRegSetSrc::Disabled {
+                write!(body, "# {} has asynchronous set from {:?}\n", name, mc.s_src)?;
+            }
+            if mc.r_src != XC2MCRegResetSrc::Disabled {
+                write!(body, "# {} has asynchronous reset from {:?}\n", name, mc.r_src)?;
+            }
+            write!(body, ".latch {}_d {}_q {} {} {}\n", name, name, latch_type, clk, mc.init_state as u8)?;
+
+            // Feedback into the ZIA
+            write!(body, ".names {} {}_fb\n1 1\n", match mc.fb_mode {
+                XC2MCFeedbackMode::Disabled => String::from("const0"),
+                XC2MCFeedbackMode::COMB => format!("{}_xor", name),
+                XC2MCFeedbackMode::REG => format!("{}_q", name),
+            }, name)?;
+
+            if !has_iob[fb][i] {
+                write!(body, ".names const0 {}_pad_zia\n1 1\n", name)?;
+            }
+        }
+    }
+
+    // Special product terms
+    for &(fb, pt) in &pt_nets {
+        let cubes = and_term_cube(&bits.get_fb()[fb].and_terms[pt as usize]).into_iter().collect::<Vec<_>>();
+        write_cover(&mut body, fb, &cubes, &format!("fb{}_pt{}", fb + 1, pt))?;
+    }
+
+    write!(writer, "# BLIF model of a {}-{}-{} bitstream written by xc2bit\n",
+        device, bitstream.speed_grade, bitstream.package)?;
+    write!(writer, ".model {}\n", model_name)?;
+    if !inputs.is_empty() {
+        write!(writer, ".inputs {}\n", inputs.join(" "))?;
+    }
+    if !outputs.is_empty() {
+        write!(writer, ".outputs {}\n", outputs.join(" "))?;
+    }
+    writer.write_all(&body)?;
+    write!(writer, ".end\n")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blif_contents() {
+        let mut bitstream = XC2Bitstream::blank_bitstream(XC2Device::XC2C32A, XC2Speed::Speed6, XC2Package::VQ44)
+            .unwrap();
+        bitstream.bits.get_global_nets_mut().gck_enable[0] = true;
+        match bitstream.bits {
+            XC2BitstreamBits::XC2C32A{ref mut iobs, ..} => {
+                iobs[1].zia_mode = XC2IOBZIAMode::PAD;
+                iobs[2].obuf_mode = XC2IOBOBufMode::PushPull;
+                iobs[2].obuf_uses_ff = true;
+            },
+            _ => unreachable!(),
+        }
+        {
+            let fb = &mut bitstream.bits.get_fb_mut()[0];
+            fb.zia_bits[0].selected = XC2ZIAInput::IBuf{ibuf: 1};
+            fb.zia_bits[3].selected = XC2ZIAInput::Macrocell{fb: 1, ff: 0};
+            fb.and_terms[20].input[0] = true;
+            fb.and_terms[20].input_b[3] = true;
+            fb.and_terms[21].input[3] = true;
+            fb.or_terms[2].input[20] = true;
+            fb.or_terms[2].input[21] = true;
+            fb.ffs[2].reg_mode = XC2MCRegMode::TFF;
+            fb.ffs[2].init_state = false;
+        }
+
+        let mut blif = Vec::new();
+        bitstream.write_blif("test", &mut blif).unwrap();
+        let blif = String::from_utf8(blif).unwrap();
+
+        assert!(blif.contains(".model test\n.inputs GCK0 dedicated_input FB1_2_in\n.outputs FB1_3_out\n"));
+        assert!(blif.contains(".names FB1_2_in FB1_2_pad_zia\n1 1\n"));
+        assert!(blif.contains(".names FB1_3_q FB1_3_out\n1 1\n"));
+        assert!(blif.contains(".names FB1_2_pad_zia fb1_zia0\n1 1\n"));
+        assert!(blif.contains(".names FB2_1_fb fb1_zia3\n1 1\n"));
+        assert!(blif.contains(".names fb1_zia0 fb1_zia3 FB1_3_or\n10 1\n-1 1\n"));
+        assert!(blif.contains(".names FB1_3_or FB1_3_xor\n1 1\n"));
+        assert!(blif.contains(".names FB1_3_xor FB1_3_q FB1_3_d\n10 1\n01 1\n"));
+        assert!(blif.contains(".latch FB1_3_d FB1_3_q re GCK0 0\n"));
+        assert!(blif.ends_with(".end\n"));
+    }
+}
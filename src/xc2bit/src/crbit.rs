@@ -23,12 +23,22 @@ OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
 OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 */
 
-//! Contains routines for dealing with xc2bit's "native" crbit format. TODO: Document this format.
+//! Contains routines for dealing with xc2bit's "native" crbit format.
+//!
+//! A crbit file is a plain-text image of the physical fuse array. Each row of fuses is written as one line of `0` and
+//! `1` characters, top row first, and every row must have the same width. Lines starting with `//` are comments, and
+//! a `// DEVICE <device>-<speed>-<package>` comment records the part the fuses belong to. Empty lines are ignored.
 
 use std::io;
 use std::io::Write;
 use std::str;
 
+use XC2BitError;
+
+fn crbit_error(msg: &str, line: Option<usize>) -> XC2BitError {
+    XC2BitError::CrbitParseError{msg: msg.to_owned(), line}
+}
+
 /// Struct representing a 2-dimensional fuse array and handles converting xy-coordinates into a single linear index.
 /// The x-axis is horizontal and the y-axis is vertical. The origin is at the top-left corner. (This is the standard
 /// "computer graphics" coordinate scheme.)
@@ -57,26 +67,66 @@ impl FuseArray {
         (self.w, self.v.len() / self.w)
     }
 
-    /// Processes the given data and converts it into a `FuseArray` struct.
-    pub fn from_file_contents(in_bytes: &[u8]) -> Result<FuseArray, &'static str> {
-        // let w = None;
-
+    /// Processes the given data and converts it into a `FuseArray` struct. On failure, the returned error includes
+    /// the line number of the offending line.
+    pub fn from_file_contents(in_bytes: &[u8]) -> Result<FuseArray, XC2BitError> {
         let in_str = str::from_utf8(in_bytes);
         if in_str.is_err() {
-            return Err("invalid characters in crbit");
+            return Err(crbit_error("invalid characters in crbit", None));
         }
 
-        for l in in_str.unwrap().split('\n') {
+        let mut w = None;
+        let mut v = Vec::new();
+        let mut dev_name_str = None;
+
+        for (line_num, l) in in_str.unwrap().split('\n').enumerate() {
+            let line_num = line_num + 1;
             let l = l.trim_matches(|c| c == ' ' || c == '\r' || c == '\n');
             if l.len() == 0 {
                 // ignore empty fields
                 continue;
             }
 
-            println!("{}", l);
+            if l.starts_with("//") {
+                // Comment; we want to extract // DEVICE but otherwise ignore it
+                let comment_pieces = l[2..].split_whitespace().collect::<Vec<_>>();
+                if comment_pieces.len() == 2 && comment_pieces[0] == "DEVICE" {
+                    if dev_name_str.is_some() {
+                        return Err(crbit_error("duplicate DEVICE comment", Some(line_num)));
+                    }
+                    dev_name_str = Some(comment_pieces[1].to_owned());
+                }
+                continue;
+            }
+
+            // A row of fuses
+            if let Some(w) = w {
+                if l.len() != w {
+                    return Err(crbit_error(&format!("row has {} fuses but previous rows have {}", l.len(), w),
+                        Some(line_num)));
+                }
+            } else {
+                w = Some(l.len());
+            }
+
+            for c in l.chars() {
+                match c {
+                    '0' => v.push(false),
+                    '1' => v.push(true),
+                    _ => return Err(crbit_error(&format!("invalid character '{}' in fuse row", c), Some(line_num))),
+                }
+            }
+        }
+
+        if w.is_none() {
+            return Err(crbit_error("no fuse rows in crbit", None));
         }
 
-        unimplemented!();
+        Ok(FuseArray {
+            v,
+            w: w.unwrap(),
+            dev_name_str,
+        })
     }
 
     pub fn from_dim(w: usize, h: usize) -> FuseArray {
@@ -107,3 +157,66 @@ impl FuseArray {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_no_rows() {
+        let ret = FuseArray::from_file_contents(b"// just a comment\n\n");
+
+        assert_eq!(ret.err(), Some(crbit_error("no fuse rows in crbit", None)));
+    }
+
+    #[test]
+    fn read_with_device() {
+        let ret = FuseArray::from_file_contents(b"// comment\n\n// DEVICE XC2C32A-6-VQ44\n\n010\n110\n").unwrap();
+
+        assert_eq!(ret.dim(), (3, 2));
+        assert_eq!(ret.dev_name_str, Some(String::from("XC2C32A-6-VQ44")));
+        assert_eq!(ret.get(0, 0), false);
+        assert_eq!(ret.get(1, 0), true);
+        assert_eq!(ret.get(0, 1), true);
+        assert_eq!(ret.get(2, 1), false);
+    }
+
+    #[test]
+    fn read_crlf() {
+        let ret = FuseArray::from_file_contents(b"01\r\n10\r\n").unwrap();
+
+        assert_eq!(ret.dim(), (2, 2));
+        assert_eq!(ret.dev_name_str, None);
+    }
+
+    #[test]
+    fn read_ragged_rows() {
+        let ret = FuseArray::from_file_contents(b"// DEVICE XC2C32A-6-VQ44\n0101\n010\n");
+
+        assert_eq!(ret.err().unwrap().to_string(), "line 3: row has 3 fuses but previous rows have 4");
+    }
+
+    #[test]
+    fn read_bad_character() {
+        let ret = FuseArray::from_file_contents(b"0101\n\n01x1\n");
+
+        assert_eq!(ret.err(), Some(crbit_error("invalid character 'x' in fuse row", Some(3))));
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut fuse_array = FuseArray::from_dim(5, 3);
+        fuse_array.dev_name_str = Some(String::from("XC2C64A-5-VQ44"));
+        fuse_array.set(0, 0, true);
+        fuse_array.set(4, 1, true);
+        fuse_array.set(2, 2, true);
+
+        let mut crbit_data = Vec::new();
+        fuse_array.write_to_writer(&mut crbit_data).unwrap();
+        let ret = FuseArray::from_file_contents(&crbit_data).unwrap();
+
+        assert_eq!(ret.dim(), (5, 3));
+        assert_eq!(ret.dev_name_str, fuse_array.dev_name_str);
+        assert_eq!(ret.v, fuse_array.v);
+    }
+}
@@ -0,0 +1,297 @@
+/*
+Copyright (c) 2017, Robert Ou <rqou@robertou.com> and contributors
+All rights reserved.
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice,
+   this list of conditions and the following disclaimer.
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+//! Contains routines for comparing two bitstreams setting by setting rather than fuse by fuse
+
+use std::fmt;
+
+use *;
+
+/// Adds a line to `out` if a single setting differs
+fn diff_field<T: PartialEq + fmt::Debug>(out: &mut Vec<String>, what: &str, field: &str, old: T, new: T) {
+    if old != new {
+        out.push(format!("{} {} {:?} → {:?}", what, field, old, new));
+    }
+}
+
+/// Returns the global (not per-pin) I/O settings of a bitstream along with their names
+fn bank_settings(bits: &XC2BitstreamBits) -> Vec<(String, bool)> {
+    let mut ret = Vec::new();
+
+    match bits {
+        &XC2BitstreamBits::XC2C32 {ivoltage, ovoltage, ..} |
+        &XC2BitstreamBits::XC2C64 {ivoltage, ovoltage, ..} => {
+            ret.push((String::from("ovoltage"), ovoltage));
+            ret.push((String::from("ivoltage"), ivoltage));
+        },
+        &XC2BitstreamBits::XC2C32A {legacy_ivoltage, legacy_ovoltage, ref ivoltage, ref ovoltage, ..} |
+        &XC2BitstreamBits::XC2C64A {legacy_ivoltage, legacy_ovoltage, ref ivoltage, ref ovoltage, ..} => {
+            ret.push((String::from("legacy_ovoltage"), legacy_ovoltage));
+            ret.push((String::from("legacy_ivoltage"), legacy_ivoltage));
+            for i in 0..2 {
+                ret.push((format!("ovoltage[{}]", i), ovoltage[i]));
+            }
+            for i in 0..2 {
+                ret.push((format!("ivoltage[{}]", i), ivoltage[i]));
+            }
+        },
+        &XC2BitstreamBits::XC2C128 {ref ivoltage, ref ovoltage, data_gate, use_vref, ..} |
+        &XC2BitstreamBits::XC2C256 {ref ivoltage, ref ovoltage, data_gate, use_vref, ..} => {
+            for i in 0..2 {
+                ret.push((format!("ovoltage[{}]", i), ovoltage[i]));
+            }
+            for i in 0..2 {
+                ret.push((format!("ivoltage[{}]", i), ivoltage[i]));
+            }
+            ret.push((String::from("data_gate"), data_gate));
+            ret.push((String::from("use_vref"), use_vref));
+        },
+        &XC2BitstreamBits::XC2C384 {ref ivoltage, ref ovoltage, data_gate, use_vref, ..} |
+        &XC2BitstreamBits::XC2C512 {ref ivoltage, ref ovoltage, data_gate, use_vref, ..} => {
+            for i in 0..4 {
+                ret.push((format!("ovoltage[{}]", i), ovoltage[i]));
+            }
+            for i in 0..4 {
+                ret.push((format!("ivoltage[{}]", i), ivoltage[i]));
+            }
+            ret.push((String::from("data_gate"), data_gate));
+            ret.push((String::from("use_vref"), use_vref));
+        },
+    }
+
+    ret
+}
+
+fn diff_global_nets(out: &mut Vec<String>, old: &XC2GlobalNets, new: &XC2GlobalNets) {
+    for i in 0..3 {
+        diff_field(out, "global nets", &format!("gck_enable[{}]", i), old.gck_enable[i], new.gck_enable[i]);
+    }
+    diff_field(out, "global nets", "gsr_enable", old.gsr_enable, new.gsr_enable);
+    diff_field(out, "global nets", "gsr_invert", old.gsr_invert, new.gsr_invert);
+    for i in 0..4 {
+        diff_field(out, "global nets", &format!("gts_enable[{}]", i), old.gts_enable[i], new.gts_enable[i]);
+        diff_field(out, "global nets", &format!("gts_invert[{}]", i), old.gts_invert[i], new.gts_invert[i]);
+    }
+    diff_field(out, "global nets", "global_pu", old.global_pu, new.global_pu);
+}
+
+fn diff_small_iob(out: &mut Vec<String>, device: XC2Device, iob: usize, old: &XC2MCSmallIOB, new: &XC2MCSmallIOB) {
+    let (fb, ff) = iob_num_to_fb_ff_num(device, iob as u32).unwrap();
+    let what = format!("FB{}_{} I/O", fb + 1, ff + 1);
+
+    diff_field(out, &what, "zia_mode", old.zia_mode, new.zia_mode);
+    diff_field(out, &what, "schmitt_trigger", old.schmitt_trigger, new.schmitt_trigger);
+    diff_field(out, &what, "obuf_uses_ff", old.obuf_uses_ff, new.obuf_uses_ff);
+    diff_field(out, &what, "obuf_mode", old.obuf_mode, new.obuf_mode);
+    diff_field(out, &what, "termination_enabled", old.termination_enabled, new.termination_enabled);
+    diff_field(out, &what, "slew_is_fast", old.slew_is_fast, new.slew_is_fast);
+}
+
+fn diff_large_iob(out: &mut Vec<String>, device: XC2Device, iob: usize, old: &XC2MCLargeIOB, new: &XC2MCLargeIOB) {
+    let (fb, ff) = iob_num_to_fb_ff_num(device, iob as u32).unwrap();
+    let what = format!("FB{}_{} I/O", fb + 1, ff + 1);
+
+    diff_field(out, &what, "zia_mode", old.zia_mode, new.zia_mode);
+    diff_field(out, &what, "ibuf_mode", old.ibuf_mode, new.ibuf_mode);
+    diff_field(out, &what, "obuf_uses_ff", old.obuf_uses_ff, new.obuf_uses_ff);
+    diff_field(out, &what, "obuf_mode", old.obuf_mode, new.obuf_mode);
+    diff_field(out, &what, "termination_enabled", old.termination_enabled, new.termination_enabled);
+    diff_field(out, &what, "slew_is_fast", old.slew_is_fast, new.slew_is_fast);
+    diff_field(out, &what, "uses_data_gate", old.uses_data_gate, new.uses_data_gate);
+}
+
+fn diff_mc(out: &mut Vec<String>, fb: usize, ff: usize, old: &XC2Macrocell, new: &XC2Macrocell) {
+    let what = format!("FB{}_{}", fb + 1, ff + 1);
+
+    diff_field(out, &what, "clk_src", old.clk_src, new.clk_src);
+    diff_field(out, &what, "clk_invert_pol", old.clk_invert_pol, new.clk_invert_pol);
+    diff_field(out, &what, "is_ddr", old.is_ddr, new.is_ddr);
+    diff_field(out, &what, "r_src", old.r_src, new.r_src);
+    diff_field(out, &what, "s_src", old.s_src, new.s_src);
+    diff_field(out, &what, "init_state", old.init_state, new.init_state);
+    diff_field(out, &what, "reg_mode", old.reg_mode, new.reg_mode);
+    diff_field(out, &what, "fb_mode", old.fb_mode, new.fb_mode);
+    diff_field(out, &what, "ff_in_ibuf", old.ff_in_ibuf, new.ff_in_ibuf);
+    diff_field(out, &what, "xor_mode", old.xor_mode, new.xor_mode);
+}
+
+fn diff_fb(out: &mut Vec<String>, device: XC2Device, fb: usize, old: &XC2BitstreamFB, new: &XC2BitstreamFB) {
+    for i in 0..MCS_PER_FB {
+        diff_mc(out, fb, i, &old.ffs[i], &new.ffs[i]);
+    }
+
+    for i in 0..INPUTS_PER_ANDTERM {
+        if old.zia_bits[i].selected != new.zia_bits[i].selected {
+            out.push(format!("FB{} ZIA row {}: {} → {}", fb + 1, i,
+                zia_input_name(device, old.zia_bits[i].selected), zia_input_name(device, new.zia_bits[i].selected)));
+        }
+    }
+
+    for i in 0..ANDTERMS_PER_FB {
+        for j in 0..INPUTS_PER_ANDTERM {
+            if old.and_terms[i].input[j] != new.and_terms[i].input[j] {
+                out.push(format!("FB{} AND term {} {} input {}", fb + 1, i,
+                    if new.and_terms[i].input[j] {"gained"} else {"lost"}, j));
+            }
+            if old.and_terms[i].input_b[j] != new.and_terms[i].input_b[j] {
+                out.push(format!("FB{} AND term {} {} ~input {}", fb + 1, i,
+                    if new.and_terms[i].input_b[j] {"gained"} else {"lost"}, j));
+            }
+        }
+    }
+
+    for i in 0..MCS_PER_FB {
+        for j in 0..ANDTERMS_PER_FB {
+            if old.or_terms[i].input[j] != new.or_terms[i].input[j] {
+                out.push(format!("FB{} OR term {} {} AND term {}", fb + 1, i,
+                    if new.or_terms[i].input[j] {"gained"} else {"lost"}, j));
+            }
+        }
+    }
+}
+
+/// Compares two bitstreams and returns one line for every setting that differs between them, such as
+/// "FB3_7 clk_src GCK0 → PTC" or "FB2 AND term 17 gained ~input 5". If the two bitstreams are for different
+/// device types, only that difference is reported.
+pub fn diff_bitstreams(old: &XC2Bitstream, new: &XC2Bitstream) -> Vec<String> {
+    let mut out = Vec::new();
+
+    let device = old.bits.device_type();
+    if device != new.bits.device_type() {
+        out.push(format!("device type {} → {}", device, new.bits.device_type()));
+        return out;
+    }
+
+    if old.speed_grade != new.speed_grade {
+        out.push(format!("device speed grade {} → {}", old.speed_grade, new.speed_grade));
+    }
+    if old.package != new.package {
+        out.push(format!("device package {} → {}", old.package, new.package));
+    }
+
+    for (&(ref name, old_val), &(_, new_val)) in bank_settings(&old.bits).iter().zip(bank_settings(&new.bits).iter()) {
+        diff_field(&mut out, "I/O banks", name, old_val, new_val);
+    }
+
+    if let (Some(old_clock_div), Some(new_clock_div)) = (old.bits.get_clock_div(), new.bits.get_clock_div()) {
+        diff_field(&mut out, "clock divider", "div_ratio", old_clock_div.div_ratio, new_clock_div.div_ratio);
+        diff_field(&mut out, "clock divider", "delay", old_clock_div.delay, new_clock_div.delay);
+        diff_field(&mut out, "clock divider", "enabled", old_clock_div.enabled, new_clock_div.enabled);
+    }
+
+    diff_global_nets(&mut out, old.bits.get_global_nets(), new.bits.get_global_nets());
+
+    // IOBs
+    match (&old.bits, &new.bits) {
+        (&XC2BitstreamBits::XC2C32 {iobs: ref old_iobs, inpin: ref old_inpin, ..},
+         &XC2BitstreamBits::XC2C32 {iobs: ref new_iobs, inpin: ref new_inpin, ..}) |
+        (&XC2BitstreamBits::XC2C32A {iobs: ref old_iobs, inpin: ref old_inpin, ..},
+         &XC2BitstreamBits::XC2C32A {iobs: ref new_iobs, inpin: ref new_inpin, ..}) => {
+            for i in 0..device.num_iobs() {
+                diff_small_iob(&mut out, device, i, &old_iobs[i], &new_iobs[i]);
+            }
+            diff_field(&mut out, "input-only pin", "schmitt_trigger",
+                old_inpin.schmitt_trigger, new_inpin.schmitt_trigger);
+            diff_field(&mut out, "input-only pin", "termination_enabled",
+                old_inpin.termination_enabled, new_inpin.termination_enabled);
+        },
+        (&XC2BitstreamBits::XC2C64 {iobs: ref old_iobs, ..}, &XC2BitstreamBits::XC2C64 {iobs: ref new_iobs, ..}) |
+        (&XC2BitstreamBits::XC2C64A {iobs: ref old_iobs, ..}, &XC2BitstreamBits::XC2C64A {iobs: ref new_iobs, ..}) => {
+            for i in 0..device.num_iobs() {
+                diff_small_iob(&mut out, device, i, &old_iobs[i], &new_iobs[i]);
+            }
+        },
+        (&XC2BitstreamBits::XC2C128 {iobs: ref old_iobs, ..}, &XC2BitstreamBits::XC2C128 {iobs: ref new_iobs, ..}) => {
+            for i in 0..device.num_iobs() {
+                diff_large_iob(&mut out, device, i, &old_iobs[i], &new_iobs[i]);
+            }
+        },
+        (&XC2BitstreamBits::XC2C256 {iobs: ref old_iobs, ..}, &XC2BitstreamBits::XC2C256 {iobs: ref new_iobs, ..}) => {
+            for i in 0..device.num_iobs() {
+                diff_large_iob(&mut out, device, i, &old_iobs[i], &new_iobs[i]);
+            }
+        },
+        (&XC2BitstreamBits::XC2C384 {iobs: ref old_iobs, ..}, &XC2BitstreamBits::XC2C384 {iobs: ref new_iobs, ..}) => {
+            for i in 0..device.num_iobs() {
+                diff_large_iob(&mut out, device, i, &old_iobs[i], &new_iobs[i]);
+            }
+        },
+        (&XC2BitstreamBits::XC2C512 {iobs: ref old_iobs, ..}, &XC2BitstreamBits::XC2C512 {iobs: ref new_iobs, ..}) => {
+            for i in 0..device.num_iobs() {
+                diff_large_iob(&mut out, device, i, &old_iobs[i], &new_iobs[i]);
+            }
+        },
+        _ => unreachable!(),
+    }
+
+    // FBs
+    for i in 0..device.num_fbs() {
+        diff_fb(&mut out, device, i, &old.bits.get_fb()[i], &new.bits.get_fb()[i]);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blank() -> XC2Bitstream {
+        XC2Bitstream::blank_bitstream(XC2Device::XC2C64A, XC2Speed::Speed5, XC2Package::VQ44).unwrap()
+    }
+
+    #[test]
+    fn diff_identical() {
+        assert_eq!(diff_bitstreams(&blank(), &blank()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn diff_different_devices() {
+        let other = XC2Bitstream::blank_bitstream(XC2Device::XC2C32A, XC2Speed::Speed6, XC2Package::VQ44).unwrap();
+        assert_eq!(diff_bitstreams(&blank(), &other), vec!["device type XC2C64A → XC2C32A"]);
+    }
+
+    #[test]
+    fn diff_settings() {
+        let old = blank();
+        let mut new = blank();
+        {
+            let fb = new.bits.get_fb_mut();
+            fb[2].ffs[6].clk_src = XC2MCRegClkSrc::PTC;
+            fb[1].zia_bits[12].selected = XC2ZIAInput::Zero;
+            fb[1].and_terms[17].input_b[5] = true;
+            fb[0].or_terms[3].input[17] = true;
+        }
+        new.bits.get_global_nets_mut().gck_enable[1] = true;
+
+        assert_eq!(diff_bitstreams(&old, &new), vec![
+            "global nets gck_enable[1] false → true",
+            "FB1 OR term 3 gained AND term 17",
+            "FB2 ZIA row 12: 1 → 0",
+            "FB2 AND term 17 gained ~input 5",
+            "FB3_7 clk_src GCK0 → PTC",
+        ]);
+    }
+}
@@ -0,0 +1,625 @@
+/*
+Copyright (c) 2017, Robert Ou <rqou@robertou.com> and contributors
+All rights reserved.
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice,
+   this list of conditions and the following disclaimer.
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+//! Contains a design rule checker that finds illegal or suspicious settings in a bitstream, and a lint that checks the
+//! I/O bank settings against the I/O standards requested in a UCF file.
+
+use std::fmt;
+
+use *;
+
+/// How serious a diagnostic is
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum XC2DiagnosticSeverity {
+    /// The setting is illegal and the device may not work
+    Error,
+    /// The setting is legal but probably not what was intended, or its behavior is not known
+    Warning,
+}
+
+/// A problem found by `validate_bitstream`. Function block, macrocell, and I/O pin numbers are zero-based and use the
+/// internal numbering scheme.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum XC2Diagnostic {
+    /// `ff_in_ibuf` is set on a macrocell that does not have an I/O pin
+    BuriedFFInIBuf {
+        fb: u32,
+        mc: u32,
+    },
+    /// `is_ddr` is set on a register in latch mode, which has unknown behavior
+    DDRLatch {
+        fb: u32,
+        mc: u32,
+    },
+    /// An output is controlled by a GTS net that is not enabled, so it is always enabled
+    GTSNotEnabled {
+        iob: u32,
+        gts: u32,
+    },
+    /// A register that is used is clocked by a GCK net that is not enabled
+    GCKNotEnabled {
+        fb: u32,
+        mc: u32,
+        gck: u32,
+    },
+    /// A register that is used is set or reset by the GSR net, which is not enabled
+    GSRNotEnabled {
+        fb: u32,
+        mc: u32,
+    },
+    /// Product term C is used as the XOR input and also as the clock or clock enable of a register that is used
+    PTCShared {
+        fb: u32,
+        mc: u32,
+    },
+    /// An input uses VREF or serves as VREF but `use_vref` is not set. Bitstreams from the Xilinx tools have been seen
+    /// with this, so it is only a warning.
+    VrefNotEnabled {
+        iob: u32,
+    },
+    /// An input uses DataGate but DataGate is not enabled
+    DataGateNotEnabled {
+        iob: u32,
+    },
+    /// An I/O bank with pins that are used has different input and output voltage ranges
+    BankVoltageMismatch {
+        bank: u32,
+    },
+    /// The legacy voltage range settings of the XC2C32A or XC2C64A are set to high
+    LegacyVoltageSet,
+    /// An input uses VREF but no pin in its I/O bank serves as VREF
+    NoVrefPin {
+        iob: u32,
+    },
+    /// The voltage range of the I/O bank does not match the requested I/O standard
+    ///
+    /// `bank` is `None` if the bank of the pin is not known but no bank of the device matches.
+    IOStandardVoltage {
+        iob: u32,
+        bank: Option<u32>,
+        iostandard: XC2IOStandard,
+    },
+    /// The requested I/O standard uses VREF but the input does not, or the other way around
+    IOStandardVref {
+        iob: u32,
+        iostandard: XC2IOStandard,
+    },
+    /// Two pins in the same I/O bank request I/O standards that need a different VCCIO
+    IOStandardConflict {
+        iob: u32,
+        iostandard: XC2IOStandard,
+        other_iob: u32,
+        other_iostandard: XC2IOStandard,
+        bank: u32,
+    },
+    /// The I/O bank of a pin with a requested I/O standard is not known and the banks of the device do not all agree,
+    /// so the I/O standard was not checked
+    IOStandardUnchecked {
+        iob: u32,
+        iostandard: XC2IOStandard,
+    },
+}
+
+impl XC2Diagnostic {
+    /// Returns how serious this diagnostic is
+    pub fn severity(&self) -> XC2DiagnosticSeverity {
+        match *self {
+            XC2Diagnostic::BuriedFFInIBuf{..} |
+            XC2Diagnostic::NoVrefPin{..} |
+            XC2Diagnostic::IOStandardVoltage{..} |
+            XC2Diagnostic::IOStandardVref{..} |
+            XC2Diagnostic::IOStandardConflict{..} => XC2DiagnosticSeverity::Error,
+            _ => XC2DiagnosticSeverity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for XC2Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            XC2Diagnostic::BuriedFFInIBuf{fb, mc} =>
+                write!(f, "FB{}_{}: register input comes from an I/O pin but the macrocell is buried", fb + 1, mc + 1),
+            XC2Diagnostic::DDRLatch{fb, mc} =>
+                write!(f, "FB{}_{}: latch is set to DDR mode, which has unknown behavior", fb + 1, mc + 1),
+            XC2Diagnostic::GTSNotEnabled{iob, gts} =>
+                write!(f, "I/O pin {}: output is controlled by GTS{}, which is not enabled", iob, gts),
+            XC2Diagnostic::GCKNotEnabled{fb, mc, gck} =>
+                write!(f, "FB{}_{}: register is clocked by GCK{}, which is not enabled", fb + 1, mc + 1, gck),
+            XC2Diagnostic::GSRNotEnabled{fb, mc} =>
+                write!(f, "FB{}_{}: register uses GSR, which is not enabled", fb + 1, mc + 1),
+            XC2Diagnostic::PTCShared{fb, mc} =>
+                write!(f, "FB{}_{}: PTC is used as the XOR input and also by the register", fb + 1, mc + 1),
+            XC2Diagnostic::VrefNotEnabled{iob} =>
+                write!(f, "I/O pin {}: input uses VREF, which is not enabled", iob),
+            XC2Diagnostic::DataGateNotEnabled{iob} =>
+                write!(f, "I/O pin {}: input uses DataGate, which is not enabled", iob),
+            XC2Diagnostic::BankVoltageMismatch{bank} =>
+                write!(f, "bank {}: input and output voltage ranges are different", bank),
+            XC2Diagnostic::LegacyVoltageSet =>
+                write!(f, "legacy voltage range is set to high"),
+            XC2Diagnostic::NoVrefPin{iob} =>
+                write!(f, "I/O pin {}: input uses VREF but no pin in its bank serves as VREF", iob),
+            XC2Diagnostic::IOStandardVoltage{iob, bank, iostandard} => {
+                let range = if iostandard.is_high_voltage() {"high"} else {"low"};
+                match bank {
+                    Some(bank) => write!(f, "I/O pin {}: {} needs bank {} to use the {} voltage range", iob,
+                        iostandard, bank, range),
+                    None => write!(f, "I/O pin {}: {} needs its bank to use the {} voltage range", iob, iostandard,
+                        range),
+                }
+            },
+            XC2Diagnostic::IOStandardVref{iob, iostandard} =>
+                write!(f, "I/O pin {}: {} {} VREF but the input is not set up for this", iob, iostandard,
+                    if iostandard.uses_vref() {"uses"} else {"does not use"}),
+            XC2Diagnostic::IOStandardConflict{iob, iostandard, other_iob, other_iostandard, bank} =>
+                write!(f, "I/O pin {}: {} cannot be used in bank {} together with {} on I/O pin {}", iob, iostandard,
+                    bank, other_iostandard, other_iob),
+            XC2Diagnostic::IOStandardUnchecked{iob, iostandard} =>
+                write!(f, "I/O pin {}: the I/O bank is not known, so {} was not checked", iob, iostandard),
+        }
+    }
+}
+
+/// Returns the `use_vref` and `data_gate` settings, which are always `false` on devices that do not have them
+fn vref_data_gate(bits: &XC2BitstreamBits) -> (bool, bool) {
+    match bits {
+        &XC2BitstreamBits::XC2C128{use_vref, data_gate, ..} |
+        &XC2BitstreamBits::XC2C256{use_vref, data_gate, ..} |
+        &XC2BitstreamBits::XC2C384{use_vref, data_gate, ..} |
+        &XC2BitstreamBits::XC2C512{use_vref, data_gate, ..} => (use_vref, data_gate),
+        _ => (false, false),
+    }
+}
+
+/// Returns whether the input and the output of an I/O pin are used
+fn iob_in_out_used(bits: &XC2BitstreamBits, iob: u32) -> (bool, bool) {
+    let (zia_mode, obuf_mode) = match bits.get_small_iob(iob as usize) {
+        Some(x) => (x.zia_mode, x.obuf_mode),
+        None => {
+            let x = bits.get_large_iob(iob as usize).unwrap();
+            (x.zia_mode, x.obuf_mode)
+        }
+    };
+    let ff_in_ibuf = match iob_num_to_fb_ff_num(bits.device_type(), iob) {
+        Some((fb, ff)) => bits.get_fb()[fb as usize].ffs[ff as usize].ff_in_ibuf,
+        None => false,
+    };
+    (zia_mode == XC2IOBZIAMode::PAD || ff_in_ibuf, obuf_mode != XC2IOBOBufMode::Disabled)
+}
+
+/// Checks a bitstream for illegal or suspicious settings and returns everything that was found
+pub fn validate_bitstream(bitstream: &XC2Bitstream) -> Vec<XC2Diagnostic> {
+    let bits = &bitstream.bits;
+    let device = bits.device_type();
+    let global_nets = bits.get_global_nets();
+    let (use_vref, data_gate) = vref_data_gate(bits);
+
+    let mut ret = Vec::new();
+
+    for fb in 0..device.num_fbs() {
+        for mc in 0..MCS_PER_FB {
+            let ff = &bits.get_fb()[fb].ffs[mc];
+            let (fb, mc) = (fb as u32, mc as u32);
+            let iob = fb_ff_num_to_iob_num(device, fb, mc);
+
+            if ff.ff_in_ibuf && iob.is_none() {
+                ret.push(XC2Diagnostic::BuriedFFInIBuf{fb, mc});
+            }
+            if ff.is_ddr && ff.reg_mode == XC2MCRegMode::LATCH {
+                ret.push(XC2Diagnostic::DDRLatch{fb, mc});
+            }
+
+            // The rest only matters if the output of the register goes somewhere
+            let reg_used = ff.fb_mode == XC2MCFeedbackMode::REG || match iob {
+                Some(iob) => {
+                    let (zia_mode, obuf_uses_ff, obuf_mode) = match bits.get_small_iob(iob as usize) {
+                        Some(x) => (x.zia_mode, x.obuf_uses_ff, x.obuf_mode),
+                        None => {
+                            let x = bits.get_large_iob(iob as usize).unwrap();
+                            (x.zia_mode, x.obuf_uses_ff, x.obuf_mode)
+                        }
+                    };
+                    zia_mode == XC2IOBZIAMode::REG || (obuf_uses_ff && obuf_mode != XC2IOBOBufMode::Disabled &&
+                        obuf_mode != XC2IOBOBufMode::CGND)
+                },
+                None => false,
+            };
+            if !reg_used {
+                continue;
+            }
+
+            let gck = match ff.clk_src {
+                XC2MCRegClkSrc::GCK0 => Some(0),
+                XC2MCRegClkSrc::GCK1 => Some(1),
+                XC2MCRegClkSrc::GCK2 => Some(2),
+                _ => None,
+            };
+            if let Some(gck) = gck {
+                if !global_nets.gck_enable[gck as usize] {
+                    ret.push(XC2Diagnostic::GCKNotEnabled{fb, mc, gck});
+                }
+            }
+            if (ff.s_src == XC2MCRegSetSrc::GSR || ff.r_src == XC2MCRegResetSrc::GSR) && !global_nets.gsr_enable {
+                ret.push(XC2Diagnostic::GSRNotEnabled{fb, mc});
+            }
+            let xor_uses_ptc = ff.xor_mode == XC2MCXorMode::PTC || ff.xor_mode == XC2MCXorMode::PTCB;
+            let reg_uses_ptc = ff.clk_src == XC2MCRegClkSrc::PTC || ff.reg_mode == XC2MCRegMode::DFFCE;
+            if xor_uses_ptc && reg_uses_ptc {
+                ret.push(XC2Diagnostic::PTCShared{fb, mc});
+            }
+        }
+    }
+
+    for i in 0..device.num_iobs() {
+        let iob = i as u32;
+        let obuf_mode = match bits.get_small_iob(i) {
+            Some(x) => x.obuf_mode,
+            None => {
+                let x = bits.get_large_iob(i).unwrap();
+                if (x.ibuf_mode == XC2IOBIbufMode::UsesVref || x.ibuf_mode == XC2IOBIbufMode::IsVref) && !use_vref {
+                    ret.push(XC2Diagnostic::VrefNotEnabled{iob});
+                }
+                if x.uses_data_gate && !data_gate {
+                    ret.push(XC2Diagnostic::DataGateNotEnabled{iob});
+                }
+                x.obuf_mode
+            }
+        };
+
+        let gts = match obuf_mode {
+            XC2IOBOBufMode::TriStateGTS0 => Some(0),
+            XC2IOBOBufMode::TriStateGTS1 => Some(1),
+            XC2IOBOBufMode::TriStateGTS2 => Some(2),
+            XC2IOBOBufMode::TriStateGTS3 => Some(3),
+            _ => None,
+        };
+        if let Some(gts) = gts {
+            if !global_nets.gts_enable[gts as usize] {
+                ret.push(XC2Diagnostic::GTSNotEnabled{iob, gts});
+            }
+        }
+    }
+
+    ret
+}
+
+/// Checks the I/O bank settings of a bitstream. Every used I/O pin is checked against the voltage ranges and VREF pins
+/// of its bank, and every net in `nets` with both a `LOC` and an `IOSTANDARD` is checked against the settings of its
+/// pin and bank. Nets whose `LOC` is not a known I/O pin are skipped because `check_ucf` already reports them. `nets`
+/// can be empty to only check the bitstream itself.
+pub fn check_io_banks(bitstream: &XC2Bitstream, nets: &[XC2UCFNet]) -> Vec<XC2Diagnostic> {
+    let bits = &bitstream.bits;
+    let device = bits.device_type();
+    let (use_vref, _) = vref_data_gate(bits);
+    let ibuf_mode = |iob: u32| bits.get_large_iob(iob as usize).map(|x| x.ibuf_mode);
+
+    let mut ret = Vec::new();
+
+    // If the banks of the pins are not known, assume that every bank is used and that a VREF pin anywhere can serve
+    // every bank
+    let banks_known = iob_to_bank(device, 0).is_some();
+    let mut bank_used = vec![!banks_known; num_banks(device)];
+    let mut bank_has_vref = vec![false; num_banks(device)];
+    let mut any_vref = false;
+    for iob in 0..device.num_iobs() as u32 {
+        let (input_used, output_used) = iob_in_out_used(bits, iob);
+        let is_vref = ibuf_mode(iob) == Some(XC2IOBIbufMode::IsVref);
+        any_vref |= is_vref;
+        if let Some(bank) = iob_to_bank(device, iob) {
+            bank_used[bank as usize] |= input_used || output_used || is_vref;
+            bank_has_vref[bank as usize] |= is_vref;
+        }
+    }
+
+    for (bank, &used) in bank_used.iter().enumerate() {
+        let (ivoltage, ovoltage) = bits.get_bank_voltage(bank).unwrap();
+        if used && ivoltage != ovoltage {
+            ret.push(XC2Diagnostic::BankVoltageMismatch{bank: bank as u32});
+        }
+    }
+    match bits {
+        &XC2BitstreamBits::XC2C32A{legacy_ivoltage, legacy_ovoltage, ..} |
+        &XC2BitstreamBits::XC2C64A{legacy_ivoltage, legacy_ovoltage, ..} if legacy_ivoltage || legacy_ovoltage => {
+            ret.push(XC2Diagnostic::LegacyVoltageSet);
+        },
+        _ => {},
+    }
+
+    for iob in 0..device.num_iobs() as u32 {
+        let has_vref = match iob_to_bank(device, iob) {
+            Some(bank) => bank_has_vref[bank as usize],
+            None => any_vref,
+        };
+        if ibuf_mode(iob) == Some(XC2IOBIbufMode::UsesVref) && iob_in_out_used(bits, iob).0 && !has_vref {
+            ret.push(XC2Diagnostic::NoVrefPin{iob});
+        }
+    }
+
+    // Pins with a requested I/O standard and a known bank, for finding conflicts
+    let mut requested: Vec<(u32, u32, XC2IOStandard)> = Vec::new();
+    for net in nets {
+        let iostandard = match net.iostandard {
+            Some(x) => x,
+            None => continue,
+        };
+        let iob = match net.loc.as_ref().and_then(|loc| ucf_loc_to_iob(device, bitstream.package, loc)) {
+            Some(x) => x,
+            None => continue,
+        };
+
+        let vref_ok = if iostandard.uses_vref() {
+            ibuf_mode(iob) == Some(XC2IOBIbufMode::UsesVref) && use_vref
+        } else {
+            ibuf_mode(iob) != Some(XC2IOBIbufMode::UsesVref) && ibuf_mode(iob) != Some(XC2IOBIbufMode::IsVref)
+        };
+        if !vref_ok {
+            ret.push(XC2Diagnostic::IOStandardVref{iob, iostandard});
+        }
+
+        // Only check the directions that are used, or both if the pin is not used at all
+        let (input_used, output_used) = iob_in_out_used(bits, iob);
+        let high = iostandard.is_high_voltage();
+        let bank_ok = |bank: usize| {
+            let (ivoltage, ovoltage) = bits.get_bank_voltage(bank).unwrap();
+            !(((input_used || !output_used) && ivoltage != high) || ((output_used || !input_used) && ovoltage != high))
+        };
+
+        let bank = match iob_to_bank(device, iob) {
+            Some(x) => x,
+            None => {
+                // The pin is in one of the banks, so it can still be checked if all of them agree. Conflicts with
+                // other pins can only be found once the bank is known.
+                let num_ok = (0..num_banks(device)).filter(|&bank| bank_ok(bank)).count();
+                if num_ok == 0 {
+                    ret.push(XC2Diagnostic::IOStandardVoltage{iob, bank: None, iostandard});
+                } else if num_ok != num_banks(device) {
+                    ret.push(XC2Diagnostic::IOStandardUnchecked{iob, iostandard});
+                }
+                continue;
+            }
+        };
+
+        if !bank_ok(bank as usize) {
+            ret.push(XC2Diagnostic::IOStandardVoltage{iob, bank: Some(bank), iostandard});
+        }
+
+        let conflict = requested.iter()
+            .find(|&&(_, other_bank, other)| other_bank == bank && other.vccio_mv() != iostandard.vccio_mv());
+        if let Some(&(other_iob, _, other_iostandard)) = conflict {
+            ret.push(XC2Diagnostic::IOStandardConflict{iob, iostandard, other_iob, other_iostandard, bank});
+        }
+        requested.push((iob, bank, iostandard));
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blank(device: XC2Device, package: XC2Package) -> XC2Bitstream {
+        let speed = if device == XC2Device::XC2C32A {XC2Speed::Speed6} else {XC2Speed::Speed7};
+        XC2Bitstream::blank_bitstream(device, speed, package).unwrap()
+    }
+
+    #[test]
+    fn drc_blank() {
+        assert!(blank(XC2Device::XC2C32A, XC2Package::VQ44).validate().is_empty());
+        assert!(blank(XC2Device::XC2C256, XC2Package::TQ144).validate().is_empty());
+    }
+
+    #[test]
+    fn drc_macrocells() {
+        let mut bitstream = blank(XC2Device::XC2C32A, XC2Package::VQ44);
+        {
+            let ff = &mut bitstream.bits.get_fb_mut()[0].ffs[1];
+            ff.reg_mode = XC2MCRegMode::LATCH;
+            ff.is_ddr = true;
+        }
+        {
+            // Used through the ZIA
+            let ff = &mut bitstream.bits.get_fb_mut()[1].ffs[2];
+            ff.fb_mode = XC2MCFeedbackMode::REG;
+            ff.clk_src = XC2MCRegClkSrc::GCK1;
+            ff.r_src = XC2MCRegResetSrc::GSR;
+        }
+        {
+            // PTC as both the clock and the XOR input
+            let ff = &mut bitstream.bits.get_fb_mut()[1].ffs[3];
+            ff.fb_mode = XC2MCFeedbackMode::REG;
+            ff.clk_src = XC2MCRegClkSrc::PTC;
+            ff.xor_mode = XC2MCXorMode::PTCB;
+        }
+        {
+            // Not used, so nothing is reported
+            let ff = &mut bitstream.bits.get_fb_mut()[1].ffs[4];
+            ff.clk_src = XC2MCRegClkSrc::GCK2;
+            ff.s_src = XC2MCRegSetSrc::GSR;
+        }
+
+        let diagnostics = bitstream.validate();
+        assert_eq!(diagnostics, vec![
+            XC2Diagnostic::DDRLatch{fb: 0, mc: 1},
+            XC2Diagnostic::GCKNotEnabled{fb: 1, mc: 2, gck: 1},
+            XC2Diagnostic::GSRNotEnabled{fb: 1, mc: 2},
+            XC2Diagnostic::PTCShared{fb: 1, mc: 3},
+        ]);
+        assert!(diagnostics.iter().all(|x| x.severity() == XC2DiagnosticSeverity::Warning));
+
+        bitstream.bits.get_global_nets_mut().gck_enable[1] = true;
+        bitstream.bits.get_global_nets_mut().gsr_enable = true;
+        assert_eq!(bitstream.validate().len(), 2);
+    }
+
+    #[test]
+    fn drc_buried_ff_in_ibuf() {
+        let mut bitstream = blank(XC2Device::XC2C128, XC2Package::VQ100);
+        // FB1_7 to FB1_10 are buried in the XC2C128
+        bitstream.bits.get_fb_mut()[0].ffs[7].ff_in_ibuf = true;
+        bitstream.bits.get_fb_mut()[0].ffs[0].ff_in_ibuf = true;
+        let diagnostics = bitstream.validate();
+        assert_eq!(diagnostics, vec![XC2Diagnostic::BuriedFFInIBuf{fb: 0, mc: 7}]);
+        assert_eq!(diagnostics[0].severity(), XC2DiagnosticSeverity::Error);
+        assert_eq!(diagnostics[0].to_string(),
+            "FB1_8: register input comes from an I/O pin but the macrocell is buried");
+    }
+
+    #[test]
+    fn drc_iobs() {
+        let mut bitstream = blank(XC2Device::XC2C128, XC2Package::VQ100);
+        match bitstream.bits {
+            XC2BitstreamBits::XC2C128{ref mut iobs, ..} => {
+                iobs[0].obuf_mode = XC2IOBOBufMode::TriStateGTS2;
+                iobs[1].ibuf_mode = XC2IOBIbufMode::IsVref;
+                iobs[2].ibuf_mode = XC2IOBIbufMode::UsesVref;
+                iobs[3].uses_data_gate = true;
+            },
+            _ => unreachable!(),
+        }
+        assert_eq!(bitstream.validate(), vec![
+            XC2Diagnostic::GTSNotEnabled{iob: 0, gts: 2},
+            XC2Diagnostic::VrefNotEnabled{iob: 1},
+            XC2Diagnostic::VrefNotEnabled{iob: 2},
+            XC2Diagnostic::DataGateNotEnabled{iob: 3},
+        ]);
+
+        bitstream.bits.get_global_nets_mut().gts_enable[2] = true;
+        match bitstream.bits {
+            XC2BitstreamBits::XC2C128{ref mut use_vref, ref mut data_gate, ..} => {
+                *use_vref = true;
+                *data_gate = true;
+            },
+            _ => unreachable!(),
+        }
+        assert!(bitstream.validate().is_empty());
+    }
+
+    #[test]
+    fn drc_io_bank_settings() {
+        let mut bitstream = blank(XC2Device::XC2C32A, XC2Package::VQ44);
+        bitstream.bits.get_small_iob_mut(0).unwrap().obuf_mode = XC2IOBOBufMode::PushPull;
+        *bitstream.bits.get_bank_voltage_mut(1).unwrap().0 = true;
+        // Bank 0 has no used pins
+        *bitstream.bits.get_bank_voltage_mut(0).unwrap().0 = true;
+        match bitstream.bits {
+            XC2BitstreamBits::XC2C32A{ref mut legacy_ovoltage, ..} => *legacy_ovoltage = true,
+            _ => unreachable!(),
+        }
+        let diagnostics = check_io_banks(&bitstream, &[]);
+        assert_eq!(diagnostics, vec![XC2Diagnostic::BankVoltageMismatch{bank: 1}, XC2Diagnostic::LegacyVoltageSet]);
+        assert!(diagnostics.iter().all(|x| x.severity() == XC2DiagnosticSeverity::Warning));
+    }
+
+    #[test]
+    fn drc_io_standards() {
+        let mut bitstream = blank(XC2Device::XC2C32A, XC2Package::VQ44);
+        bitstream.bits.get_small_iob_mut(0).unwrap().obuf_mode = XC2IOBOBufMode::PushPull;
+        {
+            let (ivoltage, ovoltage) = bitstream.bits.get_bank_voltage_mut(1).unwrap();
+            *ivoltage = true;
+            *ovoltage = true;
+        }
+        let nets = read_ucf("NET \"a\" LOC = P38 | IOSTANDARD = LVCMOS33;\n\
+            NET \"b\" LOC = P37 | IOSTANDARD = LVCMOS25;\n\
+            NET \"c\" LOC = P39 | IOSTANDARD = LVCMOS33;\n\
+            NET \"d\" LOC = P40 | IOSTANDARD = HSTL_I;\n\
+            NET \"e\" IOSTANDARD = LVCMOS18;\n\
+            NET \"f\" LOC = P99 | IOSTANDARD = LVCMOS18;").unwrap();
+        let diagnostics = check_io_banks(&bitstream, &nets);
+        assert_eq!(diagnostics, vec![
+            XC2Diagnostic::IOStandardConflict{iob: 1, iostandard: XC2IOStandard::LVCMOS25, other_iob: 0,
+                other_iostandard: XC2IOStandard::LVCMOS33, bank: 1},
+            XC2Diagnostic::IOStandardVoltage{iob: 16, bank: Some(0), iostandard: XC2IOStandard::LVCMOS33},
+            XC2Diagnostic::IOStandardVref{iob: 17, iostandard: XC2IOStandard::HSTLI},
+            XC2Diagnostic::IOStandardConflict{iob: 17, iostandard: XC2IOStandard::HSTLI, other_iob: 16,
+                other_iostandard: XC2IOStandard::LVCMOS33, bank: 0},
+        ]);
+        assert_eq!(diagnostics[1].to_string(), "I/O pin 16: LVCMOS33 needs bank 0 to use the high voltage range");
+
+        // Only the input voltage range matters for a pin that is only an input
+        bitstream.bits.get_small_iob_mut(16).unwrap().zia_mode = XC2IOBZIAMode::PAD;
+        *bitstream.bits.get_bank_voltage_mut(0).unwrap().0 = true;
+        let nets = read_ucf("NET \"c\" LOC = P39 | IOSTANDARD = LVCMOS33;").unwrap();
+        assert_eq!(check_io_banks(&bitstream, &nets), vec![XC2Diagnostic::BankVoltageMismatch{bank: 0}]);
+    }
+
+    #[test]
+    fn drc_io_vref() {
+        let mut bitstream = blank(XC2Device::XC2C128, XC2Package::VQ100);
+        {
+            let iob = bitstream.bits.get_large_iob_mut(2).unwrap();
+            iob.ibuf_mode = XC2IOBIbufMode::UsesVref;
+            iob.zia_mode = XC2IOBZIAMode::PAD;
+        }
+        match bitstream.bits {
+            XC2BitstreamBits::XC2C128{ref mut use_vref, ..} => *use_vref = true,
+            _ => unreachable!(),
+        }
+        for bank in 0..2 {
+            let (ivoltage, ovoltage) = bitstream.bits.get_bank_voltage_mut(bank).unwrap();
+            *ivoltage = true;
+            *ovoltage = true;
+        }
+        let nets = read_ucf("NET \"a\" LOC = FB1_3 | IOSTANDARD = SSTL2_I;").unwrap();
+        assert_eq!(check_io_banks(&bitstream, &nets), vec![XC2Diagnostic::NoVrefPin{iob: 2}]);
+
+        bitstream.bits.get_large_iob_mut(5).unwrap().ibuf_mode = XC2IOBIbufMode::IsVref;
+        assert_eq!(check_io_banks(&bitstream, &nets), vec![]);
+    }
+
+    #[test]
+    fn drc_io_unknown_banks() {
+        // The banks of the XC2C64A pins are not known, but the I/O standards can still be checked if both banks agree
+        let mut bitstream = blank(XC2Device::XC2C64A, XC2Package::VQ100);
+        let nets = read_ucf("NET \"a\" LOC = FB1_1 | IOSTANDARD = LVCMOS18;\n\
+            NET \"b\" LOC = FB4_16 | IOSTANDARD = LVCMOS18;").unwrap();
+        assert_eq!(check_io_banks(&bitstream, &nets), vec![]);
+
+        let nets = read_ucf("NET \"a\" LOC = FB1_1 | IOSTANDARD = LVCMOS33;").unwrap();
+        let diagnostics = check_io_banks(&bitstream, &nets);
+        assert_eq!(diagnostics, vec![
+            XC2Diagnostic::IOStandardVoltage{iob: 0, bank: None, iostandard: XC2IOStandard::LVCMOS33},
+        ]);
+        assert_eq!(diagnostics[0].to_string(), "I/O pin 0: LVCMOS33 needs its bank to use the high voltage range");
+
+        for bank in 0..2 {
+            let (ivoltage, ovoltage) = bitstream.bits.get_bank_voltage_mut(bank).unwrap();
+            *ivoltage = true;
+            *ovoltage = true;
+        }
+        assert_eq!(check_io_banks(&bitstream, &nets), vec![]);
+
+        // Once the banks differ, it depends on which bank the pin is in
+        {
+            let (ivoltage, ovoltage) = bitstream.bits.get_bank_voltage_mut(1).unwrap();
+            *ivoltage = false;
+            *ovoltage = false;
+        }
+        assert_eq!(check_io_banks(&bitstream, &nets), vec![
+            XC2Diagnostic::IOStandardUnchecked{iob: 0, iostandard: XC2IOStandard::LVCMOS33},
+        ]);
+    }
+}
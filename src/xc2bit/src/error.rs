@@ -0,0 +1,303 @@
+/*
+Copyright (c) 2017, Robert Ou <rqou@robertou.com> and contributors
+All rights reserved.
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice,
+   this list of conditions and the following disclaimer.
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+//! Contains the error type returned by the fallible functions of this crate
+
+use std::error;
+use std::fmt;
+use std::io;
+
+use *;
+
+/// Errors that can occur while reading, writing, fitting, or simulating a bitstream. Function block and I/O pin numbers
+/// are zero-based and use the internal numbering scheme. Fuse indices are logical fuse indices (the numbering used in
+/// .jed files), even when the bitstream was read from some other format.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum XC2BitError {
+    /// A .jed file could not be parsed. `line` and `column` are one-based and point to the start of the field or other
+    /// part of the file that could not be parsed.
+    JedParseError {
+        msg: &'static str,
+        line: usize,
+        column: usize,
+    },
+    /// The device name is missing
+    MissingDeviceName,
+    /// The device name is not a valid device, speed grade, and package
+    BadDeviceName(String),
+    /// The device is not offered in this speed grade and package
+    BadPartCombination(XC2Device, XC2Speed, XC2Package),
+    /// The number of fuses does not match the device
+    WrongFuseCount {
+        expected: usize,
+        found: usize,
+    },
+    /// The dimensions (width, height) of a physical fuse array do not match the device
+    WrongFuseArrayDims {
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+    /// The fuses of a ZIA row do not select any of the inputs of that row. `fuse_idx` is the first fuse of the row.
+    UnknownZIAChoice {
+        fb: u32,
+        row: u32,
+        fuse_idx: usize,
+    },
+    /// The output enable fuses of an I/O pin do not select any known mode. `fuse_idx` is the first output enable fuse.
+    UnknownOEMode {
+        iob: u32,
+        fuse_idx: usize,
+    },
+    /// A ZIA row is set to an input that cannot be selected by that row (or that does not exist on the device), so the
+    /// bitstream cannot be written
+    InvalidZIAInput {
+        fb: u32,
+        row: u32,
+        input: XC2ZIAInput,
+    },
+    /// The clock divider is enabled, but the crbit format cannot store the clock divider settings
+    ClockDivNotInCrbit,
+    /// A UCF file could not be parsed. `line` is one-based.
+    UCFParseError {
+        msg: String,
+        line: usize,
+    },
+    /// The UCF constraints on a net cannot be used with this device and package
+    UCFBadConstraint {
+        net: String,
+        msg: String,
+    },
+    /// Two nets in a UCF file need settings that cannot both be used
+    UCFConflict {
+        net_a: String,
+        setting_a: String,
+        net_b: String,
+        setting_b: String,
+    },
+    /// The bitstream does not match the UCF constraints on a net. `line` is the first line with constraints on the net.
+    UCFMismatch {
+        net: String,
+        line: usize,
+        expected: String,
+        found: String,
+    },
+    /// A crbit file could not be parsed. `line` is one-based, or `None` if the problem is not on a particular line.
+    CrbitParseError {
+        msg: String,
+        line: Option<usize>,
+    },
+    /// An XSVF file could not be parsed or does not program a single Coolrunner-II device
+    XSVFParseError {
+        msg: &'static str,
+    },
+    /// A dump in the format written by `dump_human_readable` could not be parsed. `line` is one-based, or `None` if the
+    /// problem is not on a particular line.
+    DumpParseError {
+        msg: String,
+        line: Option<usize>,
+    },
+    /// A Yosys JSON netlist could not be read
+    NetlistError {
+        msg: String,
+    },
+    /// The logic in a simulation kept changing without any time passing, for example because of a combinational loop
+    SimulationDidNotSettle {
+        msg: &'static str,
+    },
+    /// A stimulus for the timing simulator could not be parsed. `line` is one-based.
+    StimulusParseError {
+        msg: String,
+        line: usize,
+    },
+    /// A netlist could not be fitted into the device
+    FitError {
+        msg: String,
+    },
+    /// The I/O pin does not exist in the device
+    BadIOBIndex {
+        device: XC2Device,
+        iob: u32,
+    },
+}
+
+impl fmt::Display for XC2BitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            XC2BitError::JedParseError{msg, line, column} => write!(f, "line {}, column {}: {}", line, column, msg),
+            XC2BitError::MissingDeviceName => write!(f, "missing device name"),
+            XC2BitError::BadDeviceName(ref name) => write!(f, "malformed device name \"{}\"", name),
+            XC2BitError::BadPartCombination(device, speed, package) =>
+                write!(f, "{}-{}-{} is not a legal combination of device, speed grade, and package", device, speed,
+                    package),
+            XC2BitError::WrongFuseCount{expected, found} =>
+                write!(f, "wrong number of fuses (expected {}, found {})", expected, found),
+            XC2BitError::WrongFuseArrayDims{expected, found} =>
+                write!(f, "wrong fuse array dimensions (expected {}x{}, found {}x{})", expected.0, expected.1, found.0,
+                    found.1),
+            XC2BitError::UnknownZIAChoice{fb, row, fuse_idx} =>
+                write!(f, "FB{} ZIA row {}: unknown ZIA input choice (fuse {})", fb + 1, row, fuse_idx),
+            XC2BitError::UnknownOEMode{iob, fuse_idx} =>
+                write!(f, "I/O pin {}: unknown output enable mode (fuse {})", iob, fuse_idx),
+            XC2BitError::InvalidZIAInput{fb, row, input} => {
+                write!(f, "FB{} ZIA row {}: cannot select ", fb + 1, row)?;
+                match input {
+                    XC2ZIAInput::Zero => write!(f, "constant 0"),
+                    XC2ZIAInput::One => write!(f, "constant 1"),
+                    XC2ZIAInput::Macrocell{fb, ff} => write!(f, "FB{}_{} FF", fb + 1, ff + 1),
+                    XC2ZIAInput::IBuf{ibuf} => write!(f, "input from I/O pin {}", ibuf),
+                    XC2ZIAInput::DedicatedInput => write!(f, "dedicated input"),
+                }
+            },
+            XC2BitError::ClockDivNotInCrbit => write!(f, "the clock divider cannot be stored in crbit format"),
+            XC2BitError::UCFParseError{ref msg, line} => write!(f, "line {}: {}", line, msg),
+            XC2BitError::UCFBadConstraint{ref net, ref msg} => write!(f, "net \"{}\": {}", net, msg),
+            XC2BitError::UCFConflict{ref net_a, ref setting_a, ref net_b, ref setting_b} =>
+                write!(f, "nets \"{}\" and \"{}\" need conflicting settings: {} and {}", net_a, net_b, setting_a,
+                    setting_b),
+            XC2BitError::UCFMismatch{ref net, line, ref expected, ref found} =>
+                write!(f, "net \"{}\" (line {}): constraints need {} but the bitstream has {}", net, line, expected,
+                    found),
+            XC2BitError::CrbitParseError{ref msg, line: Some(line)} |
+            XC2BitError::DumpParseError{ref msg, line: Some(line)} => write!(f, "line {}: {}", line, msg),
+            XC2BitError::CrbitParseError{ref msg, line: None} |
+            XC2BitError::DumpParseError{ref msg, line: None} |
+            XC2BitError::NetlistError{ref msg} |
+            XC2BitError::FitError{ref msg} => write!(f, "{}", msg),
+            XC2BitError::XSVFParseError{msg} |
+            XC2BitError::SimulationDidNotSettle{msg} => write!(f, "{}", msg),
+            XC2BitError::StimulusParseError{ref msg, line} => write!(f, "line {}: {}", line, msg),
+            XC2BitError::BadIOBIndex{device, iob} => write!(f, "I/O pin {} does not exist in the {}", iob, device),
+        }
+    }
+}
+
+impl error::Error for XC2BitError {}
+
+impl From<XC2BitError> for io::Error {
+    fn from(err: XC2BitError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidInput, err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fusemap_logical::{mc_fuse_idx};
+
+    fn blank_fuses() -> Vec<bool> {
+        let bitstream = XC2Bitstream::blank_bitstream(XC2Device::XC2C32A, XC2Speed::Speed6, XC2Package::VQ44).unwrap();
+        let mut jed = Vec::new();
+        bitstream.write_jed(&mut jed).unwrap();
+        read_jed(&jed).unwrap().0
+    }
+
+    #[test]
+    fn error_zia_context() {
+        let mut fuses = blank_fuses();
+        // Row 3 of FB2 with nothing selected
+        for i in 0..8 {
+            fuses[6128 + 3 * 8 + i] = false;
+        }
+        let err = process_jed(&fuses, "XC2C32A-6-VQ44").err().unwrap();
+        assert_eq!(err, XC2BitError::UnknownZIAChoice{fb: 1, row: 3, fuse_idx: 6152});
+        assert_eq!(err.to_string(), "FB2 ZIA row 3: unknown ZIA input choice (fuse 6152)");
+    }
+
+    #[test]
+    fn error_oe_context() {
+        let mut fuses = blank_fuses();
+        let (fb, mc) = iob_num_to_fb_ff_num(XC2Device::XC2C32A, 5).unwrap();
+        let oe_idx = mc_fuse_idx(XC2Device::XC2C32A, fb, mc) + 20;
+        fuses[oe_idx] = false;
+        fuses[oe_idx + 1] = true;
+        fuses[oe_idx + 2] = true;
+        fuses[oe_idx + 3] = true;
+        assert_eq!(process_jed(&fuses, "XC2C32A-6-VQ44").err().unwrap(),
+            XC2BitError::UnknownOEMode{iob: 5, fuse_idx: oe_idx});
+    }
+
+    #[test]
+    fn error_device() {
+        let fuses = blank_fuses();
+        assert_eq!(process_jed(&fuses, "XC2C32A-5-VQ44").err().unwrap(),
+            XC2BitError::BadDeviceName(String::from("XC2C32A-5-VQ44")));
+        assert_eq!(process_jed(&fuses[1..], "XC2C32A-6-VQ44").err().unwrap(),
+            XC2BitError::WrongFuseCount{expected: 12278, found: 12277});
+        assert_eq!(XC2Bitstream::blank_bitstream(XC2Device::XC2C32A, XC2Speed::Speed5, XC2Package::VQ44).err().unwrap()
+            .to_string(), "XC2C32A-5-VQ44 is not a legal combination of device, speed grade, and package");
+    }
+
+    #[test]
+    fn error_invalid_zia_input() {
+        let mut bitstream = XC2Bitstream::blank_bitstream(XC2Device::XC2C32A, XC2Speed::Speed6, XC2Package::VQ44)
+            .unwrap();
+        let input = XC2ZIAInput::Macrocell{fb: 1, ff: 2};
+        bitstream.bits.get_fb_mut()[1].zia_bits[4].selected = input;
+        let err = XC2BitError::InvalidZIAInput{fb: 1, row: 4, input: input};
+        assert_eq!(bitstream.to_crbit().err().unwrap(), err);
+        assert_eq!(err.to_string(), "FB2 ZIA row 4: cannot select FB2_3 FF");
+
+        let mut jed = Vec::new();
+        let io_err = bitstream.write_jed(&mut jed).err().unwrap();
+        assert_eq!(io_err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(io_err.get_ref().unwrap().downcast_ref::<XC2BitError>(), Some(&err));
+    }
+
+    #[test]
+    fn error_clock_div_in_crbit() {
+        let mut bitstream = XC2Bitstream::blank_bitstream(XC2Device::XC2C128, XC2Speed::Speed6, XC2Package::VQ100)
+            .unwrap();
+        bitstream.bits.get_clock_div_mut().unwrap().div_ratio = XC2ClockDivRatio::Div2;
+        assert!(bitstream.to_crbit().is_ok());
+
+        bitstream.bits.get_clock_div_mut().unwrap().enabled = true;
+        assert_eq!(bitstream.to_crbit().err().unwrap(), XC2BitError::ClockDivNotInCrbit);
+        let io_err = bitstream.write_svf(&SVFChain::default(), &mut Vec::new()).err().unwrap();
+        assert_eq!(io_err.get_ref().unwrap().downcast_ref::<XC2BitError>(), Some(&XC2BitError::ClockDivNotInCrbit));
+    }
+
+    #[test]
+    fn error_invalid_zia_ibuf() {
+        let mut bitstream = XC2Bitstream::blank_bitstream(XC2Device::XC2C32A, XC2Speed::Speed6, XC2Package::VQ44)
+            .unwrap();
+        let input = XC2ZIAInput::IBuf{ibuf: 100};
+        bitstream.bits.get_fb_mut()[0].zia_bits[0].selected = input;
+        let err = XC2BitError::InvalidZIAInput{fb: 0, row: 0, input: input};
+        assert_eq!(err.to_string(), "FB1 ZIA row 0: cannot select input from I/O pin 100");
+
+        let mut txt = Vec::new();
+        let io_err = bitstream.dump_human_readable(&mut txt).err().unwrap();
+        assert_eq!(io_err.get_ref().unwrap().downcast_ref::<XC2BitError>(), Some(&err));
+    }
+
+    #[test]
+    fn error_trait() {
+        fn read(data: &[u8]) -> Result<XC2Bitstream, Box<dyn error::Error>> {
+            let (fuses, device) = read_jed(data)?;
+            Ok(process_jed(&fuses, &device.unwrap_or_default())?)
+        }
+        assert_eq!(read(b"\x02F0*\x030000").err().unwrap().to_string(), "malformed device name \"\"");
+    }
+}
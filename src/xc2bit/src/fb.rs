@@ -29,6 +29,7 @@ use std::io;
 use std::io::Write;
 
 use *;
+use fusemap_logical::{fb_fuse_idx};
 use fusemap_physical::{zia_block_loc, and_block_loc, or_block_loc};
 use pla::{read_and_term_logical, read_or_term_logical};
 use mc::{read_small_ff_logical, read_large_ff_logical, read_large_buried_ff_logical};
@@ -39,12 +40,15 @@ use zia::{encode_32_zia_choice, encode_64_zia_choice, encode_128_zia_choice, enc
 
 /// Represents a collection of all the parts that make up one function block
 #[derive(Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct XC2BitstreamFB {
     /// The AND terms of the PLA part of the function block
+    #[cfg_attr(feature = "serde", serde(with = "serde_array"))]
     pub and_terms: [XC2PLAAndTerm; ANDTERMS_PER_FB],
     /// The OR terms of the PLA part of the function block
     pub or_terms: [XC2PLAOrTerm; MCS_PER_FB],
     /// The inputs to the function block from the ZIA
+    #[cfg_attr(feature = "serde", serde(with = "serde_array"))]
     pub zia_bits: [XC2ZIARowPiece; INPUTS_PER_ANDTERM],
     /// The macrocells of the function block
     pub ffs: [XC2Macrocell; MCS_PER_FB],
@@ -82,6 +86,24 @@ fn zia_row_crbit_helper(x: usize, y: usize, zia_row: usize, zia_bits: &[bool], h
     }
 }
 
+/// Internal helper that reads a ZIA row from the fuse array. This is the inverse of `zia_row_crbit_helper`.
+fn zia_row_crbit_read_helper(x: usize, y: usize, zia_row: usize, zia_bits: &mut [bool], has_gap: bool,
+    fuse_array: &FuseArray) {
+
+    let zia_bits_len = zia_bits.len();
+    for zia_bit in 0..zia_bits_len {
+        let mut in_y = y + zia_row;
+        if has_gap && zia_row >= 20 {
+            // There is an OR array in the middle, 8 rows high
+            in_y += 8;
+        }
+
+        let in_x = x + zia_bit * 2;
+
+        zia_bits[zia_bits_len - 1 - zia_bit] = fuse_array.get(in_x, in_y);
+    }
+}
+
 // Weird mapping here in (mostly) groups of 3
 // TODO: Explain better
 static AND_BLOCK_TYPE2_P2L_MAP: [usize; ANDTERMS_PER_FB] = [
@@ -124,7 +146,8 @@ impl XC2BitstreamFB {
                 XC2ZIAInput::Macrocell{fb, ff} =>
                     write!(writer, "FB{}_{} FF\n", fb + 1, ff + 1)?,
                 XC2ZIAInput::IBuf{ibuf} => {
-                    let (fb, ff) = iob_num_to_fb_ff_num(device, ibuf).unwrap();
+                    let (fb, ff) = iob_num_to_fb_ff_num(device, ibuf)
+                        .ok_or(XC2BitError::InvalidZIAInput{fb: fb, row: i as u32, input: self.zia_bits[i].selected})?;
                     write!(writer, "FB{}_{} pad\n", fb + 1, ff + 1)?;
                 },
                 XC2ZIAInput::DedicatedInput => write!(writer, "dedicated input\n")?,
@@ -179,7 +202,7 @@ impl XC2BitstreamFB {
     /// Write the crbit representation of the settings for this FB to the given `fuse_array`.
     /// `device` must be the device type this FB was extracted from.
     /// `fb` must be the index of this function block.
-    pub fn to_crbit(&self, device: XC2Device, fb: u32, fuse_array: &mut FuseArray) {
+    pub fn to_crbit(&self, device: XC2Device, fb: u32, fuse_array: &mut FuseArray) -> Result<(), XC2BitError> {
         for i in 0..MCS_PER_FB {
             self.ffs[i].to_crbit(device, fb, i as u32, fuse_array);
         }
@@ -187,46 +210,42 @@ impl XC2BitstreamFB {
         // ZIA
         let (x, y) = zia_block_loc(device, fb);
         for zia_row in 0..INPUTS_PER_ANDTERM {
+            let zia_choice = self.zia_bits[zia_row].selected;
+            let invalid_zia = XC2BitError::InvalidZIAInput{fb: fb, row: zia_row as u32, input: zia_choice};
             match device {
                 XC2Device::XC2C32 | XC2Device::XC2C32A => {
-                    let zia_choice_bits = encode_32_zia_choice(zia_row as u32, self.zia_bits[zia_row].selected)
-                        // FIXME: Fold this into the error system??
-                        .expect("invalid ZIA input");
+                    let zia_choice_bits = encode_32_zia_choice(zia_row as u32, zia_choice)
+                        .ok_or(invalid_zia)?;
 
                     zia_row_crbit_helper(x, y, zia_row, &zia_choice_bits, true, fuse_array);
                 },
                 XC2Device::XC2C64 | XC2Device::XC2C64A => {
-                    let zia_choice_bits = encode_64_zia_choice(zia_row as u32, self.zia_bits[zia_row].selected)
-                        // FIXME: Fold this into the error system??
-                        .expect("invalid ZIA input");
+                    let zia_choice_bits = encode_64_zia_choice(zia_row as u32, zia_choice)
+                        .ok_or(invalid_zia)?;
 
                     zia_row_crbit_helper(x, y, zia_row, &zia_choice_bits, true, fuse_array);
                 },
                 XC2Device::XC2C128 => {
-                    let zia_choice_bits = encode_128_zia_choice(zia_row as u32, self.zia_bits[zia_row].selected)
-                        // FIXME: Fold this into the error system??
-                        .expect("invalid ZIA input");
+                    let zia_choice_bits = encode_128_zia_choice(zia_row as u32, zia_choice)
+                        .ok_or(invalid_zia)?;
 
                     zia_row_crbit_helper(x, y, zia_row, &zia_choice_bits, false, fuse_array);
                 },
                 XC2Device::XC2C256 => {
-                    let zia_choice_bits = encode_256_zia_choice(zia_row as u32, self.zia_bits[zia_row].selected)
-                        // FIXME: Fold this into the error system??
-                        .expect("invalid ZIA input");
+                    let zia_choice_bits = encode_256_zia_choice(zia_row as u32, zia_choice)
+                        .ok_or(invalid_zia)?;
 
                     zia_row_crbit_helper(x, y, zia_row, &zia_choice_bits, true, fuse_array);
                 },
                 XC2Device::XC2C384 => {
-                    let zia_choice_bits = encode_384_zia_choice(zia_row as u32, self.zia_bits[zia_row].selected)
-                        // FIXME: Fold this into the error system??
-                        .expect("invalid ZIA input");
+                    let zia_choice_bits = encode_384_zia_choice(zia_row as u32, zia_choice)
+                        .ok_or(invalid_zia)?;
 
                     zia_row_crbit_helper(x, y, zia_row, &zia_choice_bits, false, fuse_array);
                 },
                 XC2Device::XC2C512 => {
-                    let zia_choice_bits = encode_512_zia_choice(zia_row as u32, self.zia_bits[zia_row].selected)
-                        // FIXME: Fold this into the error system??
-                        .expect("invalid ZIA input");
+                    let zia_choice_bits = encode_512_zia_choice(zia_row as u32, zia_choice)
+                        .ok_or(invalid_zia)?;
 
                     zia_row_crbit_helper(x, y, zia_row, &zia_choice_bits, false, fuse_array);
                 },
@@ -333,105 +352,242 @@ impl XC2BitstreamFB {
                 }
             },
         }
+
+        Ok(())
+    }
+
+    /// Read the crbit representation of the settings for this FB from the given `fuse_array`.
+    /// `device` must be the device type the fuses were extracted from.
+    /// `fb` must be the index of this function block.
+    pub fn from_crbit(device: XC2Device, fb: u32, fuse_array: &FuseArray) -> Result<XC2BitstreamFB, XC2BitError> {
+        let mut ff_bits = [XC2Macrocell::default(); MCS_PER_FB];
+        for i in 0..MCS_PER_FB {
+            ff_bits[i] = XC2Macrocell::from_crbit(device, fb, i as u32, fuse_array);
+        }
+
+        // ZIA
+        // The fuses are gathered into a logically-ordered block so that the logical decoding functions can be reused
+        let zia_row_width = zia_get_row_width(device);
+        let has_gap = match device {
+            XC2Device::XC2C32 | XC2Device::XC2C32A | XC2Device::XC2C64 | XC2Device::XC2C64A | XC2Device::XC2C256 => true,
+            XC2Device::XC2C128 | XC2Device::XC2C384 | XC2Device::XC2C512 => false,
+        };
+        let zia_row_read_function = match device {
+            XC2Device::XC2C32 | XC2Device::XC2C32A => read_32_zia_fb_row_logical,
+            XC2Device::XC2C64 | XC2Device::XC2C64A => read_64_zia_fb_row_logical,
+            XC2Device::XC2C128 => read_128_zia_fb_row_logical,
+            XC2Device::XC2C256 => read_256_zia_fb_row_logical,
+            XC2Device::XC2C384 => read_384_zia_fb_row_logical,
+            XC2Device::XC2C512 => read_512_zia_fb_row_logical,
+        };
+        let mut zia_fuses = vec![false; zia_row_width * INPUTS_PER_ANDTERM];
+        let (x, y) = zia_block_loc(device, fb);
+        let mut zia_bits = [XC2ZIARowPiece::default(); INPUTS_PER_ANDTERM];
+        for zia_row in 0..INPUTS_PER_ANDTERM {
+            zia_row_crbit_read_helper(x, y, zia_row,
+                &mut zia_fuses[zia_row * zia_row_width..(zia_row + 1) * zia_row_width], has_gap, fuse_array);
+            // Errors must refer to the fuses in logical order, not to this temporary block
+            zia_bits[zia_row] = zia_row_read_function(&zia_fuses, fb, 0, zia_row).map_err(|_| {
+                XC2BitError::UnknownZIAChoice {
+                    fb: fb,
+                    row: zia_row as u32,
+                    fuse_idx: fb_fuse_idx(device, fb) + zia_row * zia_row_width,
+                }
+            })?;
+        }
+
+        // AND block
+        let mut and_terms = [XC2PLAAndTerm::default(); ANDTERMS_PER_FB];
+        let (x, y, mirror) = and_block_loc(device, fb);
+        match device {
+            // "Type 1" blocks (OR array is in the middle)
+            XC2Device::XC2C32 | XC2Device::XC2C32A | XC2Device::XC2C64 | XC2Device::XC2C64A | XC2Device::XC2C256 => {
+                for term_idx in 0..ANDTERMS_PER_FB {
+                    for input_idx in 0..INPUTS_PER_ANDTERM {
+                        let mut in_y = y + input_idx;
+                        if input_idx >= 20 {
+                            // There is an OR array in the middle, 8 rows high
+                            in_y += 8;
+                        }
+
+                        if !mirror {
+                            // true input
+                            and_terms[term_idx].input[input_idx] = !fuse_array.get(x + term_idx * 2 + 1, in_y);
+                            // complement input
+                            and_terms[term_idx].input_b[input_idx] = !fuse_array.get(x + term_idx * 2 + 0, in_y);
+                        } else {
+                            // true input
+                            and_terms[term_idx].input[input_idx] = !fuse_array.get(x - term_idx * 2 - 1, in_y);
+                            // complement input
+                            and_terms[term_idx].input_b[input_idx] = !fuse_array.get(x - term_idx * 2 - 0, in_y);
+                        }
+                    }
+                }
+            },
+            // "Type 2" blocks (OR array is on the sides)
+            XC2Device::XC2C128 | XC2Device::XC2C384 | XC2Device::XC2C512 => {
+                for term_idx in 0..ANDTERMS_PER_FB {
+                    for input_idx in 0..INPUTS_PER_ANDTERM {
+                        let phys_term_idx = AND_BLOCK_TYPE2_P2L_MAP[term_idx];
+                        if !mirror {
+                            // true input
+                            and_terms[phys_term_idx].input[input_idx] =
+                                !fuse_array.get(x + term_idx * 2 + 1, y + input_idx);
+                            // complement input
+                            and_terms[phys_term_idx].input_b[input_idx] =
+                                !fuse_array.get(x + term_idx * 2 + 0, y + input_idx);
+                        } else {
+                            // true input
+                            and_terms[phys_term_idx].input[input_idx] =
+                                !fuse_array.get(x - term_idx * 2 - 1, y + input_idx);
+                            // complement input
+                            and_terms[phys_term_idx].input_b[input_idx] =
+                                !fuse_array.get(x - term_idx * 2 - 0, y + input_idx);
+                        }
+                    }
+                }
+            },
+        }
+
+        // OR block
+        let mut or_terms = [XC2PLAOrTerm::default(); MCS_PER_FB];
+        let (x, y, mirror) = or_block_loc(device, fb);
+        match device {
+            // "Type 1" blocks (OR array is in the middle)
+            XC2Device::XC2C32 | XC2Device::XC2C32A | XC2Device::XC2C64 | XC2Device::XC2C64A | XC2Device::XC2C256 => {
+                for or_term_idx in 0..MCS_PER_FB {
+                    for and_term_idx in 0..ANDTERMS_PER_FB {
+                        let in_y = y + (or_term_idx / 2);
+                        let off_x = and_term_idx * 2 + (or_term_idx % 2);
+                        let in_x = if !mirror {
+                            x + off_x
+                        } else {
+                            x - off_x
+                        };
+
+                        or_terms[or_term_idx].input[and_term_idx] = !fuse_array.get(in_x, in_y);
+                    }
+                }
+            },
+            // "Type 2" blocks (OR array is on the sides)
+            XC2Device::XC2C128 | XC2Device::XC2C384 | XC2Device::XC2C512 => {
+                for or_term_idx in 0..MCS_PER_FB {
+                    for and_term_idx in 0..ANDTERMS_PER_FB {
+                        let in_y = y + OR_BLOCK_TYPE2_ROW_MAP[and_term_idx / 2];
+                        let mut in_x = or_term_idx * 2;
+                        // This must match the (unexplained) ordering used in to_crbit
+                        if OR_BLOCK_TYPE2_ROW_MAP[and_term_idx / 2] >= 23 {
+                            // "Reverse"
+                            if and_term_idx % 2 == 0 {
+                                in_x += 1;
+                            }
+                        } else {
+                            if and_term_idx % 2 == 1 {
+                                in_x += 1;
+                            }
+                        }
+
+                        let in_x = if !mirror {
+                            x + in_x
+                        } else {
+                            x - in_x
+                        };
+
+                        or_terms[or_term_idx].input[and_term_idx] = !fuse_array.get(in_x, in_y);
+                    }
+                }
+            },
+        }
+
+        Ok(XC2BitstreamFB {
+            and_terms: and_terms,
+            or_terms: or_terms,
+            zia_bits: zia_bits,
+            ffs: ff_bits,
+        })
     }
 
     /// Write the .JED representation of the settings for this FB to the given `writer` object.
     /// `device` must be the device type this FB was extracted from and is needed to encode the ZIA.
+    /// `fb` must be the index of this function block.
     /// `fuse_base` must be the starting fuse number of this function block.
-    pub fn write_to_jed(&self, device: XC2Device, fuse_base: usize, writer: &mut Write) -> Result<(), io::Error> {
+    pub fn write_to_jed(&self, device: XC2Device, fb: u32, fuse_base: usize, writer: &mut JedFuseWriter)
+        -> Result<(), io::Error> {
         // ZIA
         let zia_row_width = zia_get_row_width(device);
         for i in 0..INPUTS_PER_ANDTERM {
-            write!(writer, "L{:06} ", fuse_base + i * zia_row_width)?;
+            let zia_choice = self.zia_bits[i].selected;
+            let invalid_zia = XC2BitError::InvalidZIAInput{fb: fb, row: i as u32, input: zia_choice};
+            writer.start_field(fuse_base + i * zia_row_width)?;
             match device {
                 XC2Device::XC2C32 | XC2Device::XC2C32A => {
-                    let zia_choice_bits = encode_32_zia_choice(i as u32, self.zia_bits[i].selected)
-                        // FIXME: Fold this into the error system??
-                        .expect("invalid ZIA input");
+                    let zia_choice_bits = encode_32_zia_choice(i as u32, zia_choice)
+                        .ok_or(invalid_zia)?;
                     for j in 0..zia_choice_bits.len() {
-                        write!(writer, "{}", if zia_choice_bits[j] {"1"} else {"0"})?;
+                        writer.fuse(zia_choice_bits[j])?;
                     }
                 },
                 XC2Device::XC2C64 | XC2Device::XC2C64A => {
-                    let zia_choice_bits = encode_64_zia_choice(i as u32, self.zia_bits[i].selected)
-                        // FIXME: Fold this into the error system??
-                        .expect("invalid ZIA input");
+                    let zia_choice_bits = encode_64_zia_choice(i as u32, zia_choice)
+                        .ok_or(invalid_zia)?;
                     for j in 0..zia_choice_bits.len() {
-                        write!(writer, "{}", if zia_choice_bits[j] {"1"} else {"0"})?;
+                        writer.fuse(zia_choice_bits[j])?;
                     }
                 },
                 XC2Device::XC2C128 => {
-                    let zia_choice_bits = encode_128_zia_choice(i as u32, self.zia_bits[i].selected)
-                        // FIXME: Fold this into the error system??
-                        .expect("invalid ZIA input");
+                    let zia_choice_bits = encode_128_zia_choice(i as u32, zia_choice)
+                        .ok_or(invalid_zia)?;
                     for j in 0..zia_choice_bits.len() {
-                        write!(writer, "{}", if zia_choice_bits[j] {"1"} else {"0"})?;
+                        writer.fuse(zia_choice_bits[j])?;
                     }
                 },
                 XC2Device::XC2C256 => {
-                    let zia_choice_bits = encode_256_zia_choice(i as u32, self.zia_bits[i].selected)
-                        // FIXME: Fold this into the error system??
-                        .expect("invalid ZIA input");
+                    let zia_choice_bits = encode_256_zia_choice(i as u32, zia_choice)
+                        .ok_or(invalid_zia)?;
                     for j in 0..zia_choice_bits.len() {
-                        write!(writer, "{}", if zia_choice_bits[j] {"1"} else {"0"})?;
+                        writer.fuse(zia_choice_bits[j])?;
                     }
                 },
                 XC2Device::XC2C384 => {
-                    let zia_choice_bits = encode_384_zia_choice(i as u32, self.zia_bits[i].selected)
-                        // FIXME: Fold this into the error system??
-                        .expect("invalid ZIA input");
+                    let zia_choice_bits = encode_384_zia_choice(i as u32, zia_choice)
+                        .ok_or(invalid_zia)?;
                     for j in 0..zia_choice_bits.len() {
-                        write!(writer, "{}", if zia_choice_bits[j] {"1"} else {"0"})?;
+                        writer.fuse(zia_choice_bits[j])?;
                     }
                 },
                 XC2Device::XC2C512 => {
-                    let zia_choice_bits = encode_512_zia_choice(i as u32, self.zia_bits[i].selected)
-                        // FIXME: Fold this into the error system??
-                        .expect("invalid ZIA input");
+                    let zia_choice_bits = encode_512_zia_choice(i as u32, zia_choice)
+                        .ok_or(invalid_zia)?;
                     for j in 0..zia_choice_bits.len() {
-                        write!(writer, "{}", if zia_choice_bits[j] {"1"} else {"0"})?;
+                        writer.fuse(zia_choice_bits[j])?;
                     }
                 },
             }
-            write!(writer, "*\n")?;
+            writer.end_field()?;
         }
-        write!(writer, "\n")?;
+        writer.blank_line()?;
 
         // AND terms
         for i in 0..ANDTERMS_PER_FB {
-            write!(writer, "L{:06} ",
-                fuse_base + zia_row_width * INPUTS_PER_ANDTERM + i * INPUTS_PER_ANDTERM * 2)?;
+            writer.start_field(fuse_base + zia_row_width * INPUTS_PER_ANDTERM + i * INPUTS_PER_ANDTERM * 2)?;
             for j in 0..INPUTS_PER_ANDTERM {
-                if self.and_terms[i].input[j] {
-                    write!(writer, "0")?;
-                } else {
-                    write!(writer, "1")?;
-                }
-                if self.and_terms[i].input_b[j] {
-                    write!(writer, "0")?;
-                } else {
-                    write!(writer, "1")?;
-                }
+                writer.fuse(!self.and_terms[i].input[j])?;
+                writer.fuse(!self.and_terms[i].input_b[j])?;
             }
-            write!(writer, "*\n")?;
+            writer.end_field()?;
         }
-        write!(writer, "\n")?;
+        writer.blank_line()?;
 
         // OR terms
         for i in 0..ANDTERMS_PER_FB {
-            write!(writer, "L{:06} ",
-                fuse_base + zia_row_width * INPUTS_PER_ANDTERM +
+            writer.start_field(fuse_base + zia_row_width * INPUTS_PER_ANDTERM +
                 ANDTERMS_PER_FB * INPUTS_PER_ANDTERM * 2 + i * MCS_PER_FB)?;
             for j in 0..MCS_PER_FB {
-                if self.or_terms[j].input[i] {
-                    write!(writer, "0")?;
-                } else {
-                    write!(writer, "1")?;
-                }
+                writer.fuse(!self.or_terms[j].input[i])?;
             }
-            write!(writer, "*\n")?;
+            writer.end_field()?;
         }
-        write!(writer, "\n")?;
+        writer.blank_line()?;
 
         Ok(())
     }
@@ -439,7 +595,7 @@ impl XC2BitstreamFB {
 
 /// Internal function that reads a function block
 pub fn read_fb_logical(device: XC2Device, fuses: &[bool], fb: u32, fuse_base: usize)
-    -> Result<XC2BitstreamFB, &'static str> {
+    -> Result<XC2BitstreamFB, XC2BitError> {
 
     let zia_row_width = zia_get_row_width(device);
     let size_of_zia = zia_row_width * INPUTS_PER_ANDTERM;
@@ -475,7 +631,7 @@ pub fn read_fb_logical(device: XC2Device, fuses: &[bool], fb: u32, fuse_base: us
     let mut zia_bits = [XC2ZIARowPiece::default(); INPUTS_PER_ANDTERM];
     let zia_block_idx = fuse_base;
     for i in 0..zia_bits.len() {
-        let result = zia_row_read_function(fuses, zia_block_idx, i)?;
+        let result = zia_row_read_function(fuses, fb, zia_block_idx, i)?;
         zia_bits[i] = result;
     }
 
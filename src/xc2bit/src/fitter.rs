@@ -0,0 +1,1050 @@
+/*
+Copyright (c) 2017, Robert Ou <rqou@robertou.com> and contributors
+All rights reserved.
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice,
+   this list of conditions and the following disclaimer.
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+//! Contains a simple fitter that turns a netlist of Coolrunner-II primitives into a bitstream.
+//!
+//! The netlist must use the primitives produced by the Yosys `synth_coolrunner2` command: `ANDTERM`, `ORTERM`,
+//! `MACROCELL_XOR`, the registers (`FDCP`, `FDCP_N`, `FDDCP`, `LDCP`, `LDCP_N`, `FTCP`, `FTCP_N`, `FTDCP`, `FDCPE`,
+//! `FDCPE_N`, `FDDCPE`), `IBUF`, `IOBUFE`, `BUFG`, `BUFGSR`, and `BUFGTS`.
+//!
+//! I/O buffers can be locked to a macrocell with a `LOC` attribute such as `"FB1_5"`, and global buffers can be locked
+//! with a `LOC` attribute such as `"GCK1"` or `"GTS2"`. Global buffers without a `LOC` are numbered in order. Pins that
+//! are not bonded out in the package are never used. Global buffers must be driven by an `IBUF`, and that `IBUF` must
+//! be locked to the matching dedicated pin by the user because the pin database does not list the dedicated pins yet.
+//! Placement is greedy and function blocks are filled in order.
+
+use std::collections::{HashMap, HashSet};
+
+use *;
+use human_readable::{parse_fb_ff_name};
+use netlist::{NET_ZERO, NET_ONE};
+
+static REG_TYPES: [&str; 11] = [
+    "FDCP", "FDCP_N", "FDDCP", "LDCP", "LDCP_N", "FTCP", "FTCP_N", "FTDCP", "FDCPE", "FDCPE_N", "FDDCPE"];
+
+/// Returns the output port of a primitive, or `None` if it is not a primitive that the fitter understands
+fn output_port(cell_type: &str) -> Option<&'static str> {
+    match cell_type {
+        "ANDTERM" | "ORTERM" | "MACROCELL_XOR" => Some("OUT"),
+        "IBUF" | "IOBUFE" | "BUFG" | "BUFGSR" | "BUFGTS" => Some("O"),
+        x if REG_TYPES.contains(&x) => Some("Q"),
+        _ => None,
+    }
+}
+
+/// Returns the data input port of a register
+fn reg_data_port(cell_type: &str) -> &'static str {
+    if cell_type.starts_with("FT") {"T"} else {"D"}
+}
+
+/// Returns the clock input port of a register
+fn reg_clk_port(cell_type: &str) -> &'static str {
+    if cell_type.starts_with("LD") {"G"} else {"C"}
+}
+
+/// The logic that has to be placed into one macrocell
+#[derive(Default)]
+struct McGroup {
+    xor: Option<usize>,
+    reg: Option<usize>,
+    /// The IOBUFE that is driven by this macrocell
+    obuf: Option<usize>,
+    /// The IBUF or IOBUFE that drives the register directly
+    ibuf: Option<usize>,
+    /// The AND terms used by this macrocell
+    pts: HashSet<usize>,
+    /// The nets that the AND terms used by this macrocell need from the ZIA
+    zia_nets: HashSet<usize>,
+    /// Where this macrocell is, as (fb, mc). Only valid after placement.
+    slot: (usize, usize),
+}
+
+impl McGroup {
+    fn pin(&self) -> Option<usize> {
+        self.obuf.or(self.ibuf)
+    }
+}
+
+/// Internal state of the fitter
+struct Fitter<'a> {
+    netlist: &'a XC2Netlist,
+    /// Which cell drives each net
+    drivers: HashMap<usize, usize>,
+    groups: Vec<McGroup>,
+    /// Which group each MACROCELL_XOR and register belongs to
+    group_of_cell: HashMap<usize, usize>,
+    /// Where each placed IBUF and IOBUFE is, as (fb, mc)
+    pin_slot: HashMap<usize, (usize, usize)>,
+    /// GCK, GTS, and GSR number of each global buffer
+    global_buf_idx: HashMap<usize, usize>,
+}
+
+impl<'a> Fitter<'a> {
+    fn cell(&self, idx: usize) -> &'a XC2NetlistCell {
+        &self.netlist.cells[idx]
+    }
+
+    /// Returns the cell driving a net, or `None` for constants and undriven nets
+    fn driver(&self, net: Option<usize>) -> Option<usize> {
+        net.and_then(|net| self.drivers.get(&net).cloned())
+    }
+
+    /// Returns the cell driving a net if it is of the given type
+    fn driver_of_type(&self, net: Option<usize>, cell_type: &str) -> Option<usize> {
+        self.driver(net).and_then(|x| if self.cell(x).cell_type == cell_type {Some(x)} else {None})
+    }
+
+    /// Returns the AND term driving a control input (clock, set, reset, clock enable, or output enable)
+    fn control_pt(&self, net: Option<usize>) -> Option<usize> {
+        self.driver_of_type(net, "ANDTERM")
+    }
+
+    /// Returns the GCK, GTS, or GSR number of the global buffer of the given type driving a net
+    fn global_buf(&self, net: Option<usize>, cell_type: &str) -> Option<usize> {
+        self.driver_of_type(net, cell_type).and_then(|x| self.global_buf_idx.get(&x).cloned())
+    }
+
+    /// Returns the AND terms that make up the OR term feeding a MACROCELL_XOR
+    fn orterm_pts(&self, xor: usize) -> Result<Vec<usize>, String> {
+        let xor_cell = self.cell(xor);
+        let net = xor_cell.port("IN_ORTERM");
+        if net.is_none() || net == Some(NET_ZERO) {
+            return Ok(Vec::new());
+        }
+
+        let driver = match self.driver(net) {
+            Some(x) => x,
+            None => return Err(format!("OR term input of {} is not driven", xor_cell.name)),
+        };
+        let driver_cell = self.cell(driver);
+        match driver_cell.cell_type.as_ref() {
+            "ANDTERM" => Ok(vec![driver]),
+            "ORTERM" => {
+                let mut pts = Vec::new();
+                for &net in driver_cell.port_bits("IN") {
+                    if net.is_none() || net == Some(NET_ZERO) {
+                        continue;
+                    }
+                    match self.driver_of_type(net, "ANDTERM") {
+                        Some(x) => pts.push(x),
+                        None => return Err(format!("input of {} is not driven by an ANDTERM", driver_cell.name)),
+                    }
+                }
+                Ok(pts)
+            },
+            _ => Err(format!("OR term input of {} is not driven by an ORTERM", xor_cell.name)),
+        }
+    }
+
+    /// Collects the AND terms and ZIA nets used by a group
+    fn collect_group_resources(&mut self, group_idx: usize) -> Result<(), String> {
+        let mut pts = HashSet::new();
+        {
+            let group = &self.groups[group_idx];
+
+            if let Some(xor) = group.xor {
+                for pt in self.orterm_pts(xor)? {
+                    pts.insert(pt);
+                }
+                if let Some(pt) = self.control_pt(self.cell(xor).port("IN_PTC")) {
+                    pts.insert(pt);
+                }
+            }
+
+            if let Some(reg) = group.reg {
+                let reg_cell = self.cell(reg);
+                for port in &[reg_clk_port(&reg_cell.cell_type), "PRE", "CLR", "CE"] {
+                    if let Some(pt) = self.control_pt(reg_cell.port(port)) {
+                        pts.insert(pt);
+                    }
+                }
+            }
+
+            if let Some(obuf) = group.obuf {
+                if let Some(pt) = self.control_pt(self.cell(obuf).port("E")) {
+                    pts.insert(pt);
+                }
+            }
+        }
+
+        let mut zia_nets = HashSet::new();
+        for &pt in &pts {
+            let pt_cell = self.cell(pt);
+            for &net in pt_cell.port_bits("IN").iter().chain(pt_cell.port_bits("IN_B").iter()) {
+                if let Some(net) = net {
+                    if net != NET_ZERO && net != NET_ONE {
+                        zia_nets.insert(net);
+                    }
+                }
+            }
+        }
+
+        self.groups[group_idx].pts = pts;
+        self.groups[group_idx].zia_nets = zia_nets;
+        Ok(())
+    }
+}
+
+/// Returns whether a macrocell has a pin that is bonded out in the package. If the pinout of the package is not known,
+/// every pin is assumed to be bonded out.
+fn slot_has_pin(device: XC2Device, package: XC2Package, (fb, mc): (usize, usize)) -> bool {
+    match fb_ff_num_to_iob_num(device, fb as u32, mc as u32) {
+        Some(iob) => iob_is_bonded(device, package, iob) != Some(false),
+        None => false,
+    }
+}
+
+/// Returns the slot requested by the `LOC` attribute of a cell, if any
+fn loc_slot(device: XC2Device, package: XC2Package, cell: &XC2NetlistCell)
+    -> Result<Option<(usize, usize)>, String> {
+
+    let loc = match cell.attributes.get("LOC") {
+        Some(x) => x,
+        None => return Ok(None),
+    };
+    let slot = match parse_fb_ff_name(loc) {
+        Some((fb, mc)) if fb_ff_num_to_iob_num(device, fb, mc).is_some() => (fb as usize, mc as usize),
+        _ => return Err(format!("invalid LOC \"{}\" on {}", loc, cell.name)),
+    };
+    if !slot_has_pin(device, package, slot) {
+        return Err(format!("LOC \"{}\" on {} is not bonded out in the {} package", loc, cell.name, package));
+    }
+    Ok(Some(slot))
+}
+
+/// Returns the I/O pin of a macrocell, or an error if the macrocell does not have one
+fn slot_iob(device: XC2Device, (fb, mc): (usize, usize)) -> Result<usize, String> {
+    fb_ff_num_to_iob_num(device, fb as u32, mc as u32).map(|x| x as usize)
+        .ok_or(format!("FB{}_{} does not have a pin", fb + 1, mc + 1))
+}
+
+/// Places a product term into the given slot of the PLA if the slot is free or already holds the same term
+fn place_pt(pt_slots: &mut [Option<usize>], idx: u32, pt: usize) -> bool {
+    match pt_slots[idx as usize] {
+        None => {
+            pt_slots[idx as usize] = Some(pt);
+            true
+        },
+        Some(x) => x == pt,
+    }
+}
+
+/// Sets the output buffer configuration of an I/O pin
+fn set_iob_output(bits: &mut XC2BitstreamBits, iob: usize, obuf_mode: XC2IOBOBufMode, obuf_uses_ff: bool) {
+    match bits {
+        &mut XC2BitstreamBits::XC2C32 {ref mut iobs, ..} |
+        &mut XC2BitstreamBits::XC2C32A {ref mut iobs, ..} => {
+            iobs[iob].obuf_mode = obuf_mode;
+            iobs[iob].obuf_uses_ff = obuf_uses_ff;
+        },
+        &mut XC2BitstreamBits::XC2C64 {ref mut iobs, ..} |
+        &mut XC2BitstreamBits::XC2C64A {ref mut iobs, ..} => {
+            iobs[iob].obuf_mode = obuf_mode;
+            iobs[iob].obuf_uses_ff = obuf_uses_ff;
+        },
+        &mut XC2BitstreamBits::XC2C128 {ref mut iobs, ..} => {
+            iobs[iob].obuf_mode = obuf_mode;
+            iobs[iob].obuf_uses_ff = obuf_uses_ff;
+        },
+        &mut XC2BitstreamBits::XC2C256 {ref mut iobs, ..} => {
+            iobs[iob].obuf_mode = obuf_mode;
+            iobs[iob].obuf_uses_ff = obuf_uses_ff;
+        },
+        &mut XC2BitstreamBits::XC2C384 {ref mut iobs, ..} => {
+            iobs[iob].obuf_mode = obuf_mode;
+            iobs[iob].obuf_uses_ff = obuf_uses_ff;
+        },
+        &mut XC2BitstreamBits::XC2C512 {ref mut iobs, ..} => {
+            iobs[iob].obuf_mode = obuf_mode;
+            iobs[iob].obuf_uses_ff = obuf_uses_ff;
+        },
+    }
+}
+
+/// Sets which signal an I/O pin sends into the ZIA
+fn set_iob_zia_mode(bits: &mut XC2BitstreamBits, iob: usize, zia_mode: XC2IOBZIAMode) {
+    match bits {
+        &mut XC2BitstreamBits::XC2C32 {ref mut iobs, ..} |
+        &mut XC2BitstreamBits::XC2C32A {ref mut iobs, ..} => iobs[iob].zia_mode = zia_mode,
+        &mut XC2BitstreamBits::XC2C64 {ref mut iobs, ..} |
+        &mut XC2BitstreamBits::XC2C64A {ref mut iobs, ..} => iobs[iob].zia_mode = zia_mode,
+        &mut XC2BitstreamBits::XC2C128 {ref mut iobs, ..} => iobs[iob].zia_mode = zia_mode,
+        &mut XC2BitstreamBits::XC2C256 {ref mut iobs, ..} => iobs[iob].zia_mode = zia_mode,
+        &mut XC2BitstreamBits::XC2C384 {ref mut iobs, ..} => iobs[iob].zia_mode = zia_mode,
+        &mut XC2BitstreamBits::XC2C512 {ref mut iobs, ..} => iobs[iob].zia_mode = zia_mode,
+    }
+}
+
+/// Fits a netlist of Coolrunner-II primitives into a new bitstream for the given part
+pub fn fit_netlist(netlist: &XC2Netlist, device: XC2Device, speed_grade: XC2Speed, package: XC2Package)
+    -> Result<XC2Bitstream, XC2BitError> {
+
+    let mut bitstream = XC2Bitstream::blank_bitstream(device, speed_grade, package)?;
+    place_and_route(netlist, device, package, &mut bitstream).map_err(|msg| XC2BitError::FitError{msg})?;
+    Ok(bitstream)
+}
+
+fn place_and_route(netlist: &XC2Netlist, device: XC2Device, package: XC2Package, bitstream: &mut XC2Bitstream)
+    -> Result<(), String> {
+
+    let mut fitter = Fitter {
+        netlist,
+        drivers: HashMap::new(),
+        groups: Vec::new(),
+        group_of_cell: HashMap::new(),
+        pin_slot: HashMap::new(),
+        global_buf_idx: HashMap::new(),
+    };
+
+    // Find the driver of every net
+    for (i, cell) in netlist.cells.iter().enumerate() {
+        let out_port = match output_port(&cell.cell_type) {
+            Some(x) => x,
+            None => return Err(format!("{} has unsupported type {}", cell.name, cell.cell_type)),
+        };
+        if let Some(net) = cell.port(out_port) {
+            if fitter.drivers.insert(net, i).is_some() {
+                return Err(format!("net driven by {} has more than one driver", cell.name));
+            }
+        }
+    }
+
+    // Global buffers
+    let mut global_ibufs = HashSet::new();
+    {
+        let global_nets = bitstream.bits.get_global_nets_mut();
+        for &(cell_type, prefix, count) in &[("BUFG", "GCK", 3), ("BUFGTS", "GTS", 4), ("BUFGSR", "GSR", 1)] {
+            let mut used = vec![false; count];
+            let bufs = netlist.cells.iter().enumerate().filter(|&(_, c)| c.cell_type == cell_type).collect::<Vec<_>>();
+
+            // Locked buffers first
+            for &(i, cell) in &bufs {
+                if let Some(loc) = cell.attributes.get("LOC") {
+                    let idx = if count == 1 && loc == prefix {
+                        Some(0)
+                    } else if let Some(num) = loc.strip_prefix(prefix) {
+                        num.parse::<usize>().ok().and_then(|x| if x < count {Some(x)} else {None})
+                    } else {
+                        None
+                    };
+                    match idx {
+                        Some(idx) if !used[idx] => {
+                            used[idx] = true;
+                            fitter.global_buf_idx.insert(i, idx);
+                        },
+                        _ => return Err(format!("invalid LOC \"{}\" on {}", loc, cell.name)),
+                    }
+                }
+            }
+            for &(i, cell) in &bufs {
+                if !cell.attributes.contains_key("LOC") {
+                    match used.iter().position(|&x| !x) {
+                        Some(idx) => {
+                            used[idx] = true;
+                            fitter.global_buf_idx.insert(i, idx);
+                        },
+                        None => return Err(format!("too many {} buffers", cell_type)),
+                    }
+                }
+            }
+
+            for &(i, cell) in &bufs {
+                let ibuf = match fitter.driver_of_type(cell.port("I"), "IBUF") {
+                    Some(x) => x,
+                    None => return Err(format!("input of {} must come from an IBUF", cell.name)),
+                };
+                if !netlist.cells[ibuf].attributes.contains_key("LOC") {
+                    return Err(format!("{} drives {} and must be locked to its dedicated pin",
+                        netlist.cells[ibuf].name, cell.name));
+                }
+                global_ibufs.insert(ibuf);
+
+                let idx = fitter.global_buf_idx[&i];
+                let invert = cell.parameter("INVERT", 0) != 0;
+                match cell_type {
+                    "BUFG" => global_nets.gck_enable[idx] = true,
+                    "BUFGTS" => {
+                        global_nets.gts_enable[idx] = true;
+                        global_nets.gts_invert[idx] = !invert;
+                    },
+                    _ => {
+                        global_nets.gsr_enable = true;
+                        global_nets.gsr_invert = !invert;
+                    },
+                }
+            }
+        }
+    }
+
+    // Group the logic into macrocells
+    for (i, cell) in netlist.cells.iter().enumerate() {
+        if cell.cell_type == "MACROCELL_XOR" {
+            fitter.group_of_cell.insert(i, fitter.groups.len());
+            fitter.groups.push(McGroup {xor: Some(i), ..Default::default()});
+        }
+    }
+    for (i, cell) in netlist.cells.iter().enumerate() {
+        if !REG_TYPES.contains(&cell.cell_type.as_ref()) {
+            continue;
+        }
+
+        let d = cell.port(reg_data_port(&cell.cell_type));
+        let d_driver = fitter.driver(d);
+        match d_driver.map(|x| (x, netlist.cells[x].cell_type.as_ref())) {
+            Some((xor, "MACROCELL_XOR")) => {
+                let group_idx = fitter.group_of_cell[&xor];
+                if fitter.groups[group_idx].reg.is_some() {
+                    return Err(format!("{} drives more than one register", netlist.cells[xor].name));
+                }
+                fitter.groups[group_idx].reg = Some(i);
+                fitter.group_of_cell.insert(i, group_idx);
+            },
+            Some((pin, "IBUF")) | Some((pin, "IOBUFE")) => {
+                if fitter.groups.iter().any(|g| g.ibuf == Some(pin)) {
+                    return Err(format!("{} drives more than one input register", netlist.cells[pin].name));
+                }
+                fitter.group_of_cell.insert(i, fitter.groups.len());
+                fitter.groups.push(McGroup {reg: Some(i), ibuf: Some(pin), ..Default::default()});
+            },
+            _ => return Err(format!("input of {} must come from a MACROCELL_XOR or a pin", cell.name)),
+        }
+    }
+    let mut input_pins = Vec::new();
+    for (i, cell) in netlist.cells.iter().enumerate() {
+        if cell.cell_type == "IOBUFE" {
+            let in_driver = fitter.driver(cell.port("I"));
+            let group_idx = match in_driver.and_then(|x| fitter.group_of_cell.get(&x)) {
+                Some(&x) => x,
+                None => return Err(format!("output of {} must come from a macrocell", cell.name)),
+            };
+            if fitter.groups[group_idx].obuf.is_some() {
+                return Err(format!("{} drives more than one output", netlist.cells[in_driver.unwrap()].name));
+            }
+            if fitter.groups[group_idx].ibuf.is_some() && fitter.groups[group_idx].ibuf != Some(i) {
+                return Err(format!("{} must be in the same macrocell as its input register", cell.name));
+            }
+            fitter.groups[group_idx].obuf = Some(i);
+        } else if cell.cell_type == "IBUF" {
+            // Find out if this pin is used for anything other than an input register
+            let out_net = cell.port("O");
+            let needs_iob = global_ibufs.contains(&i) || netlist.cells.iter().any(|c| {
+                c.cell_type == "ANDTERM" &&
+                    c.port_bits("IN").iter().chain(c.port_bits("IN_B").iter()).any(|&x| x.is_some() && x == out_net)
+            });
+            let in_group = fitter.groups.iter().any(|g| g.ibuf == Some(i));
+            if needs_iob && !in_group {
+                input_pins.push(i);
+            }
+        }
+    }
+
+    for i in 0..fitter.groups.len() {
+        fitter.collect_group_resources(i)?;
+    }
+
+    // Placement
+    let num_fbs = device.num_fbs();
+    let mut mc_used = vec![[false; MCS_PER_FB]; num_fbs];
+    let mut iob_used = vec![[false; MCS_PER_FB]; num_fbs];
+    let mut fb_pts = vec![HashSet::new(); num_fbs];
+    let mut fb_zia_nets = vec![HashSet::new(); num_fbs];
+
+    // Locked pins first, then pins, then buried logic
+    let mut group_order = (0..fitter.groups.len()).collect::<Vec<_>>();
+    let mut group_locs = Vec::new();
+    for group in &fitter.groups {
+        group_locs.push(match group.pin() {
+            Some(pin) => loc_slot(device, package, &netlist.cells[pin])?,
+            None => None,
+        });
+    }
+    group_order.sort_by_key(|&i| (group_locs[i].is_none(), fitter.groups[i].pin().is_none()));
+
+    let mut pin_locs = Vec::new();
+    for &pin in &input_pins {
+        pin_locs.push(loc_slot(device, package, &netlist.cells[pin])?);
+    }
+
+    // Locked input-only pins
+    for (&pin, &loc) in input_pins.iter().zip(pin_locs.iter()) {
+        if let Some((fb, mc)) = loc {
+            if iob_used[fb][mc] {
+                return Err(format!("more than one pin is locked to FB{}_{}", fb + 1, mc + 1));
+            }
+            iob_used[fb][mc] = true;
+            fitter.pin_slot.insert(pin, (fb, mc));
+        }
+    }
+
+    for &group_idx in &group_order {
+        let needs_iob = fitter.groups[group_idx].pin().is_some();
+        let fits = |fb: usize, group: &McGroup, fb_pts: &[HashSet<usize>], fb_zia_nets: &[HashSet<usize>]| {
+            fb_pts[fb].union(&group.pts).count() <= ANDTERMS_PER_FB &&
+                fb_zia_nets[fb].union(&group.zia_nets).count() <= INPUTS_PER_ANDTERM
+        };
+
+        let slot = if let Some((fb, mc)) = group_locs[group_idx] {
+            if mc_used[fb][mc] || iob_used[fb][mc] {
+                return Err(format!("more than one pin is locked to FB{}_{}", fb + 1, mc + 1));
+            }
+            Some((fb, mc))
+        } else {
+            // Buried logic prefers macrocells without a pin, and then macrocells whose pin is only used as an input
+            let mut best = None;
+            for fb in 0..num_fbs {
+                if !fits(fb, &fitter.groups[group_idx], &fb_pts, &fb_zia_nets) {
+                    continue;
+                }
+                for mc in 0..MCS_PER_FB {
+                    if mc_used[fb][mc] {
+                        continue;
+                    }
+                    let has_iob = slot_has_pin(device, package, (fb, mc));
+                    let score = if needs_iob {
+                        if !has_iob || iob_used[fb][mc] {
+                            continue;
+                        }
+                        0
+                    } else if !has_iob {
+                        0
+                    } else if iob_used[fb][mc] {
+                        1
+                    } else {
+                        2
+                    };
+                    if best.map(|(_, _, best_score)| score < best_score).unwrap_or(true) {
+                        best = Some((fb, mc, score));
+                    }
+                }
+                if best.map(|(_, _, score)| score == 0).unwrap_or(false) {
+                    break;
+                }
+            }
+            best.map(|(fb, mc, _)| (fb, mc))
+        };
+
+        let (fb, mc) = match slot {
+            Some(x) => x,
+            None => return Err(String::from("design does not fit")),
+        };
+
+        mc_used[fb][mc] = true;
+        if let Some(pin) = fitter.groups[group_idx].pin() {
+            iob_used[fb][mc] = true;
+            fitter.pin_slot.insert(pin, (fb, mc));
+        }
+        for &pt in &fitter.groups[group_idx].pts {
+            fb_pts[fb].insert(pt);
+        }
+        for &net in &fitter.groups[group_idx].zia_nets {
+            fb_zia_nets[fb].insert(net);
+        }
+        fitter.groups[group_idx].slot = (fb, mc);
+    }
+
+    // Unlocked input-only pins
+    for (&pin, &loc) in input_pins.iter().zip(pin_locs.iter()) {
+        if loc.is_none() {
+            let mut slot = None;
+            'search: for fb in 0..num_fbs {
+                for mc in 0..MCS_PER_FB {
+                    if !iob_used[fb][mc] && slot_has_pin(device, package, (fb, mc)) {
+                        slot = Some((fb, mc));
+                        break 'search;
+                    }
+                }
+            }
+            match slot {
+                Some((fb, mc)) => {
+                    iob_used[fb][mc] = true;
+                    fitter.pin_slot.insert(pin, (fb, mc));
+                },
+                None => return Err(String::from("design does not fit (not enough pins)")),
+            }
+        }
+    }
+
+    // Work out what each macrocell needs to send into the ZIA
+    let mut xor_to_zia = HashSet::new();
+    let mut reg_to_zia = HashSet::new();
+    let mut pad_to_zia = HashSet::new();
+    for pts in &fb_pts {
+        for &pt in pts {
+            let pt_cell = &netlist.cells[pt];
+            for &net in pt_cell.port_bits("IN").iter().chain(pt_cell.port_bits("IN_B").iter()) {
+                if let Some(driver) = fitter.driver(net) {
+                    match netlist.cells[driver].cell_type.as_ref() {
+                        "MACROCELL_XOR" => {xor_to_zia.insert(fitter.group_of_cell[&driver]);},
+                        "IBUF" | "IOBUFE" => {pad_to_zia.insert(driver);},
+                        x if REG_TYPES.contains(&x) => {reg_to_zia.insert(fitter.group_of_cell[&driver]);},
+                        _ => return Err(format!("{} cannot be an input to the ZIA", netlist.cells[driver].name)),
+                    }
+                }
+            }
+        }
+    }
+
+    // Source of each net that goes into the ZIA
+    let mut zia_sources = HashMap::new();
+    for (group_idx, group) in fitter.groups.iter().enumerate() {
+        let (fb, mc) = group.slot;
+        let mc_input = XC2ZIAInput::Macrocell{fb: fb as u32, ff: mc as u32};
+
+        let ffs = &mut bitstream.bits.get_fb_mut()[fb].ffs;
+        let xor_net = group.xor.and_then(|x| netlist.cells[x].port("OUT"));
+        let q_net = group.reg.and_then(|x| netlist.cells[x].port("Q"));
+        if let (true, Some(xor_net)) = (xor_to_zia.contains(&group_idx), xor_net) {
+            ffs[mc].fb_mode = XC2MCFeedbackMode::COMB;
+            zia_sources.insert(xor_net, mc_input);
+        }
+        if let (true, Some(q_net)) = (reg_to_zia.contains(&group_idx), q_net) {
+            if !xor_to_zia.contains(&group_idx) {
+                ffs[mc].fb_mode = XC2MCFeedbackMode::REG;
+                zia_sources.insert(q_net, mc_input);
+            } else {
+                // Use the path through the I/O pin instead
+                let iob = fb_ff_num_to_iob_num(device, fb as u32, mc as u32);
+                let pad_used = fitter.pin_slot.iter().any(|(pin, &slot)| slot == (fb, mc) && pad_to_zia.contains(pin));
+                match iob {
+                    Some(iob) if !pad_used => {zia_sources.insert(q_net, XC2ZIAInput::IBuf{ibuf: iob});},
+                    _ => return Err(format!("FB{}_{} needs both combinatorial and registered feedback", fb + 1,
+                        mc + 1)),
+                }
+            }
+        }
+    }
+    for &pin in &pad_to_zia {
+        let iob = match fitter.pin_slot.get(&pin) {
+            Some(&slot) => slot_iob(device, slot)?,
+            None => return Err(format!("{} was not placed", netlist.cells[pin].name)),
+        };
+        if let Some(net) = netlist.cells[pin].port("O") {
+            zia_sources.insert(net, XC2ZIAInput::IBuf{ibuf: iob as u32});
+        }
+    }
+    zia_sources.insert(NET_ZERO, XC2ZIAInput::Zero);
+    zia_sources.insert(NET_ONE, XC2ZIAInput::One);
+
+    // Product term allocation, macrocells, and the ZIA for each FB
+    let mut oe_slots = HashMap::new();
+    for fb in 0..num_fbs {
+        let mut pt_slots = [None; ANDTERMS_PER_FB];
+
+        let mut fb_groups = fitter.groups.iter().filter(|g| g.slot.0 == fb).collect::<Vec<_>>();
+        fb_groups.sort_by_key(|g| g.slot.1);
+
+        // Product terms that can only go in one place
+        for group in &fb_groups {
+            let mc = group.slot.1 as u32;
+            if let Some(xor) = group.xor {
+                if let Some(pt) = fitter.control_pt(netlist.cells[xor].port("IN_PTC")) {
+                    place_pt(&mut pt_slots, get_ptc(mc), pt);
+                }
+            }
+            if let Some(reg) = group.reg {
+                if let Some(pt) = fitter.control_pt(netlist.cells[reg].port("CE")) {
+                    if !place_pt(&mut pt_slots, get_ptc(mc), pt) {
+                        return Err(format!("clock enable of {} conflicts with its XOR input",
+                            netlist.cells[reg].name));
+                    }
+                }
+            }
+        }
+
+        // Product terms that can go in either a per-macrocell or a per-FB slot
+        let mut ctrl_slots = HashMap::new();
+        for group in &fb_groups {
+            let mc = group.slot.1 as u32;
+            let mut choices = Vec::new();
+            if let Some(reg) = group.reg {
+                let reg_cell = &netlist.cells[reg];
+                choices.push((reg, reg_clk_port(&reg_cell.cell_type), get_ptc(mc), CTC));
+                choices.push((reg, "PRE", get_pta(mc), CTS));
+                choices.push((reg, "CLR", get_pta(mc), CTR));
+            }
+            if let Some(obuf) = group.obuf {
+                choices.push((obuf, "E", get_ptb(mc), CTE));
+            }
+
+            for (cell, port, local_slot, shared_slot) in choices {
+                if let Some(pt) = fitter.control_pt(netlist.cells[cell].port(port)) {
+                    let slot = if place_pt(&mut pt_slots, local_slot, pt) {
+                        local_slot
+                    } else if place_pt(&mut pt_slots, shared_slot, pt) {
+                        shared_slot
+                    } else {
+                        return Err(format!("not enough control terms for {} in FB{}", netlist.cells[cell].name,
+                            fb + 1));
+                    };
+                    ctrl_slots.insert((cell, port), slot);
+                    if port == "E" {
+                        oe_slots.insert(cell, slot);
+                    }
+                }
+            }
+        }
+
+        // All other product terms can go anywhere
+        let mut other_pts = fb_pts[fb].iter().cloned().filter(|pt| !pt_slots.contains(&Some(*pt))).collect::<Vec<_>>();
+        other_pts.sort();
+        for pt in other_pts {
+            match pt_slots.iter().position(|x| x.is_none()) {
+                Some(idx) => pt_slots[idx] = Some(pt),
+                None => return Err(format!("not enough product terms in FB{}", fb + 1)),
+            }
+        }
+
+        // ZIA
+        let mut signals = Vec::new();
+        for pt in pt_slots.iter().filter_map(|&x| x) {
+            let pt_cell = &netlist.cells[pt];
+            for &net in pt_cell.port_bits("IN").iter().chain(pt_cell.port_bits("IN_B").iter()) {
+                let signal = match net.and_then(|net| zia_sources.get(&net)) {
+                    Some(&x) => x,
+                    None => return Err(format!("input of {} is not connected to anything", pt_cell.name)),
+                };
+                if !signals.contains(&signal) {
+                    signals.push(signal);
+                }
+            }
+        }
+        let zia_rows = match zia_route_inputs(device, &signals) {
+            Ok(x) => x,
+            Err(conflict) => return Err(format!("could not route the ZIA for FB{}, these inputs conflict: {}", fb + 1,
+                conflict.iter().map(|&x| zia_input_name(device, x)).collect::<Vec<_>>().join(", "))),
+        };
+
+        let fb_bits = &mut bitstream.bits.get_fb_mut()[fb];
+        for row in 0..INPUTS_PER_ANDTERM {
+            if let Some(signal) = zia_rows[row] {
+                fb_bits.zia_bits[row].selected = signal;
+            }
+        }
+        let row_of_net = |net: Option<usize>, pt_cell: &XC2NetlistCell| {
+            net.and_then(|net| zia_sources.get(&net))
+                .and_then(|&signal| zia_rows.iter().position(|&x| x == Some(signal)))
+                .ok_or(format!("input of {} is not connected to anything", pt_cell.name))
+        };
+
+        // AND terms
+        for idx in 0..ANDTERMS_PER_FB {
+            if let Some(pt) = pt_slots[idx] {
+                let pt_cell = &netlist.cells[pt];
+                for &net in pt_cell.port_bits("IN") {
+                    fb_bits.and_terms[idx].input[row_of_net(net, pt_cell)?] = true;
+                }
+                for &net in pt_cell.port_bits("IN_B") {
+                    fb_bits.and_terms[idx].input_b[row_of_net(net, pt_cell)?] = true;
+                }
+            }
+        }
+
+        // Macrocells
+        for group in &fb_groups {
+            let mc = group.slot.1;
+            let mc_bits = &mut fb_bits.ffs[mc];
+
+            if let Some(xor) = group.xor {
+                let xor_cell = &netlist.cells[xor];
+                for pt in fitter.orterm_pts(xor)? {
+                    match pt_slots.iter().position(|&x| x == Some(pt)) {
+                        Some(idx) => fb_bits.or_terms[mc].input[idx] = true,
+                        None => return Err(format!("{} was not placed in FB{}", netlist.cells[pt].name, fb + 1)),
+                    }
+                }
+
+                let uses_ptc = fitter.control_pt(xor_cell.port("IN_PTC")).is_some();
+                mc_bits.xor_mode = match (uses_ptc, xor_cell.parameter("INVERT_OUT", 0) != 0) {
+                    (false, false) => XC2MCXorMode::ZERO,
+                    (false, true) => XC2MCXorMode::ONE,
+                    (true, false) => XC2MCXorMode::PTC,
+                    (true, true) => XC2MCXorMode::PTCB,
+                };
+            }
+
+            if let Some(reg) = group.reg {
+                let reg_cell = &netlist.cells[reg];
+                let cell_type = reg_cell.cell_type.as_ref();
+
+                mc_bits.reg_mode = match cell_type {
+                    "LDCP" | "LDCP_N" => XC2MCRegMode::LATCH,
+                    "FTCP" | "FTCP_N" | "FTDCP" => XC2MCRegMode::TFF,
+                    "FDCPE" | "FDCPE_N" | "FDDCPE" => XC2MCRegMode::DFFCE,
+                    _ => XC2MCRegMode::DFF,
+                };
+                mc_bits.clk_invert_pol = cell_type.ends_with("_N");
+                mc_bits.is_ddr = cell_type.starts_with("FDD") || cell_type == "FTDCP";
+                mc_bits.init_state = reg_cell.parameter("INIT", 0) != 0;
+                mc_bits.ff_in_ibuf = group.ibuf.is_some();
+
+                let clk_port = reg_clk_port(cell_type);
+                let clk_net = reg_cell.port(clk_port);
+                mc_bits.clk_src = if let Some(&slot) = ctrl_slots.get(&(reg, clk_port)) {
+                    if slot == CTC {XC2MCRegClkSrc::CTC} else {XC2MCRegClkSrc::PTC}
+                } else if let Some(idx) = fitter.global_buf(clk_net, "BUFG") {
+                    match idx {
+                        0 => XC2MCRegClkSrc::GCK0,
+                        1 => XC2MCRegClkSrc::GCK1,
+                        _ => XC2MCRegClkSrc::GCK2,
+                    }
+                } else {
+                    return Err(format!("clock of {} must come from a BUFG or an ANDTERM", reg_cell.name));
+                };
+
+                let set_reset_src = |port: &str| {
+                    let net = reg_cell.port(port);
+                    if net.is_none() || net == Some(NET_ZERO) {
+                        Ok(None)
+                    } else if let Some(&slot) = ctrl_slots.get(&(reg, port)) {
+                        Ok(Some(Some(slot)))
+                    } else if fitter.driver_of_type(net, "BUFGSR").is_some() {
+                        // No product term means the GSR
+                        Ok(Some(None))
+                    } else {
+                        Err(format!("{} of {} must come from a BUFGSR or an ANDTERM", port, reg_cell.name))
+                    }
+                };
+                mc_bits.s_src = match set_reset_src("PRE")? {
+                    None => XC2MCRegSetSrc::Disabled,
+                    Some(None) => XC2MCRegSetSrc::GSR,
+                    Some(Some(x)) if x == CTS => XC2MCRegSetSrc::CTS,
+                    Some(Some(_)) => XC2MCRegSetSrc::PTA,
+                };
+                mc_bits.r_src = match set_reset_src("CLR")? {
+                    None => XC2MCRegResetSrc::Disabled,
+                    Some(None) => XC2MCRegResetSrc::GSR,
+                    Some(Some(x)) if x == CTR => XC2MCRegResetSrc::CTR,
+                    Some(Some(_)) => XC2MCRegResetSrc::PTA,
+                };
+            }
+        }
+    }
+
+    // I/O pins
+    for group in &fitter.groups {
+        if let Some(obuf) = group.obuf {
+            let obuf_cell = &netlist.cells[obuf];
+            let e_net = obuf_cell.port("E");
+            let obuf_mode = if e_net == Some(NET_ONE) {
+                XC2IOBOBufMode::PushPull
+            } else if e_net.is_none() || e_net == Some(NET_ZERO) {
+                XC2IOBOBufMode::Disabled
+            } else if let Some(idx) = fitter.global_buf(e_net, "BUFGTS") {
+                match idx {
+                    0 => XC2IOBOBufMode::TriStateGTS0,
+                    1 => XC2IOBOBufMode::TriStateGTS1,
+                    2 => XC2IOBOBufMode::TriStateGTS2,
+                    _ => XC2IOBOBufMode::TriStateGTS3,
+                }
+            } else if let Some(&slot) = oe_slots.get(&obuf) {
+                if slot == CTE {XC2IOBOBufMode::TriStateCTE} else {XC2IOBOBufMode::TriStatePTB}
+            } else {
+                return Err(format!("output enable of {} must come from a BUFGTS or an ANDTERM", obuf_cell.name));
+            };
+            let obuf_uses_ff = fitter.driver(obuf_cell.port("I")) == group.reg;
+
+            let iob = slot_iob(device, group.slot)?;
+            set_iob_output(&mut bitstream.bits, iob, obuf_mode, obuf_uses_ff);
+        }
+    }
+    for (&pin, &slot) in &fitter.pin_slot {
+        if pad_to_zia.contains(&pin) {
+            set_iob_zia_mode(&mut bitstream.bits, slot_iob(device, slot)?, XC2IOBZIAMode::PAD);
+        }
+    }
+    for group in &fitter.groups {
+        if let Some(reg) = group.reg {
+            if reg_to_zia.contains(&fitter.group_of_cell[&reg]) && xor_to_zia.contains(&fitter.group_of_cell[&reg]) {
+                set_iob_zia_mode(&mut bitstream.bits, slot_iob(device, group.slot)?, XC2IOBZIAMode::REG);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(name: &str, cell_type: &str, ports: &[(&str, &[Option<usize>])]) -> XC2NetlistCell {
+        XC2NetlistCell {
+            name: name.to_owned(),
+            cell_type: cell_type.to_owned(),
+            parameters: HashMap::new(),
+            attributes: HashMap::new(),
+            connections: ports.iter().map(|&(port, bits)| (port.to_owned(), bits.to_vec())).collect(),
+        }
+    }
+
+    fn fit(netlist: &XC2Netlist) -> Result<XC2Bitstream, XC2BitError> {
+        fit_netlist(netlist, XC2Device::XC2C32A, XC2Speed::Speed6, XC2Package::VQ44)
+    }
+
+    #[test]
+    fn fit_and_gate() {
+        let mut netlist = XC2Netlist::default();
+        netlist.cells.push(cell("a", "IBUF", &[("I", &[Some(100)]), ("O", &[Some(2)])]));
+        netlist.cells.push(cell("b", "IBUF", &[("I", &[Some(101)]), ("O", &[Some(3)])]));
+        netlist.cells.push(cell("pt", "ANDTERM", &[("IN", &[Some(2)]), ("IN_B", &[Some(3)]), ("OUT", &[Some(4)])]));
+        netlist.cells.push(cell("or", "ORTERM", &[("IN", &[Some(4)]), ("OUT", &[Some(5)])]));
+        netlist.cells.push(cell("xor", "MACROCELL_XOR", &[("IN_ORTERM", &[Some(5)]), ("OUT", &[Some(6)])]));
+        let mut obuf = cell("y", "IOBUFE", &[("I", &[Some(6)]), ("E", &[Some(NET_ONE)]), ("IO", &[Some(102)])]);
+        obuf.attributes.insert(String::from("LOC"), String::from("FB2_3"));
+        netlist.cells.push(obuf);
+
+        let bitstream = fit(&netlist).unwrap();
+        let fb = &bitstream.bits.get_fb()[1];
+        assert_eq!(fb.ffs[2].xor_mode, XC2MCXorMode::ZERO);
+        assert_eq!(fb.or_terms[2].input.iter().filter(|&&x| x).count(), 1);
+        let pt = fb.or_terms[2].input.iter().position(|&x| x).unwrap();
+        assert_eq!(fb.and_terms[pt].input.iter().filter(|&&x| x).count(), 1);
+        assert_eq!(fb.and_terms[pt].input_b.iter().filter(|&&x| x).count(), 1);
+
+        let iob = fb_ff_num_to_iob_num(XC2Device::XC2C32A, 1, 2).unwrap() as usize;
+        let iob = bitstream.bits.get_small_iob(iob).unwrap();
+        assert_eq!(iob.obuf_mode, XC2IOBOBufMode::PushPull);
+        assert!(!iob.obuf_uses_ff);
+
+        // The two input pins must be routed into the ZIA of the FB using them
+        let row_a = fb.and_terms[pt].input.iter().position(|&x| x).unwrap();
+        let row_b = fb.and_terms[pt].input_b.iter().position(|&x| x).unwrap();
+        for &row in &[row_a, row_b] {
+            match fb.zia_bits[row].selected {
+                XC2ZIAInput::IBuf{ibuf} => {
+                    assert_eq!(bitstream.bits.get_small_iob(ibuf as usize).unwrap().zia_mode, XC2IOBZIAMode::PAD);
+                },
+                _ => panic!("ZIA row {} is not an input pin", row),
+            }
+        }
+
+        // The result must be something that can be written out
+        let mut dump = Vec::new();
+        bitstream.dump_human_readable(&mut dump).unwrap();
+        assert!(read_human_readable(&dump).is_ok());
+    }
+
+    #[test]
+    fn fit_toggle_flip_flop() {
+        let mut netlist = XC2Netlist::default();
+        let mut clk_ibuf = cell("clk_ibuf", "IBUF", &[("I", &[Some(100)]), ("O", &[Some(2)])]);
+        clk_ibuf.attributes.insert(String::from("LOC"), String::from("FB2_16"));
+        netlist.cells.push(clk_ibuf);
+        netlist.cells.push(cell("clk", "BUFG", &[("I", &[Some(2)]), ("O", &[Some(3)])]));
+        let mut xor = cell("xor", "MACROCELL_XOR", &[("OUT", &[Some(4)])]);
+        xor.parameters.insert(String::from("INVERT_OUT"), 1);
+        netlist.cells.push(xor);
+        netlist.cells.push(cell("ff", "FTCP", &[("C", &[Some(3)]), ("T", &[Some(4)]), ("PRE", &[Some(NET_ZERO)]),
+            ("CLR", &[Some(NET_ZERO)]), ("Q", &[Some(5)])]));
+        netlist.cells.push(cell("q", "IOBUFE", &[("I", &[Some(5)]), ("E", &[Some(NET_ONE)]), ("IO", &[Some(101)])]));
+
+        let bitstream = fit(&netlist).unwrap();
+        assert!(bitstream.bits.get_global_nets().gck_enable[0]);
+        let mc = &bitstream.bits.get_fb()[0].ffs[0];
+        assert_eq!(mc.reg_mode, XC2MCRegMode::TFF);
+        assert_eq!(mc.clk_src, XC2MCRegClkSrc::GCK0);
+        assert_eq!(mc.xor_mode, XC2MCXorMode::ONE);
+        assert_eq!(mc.s_src, XC2MCRegSetSrc::Disabled);
+        assert_eq!(mc.r_src, XC2MCRegResetSrc::Disabled);
+
+        let iob = fb_ff_num_to_iob_num(XC2Device::XC2C32A, 0, 0).unwrap() as usize;
+        assert!(bitstream.bits.get_small_iob(iob).unwrap().obuf_uses_ff);
+    }
+
+    #[test]
+    fn fit_errors() {
+        let mut netlist = XC2Netlist::default();
+        netlist.cells.push(cell("x", "LUT4", &[]));
+        assert!(fit(&netlist).err().unwrap().to_string().contains("unsupported"));
+
+        let mut netlist = XC2Netlist::default();
+        let mut xor = cell("xor", "MACROCELL_XOR", &[("OUT", &[Some(2)])]);
+        xor.attributes.insert(String::from("LOC"), String::from("FB1_1"));
+        netlist.cells.push(xor);
+        let mut obuf = cell("y", "IOBUFE", &[("I", &[Some(2)]), ("E", &[Some(NET_ONE)])]);
+        obuf.attributes.insert(String::from("LOC"), String::from("FB9_1"));
+        netlist.cells.push(obuf);
+        assert!(fit(&netlist).err().unwrap().to_string().contains("invalid LOC"));
+
+        // Input of a product term that nothing drives
+        let mut netlist = XC2Netlist::default();
+        netlist.cells.push(cell("pt", "ANDTERM", &[("IN", &[Some(50)]), ("OUT", &[Some(2)])]));
+        netlist.cells.push(cell("xor", "MACROCELL_XOR", &[("IN_ORTERM", &[Some(2)]), ("OUT", &[Some(3)])]));
+        netlist.cells.push(cell("y", "IOBUFE", &[("I", &[Some(3)]), ("E", &[Some(NET_ONE)])]));
+        assert!(fit(&netlist).err().unwrap().to_string().contains("not connected to anything"));
+
+        // One pin feeding two input registers
+        let mut netlist = XC2Netlist::default();
+        netlist.cells.push(cell("a", "IBUF", &[("I", &[Some(100)]), ("O", &[Some(2)])]));
+        netlist.cells.push(cell("ff0", "FDCP", &[("C", &[Some(NET_ONE)]), ("D", &[Some(2)]), ("Q", &[Some(3)])]));
+        netlist.cells.push(cell("ff1", "FDCP", &[("C", &[Some(NET_ONE)]), ("D", &[Some(2)]), ("Q", &[Some(4)])]));
+        assert!(fit(&netlist).err().unwrap().to_string().contains("more than one input register"));
+    }
+
+    #[test]
+    fn fit_unbonded_pins() {
+        // FB1_1 to FB1_3 are not bonded out in the QFG32 package
+        let mut netlist = XC2Netlist::default();
+        netlist.cells.push(cell("xor", "MACROCELL_XOR", &[("OUT", &[Some(2)])]));
+        netlist.cells.push(cell("y", "IOBUFE", &[("I", &[Some(2)]), ("E", &[Some(NET_ONE)]), ("IO", &[Some(100)])]));
+        let bitstream = fit_netlist(&netlist, XC2Device::XC2C32A, XC2Speed::Speed6, XC2Package::QFG32).unwrap();
+        let iob = fb_ff_num_to_iob_num(XC2Device::XC2C32A, 0, 3).unwrap() as usize;
+        assert_eq!(bitstream.bits.get_small_iob(iob).unwrap().obuf_mode, XC2IOBOBufMode::PushPull);
+
+        netlist.cells[1].attributes.insert(String::from("LOC"), String::from("FB1_1"));
+        let err = fit_netlist(&netlist, XC2Device::XC2C32A, XC2Speed::Speed6, XC2Package::QFG32).err().unwrap();
+        assert_eq!(err.to_string(), "LOC \"FB1_1\" on y is not bonded out in the QFG32 package");
+        assert!(fit(&netlist).is_ok());
+    }
+
+    #[test]
+    fn fit_global_buffer_errors() {
+        // Global buffer that is not driven by a pin
+        let mut netlist = XC2Netlist::default();
+        netlist.cells.push(cell("pt", "ANDTERM", &[("IN", &[Some(NET_ONE)]), ("OUT", &[Some(2)])]));
+        netlist.cells.push(cell("clk", "BUFG", &[("I", &[Some(2)]), ("O", &[Some(3)])]));
+        assert!(fit(&netlist).err().unwrap().to_string().contains("must come from an IBUF"));
+
+        let mut netlist = XC2Netlist::default();
+        netlist.cells.push(cell("gts", "BUFGTS", &[("O", &[Some(3)])]));
+        assert!(fit(&netlist).err().unwrap().to_string().contains("must come from an IBUF"));
+
+        // Pin that only feeds a global buffer, without a LOC
+        let mut netlist = XC2Netlist::default();
+        netlist.cells.push(cell("clk_ibuf", "IBUF", &[("I", &[Some(100)]), ("O", &[Some(2)])]));
+        netlist.cells.push(cell("clk", "BUFG", &[("I", &[Some(2)]), ("O", &[Some(3)])]));
+        assert!(fit(&netlist).err().unwrap().to_string().contains("must be locked"));
+
+        // That pin must not be given to anything else
+        let mut clk_ibuf = cell("clk_ibuf", "IBUF", &[("I", &[Some(100)]), ("O", &[Some(2)])]);
+        clk_ibuf.attributes.insert(String::from("LOC"), String::from("FB1_1"));
+        netlist.cells[0] = clk_ibuf;
+        netlist.cells.push(cell("xor", "MACROCELL_XOR", &[("OUT", &[Some(4)])]));
+        let mut obuf = cell("y", "IOBUFE", &[("I", &[Some(4)]), ("E", &[Some(NET_ONE)])]);
+        obuf.attributes.insert(String::from("LOC"), String::from("FB1_1"));
+        netlist.cells.push(obuf);
+        assert!(fit(&netlist).err().unwrap().to_string().contains("more than one pin is locked to FB1_1"));
+    }
+}
@@ -24,6 +24,7 @@ OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 */
 
 use *;
+use zia::{zia_get_row_width};
 
 /// Helper function that returns the first fuse for a given function block. This is made more complicated by buried
 /// macrocells in the larger devices
@@ -148,6 +149,30 @@ pub fn fb_fuse_idx(device: XC2Device, fb: u32) -> usize {
     }
 }
 
+/// Helper function that returns the first fuse for a given macrocell. Buried macrocells in the larger devices have
+/// fewer fuses than the others.
+pub fn mc_fuse_idx(device: XC2Device, fb: u32, mc: u32) -> usize {
+    let size_of_zia = zia_get_row_width(device) * INPUTS_PER_ANDTERM;
+    let size_of_and = INPUTS_PER_ANDTERM * 2 * ANDTERMS_PER_FB;
+    let size_of_or = ANDTERMS_PER_FB * MCS_PER_FB;
+    let device_is_large = match device {
+        XC2Device::XC2C32 | XC2Device::XC2C32A | XC2Device::XC2C64 | XC2Device::XC2C64A => false,
+        _ => true,
+    };
+
+    let mut ret = fb_fuse_idx(device, fb) + size_of_zia + size_of_and + size_of_or;
+    for i in 0..mc {
+        ret += if !device_is_large {
+            27
+        } else if fb_ff_num_to_iob_num(device, fb, i).is_none() {
+            16
+        } else {
+            29
+        };
+    }
+    ret
+}
+
 pub fn gck_fuse_idx(device: XC2Device) -> usize {
     match device {
         XC2Device::XC2C32 | XC2Device::XC2C32A => 12256,
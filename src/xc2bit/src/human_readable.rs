@@ -0,0 +1,612 @@
+/*
+Copyright (c) 2017, Robert Ou <rqou@robertou.com> and contributors
+All rights reserved.
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice,
+   this list of conditions and the following disclaimer.
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+//! Contains routines for reading back the output of `dump_human_readable`.
+//!
+//! The dump must have exactly the same lines in exactly the same order as the ones written by `dump_human_readable`,
+//! but trailing whitespace is ignored so that the dump can be hand-edited. Errors are reported with the line number.
+
+use std::str;
+
+use *;
+use zia::{encode_32_zia_choice, encode_64_zia_choice, encode_128_zia_choice, encode_256_zia_choice,
+          encode_384_zia_choice, encode_512_zia_choice};
+
+static ENABLED_CHOICES: [(&str, bool); 2] = [("enabled", true), ("disabled", false)];
+static YES_NO_CHOICES: [(&str, bool); 2] = [("yes", true), ("no", false)];
+static VOLTAGE_CHOICES: [(&str, bool); 2] = [("high", true), ("low", false)];
+
+static CLOCK_DIV_RATIO_CHOICES: [(&str, XC2ClockDivRatio); 8] = [
+    ("2", XC2ClockDivRatio::Div2),
+    ("4", XC2ClockDivRatio::Div4),
+    ("6", XC2ClockDivRatio::Div6),
+    ("8", XC2ClockDivRatio::Div8),
+    ("10", XC2ClockDivRatio::Div10),
+    ("12", XC2ClockDivRatio::Div12),
+    ("14", XC2ClockDivRatio::Div14),
+    ("16", XC2ClockDivRatio::Div16),
+];
+
+// (enabled, inverted)
+static GSR_CHOICES: [(&str, (bool, bool)); 4] = [
+    ("enabled, active high", (true, true)),
+    ("enabled, active low", (true, false)),
+    ("disabled, active high", (false, true)),
+    ("disabled, active low", (false, false)),
+];
+static GTS_CHOICES: [(&str, (bool, bool)); 4] = [
+    ("enabled, acts as !T", (true, true)),
+    ("enabled, acts as T", (true, false)),
+    ("disabled, acts as !T", (false, true)),
+    ("disabled, acts as T", (false, false)),
+];
+
+static OBUF_MODE_CHOICES: [(&str, XC2IOBOBufMode); 10] = [
+    ("disabled", XC2IOBOBufMode::Disabled),
+    ("push-pull", XC2IOBOBufMode::PushPull),
+    ("open-drain", XC2IOBOBufMode::OpenDrain),
+    ("GTS0-controlled tri-state", XC2IOBOBufMode::TriStateGTS0),
+    ("GTS1-controlled tri-state", XC2IOBOBufMode::TriStateGTS1),
+    ("GTS2-controlled tri-state", XC2IOBOBufMode::TriStateGTS2),
+    ("GTS3-controlled tri-state", XC2IOBOBufMode::TriStateGTS3),
+    ("PTB-controlled tri-state", XC2IOBOBufMode::TriStatePTB),
+    ("CTE-controlled tri-state", XC2IOBOBufMode::TriStateCTE),
+    ("CGND", XC2IOBOBufMode::CGND),
+];
+static IBUF_MODE_CHOICES: [(&str, XC2IOBIbufMode); 4] = [
+    ("no VREF, no Schmitt trigger", XC2IOBIbufMode::NoVrefNoSt),
+    ("no VREF, Schmitt trigger", XC2IOBIbufMode::NoVrefSt),
+    ("uses VREF (HSTL/SSTL)", XC2IOBIbufMode::UsesVref),
+    ("is a VREF pin", XC2IOBIbufMode::IsVref),
+];
+static OBUF_SRC_CHOICES: [(&str, bool); 2] = [("FF", true), ("XOR gate", false)];
+static SLEW_CHOICES: [(&str, bool); 2] = [("fast", true), ("slow", false)];
+static IOB_ZIA_MODE_CHOICES: [(&str, XC2IOBZIAMode); 3] = [
+    ("disabled", XC2IOBZIAMode::Disabled),
+    ("input pad", XC2IOBZIAMode::PAD),
+    ("register", XC2IOBZIAMode::REG),
+];
+
+static REG_MODE_CHOICES: [(&str, XC2MCRegMode); 4] = [
+    ("D flip-flop", XC2MCRegMode::DFF),
+    ("transparent latch", XC2MCRegMode::LATCH),
+    ("T flip-flop", XC2MCRegMode::TFF),
+    ("D flip-flop with clock-enable", XC2MCRegMode::DFFCE),
+];
+static INIT_STATE_CHOICES: [(&str, bool); 2] = [("1", true), ("0", false)];
+static CLK_EDGE_CHOICES: [(&str, bool); 2] = [("falling-edge triggered", true), ("rising-edge triggered", false)];
+static CLK_SRC_CHOICES: [(&str, XC2MCRegClkSrc); 5] = [
+    ("GCK0", XC2MCRegClkSrc::GCK0),
+    ("GCK1", XC2MCRegClkSrc::GCK1),
+    ("GCK2", XC2MCRegClkSrc::GCK2),
+    ("PTC", XC2MCRegClkSrc::PTC),
+    ("CTC", XC2MCRegClkSrc::CTC),
+];
+static SET_SRC_CHOICES: [(&str, XC2MCRegSetSrc); 4] = [
+    ("disabled", XC2MCRegSetSrc::Disabled),
+    ("PTA", XC2MCRegSetSrc::PTA),
+    ("GSR", XC2MCRegSetSrc::GSR),
+    ("CTS", XC2MCRegSetSrc::CTS),
+];
+static RESET_SRC_CHOICES: [(&str, XC2MCRegResetSrc); 4] = [
+    ("disabled", XC2MCRegResetSrc::Disabled),
+    ("PTA", XC2MCRegResetSrc::PTA),
+    ("GSR", XC2MCRegResetSrc::GSR),
+    ("CTR", XC2MCRegResetSrc::CTR),
+];
+static XOR_MODE_CHOICES: [(&str, XC2MCXorMode); 4] = [
+    ("0", XC2MCXorMode::ZERO),
+    ("1", XC2MCXorMode::ONE),
+    ("PTC", XC2MCXorMode::PTC),
+    ("~PTC", XC2MCXorMode::PTCB),
+];
+static FB_MODE_CHOICES: [(&str, XC2MCFeedbackMode); 3] = [
+    ("disabled", XC2MCFeedbackMode::Disabled),
+    ("combinatorial", XC2MCFeedbackMode::COMB),
+    ("registered", XC2MCFeedbackMode::REG),
+];
+
+/// Helper for reading the dump one line at a time while keeping track of the line number
+struct DumpReader<'a> {
+    lines: Vec<&'a str>,
+    pos: usize,
+}
+
+impl<'a> DumpReader<'a> {
+    /// Line number of the line that was most recently read, for error messages
+    fn line_num(&self) -> usize {
+        self.pos
+    }
+
+    fn err(&self, msg: &str) -> XC2BitError {
+        XC2BitError::DumpParseError{msg: msg.to_owned(), line: Some(self.line_num())}
+    }
+
+    fn next_line(&mut self) -> Result<&'a str, XC2BitError> {
+        if self.pos >= self.lines.len() {
+            return Err(XC2BitError::DumpParseError{
+                msg: String::from("unexpected end of dump"),
+                line: Some(self.pos + 1),
+            });
+        }
+
+        let l = self.lines[self.pos];
+        self.pos += 1;
+        Ok(l)
+    }
+
+    /// Reads a line that must be exactly `expected`
+    fn expect_line(&mut self, expected: &str) -> Result<(), XC2BitError> {
+        let l = self.next_line()?;
+        if l != expected {
+            return Err(self.err(&format!("expected \"{}\"", expected)));
+        }
+        Ok(())
+    }
+
+    /// Reads a line that must start with `prefix` and returns the rest of it
+    fn expect_value(&mut self, prefix: &str) -> Result<&'a str, XC2BitError> {
+        let l = self.next_line()?;
+        if !l.starts_with(prefix) {
+            return Err(self.err(&format!("expected \"{}\"", prefix)));
+        }
+        Ok(&l[prefix.len()..])
+    }
+
+    /// Reads a line that must start with `prefix` and be followed by one of the strings in `choices`
+    fn expect_choice<T: Copy>(&mut self, prefix: &str, choices: &[(&str, T)]) -> Result<T, XC2BitError> {
+        let value = self.expect_value(prefix)?;
+        for &(s, x) in choices {
+            if value == s {
+                return Ok(x);
+            }
+        }
+        Err(self.err(&format!("invalid value \"{}\" after \"{}\"", value, prefix)))
+    }
+}
+
+/// Parses a macrocell name such as "FB1_2" into a zero-based function block and macrocell number
+pub fn parse_fb_ff_name(s: &str) -> Option<(u32, u32)> {
+    if !s.starts_with("FB") {
+        return None;
+    }
+
+    let mut pieces = s[2..].split('_');
+    let fb = pieces.next().and_then(|x| x.parse::<u32>().ok());
+    let ff = pieces.next().and_then(|x| x.parse::<u32>().ok());
+    if pieces.next().is_some() {
+        return None;
+    }
+
+    match (fb, ff) {
+        (Some(fb), Some(ff)) if fb >= 1 && ff >= 1 => Some((fb - 1, ff - 1)),
+        _ => None,
+    }
+}
+
+/// Reads a row label such as " 3:" and returns the rest of the line
+fn read_row_label<'a>(reader: &mut DumpReader<'a>, expected_row: usize) -> Result<&'a str, XC2BitError> {
+    let l = reader.next_line()?;
+    let colon_pos = match l.find(':') {
+        Some(x) => x,
+        None => return Err(reader.err("missing row number")),
+    };
+    if l[..colon_pos].trim().parse::<usize>() != Ok(expected_row) {
+        return Err(reader.err(&format!("expected row {}", expected_row)));
+    }
+    Ok(&l[colon_pos + 1..])
+}
+
+/// Reads one row of an AND or OR term table. Each cell is either blank or filled with X characters.
+fn read_pla_row(reader: &mut DumpReader, expected_row: usize, out: &mut [bool]) -> Result<(), XC2BitError> {
+    let row = read_row_label(reader, expected_row)?;
+    if !row.starts_with('|') {
+        return Err(reader.err("expected '|'"));
+    }
+
+    let cells = row[1..].split('|').collect::<Vec<_>>();
+    if cells.len() != out.len() {
+        return Err(reader.err(&format!("row has {} entries but should have {}", cells.len(), out.len())));
+    }
+
+    for i in 0..out.len() {
+        let cell = cells[i].trim();
+        if cell.is_empty() {
+            out[i] = false;
+        } else if cell.chars().all(|c| c == 'X') {
+            out[i] = true;
+        } else {
+            return Err(reader.err(&format!("invalid entry \"{}\"", cell)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks whether `choice` can be selected on the given row of the ZIA
+fn zia_choice_is_valid(device: XC2Device, row: u32, choice: XC2ZIAInput) -> bool {
+    match device {
+        XC2Device::XC2C32 | XC2Device::XC2C32A => encode_32_zia_choice(row, choice).is_some(),
+        XC2Device::XC2C64 | XC2Device::XC2C64A => encode_64_zia_choice(row, choice).is_some(),
+        XC2Device::XC2C128 => encode_128_zia_choice(row, choice).is_some(),
+        XC2Device::XC2C256 => encode_256_zia_choice(row, choice).is_some(),
+        XC2Device::XC2C384 => encode_384_zia_choice(row, choice).is_some(),
+        XC2Device::XC2C512 => encode_512_zia_choice(row, choice).is_some(),
+    }
+}
+
+fn read_iob_header(reader: &mut DumpReader, device: XC2Device, iob: u32) -> Result<(), XC2BitError> {
+    let (fb, ff) = iob_num_to_fb_ff_num(device, iob).unwrap();
+    reader.expect_line("")?;
+    reader.expect_line(&format!("I/O configuration for FB{}_{}", fb + 1, ff + 1))
+}
+
+fn read_small_iob(reader: &mut DumpReader, device: XC2Device, iob: u32) -> Result<XC2MCSmallIOB, XC2BitError> {
+    read_iob_header(reader, device, iob)?;
+
+    let obuf_mode = reader.expect_choice("output mode: ", &OBUF_MODE_CHOICES)?;
+    let obuf_uses_ff = reader.expect_choice("output comes from ", &OBUF_SRC_CHOICES)?;
+    let slew_is_fast = reader.expect_choice("slew rate: ", &SLEW_CHOICES)?;
+    let zia_mode = reader.expect_choice("ZIA driven from: ", &IOB_ZIA_MODE_CHOICES)?;
+    let schmitt_trigger = reader.expect_choice("Schmitt trigger input: ", &YES_NO_CHOICES)?;
+    let termination_enabled = reader.expect_choice("termination: ", &YES_NO_CHOICES)?;
+
+    Ok(XC2MCSmallIOB {
+        zia_mode,
+        schmitt_trigger,
+        obuf_uses_ff,
+        obuf_mode,
+        termination_enabled,
+        slew_is_fast,
+    })
+}
+
+fn read_large_iob(reader: &mut DumpReader, device: XC2Device, iob: u32) -> Result<XC2MCLargeIOB, XC2BitError> {
+    read_iob_header(reader, device, iob)?;
+
+    let obuf_mode = reader.expect_choice("output mode: ", &OBUF_MODE_CHOICES)?;
+    let ibuf_mode = reader.expect_choice("input mode: ", &IBUF_MODE_CHOICES)?;
+    let obuf_uses_ff = reader.expect_choice("output comes from ", &OBUF_SRC_CHOICES)?;
+    let slew_is_fast = reader.expect_choice("slew rate: ", &SLEW_CHOICES)?;
+    let zia_mode = reader.expect_choice("ZIA driven from: ", &IOB_ZIA_MODE_CHOICES)?;
+    let termination_enabled = reader.expect_choice("termination: ", &YES_NO_CHOICES)?;
+    let uses_data_gate = reader.expect_choice("DataGate used: ", &YES_NO_CHOICES)?;
+
+    Ok(XC2MCLargeIOB {
+        zia_mode,
+        ibuf_mode,
+        obuf_uses_ff,
+        obuf_mode,
+        termination_enabled,
+        slew_is_fast,
+        uses_data_gate,
+    })
+}
+
+fn read_mc(reader: &mut DumpReader, fb: u32, ff: u32) -> Result<XC2Macrocell, XC2BitError> {
+    reader.expect_line("")?;
+    reader.expect_line(&format!("FF configuration for FB{}_{}", fb + 1, ff + 1))?;
+
+    let reg_mode = reader.expect_choice("FF mode: ", &REG_MODE_CHOICES)?;
+    let init_state = reader.expect_choice("initial state: ", &INIT_STATE_CHOICES)?;
+    let clk_invert_pol = reader.expect_choice("", &CLK_EDGE_CHOICES)?;
+    let is_ddr = reader.expect_choice("DDR: ", &YES_NO_CHOICES)?;
+    let clk_src = reader.expect_choice("clock source: ", &CLK_SRC_CHOICES)?;
+    let s_src = reader.expect_choice("set source: ", &SET_SRC_CHOICES)?;
+    let r_src = reader.expect_choice("reset source: ", &RESET_SRC_CHOICES)?;
+    let ff_in_ibuf = reader.expect_choice("using ibuf direct path: ", &YES_NO_CHOICES)?;
+    let xor_mode = reader.expect_choice("XOR gate input: ", &XOR_MODE_CHOICES)?;
+    let fb_mode = reader.expect_choice("ZIA feedback: ", &FB_MODE_CHOICES)?;
+
+    Ok(XC2Macrocell {
+        clk_src,
+        clk_invert_pol,
+        is_ddr,
+        r_src,
+        s_src,
+        init_state,
+        reg_mode,
+        fb_mode,
+        ff_in_ibuf,
+        xor_mode,
+    })
+}
+
+fn read_fb(reader: &mut DumpReader, device: XC2Device, fb: u32, out: &mut XC2BitstreamFB) -> Result<(), XC2BitError> {
+    for i in 0..MCS_PER_FB {
+        out.ffs[i] = read_mc(reader, fb, i as u32)?;
+    }
+
+    reader.expect_line("")?;
+    reader.expect_line(&format!("ZIA inputs for FB{}", fb + 1))?;
+    for i in 0..INPUTS_PER_ANDTERM {
+        let value = read_row_label(reader, i)?.trim();
+        let choice = if value == "0" {
+            XC2ZIAInput::Zero
+        } else if value == "1" {
+            XC2ZIAInput::One
+        } else if value == "dedicated input" {
+            XC2ZIAInput::DedicatedInput
+        } else if let Some(name) = value.strip_suffix(" FF") {
+            match parse_fb_ff_name(name) {
+                Some((fb, ff)) if (fb as usize) < device.num_fbs() && (ff as usize) < MCS_PER_FB =>
+                    XC2ZIAInput::Macrocell{fb, ff},
+                _ => return Err(reader.err(&format!("invalid macrocell \"{}\"", value))),
+            }
+        } else if let Some(name) = value.strip_suffix(" pad") {
+            match parse_fb_ff_name(name)
+                .and_then(|(fb, ff)| fb_ff_num_to_iob_num(device, fb, ff)) {
+
+                Some(ibuf) => XC2ZIAInput::IBuf{ibuf},
+                None => return Err(reader.err(&format!("invalid pad \"{}\"", value))),
+            }
+        } else {
+            return Err(reader.err(&format!("invalid ZIA input \"{}\"", value)));
+        };
+
+        if !zia_choice_is_valid(device, i as u32, choice) {
+            return Err(reader.err(&format!("\"{}\" cannot be selected on ZIA row {}", value, i)));
+        }
+        out.zia_bits[i].selected = choice;
+    }
+
+    reader.expect_line("")?;
+    reader.expect_line(&format!("AND terms for FB{}", fb + 1))?;
+    reader.expect_value("   |")?;
+    for i in 0..ANDTERMS_PER_FB {
+        let mut row = [false; INPUTS_PER_ANDTERM * 2];
+        read_pla_row(reader, i, &mut row)?;
+        for j in 0..INPUTS_PER_ANDTERM {
+            out.and_terms[i].input[j] = row[j * 2];
+            out.and_terms[i].input_b[j] = row[j * 2 + 1];
+        }
+    }
+
+    reader.expect_line("")?;
+    reader.expect_line(&format!("OR terms for FB{}", fb + 1))?;
+    reader.expect_value("   |")?;
+    for i in 0..MCS_PER_FB {
+        read_pla_row(reader, i, &mut out.or_terms[i].input)?;
+    }
+
+    Ok(())
+}
+
+/// Reads a dump in the format written by `XC2Bitstream::dump_human_readable` back into a bitstream. Errors contain
+/// the line number where the problem was found.
+pub fn read_human_readable(in_bytes: &[u8]) -> Result<XC2Bitstream, XC2BitError> {
+    let in_str = match str::from_utf8(in_bytes) {
+        Ok(x) => x,
+        Err(_) => return Err(XC2BitError::DumpParseError{msg: String::from("invalid characters in dump"), line: None}),
+    };
+
+    let mut reader = DumpReader {
+        lines: in_str.split('\n').map(|l| l.trim_end()).collect::<Vec<_>>(),
+        pos: 0,
+    };
+
+    reader.expect_line("xc2bit dump")?;
+    let speed_grade = reader.expect_value("device speed grade: ")?;
+    let package = reader.expect_value("device package: ")?;
+    let device = reader.expect_value("device type: ")?;
+    let (device, speed_grade, package) =
+        match parse_part_name_string(&format!("{}-{}-{}", device, speed_grade, package)) {
+            Some(x) => x,
+            None => return Err(reader.err("invalid device, speed grade, or package")),
+        };
+    let mut bitstream = match XC2Bitstream::blank_bitstream(device, speed_grade, package) {
+        Ok(x) => x,
+        Err(e) => return Err(reader.err(&e.to_string())),
+    };
+
+    // Bank voltages
+    match bitstream.bits {
+        XC2BitstreamBits::XC2C32 {ref mut ivoltage, ref mut ovoltage, ..} |
+        XC2BitstreamBits::XC2C64 {ref mut ivoltage, ref mut ovoltage, ..} => {
+            *ovoltage = reader.expect_choice("output voltage range: ", &VOLTAGE_CHOICES)?;
+            *ivoltage = reader.expect_choice("input voltage range: ", &VOLTAGE_CHOICES)?;
+        },
+        XC2BitstreamBits::XC2C32A {ref mut legacy_ivoltage, ref mut legacy_ovoltage, ref mut ivoltage,
+                                   ref mut ovoltage, ..} |
+        XC2BitstreamBits::XC2C64A {ref mut legacy_ivoltage, ref mut legacy_ovoltage, ref mut ivoltage,
+                                   ref mut ovoltage, ..} => {
+            *legacy_ovoltage = reader.expect_choice("legacy output voltage range: ", &VOLTAGE_CHOICES)?;
+            *legacy_ivoltage = reader.expect_choice("legacy input voltage range: ", &VOLTAGE_CHOICES)?;
+            for i in 0..2 {
+                ovoltage[i] = reader.expect_choice(&format!("bank {} output voltage range: ", i), &VOLTAGE_CHOICES)?;
+            }
+            for i in 0..2 {
+                ivoltage[i] = reader.expect_choice(&format!("bank {} input voltage range: ", i), &VOLTAGE_CHOICES)?;
+            }
+        },
+        XC2BitstreamBits::XC2C128 {ref mut ivoltage, ref mut ovoltage, ref mut data_gate, ref mut use_vref, ..} |
+        XC2BitstreamBits::XC2C256 {ref mut ivoltage, ref mut ovoltage, ref mut data_gate, ref mut use_vref, ..} => {
+            for i in 0..2 {
+                ovoltage[i] = reader.expect_choice(&format!("bank {} output voltage range: ", i), &VOLTAGE_CHOICES)?;
+            }
+            for i in 0..2 {
+                ivoltage[i] = reader.expect_choice(&format!("bank {} input voltage range: ", i), &VOLTAGE_CHOICES)?;
+            }
+            *data_gate = reader.expect_choice("DataGate used: ", &YES_NO_CHOICES)?;
+            *use_vref = reader.expect_choice("VREF used: ", &YES_NO_CHOICES)?;
+        },
+        XC2BitstreamBits::XC2C384 {ref mut ivoltage, ref mut ovoltage, ref mut data_gate, ref mut use_vref, ..} |
+        XC2BitstreamBits::XC2C512 {ref mut ivoltage, ref mut ovoltage, ref mut data_gate, ref mut use_vref, ..} => {
+            for i in 0..4 {
+                ovoltage[i] = reader.expect_choice(&format!("bank {} output voltage range: ", i), &VOLTAGE_CHOICES)?;
+            }
+            for i in 0..4 {
+                ivoltage[i] = reader.expect_choice(&format!("bank {} input voltage range: ", i), &VOLTAGE_CHOICES)?;
+            }
+            *data_gate = reader.expect_choice("DataGate used: ", &YES_NO_CHOICES)?;
+            *use_vref = reader.expect_choice("VREF used: ", &YES_NO_CHOICES)?;
+        }
+    }
+
+    // Clock divider
+    if let Some(clock_div) = bitstream.bits.get_clock_div_mut() {
+        reader.expect_line("")?;
+        clock_div.enabled = reader.expect_choice("GCK2 clock divider ", &ENABLED_CHOICES)?;
+        clock_div.delay = reader.expect_choice("clock divider delay ", &ENABLED_CHOICES)?;
+        clock_div.div_ratio = reader.expect_choice("clock division ratio: ", &CLOCK_DIV_RATIO_CHOICES)?;
+    }
+
+    // Global net configuration
+    {
+        let global_nets = bitstream.bits.get_global_nets_mut();
+        reader.expect_line("")?;
+        for i in 0..3 {
+            global_nets.gck_enable[i] = reader.expect_choice(&format!("GCK{} ", i), &ENABLED_CHOICES)?;
+        }
+        let (gsr_enable, gsr_invert) = reader.expect_choice("GSR ", &GSR_CHOICES)?;
+        global_nets.gsr_enable = gsr_enable;
+        global_nets.gsr_invert = gsr_invert;
+        for i in 0..4 {
+            let (gts_enable, gts_invert) = reader.expect_choice(&format!("GTS{} ", i), &GTS_CHOICES)?;
+            global_nets.gts_enable[i] = gts_enable;
+            global_nets.gts_invert[i] = gts_invert;
+        }
+        global_nets.global_pu = reader.expect_choice("global termination is ",
+            &[("pull-up", true), ("bus hold", false)])?;
+    }
+
+    // IOBs
+    match bitstream.bits {
+        XC2BitstreamBits::XC2C32 {ref mut iobs, ..} |
+        XC2BitstreamBits::XC2C32A {ref mut iobs, ..} => {
+            for i in 0..device.num_iobs() {
+                iobs[i] = read_small_iob(&mut reader, device, i as u32)?;
+            }
+        },
+        XC2BitstreamBits::XC2C64 {ref mut iobs, ..} |
+        XC2BitstreamBits::XC2C64A {ref mut iobs, ..} => {
+            for i in 0..device.num_iobs() {
+                iobs[i] = read_small_iob(&mut reader, device, i as u32)?;
+            }
+        },
+        XC2BitstreamBits::XC2C128 {ref mut iobs, ..} => {
+            for i in 0..device.num_iobs() {
+                iobs[i] = read_large_iob(&mut reader, device, i as u32)?;
+            }
+        },
+        XC2BitstreamBits::XC2C256 {ref mut iobs, ..} => {
+            for i in 0..device.num_iobs() {
+                iobs[i] = read_large_iob(&mut reader, device, i as u32)?;
+            }
+        },
+        XC2BitstreamBits::XC2C384 {ref mut iobs, ..} => {
+            for i in 0..device.num_iobs() {
+                iobs[i] = read_large_iob(&mut reader, device, i as u32)?;
+            }
+        },
+        XC2BitstreamBits::XC2C512 {ref mut iobs, ..} => {
+            for i in 0..device.num_iobs() {
+                iobs[i] = read_large_iob(&mut reader, device, i as u32)?;
+            }
+        },
+    }
+
+    // Input-only pin
+    match bitstream.bits {
+        XC2BitstreamBits::XC2C32 {ref mut inpin, ..} | XC2BitstreamBits::XC2C32A {ref mut inpin, ..} => {
+            reader.expect_line("")?;
+            reader.expect_line("I/O configuration for input-only pin")?;
+            inpin.schmitt_trigger = reader.expect_choice("Schmitt trigger input: ", &YES_NO_CHOICES)?;
+            inpin.termination_enabled = reader.expect_choice("termination: ", &YES_NO_CHOICES)?;
+        },
+        _ => {}
+    }
+
+    // FBs
+    for i in 0..device.num_fbs() {
+        read_fb(&mut reader, device, i as u32, &mut bitstream.bits.get_fb_mut()[i])?;
+    }
+
+    // Only blank lines are allowed after the last FB
+    while reader.pos < reader.lines.len() {
+        if !reader.next_line()?.is_empty() {
+            return Err(reader.err("unexpected data after the end of the dump"));
+        }
+    }
+
+    Ok(bitstream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blank_dump() -> Vec<u8> {
+        let bitstream = XC2Bitstream::blank_bitstream(XC2Device::XC2C32A, XC2Speed::Speed6, XC2Package::VQ44)
+            .unwrap();
+        let mut dump = Vec::new();
+        bitstream.dump_human_readable(&mut dump).unwrap();
+        dump
+    }
+
+    #[test]
+    fn read_blank_dump() {
+        let dump = blank_dump();
+        let bitstream = read_human_readable(&dump).unwrap();
+        let mut new_dump = Vec::new();
+        bitstream.dump_human_readable(&mut new_dump).unwrap();
+        assert_eq!(dump, new_dump);
+    }
+
+    #[test]
+    fn ignores_trailing_whitespace() {
+        let dump = String::from_utf8(blank_dump()).unwrap();
+        let dump = dump.lines().map(|l| format!("{}  \r\n", l.trim_end())).collect::<String>();
+        let bitstream = read_human_readable(dump.as_bytes()).unwrap();
+        let mut new_dump = Vec::new();
+        bitstream.dump_human_readable(&mut new_dump).unwrap();
+        assert_eq!(blank_dump(), new_dump);
+    }
+
+    #[test]
+    fn reports_line_numbers() {
+        let dump = String::from_utf8(blank_dump()).unwrap();
+
+        let bad_value = dump.replacen("GSR disabled, active low", "GSR sometimes", 1);
+        let line_num = dump.lines().position(|l| l.starts_with("GSR")).unwrap() + 1;
+        assert_eq!(read_human_readable(bad_value.as_bytes()).err().unwrap().to_string(),
+            format!("line {}: invalid value \"sometimes\" after \"GSR \"", line_num));
+
+        let bad_zia = dump.replacen(" 0: 1\n", " 0: FB9_1 FF\n", 1);
+        let line_num = dump.lines().position(|l| l == " 0: 1").unwrap() + 1;
+        assert_eq!(read_human_readable(bad_zia.as_bytes()).err().unwrap().to_string(),
+            format!("line {}: invalid macrocell \"FB9_1 FF\"", line_num));
+
+        let truncated = dump.lines().take(10).collect::<Vec<_>>().join("\n");
+        assert_eq!(read_human_readable(truncated.as_bytes()).err().unwrap(), XC2BitError::DumpParseError{
+            msg: String::from("unexpected end of dump"),
+            line: Some(11),
+        });
+
+        let extra = format!("{}garbage\n", dump);
+        assert_eq!(read_human_readable(extra.as_bytes()).err().unwrap().to_string(),
+            format!("line {}: unexpected data after the end of the dump", dump.lines().count() + 1));
+    }
+}
@@ -29,6 +29,7 @@ use std::io;
 use std::io::Write;
 
 use *;
+use fusemap_logical::{mc_fuse_idx};
 use fusemap_physical::{mc_block_loc};
 use mc::{MC_TO_ROW_MAP_LARGE};
 
@@ -36,6 +37,7 @@ use mc::{MC_TO_ROW_MAP_LARGE};
 /// input pin directly or from the output of the register in the macrocell corresponding to this I/O pin. The latter
 /// is used to allow for buried combinatorial feedback in a macrocell without "wasting" the register.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum XC2IOBZIAMode {
     Disabled,
     PAD,
@@ -44,6 +46,7 @@ pub enum XC2IOBZIAMode {
 
 /// Mode selection for the I/O pin's output buffer. See the Xilinx Coolrunner-II documentation for more information.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum XC2IOBOBufMode {
     Disabled,
     PushPull,
@@ -59,6 +62,7 @@ pub enum XC2IOBOBufMode {
 
 /// Represents an I/O pin on "small" (32 and 64 macrocell) devices.
 #[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct XC2MCSmallIOB {
     /// Mux selection for the ZIA input for this pin
     pub zia_mode: XC2IOBZIAMode,
@@ -95,10 +99,10 @@ impl Default for XC2MCSmallIOB {
 
 impl XC2MCSmallIOB {
     /// Dump a human-readable explanation of the settings for this pin to the given `writer` object.
-    /// `my_idx` must be the index of this I/O pin in the internal numbering scheme.
+    /// `my_idx` must be the index of this I/O pin in the internal numbering scheme, otherwise an error is returned.
     pub fn dump_human_readable(&self, device: XC2Device, my_idx: u32, writer: &mut Write) -> Result<(), io::Error> {
         write!(writer, "\n")?;
-        let (fb, ff) = iob_num_to_fb_ff_num(device, my_idx).unwrap();
+        let (fb, ff) = iob_num_to_fb_ff_num(device, my_idx).ok_or(XC2BitError::BadIOBIndex{device, iob: my_idx})?;
         write!(writer, "I/O configuration for FB{}_{}\n", fb + 1, ff + 1)?;
         write!(writer, "output mode: {}\n", match self.obuf_mode {
             XC2IOBOBufMode::Disabled => "disabled",
@@ -218,10 +222,94 @@ impl XC2MCSmallIOB {
             _ => unreachable!(),
         }
     }
+
+    /// Read the crbit representation of the I/O pin `iob` from the given `fuse_array`.
+    pub fn from_crbit(device: XC2Device, iob: u32, fuse_array: &FuseArray) -> Result<XC2MCSmallIOB, XC2BitError> {
+        let (fb, mc) = iob_num_to_fb_ff_num(device, iob).unwrap();
+        let (x, y, mirror) = mc_block_loc(device, fb);
+        // direction
+        let x = x as i32;
+        let d = if !mirror {1} else {-1};
+        let inz;
+        let st;
+        let regcom;
+        let oe;
+        let tm;
+        let slw;
+        match device {
+            XC2Device::XC2C32 | XC2Device::XC2C32A => {
+                // The "32" variant
+                // each macrocell is 3 rows high
+                let y = y + (mc as usize) * 3;
+
+                inz = (fuse_array.get((x + d * 2) as usize, y + 1),
+                       fuse_array.get((x + d * 3) as usize, y + 1));
+                st = fuse_array.get((x + d * 7) as usize, y + 1);
+                regcom = fuse_array.get((x + d * 1) as usize, y + 2);
+                oe = (fuse_array.get((x + d * 2) as usize, y + 2),
+                      fuse_array.get((x + d * 3) as usize, y + 2),
+                      fuse_array.get((x + d * 4) as usize, y + 2),
+                      fuse_array.get((x + d * 5) as usize, y + 2));
+                tm = fuse_array.get((x + d * 6) as usize, y + 2);
+                slw = fuse_array.get((x + d * 7) as usize, y + 2);
+            },
+            XC2Device::XC2C64 | XC2Device::XC2C64A => {
+                // The "64" variant
+                // each macrocell is 3 rows high
+                let y = y + (mc as usize) * 3;
+
+                inz = (fuse_array.get((x + d * 5) as usize, y + 1),
+                       fuse_array.get((x + d * 6) as usize, y + 1));
+                st = fuse_array.get((x + d * 1) as usize, y + 1);
+                regcom = fuse_array.get((x + d * 0) as usize, y + 1);
+                oe = (fuse_array.get((x + d * 3) as usize, y + 2),
+                      fuse_array.get((x + d * 4) as usize, y + 2),
+                      fuse_array.get((x + d * 5) as usize, y + 2),
+                      fuse_array.get((x + d * 6) as usize, y + 2));
+                tm = fuse_array.get((x + d * 2) as usize, y + 2);
+                slw = fuse_array.get((x + d * 1) as usize, y + 2);
+            },
+            _ => unreachable!(),
+        }
+
+        let input_to_zia = match inz {
+            (false, false) => XC2IOBZIAMode::PAD,
+            (true, false) => XC2IOBZIAMode::REG,
+            (_, true) => XC2IOBZIAMode::Disabled,
+        };
+
+        let output_mode = match oe {
+            (false, false, false, false) => XC2IOBOBufMode::PushPull,
+            (false, false, false, true)  => XC2IOBOBufMode::OpenDrain,
+            (false, false, true, false)  => XC2IOBOBufMode::TriStateGTS1,
+            (false, true, false, false)  => XC2IOBOBufMode::TriStatePTB,
+            (false, true, true, false)   => XC2IOBOBufMode::TriStateGTS3,
+            (true, false, false, false)  => XC2IOBOBufMode::TriStateCTE,
+            (true, false, true, false)   => XC2IOBOBufMode::TriStateGTS2,
+            (true, true, false, false)   => XC2IOBOBufMode::TriStateGTS0,
+            (true, true, true, false)    => XC2IOBOBufMode::CGND,
+            (true, true, true, true)     => XC2IOBOBufMode::Disabled,
+            _ => return Err(XC2BitError::UnknownOEMode {
+                iob: iob,
+                // Errors refer to the fuses in logical order
+                fuse_idx: mc_fuse_idx(device, fb, mc) + 20,
+            }),
+        };
+
+        Ok(XC2MCSmallIOB {
+            zia_mode: input_to_zia,
+            schmitt_trigger: st,
+            obuf_uses_ff: !regcom,
+            obuf_mode: output_mode,
+            termination_enabled: tm,
+            slew_is_fast: !slw,
+        })
+    }
 }
 
 /// Input mode selection on larger parts with VREF
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum XC2IOBIbufMode {
     /// This input buffer is not using VREF, and it is also not using the Schmitt trigger
     NoVrefNoSt,
@@ -235,6 +323,7 @@ pub enum XC2IOBIbufMode {
 
 /// Represents an I/O pin on "large" (128 and greater macrocell) devices.
 #[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct XC2MCLargeIOB {
     /// Mux selection for the ZIA input for this pin
     pub zia_mode: XC2IOBZIAMode,
@@ -274,10 +363,10 @@ impl Default for XC2MCLargeIOB {
 
 impl XC2MCLargeIOB {
     /// Dump a human-readable explanation of the settings for this pin to the given `writer` object.
-    /// `my_idx` must be the index of this I/O pin in the internal numbering scheme.
+    /// `my_idx` must be the index of this I/O pin in the internal numbering scheme, otherwise an error is returned.
     pub fn dump_human_readable(&self, device: XC2Device, my_idx: u32, writer: &mut Write) -> Result<(), io::Error> {
         write!(writer, "\n")?;
-        let (fb, ff) = iob_num_to_fb_ff_num(device, my_idx).unwrap();
+        let (fb, ff) = iob_num_to_fb_ff_num(device, my_idx).ok_or(XC2BitError::BadIOBIndex{device, iob: my_idx})?;
         write!(writer, "I/O configuration for FB{}_{}\n", fb + 1, ff + 1)?;
         write!(writer, "output mode: {}\n", match self.obuf_mode {
             XC2IOBOBufMode::Disabled => "disabled",
@@ -391,10 +480,14 @@ impl XC2MCLargeIOB {
                 // we need this funny lookup table, but otherwise macrocells are 2x15
                 let y = y + MC_TO_ROW_MAP_LARGE[mc as usize];
 
+                // Row 0 is INz, FB, DG, InMod, Tm, then the clock settings, and row 1 is XorIn, Oe, Slw, RegCom, then
+                // the register settings. This is the order that XC2Macrocell::to_crbit leaves room for. Row 1
+                // x = 0/1 is XorIn and row 2 belongs to the next macrocell, so INz and Tm cannot go there.
+
                 // inz
                 let inz = self.inz();
-                fuse_array.set((x + d * 0) as usize, y + 1, inz.0);
-                fuse_array.set((x + d * 1) as usize, y + 1, inz.1);
+                fuse_array.set((x + d * 0) as usize, y + 0, inz.0);
+                fuse_array.set((x + d * 1) as usize, y + 0, inz.1);
 
                 // dg
                 fuse_array.set((x + d * 4) as usize, y + 0, self.uses_data_gate);
@@ -405,7 +498,7 @@ impl XC2MCLargeIOB {
                 fuse_array.set((x + d * 6) as usize, y + 0, inmod.1);
 
                 // tm
-                fuse_array.set((x + d * 7) as usize, y + 2, self.termination_enabled);
+                fuse_array.set((x + d * 7) as usize, y + 0, self.termination_enabled);
 
                 // oe
                 let oe = self.oe();
@@ -423,9 +516,106 @@ impl XC2MCLargeIOB {
             _ => unreachable!(),
         }
     }
+
+    /// Read the crbit representation of the I/O pin `iob` from the given `fuse_array`.
+    pub fn from_crbit(device: XC2Device, iob: u32, fuse_array: &FuseArray) -> Result<XC2MCLargeIOB, XC2BitError> {
+        let (fb, mc) = iob_num_to_fb_ff_num(device, iob).unwrap();
+        let (x, y, mirror) = mc_block_loc(device, fb);
+        // direction
+        let x = x as i32;
+        let d = if !mirror {1} else {-1};
+        let inmod;
+        let dg;
+        let oe;
+        let inz;
+        let tm;
+        let slw;
+        let regcom;
+        match device {
+            XC2Device::XC2C256 => {
+                // The "256" variant
+                // each macrocell is 3 rows high
+                let y = y + (mc as usize) * 3;
+
+                inmod = (fuse_array.get((x + d * 0) as usize, y + 0),
+                         fuse_array.get((x + d * 1) as usize, y + 0));
+                dg = fuse_array.get((x + d * 4) as usize, y + 0);
+                oe = (fuse_array.get((x + d * 3) as usize, y + 1),
+                      fuse_array.get((x + d * 4) as usize, y + 1),
+                      fuse_array.get((x + d * 5) as usize, y + 1),
+                      fuse_array.get((x + d * 6) as usize, y + 1));
+                inz = (fuse_array.get((x + d * 7) as usize, y + 1),
+                       fuse_array.get((x + d * 8) as usize, y + 1));
+                tm = fuse_array.get((x + d * 2) as usize, y + 2);
+                slw = fuse_array.get((x + d * 3) as usize, y + 2);
+                regcom = fuse_array.get((x + d * 8) as usize, y + 2);
+            },
+            XC2Device::XC2C128 | XC2Device::XC2C384 | XC2Device::XC2C512 => {
+                // The "common large macrocell" variant
+                // we need this funny lookup table, but otherwise macrocells are 2x15
+                let y = y + MC_TO_ROW_MAP_LARGE[mc as usize];
+
+                inz = (fuse_array.get((x + d * 0) as usize, y + 0),
+                       fuse_array.get((x + d * 1) as usize, y + 0));
+                dg = fuse_array.get((x + d * 4) as usize, y + 0);
+                inmod = (fuse_array.get((x + d * 5) as usize, y + 0),
+                         fuse_array.get((x + d * 6) as usize, y + 0));
+                tm = fuse_array.get((x + d * 7) as usize, y + 0);
+                oe = (fuse_array.get((x + d * 2) as usize, y + 1),
+                      fuse_array.get((x + d * 3) as usize, y + 1),
+                      fuse_array.get((x + d * 4) as usize, y + 1),
+                      fuse_array.get((x + d * 5) as usize, y + 1));
+                slw = fuse_array.get((x + d * 6) as usize, y + 1);
+                regcom = fuse_array.get((x + d * 8) as usize, y + 1);
+            },
+            _ => unreachable!(),
+        }
+
+        let input_mode = match inmod {
+            (false, false) => XC2IOBIbufMode::NoVrefNoSt,
+            (false, true)  => XC2IOBIbufMode::IsVref,
+            (true, false)  => XC2IOBIbufMode::UsesVref,
+            (true, true)   => XC2IOBIbufMode::NoVrefSt,
+        };
+
+        let input_to_zia = match inz {
+            (false, false) => XC2IOBZIAMode::PAD,
+            (true, false) => XC2IOBZIAMode::REG,
+            (_, true) => XC2IOBZIAMode::Disabled,
+        };
+
+        let output_mode = match oe {
+            (false, false, false, false) => XC2IOBOBufMode::PushPull,
+            (false, false, false, true)  => XC2IOBOBufMode::OpenDrain,
+            (false, false, true, false)  => XC2IOBOBufMode::TriStateGTS1,
+            (false, true, false, false)  => XC2IOBOBufMode::TriStatePTB,
+            (false, true, true, false)   => XC2IOBOBufMode::TriStateGTS3,
+            (true, false, false, false)  => XC2IOBOBufMode::TriStateCTE,
+            (true, false, true, false)   => XC2IOBOBufMode::TriStateGTS2,
+            (true, true, false, false)   => XC2IOBOBufMode::TriStateGTS0,
+            (true, true, true, false)    => XC2IOBOBufMode::CGND,
+            (true, true, true, true)     => XC2IOBOBufMode::Disabled,
+            _ => return Err(XC2BitError::UnknownOEMode {
+                iob: iob,
+                // Errors refer to the fuses in logical order
+                fuse_idx: mc_fuse_idx(device, fb, mc) + 13,
+            }),
+        };
+
+        Ok(XC2MCLargeIOB {
+            zia_mode: input_to_zia,
+            ibuf_mode: input_mode,
+            obuf_uses_ff: !regcom,
+            obuf_mode: output_mode,
+            termination_enabled: tm,
+            slew_is_fast: !slw,
+            uses_data_gate: dg,
+        })
+    }
 }
 
 /// Represents the one additional special input-only pin on 32-macrocell devices.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct XC2ExtraIBuf {
     pub schmitt_trigger: bool,
     pub termination_enabled: bool,
@@ -1010,7 +1200,7 @@ pub fn fb_ff_num_to_iob_num(device: XC2Device, fb: u32, ff: u32) -> Option<u32>
 }
 
 /// Internal function that reads only the IO-related bits from the macrocell configuration
-pub fn read_small_iob_logical(fuses: &[bool], fuse_idx: usize) -> Result<XC2MCSmallIOB, &'static str> {
+pub fn read_small_iob_logical(fuses: &[bool], iob: u32, fuse_idx: usize) -> Result<XC2MCSmallIOB, XC2BitError> {
     let inz = (fuses[fuse_idx + 11],
                fuses[fuse_idx + 12]);
     let input_to_zia = match inz {
@@ -1037,7 +1227,10 @@ pub fn read_small_iob_logical(fuses: &[bool], fuse_idx: usize) -> Result<XC2MCSm
         (true, true, false, false)   => XC2IOBOBufMode::TriStateGTS0,
         (true, true, true, false)    => XC2IOBOBufMode::CGND,
         (true, true, true, true)     => XC2IOBOBufMode::Disabled,
-        _ => return Err("unknown Oe mode used"),
+        _ => return Err(XC2BitError::UnknownOEMode {
+            iob: iob,
+            fuse_idx: fuse_idx + 20,
+        }),
     };
 
     let tm = fuses[fuse_idx + 24];
@@ -1054,7 +1247,7 @@ pub fn read_small_iob_logical(fuses: &[bool], fuse_idx: usize) -> Result<XC2MCSm
 }
 
 /// Internal function that reads only the IO-related bits from the macrocell configuration
-pub fn read_large_iob_logical(fuses: &[bool], fuse_idx: usize) -> Result<XC2MCLargeIOB, &'static str> {
+pub fn read_large_iob_logical(fuses: &[bool], iob: u32, fuse_idx: usize) -> Result<XC2MCLargeIOB, XC2BitError> {
     let dg = fuses[fuse_idx + 5];
 
     let inmod = (fuses[fuse_idx + 8],
@@ -1089,7 +1282,10 @@ pub fn read_large_iob_logical(fuses: &[bool], fuse_idx: usize) -> Result<XC2MCLa
         (true, true, false, false)   => XC2IOBOBufMode::TriStateGTS0,
         (true, true, true, false)    => XC2IOBOBufMode::CGND,
         (true, true, true, true)     => XC2IOBOBufMode::Disabled,
-        _ => return Err("unknown Oe mode used"),
+        _ => return Err(XC2BitError::UnknownOEMode {
+            iob: iob,
+            fuse_idx: fuse_idx + 13,
+        }),
     };
 
     let regcom = fuses[fuse_idx + 20];
@@ -1118,3 +1314,51 @@ pub fn read_32_extra_ibuf_logical(fuses: &[bool]) -> XC2ExtraIBuf {
         termination_enabled: tm,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // On the XC2C128/384/512, a macrocell and its IOB use 29 of the 30 fuses in a 2x15 block. If two settings shared
+    // a location in the physical fuse array, one of them would be lost when converting to crbit and back.
+    #[test]
+    fn large_crbit_round_trip() {
+        for &(device, speed, package) in &[(XC2Device::XC2C128, XC2Speed::Speed6, XC2Package::VQ100),
+                                           (XC2Device::XC2C384, XC2Speed::Speed7, XC2Package::TQ144),
+                                           (XC2Device::XC2C512, XC2Speed::Speed7, XC2Package::PQ208)] {
+            let bitstream = XC2Bitstream::blank_bitstream(device, speed, package).unwrap();
+            let dev_name = format!("{}-{}-{}", device, speed, package);
+            let mut jed = Vec::new();
+            bitstream.write_jed(&mut jed).unwrap();
+            let (fuses, _) = read_jed(&jed).unwrap();
+
+            for mc in 0..3 {
+                let start = mc_fuse_idx(device, 0, mc);
+                for fuse_idx in start..start + 29 {
+                    let mut fuses = fuses.clone();
+                    fuses[fuse_idx] = !fuses[fuse_idx];
+                    let bitstream = match process_jed(&fuses, &dev_name) {
+                        Ok(x) => x,
+                        // Not every combination of settings is valid
+                        Err(_) => continue,
+                    };
+                    let crbit_bitstream = XC2Bitstream::from_crbit(&bitstream.to_crbit().unwrap()).unwrap();
+
+                    let mut jed = Vec::new();
+                    bitstream.write_jed(&mut jed).unwrap();
+                    let mut crbit_jed = Vec::new();
+                    crbit_bitstream.write_jed(&mut crbit_jed).unwrap();
+                    assert!(jed == crbit_jed, "{} fuse {} is lost in crbit", device, fuse_idx);
+                }
+            }
+        }
+    }
+    #[test]
+    fn dump_bad_iob_index() {
+        let mut out = Vec::new();
+        let err = XC2MCSmallIOB::default().dump_human_readable(XC2Device::XC2C32A, 32, &mut out).err().unwrap();
+        assert_eq!(err.to_string(), "I/O pin 32 does not exist in the XC2C32A");
+        let err = XC2MCLargeIOB::default().dump_human_readable(XC2Device::XC2C128, 100, &mut out).err().unwrap();
+        assert_eq!(err.to_string(), "I/O pin 100 does not exist in the XC2C128");
+    }
+}
@@ -0,0 +1,139 @@
+/*
+Copyright (c) 2017, Robert Ou <rqou@robertou.com> and contributors
+All rights reserved.
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice,
+   this list of conditions and the following disclaimer.
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+//! Contains routines for in-system configuration (ISC) programming.
+//!
+//! Devices are programmed one row of the physical fuse array at a time. While the ISC_PROGRAM instruction is loaded,
+//! each row is shifted into the data register as the fuse data (starting from the fuse at x = 0) followed by the row
+//! address (least significant bit first). Row addresses are sent in Gray-code order, so the address of row `y` is
+//! `y ^ (y >> 1)`.
+
+use *;
+
+/// JTAG instructions used for in-system programming
+pub const INSTR_BYPASS: u8 = 0xFF;
+pub const INSTR_IDCODE: u8 = 0x01;
+pub const INSTR_ISC_ENABLE: u8 = 0xE8;
+pub const INSTR_ISC_ERASE: u8 = 0xED;
+pub const INSTR_ISC_PROGRAM: u8 = 0xEA;
+pub const INSTR_ISC_READ: u8 = 0xEE;
+pub const INSTR_ISC_INIT: u8 = 0xF0;
+pub const INSTR_ISC_DISABLE: u8 = 0xC0;
+
+/// Converts a row number into the Gray-coded address used to program it
+pub fn gray_code(i: u32) -> u32 {
+    i ^ (i >> 1)
+}
+
+/// Returns the address bits for row `y` of the given device, in the order they are shifted
+pub fn isc_row_address(device: XC2Device, y: usize) -> Vec<bool> {
+    let addr = gray_code(y as u32);
+    (0..device.isc_address_width()).map(|i| addr & (1 << i) != 0).collect()
+}
+
+/// Returns the row that has the given address bits (in the order they are shifted), if there is one
+pub fn isc_row_from_address(device: XC2Device, address: &[bool]) -> Option<usize> {
+    if address.len() != device.isc_address_width() {
+        return None;
+    }
+
+    (0..device.isc_num_rows()).find(|&y| isc_row_address(device, y) == address)
+}
+
+/// Returns the `(address_bits, row_data_bits)` for every row of the fuse array in the order that the rows are
+/// programmed. The bits are in the order that they are shifted into the device. Fails if the fuse array does not have
+/// the dimensions of `device`.
+pub fn isc_rows(device: XC2Device, fuse_array: &FuseArray) -> Result<Vec<(Vec<bool>, Vec<bool>)>, XC2BitError> {
+    let expected_dims = (device.isc_data_width(), device.isc_num_rows());
+    if fuse_array.dim() != expected_dims {
+        return Err(XC2BitError::WrongFuseArrayDims {
+            expected: expected_dims,
+            found: fuse_array.dim(),
+        });
+    }
+
+    Ok((0..device.isc_num_rows()).map(|y| {
+        let data = (0..device.isc_data_width()).map(|x| fuse_array.get(x, y)).collect();
+        (isc_row_address(device, y), data)
+    }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gray_code_addresses() {
+        for i in 0..255 {
+            // Consecutive addresses must differ by exactly one bit
+            assert_eq!((gray_code(i) ^ gray_code(i + 1)).count_ones(), 1);
+        }
+    }
+
+    #[test]
+    fn address_widths() {
+        assert_eq!(XC2Device::XC2C32A.isc_address_width(), 6);
+        assert_eq!(XC2Device::XC2C64A.isc_address_width(), 7);
+        assert_eq!(XC2Device::XC2C512.isc_address_width(), 8);
+    }
+
+    #[test]
+    fn row_addresses() {
+        assert_eq!(isc_row_address(XC2Device::XC2C32A, 2), vec![true, true, false, false, false, false]);
+        assert_eq!(isc_row_from_address(XC2Device::XC2C32A, &[true, true, false, false, false, false]), Some(2));
+        // 49 is the last row, so the Gray code for 50 isn't a valid address
+        assert_eq!(isc_row_from_address(XC2Device::XC2C32A, &isc_row_address(XC2Device::XC2C64A, 50)[..6]), None);
+
+        for y in 0..XC2Device::XC2C512.isc_num_rows() {
+            assert_eq!(isc_row_from_address(XC2Device::XC2C512, &isc_row_address(XC2Device::XC2C512, y)), Some(y));
+        }
+    }
+
+    #[test]
+    fn rows_of_fuse_array() {
+        let bitstream = XC2Bitstream::blank_bitstream(XC2Device::XC2C64A, XC2Speed::Speed5, XC2Package::VQ44).unwrap();
+        let fuse_array = bitstream.to_crbit().unwrap();
+        let rows = isc_rows(XC2Device::XC2C64A, &fuse_array).unwrap();
+
+        assert_eq!(rows.len(), 98);
+        for (y, &(ref address, ref data)) in rows.iter().enumerate() {
+            assert_eq!(address.len(), 7);
+            assert_eq!(data.len(), 274);
+            assert_eq!(data[5], fuse_array.get(5, y));
+        }
+    }
+
+    #[test]
+    fn rows_of_wrong_fuse_array() {
+        let bitstream = XC2Bitstream::blank_bitstream(XC2Device::XC2C32A, XC2Speed::Speed4, XC2Package::VQ44).unwrap();
+        let fuse_array = bitstream.to_crbit().unwrap();
+
+        assert_eq!(isc_rows(XC2Device::XC2C64A, &fuse_array), Err(XC2BitError::WrongFuseArrayDims {
+            expected: (274, 98),
+            found: fuse_array.dim(),
+        }));
+        assert!(write_svf(XC2Device::XC2C64A, &fuse_array, &SVFChain::default(), &mut Vec::new()).is_err());
+    }
+}
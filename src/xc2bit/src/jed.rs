@@ -25,9 +25,14 @@ OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 //! Xilinx JED file I/O
 
+use std::fmt;
+use std::io;
+use std::io::Write;
 use std::num::Wrapping;
 use std::str;
 
+use XC2BitError;
+
 #[derive(Eq, PartialEq, Copy, Clone)]
 enum Ternary {
     Zero,
@@ -38,112 +43,244 @@ enum Ternary {
 const STX: u8 = 0x02;
 const ETX: u8 = 0x03;
 
-/// Reads .jed file and outputs the fuses as an array of booleans and optional device name
-pub fn read_jed(in_bytes: &[u8]) -> Result<(Vec<bool>, Option<String>), &'static str> {
+/// The largest `QF` value that `JedFile::fuses` accepts. This is far more than any Coolrunner-II device needs and stops
+/// a tiny file from allocating gigabytes of fuses.
+pub const MAX_JED_FUSE_COUNT: u32 = 1 << 24;
+
+/// Computes the 16-bit fuse checksum (the value of the `C` field) of the given fuses
+pub fn jed_fuse_checksum(fuses: &[bool]) -> u16 {
     let mut fuse_csum = Wrapping(0u16);
-    let mut fuse_expected_csum = None;
+
+    for i in 0..fuses.len() {
+        if fuses[i] {
+            // Fuse is a 1 and contributes to the sum
+            fuse_csum += Wrapping(1u16 << (i % 8));
+        }
+    }
+
+    fuse_csum.0
+}
+
+/// Computes the 16-bit transmission checksum of the given bytes. The bytes should start with the STX character and
+/// end with the ETX character (both of which are included in the checksum).
+pub fn jed_file_checksum(bytes: &[u8]) -> u16 {
     let mut file_csum = Wrapping(0u16);
-    let mut num_fuses: u32 = 0;
-    let mut device = None;
-    let mut jed_stx: usize = 0;
-    let mut jed_etx: usize;
-    let mut fuses_ternary = vec![];
-    let mut default_fuse = Ternary::Undef;
-
-    // Find STX
-    while in_bytes[jed_stx] != STX {
-        jed_stx += 1;
-        if jed_stx >= in_bytes.len() {
-            return Err("STX not found");
+
+    for &b in bytes {
+        file_csum += Wrapping(b as u16);
+    }
+
+    file_csum.0
+}
+
+/// Writes the `L` fields of a .jed file and keeps a copy of every fuse that was written, so that the fuse checksum can
+/// be computed from the logical fuse vector
+pub struct JedFuseWriter<'a> {
+    writer: &'a mut Write,
+    fuses: Vec<bool>,
+    fuse_idx: usize,
+}
+
+impl<'a> JedFuseWriter<'a> {
+    /// Creates a writer for a device with `num_fuses` fuses. Fuses that are never written are `false`.
+    pub fn new(writer: &'a mut Write, num_fuses: usize) -> JedFuseWriter<'a> {
+        JedFuseWriter {
+            writer,
+            fuses: vec![false; num_fuses],
+            fuse_idx: 0,
         }
     }
 
-    // Checksum and find ETX
-    jed_etx = jed_stx;
-    while in_bytes[jed_etx] != ETX {
-        file_csum += Wrapping(in_bytes[jed_etx] as u16);
-        jed_etx += 1;
-        if jed_etx >= in_bytes.len() {
-            return Err("ETX not found");
+    /// Starts an `L` field at the given fuse number
+    pub fn start_field(&mut self, fuse_idx: usize) -> Result<(), io::Error> {
+        self.fuse_idx = fuse_idx;
+        write!(self.writer, "L{:06} ", fuse_idx)
+    }
+
+    /// Writes the next fuse of the current `L` field
+    pub fn fuse(&mut self, value: bool) -> Result<(), io::Error> {
+        match self.fuses.get_mut(self.fuse_idx) {
+            Some(x) => *x = value,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "fuse index out of range")),
         }
+        self.fuse_idx += 1;
+        write!(self.writer, "{}", if value {"1"} else {"0"})
+    }
+
+    /// Ends the current `L` field
+    pub fn end_field(&mut self) -> Result<(), io::Error> {
+        write!(self.writer, "*\n")
     }
-    // Add the ETX to the checksum too
-    file_csum += Wrapping(ETX as u16);
 
-    // Check the checksum
-    if jed_etx + 4 >= in_bytes.len() {
-        return Err("unexpected end of file - checksum");
+    /// Writes an empty line between groups of fields
+    pub fn blank_line(&mut self) -> Result<(), io::Error> {
+        write!(self.writer, "\n")
     }
-    let csum_expected = &in_bytes[jed_etx + 1..jed_etx + 5];
-    let csum_expected = str::from_utf8(csum_expected);
-    if csum_expected.is_err() {
-        return Err("invalid character encountered - file checksum");
+
+    /// Returns the logical fuse vector
+    pub fn into_fuses(self) -> Vec<bool> {
+        self.fuses
     }
-    let csum_expected = u16::from_str_radix(csum_expected.unwrap(), 16);
-    if csum_expected.is_err() {
-        return Err("invalid character encountered - file checksum");
+}
+
+/// Returns the line and column (both one-based) that come after `bytes` if `bytes` starts at `pos`
+fn advance_position(pos: (usize, usize), bytes: &[u8]) -> (usize, usize) {
+    let (mut line, mut column) = pos;
+    for &b in bytes {
+        if b == b'\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
     }
-    let csum_expected = csum_expected.unwrap();
-    if csum_expected != 0 && csum_expected != file_csum.0 {
-        return Err("invalid file checksum");
+    (line, column)
+}
+
+fn jed_error(msg: &'static str, pos: (usize, usize)) -> XC2BitError {
+    XC2BitError::JedParseError {
+        msg: msg,
+        line: pos.0,
+        column: pos.1,
     }
+}
+
+/// Returns the number of bytes of whitespace at the start of the text of a field
+fn field_leading_whitespace(l: &str) -> usize {
+    l.len() - l.trim_start_matches(|c| c == ' ' || c == '\r' || c == '\n').len()
+}
 
-    // Make a str object out of the body
-    let jed_body = str::from_utf8(&in_bytes[jed_stx + 1..jed_etx]);
-    if jed_body.is_err() {
-        return Err("invalid character encountered - non-ASCII");
+/// The contents of a single field of a .jed file
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum JedFieldValue {
+    /// A field containing only whitespace
+    Empty,
+    /// `N` field (note). Contains all of the text following the `N`.
+    Note(String),
+    /// `F` field (default state of fuses that are not otherwise specified)
+    DefaultFuse(bool),
+    /// `QF` field (total number of fuses)
+    FuseCount(u32),
+    /// `QP` field (number of pins)
+    PinCount(u32),
+    /// `QV` field (maximum number of test vectors)
+    VectorCount(u32),
+    /// `L` field (states of consecutive fuses starting at the given fuse index)
+    Fuses(u32, Vec<bool>),
+    /// `C` field (fuse checksum)
+    FuseChecksum(u16),
+    /// `G` field (security fuse)
+    SecurityFuse(bool),
+    /// `U` field (user signature). Contains all of the text following the `U`.
+    UserSignature(String),
+    /// `V` field (test vector). Contains the vector number and the pin states.
+    TestVector(u32, String),
+    /// `P` field (pin list). Contains all of the text following the `P`.
+    PinList(String),
+    /// `E` field (architecture fuses). Contains all of the text following the `E`.
+    ArchitectureFuses(String),
+    /// `J` field (device identification). Contains all of the text following the `J`.
+    DeviceIdentification(String),
+    /// `X` field (default test condition). Contains all of the text following the `X`.
+    TestCondition(String),
+}
+
+impl fmt::Display for JedFieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &JedFieldValue::Empty => Ok(()),
+            &JedFieldValue::Note(ref s) => write!(f, "N{}", s),
+            &JedFieldValue::DefaultFuse(x) => write!(f, "F{}", if x {"1"} else {"0"}),
+            &JedFieldValue::FuseCount(x) => write!(f, "QF{}", x),
+            &JedFieldValue::PinCount(x) => write!(f, "QP{}", x),
+            &JedFieldValue::VectorCount(x) => write!(f, "QV{}", x),
+            &JedFieldValue::Fuses(idx, ref fuses) => {
+                write!(f, "L{:06} ", idx)?;
+                for &x in fuses {
+                    write!(f, "{}", if x {"1"} else {"0"})?;
+                }
+                Ok(())
+            },
+            &JedFieldValue::FuseChecksum(x) => write!(f, "C{:04X}", x),
+            &JedFieldValue::SecurityFuse(x) => write!(f, "G{}", if x {"1"} else {"0"}),
+            &JedFieldValue::UserSignature(ref s) => write!(f, "U{}", s),
+            &JedFieldValue::TestVector(x, ref s) => write!(f, "V{:04} {}", x, s),
+            &JedFieldValue::PinList(ref s) => write!(f, "P{}", s),
+            &JedFieldValue::ArchitectureFuses(ref s) => write!(f, "E{}", s),
+            &JedFieldValue::DeviceIdentification(ref s) => write!(f, "J{}", s),
+            &JedFieldValue::TestCondition(ref s) => write!(f, "X{}", s),
+        }
+    }
+}
+
+impl JedFieldValue {
+    /// Parses the text of one field (not including the terminating `*`). The position in an error is relative to the
+    /// start of `l`.
+    pub fn from_field_text(l: &str) -> Result<JedFieldValue, XC2BitError> {
+        JedFieldValue::parse_field(l)
+            .map_err(|msg| jed_error(msg, advance_position((1, 1), &l.as_bytes()[..field_leading_whitespace(l)])))
     }
-    let jed_body = jed_body.unwrap();
 
-    // Ready to parse each line
-    for l in jed_body.split('*') {
+    fn parse_field(l: &str) -> Result<JedFieldValue, &'static str> {
         let l = l.trim_matches(|c| c == ' ' || c == '\r' || c == '\n');
         if l.len() == 0 {
-            // FIXME: Should we do something else here?
-            // ignore empty fields
-            continue;
+            return Ok(JedFieldValue::Empty);
         }
 
-        // Now we can look at the first byte to figure out what we have
-        match l.chars().next().unwrap() {
-            'X' | 'J' => {}, // Do nothing, don't care about these
-            'F' => {
+        // Now we can look at the first character to figure out what we have
+        let field_type_len = l.chars().next().map_or(0, |c| c.len_utf8());
+        let (field_type, rest) = l.split_at(field_type_len);
+        match field_type {
+            "J" => Ok(JedFieldValue::DeviceIdentification(rest.to_owned())),
+            "X" => Ok(JedFieldValue::TestCondition(rest.to_owned())),
+            "N" => Ok(JedFieldValue::Note(rest.to_owned())),
+            "U" => Ok(JedFieldValue::UserSignature(rest.to_owned())),
+            "P" => Ok(JedFieldValue::PinList(rest.to_owned())),
+            "E" => Ok(JedFieldValue::ArchitectureFuses(rest.to_owned())),
+            "F" => {
                 // Default state
-                let (_, default_state_str) = l.split_at(1);
-                default_fuse = match default_state_str {
-                    "0" => Ternary::Zero,
-                    "1" => Ternary::One,
-                    _ => return Err("invalid character encountered - F field")
+                match rest {
+                    "0" => Ok(JedFieldValue::DefaultFuse(false)),
+                    "1" => Ok(JedFieldValue::DefaultFuse(true)),
+                    _ => Err("invalid character encountered - F field")
                 }
             },
-            'N' => {
-                // Notes; we want to extract N DEVICE but otherwise ignore it
-                let note_pieces = l.split(|c| c == ' ' || c == '\r' || c == '\n').collect::<Vec<_>>();
-                if note_pieces.len() == 3 && note_pieces[1] == "DEVICE" {
-                    device = Some(note_pieces[2].to_owned());
+            "G" => {
+                // Security fuse
+                match rest {
+                    "0" => Ok(JedFieldValue::SecurityFuse(false)),
+                    "1" => Ok(JedFieldValue::SecurityFuse(true)),
+                    _ => Err("invalid character encountered - G field")
                 }
             },
-            'Q' => {
-                // Look for QF
+            "Q" => {
                 if l.starts_with("QF") {
                     let (_, num_fuses_str) = l.split_at(2);
                     let num_fuses_maybe = u32::from_str_radix(num_fuses_str, 10);
                     if num_fuses_maybe.is_err() {
                         return Err("invalid character encountered - QF field");
                     }
-                    num_fuses = num_fuses_maybe.unwrap();
-                    fuses_ternary.reserve(num_fuses as usize);
-                    for _ in 0..num_fuses {
-                        fuses_ternary.push(Ternary::Undef);
+                    Ok(JedFieldValue::FuseCount(num_fuses_maybe.unwrap()))
+                } else if l.starts_with("QP") {
+                    let (_, num_pins_str) = l.split_at(2);
+                    let num_pins_maybe = u32::from_str_radix(num_pins_str, 10);
+                    if num_pins_maybe.is_err() {
+                        return Err("invalid character encountered - QP field");
+                    }
+                    Ok(JedFieldValue::PinCount(num_pins_maybe.unwrap()))
+                } else if l.starts_with("QV") {
+                    let (_, num_vectors_str) = l.split_at(2);
+                    let num_vectors_maybe = u32::from_str_radix(num_vectors_str, 10);
+                    if num_vectors_maybe.is_err() {
+                        return Err("invalid character encountered - QV field");
                     }
+                    Ok(JedFieldValue::VectorCount(num_vectors_maybe.unwrap()))
+                } else {
+                    Err("unrecognized field")
                 }
             },
-            'L' => {
+            "L" => {
                 // A set of fuses
-                if num_fuses == 0 {
-                    return Err("missing QF field");
-                }
-
                 let mut fuse_field_splitter = l.splitn(2, |c| c == ' ' || c == '\r' || c == '\n');
                 let fuse_idx_str = fuse_field_splitter.next();
                 let (_, fuse_idx_str) = fuse_idx_str.unwrap().split_at(1);
@@ -151,132 +288,426 @@ pub fn read_jed(in_bytes: &[u8]) -> Result<(Vec<bool>, Option<String>), &'static
                 if fuse_idx_maybe.is_err() {
                     return Err("invalid character encountered - fuse number");
                 }
-                let mut fuse_idx = fuse_idx_maybe.unwrap();
+                let fuse_idx = fuse_idx_maybe.unwrap();
 
                 let fuse_bits_part = fuse_field_splitter.next();
                 if fuse_bits_part.is_none() {
                     return Err("malformed L field");
                 }
                 let fuse_bits_part = fuse_bits_part.unwrap();
+                let mut fuses = Vec::new();
                 for fuse in fuse_bits_part.chars() {
                     match fuse {
-                        '0' => {
-                            if fuse_idx >= num_fuses {
-                                return Err("invalid fuse index out of range");
-                            }
-                            fuses_ternary[fuse_idx as usize] = Ternary::Zero;
-                            fuse_idx += 1;
-                        },
-                        '1' => {
-                            if fuse_idx >= num_fuses {
-                                return Err("invalid fuse index out of range");
-                            }
-                            fuses_ternary[fuse_idx as usize] = Ternary::One;
-                            fuse_idx += 1;
-                        },
+                        '0' => fuses.push(false),
+                        '1' => fuses.push(true),
                         ' ' | '\r' | '\n' => {}, // Do nothing
                         _ => return Err("invalid character encountered - fuse value"),
                     }
                 }
+
+                Ok(JedFieldValue::Fuses(fuse_idx, fuses))
             },
-            'C' => {
+            "C" => {
                 // Checksum
-                let (_, csum_str) = l.split_at(1);
-                if csum_str.len() != 4 {
+                if rest.len() != 4 {
                     return Err("malformed fuse checksum");
                 }
-                let csum_maybe = u16::from_str_radix(csum_str, 16);
+                let csum_maybe = u16::from_str_radix(rest, 16);
                 if csum_maybe.is_err() {
                     return Err("invalid character encountered - C");
                 }
-                fuse_expected_csum = Some(csum_maybe.unwrap());
-            }
-            _ => return Err("unrecognized field"),
+                Ok(JedFieldValue::FuseChecksum(csum_maybe.unwrap()))
+            },
+            "V" => {
+                // Test vector
+                let mut vector_splitter = rest.splitn(2, |c| c == ' ' || c == '\r' || c == '\n');
+                let vector_num_str = vector_splitter.next().unwrap();
+                let vector_num_maybe = u32::from_str_radix(vector_num_str, 10);
+                if vector_num_maybe.is_err() {
+                    return Err("invalid character encountered - V field");
+                }
+
+                let vector_part = vector_splitter.next();
+                if vector_part.is_none() {
+                    return Err("malformed V field");
+                }
+                let vector_part = vector_part.unwrap().trim_matches(|c| c == ' ' || c == '\r' || c == '\n');
+
+                Ok(JedFieldValue::TestVector(vector_num_maybe.unwrap(), vector_part.to_owned()))
+            },
+            _ => Err("unrecognized field"),
         }
     }
+}
 
-    // Fill in the default values
-    for x in &mut fuses_ternary {
-        if *x == Ternary::Undef {
-            // There cannot be undefined fuses if there isn't an F field
-            if default_fuse == Ternary::Undef {
-                return Err("missing F field")
-            }
+/// A single field of a .jed file along with its original text
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JedField {
+    text: String,
+    value: JedFieldValue,
+}
+
+impl JedField {
+    /// Creates a new field on its own line
+    pub fn new(value: JedFieldValue) -> JedField {
+        JedField {
+            text: format!("\n{}", value),
+            value: value,
+        }
+    }
+
+    /// Returns the text of the field exactly as it appears in the file, including any whitespace before it but not
+    /// including the terminating `*`. This is the text that is written out.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the parsed contents of the field
+    pub fn value(&self) -> &JedFieldValue {
+        &self.value
+    }
+
+    /// Changes the contents of the field. The text is regenerated from `value`, keeping the whitespace before it.
+    pub fn set_value(&mut self, value: JedFieldValue) {
+        self.text = format!("{}{}", &self.text[..field_leading_whitespace(&self.text)], value);
+        self.value = value;
+    }
+}
+
+/// The complete contents of a .jed file. Unlike `read_jed`, this keeps all of the fields and all of the text
+/// surrounding them so that the file can be written back out exactly as it was read.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JedFile {
+    /// Everything before the STX character
+    pub header: Vec<u8>,
+    /// Every field between the STX and ETX characters, in order
+    pub fields: Vec<JedField>,
+    /// Whitespace between the last `*` and the ETX character
+    pub trailer: String,
+    /// The transmission checksum exactly as it appears in the file
+    pub file_checksum: String,
+    /// Everything after the transmission checksum
+    pub footer: Vec<u8>,
+}
 
-            *x = default_fuse;
+impl JedFile {
+    /// Reads a .jed file. The file checksum is verified, but the fuse checksum is only verified by `fuses`.
+    pub fn from_file_contents(in_bytes: &[u8]) -> Result<JedFile, XC2BitError> {
+        let pos_at = |offset: usize| advance_position((1, 1), &in_bytes[..offset]);
+
+        // Find STX
+        let jed_stx = match in_bytes.iter().position(|&x| x == STX) {
+            Some(x) => x,
+            None => return Err(jed_error("STX not found", pos_at(in_bytes.len()))),
+        };
+
+        // Find ETX
+        let jed_etx = match in_bytes[jed_stx..].iter().position(|&x| x == ETX) {
+            Some(x) => jed_stx + x,
+            None => return Err(jed_error("ETX not found", pos_at(in_bytes.len()))),
+        };
+
+        // The ETX is included in the checksum too
+        let file_csum = jed_file_checksum(&in_bytes[jed_stx..jed_etx + 1]);
+
+        // Check the checksum
+        if jed_etx + 4 >= in_bytes.len() {
+            return Err(jed_error("unexpected end of file - checksum", pos_at(in_bytes.len())));
+        }
+        let csum_pos = pos_at(jed_etx + 1);
+        let csum_expected_str = &in_bytes[jed_etx + 1..jed_etx + 5];
+        let csum_expected_str = str::from_utf8(csum_expected_str);
+        if csum_expected_str.is_err() {
+            return Err(jed_error("invalid character encountered - file checksum", csum_pos));
+        }
+        let csum_expected_str = csum_expected_str.unwrap();
+        let csum_expected = u16::from_str_radix(csum_expected_str, 16);
+        if csum_expected.is_err() {
+            return Err(jed_error("invalid character encountered - file checksum", csum_pos));
         }
+        let csum_expected = csum_expected.unwrap();
+        if csum_expected != 0 && csum_expected != file_csum {
+            return Err(jed_error("invalid file checksum", csum_pos));
+        }
+
+        // Make a str object out of the body
+        let jed_body = match str::from_utf8(&in_bytes[jed_stx + 1..jed_etx]) {
+            Ok(x) => x,
+            Err(e) => return Err(jed_error("invalid character encountered - non-ASCII",
+                pos_at(jed_stx + 1 + e.valid_up_to()))),
+        };
+
+        // Ready to parse each field. The last piece is whatever comes after the last *.
+        let mut pieces = jed_body.split('*').collect::<Vec<_>>();
+        let trailer = pieces.pop().unwrap();
+
+        let mut fields = Vec::with_capacity(pieces.len());
+        let mut pos = pos_at(jed_stx + 1);
+        for l in pieces {
+            let value = JedFieldValue::parse_field(l)
+                .map_err(|msg| jed_error(msg, advance_position(pos, &l.as_bytes()[..field_leading_whitespace(l)])))?;
+            fields.push(JedField {
+                text: l.to_owned(),
+                value: value,
+            });
+            pos = advance_position(advance_position(pos, l.as_bytes()), b"*");
+        }
+
+        let trailer_pos = advance_position(pos, &trailer.as_bytes()[..field_leading_whitespace(trailer)]);
+        if JedFieldValue::parse_field(trailer).map_err(|msg| jed_error(msg, trailer_pos))? != JedFieldValue::Empty {
+            return Err(jed_error("unterminated field", trailer_pos));
+        }
+
+        Ok(JedFile {
+            header: in_bytes[..jed_stx].to_vec(),
+            fields: fields,
+            trailer: trailer.to_owned(),
+            file_checksum: csum_expected_str.to_owned(),
+            footer: in_bytes[jed_etx + 5..].to_vec(),
+        })
     }
 
-    // Un-ternary it
-    let fuses = fuses_ternary.iter().map(|&x| match x {
-        Ternary::Zero => false,
-        Ternary::One => true,
-        _ => unreachable!(),
-    }).collect::<Vec<_>>();
+    /// Returns the line and column (both one-based) of the start of the field with index `idx`. If `idx` is the number
+    /// of fields, returns the position of the ETX character.
+    fn field_position(&self, idx: usize) -> (usize, usize) {
+        let mut pos = advance_position(advance_position((1, 1), &self.header), &[STX]);
+        for field in &self.fields[..idx] {
+            pos = advance_position(advance_position(pos, field.text.as_bytes()), b"*");
+        }
+        let text = if idx < self.fields.len() {&self.fields[idx].text} else {&self.trailer};
+        advance_position(pos, &text.as_bytes()[..field_leading_whitespace(text)])
+    }
+
+    /// Writes the .jed file to the given `writer` object. If none of the fields were changed, the output is identical
+    /// to the file that was read. Otherwise, the transmission checksum is recomputed (unless it was zero).
+    pub fn write_to_writer(&self, writer: &mut Write) -> Result<(), io::Error> {
+        let mut jed_body = Vec::new();
+        write!(jed_body, "\x02")?;
+        for field in &self.fields {
+            write!(jed_body, "{}*", field.text)?;
+        }
+        write!(jed_body, "{}\x03", self.trailer)?;
+
+        writer.write_all(&self.header)?;
+        writer.write_all(&jed_body)?;
 
-    // Fuse checksum
-    if let Some(fuse_expected_csum) = fuse_expected_csum {
-        for i in 0..num_fuses {
-            if fuses[i as usize] {
-                // Fuse is a 1 and contributes to the sum
-                fuse_csum += Wrapping(1u16 << (i % 8));
+        let file_csum = jed_file_checksum(&jed_body);
+        match u16::from_str_radix(&self.file_checksum, 16) {
+            Ok(x) if x == 0 || x == file_csum => write!(writer, "{}", self.file_checksum)?,
+            _ => write!(writer, "{:04X}", file_csum)?,
+        }
+
+        writer.write_all(&self.footer)?;
+
+        Ok(())
+    }
+
+    /// Computes the state of every fuse using the `QF`, `F`, and `L` fields, and verifies the fuse checksum if there
+    /// is a `C` field. Fails if `QF` is larger than `MAX_JED_FUSE_COUNT` or appears more than once.
+    pub fn fuses(&self) -> Result<Vec<bool>, XC2BitError> {
+        let mut num_fuses: u32 = 0;
+        let mut have_num_fuses = false;
+        let mut fuses_ternary = vec![];
+        let mut default_fuse = Ternary::Undef;
+        let mut fuse_expected_csum = None;
+
+        for (field_idx, field) in self.fields.iter().enumerate() {
+            match field.value {
+                JedFieldValue::DefaultFuse(x) => {
+                    default_fuse = if x {Ternary::One} else {Ternary::Zero};
+                },
+                JedFieldValue::FuseCount(x) => {
+                    if have_num_fuses {
+                        return Err(jed_error("duplicate QF field", self.field_position(field_idx)));
+                    }
+                    if x > MAX_JED_FUSE_COUNT {
+                        return Err(jed_error("fuse count too large", self.field_position(field_idx)));
+                    }
+                    num_fuses = x;
+                    have_num_fuses = true;
+                    fuses_ternary = vec![Ternary::Undef; num_fuses as usize];
+                },
+                JedFieldValue::Fuses(fuse_idx, ref fuses) => {
+                    if num_fuses == 0 {
+                        return Err(jed_error("missing QF field", self.field_position(field_idx)));
+                    }
+
+                    let mut fuse_idx = fuse_idx;
+                    for &fuse in fuses {
+                        if fuse_idx >= num_fuses {
+                            return Err(jed_error("invalid fuse index out of range", self.field_position(field_idx)));
+                        }
+                        fuses_ternary[fuse_idx as usize] = if fuse {Ternary::One} else {Ternary::Zero};
+                        fuse_idx += 1;
+                    }
+                },
+                JedFieldValue::FuseChecksum(x) => {
+                    fuse_expected_csum = Some((x, field_idx));
+                },
+                _ => {},
             }
         }
 
-        if fuse_expected_csum != fuse_csum.0 {
-            return Err("invalid fuse checksum");
+        // Fill in the default values
+        for x in &mut fuses_ternary {
+            if *x == Ternary::Undef {
+                // There cannot be undefined fuses if there isn't an F field
+                if default_fuse == Ternary::Undef {
+                    return Err(jed_error("missing F field", self.field_position(self.fields.len())));
+                }
+
+                *x = default_fuse;
+            }
+        }
+
+        // Un-ternary it
+        let fuses = fuses_ternary.iter().map(|&x| match x {
+            Ternary::Zero => false,
+            Ternary::One => true,
+            _ => unreachable!(),
+        }).collect::<Vec<_>>();
+
+        // Fuse checksum
+        if let Some((fuse_expected_csum, field_idx)) = fuse_expected_csum {
+            if fuse_expected_csum != jed_fuse_checksum(&fuses) {
+                return Err(jed_error("invalid fuse checksum", self.field_position(field_idx)));
+            }
+        }
+
+        Ok(fuses)
+    }
+
+    /// Returns the device name from the `N DEVICE` note, if there is one
+    pub fn device(&self) -> Option<String> {
+        let mut device = None;
+        for note in self.notes() {
+            let note_pieces = note.split(|c| c == ' ' || c == '\r' || c == '\n').collect::<Vec<_>>();
+            if note_pieces.len() == 3 && note_pieces[1] == "DEVICE" {
+                device = Some(note_pieces[2].to_owned());
+            }
         }
+        device
+    }
+
+    /// Returns the text of every `N` field
+    pub fn notes(&self) -> Vec<&str> {
+        self.fields.iter().filter_map(|x| match x.value {
+            JedFieldValue::Note(ref s) => Some(&s[..]),
+            _ => None,
+        }).collect()
+    }
+
+    /// Returns the value of the `F` field, if there is one
+    pub fn default_fuse(&self) -> Option<bool> {
+        self.fields.iter().filter_map(|x| match x.value {
+            JedFieldValue::DefaultFuse(x) => Some(x),
+            _ => None,
+        }).last()
+    }
+
+    /// Returns the value of the `QF` field, if there is one
+    pub fn fuse_count(&self) -> Option<u32> {
+        self.fields.iter().filter_map(|x| match x.value {
+            JedFieldValue::FuseCount(x) => Some(x),
+            _ => None,
+        }).last()
+    }
+
+    /// Returns the value of the `QP` field, if there is one
+    pub fn pin_count(&self) -> Option<u32> {
+        self.fields.iter().filter_map(|x| match x.value {
+            JedFieldValue::PinCount(x) => Some(x),
+            _ => None,
+        }).last()
     }
 
-    Ok((fuses, device))
+    /// Returns the value of the `G` field, if there is one
+    pub fn security_fuse(&self) -> Option<bool> {
+        self.fields.iter().filter_map(|x| match x.value {
+            JedFieldValue::SecurityFuse(x) => Some(x),
+            _ => None,
+        }).last()
+    }
+
+    /// Returns the text of the `U` field, if there is one
+    pub fn user_signature(&self) -> Option<&str> {
+        self.fields.iter().filter_map(|x| match x.value {
+            JedFieldValue::UserSignature(ref s) => Some(&s[..]),
+            _ => None,
+        }).last()
+    }
+
+    /// Returns the number and pin states of every `V` field
+    pub fn test_vectors(&self) -> Vec<(u32, &str)> {
+        self.fields.iter().filter_map(|x| match x.value {
+            JedFieldValue::TestVector(n, ref s) => Some((n, &s[..])),
+            _ => None,
+        }).collect()
+    }
+}
+
+/// Reads .jed file and outputs the fuses as an array of booleans and optional device name
+pub fn read_jed(in_bytes: &[u8]) -> Result<(Vec<bool>, Option<String>), XC2BitError> {
+    let jed_file = JedFile::from_file_contents(in_bytes)?;
+    let fuses = jed_file.fuses()?;
+
+    Ok((fuses, jed_file.device()))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn jed_err<T>(msg: &'static str, line: usize, column: usize) -> Result<T, XC2BitError> {
+        Err(XC2BitError::JedParseError {
+            msg: msg,
+            line: line,
+            column: column,
+        })
+    }
+
     #[test]
     fn read_no_stx() {
         let ret = read_jed(b"asdf");
 
-        assert_eq!(ret, Err("STX not found"));
+        assert_eq!(ret, jed_err("STX not found", 1, 5));
     }
 
     #[test]
     fn read_no_etx() {
         let ret = read_jed(b"asdf\x02fdsa");
 
-        assert_eq!(ret, Err("ETX not found"));
+        assert_eq!(ret, jed_err("ETX not found", 1, 10));
     }
 
     #[test]
     fn read_no_csum() {
         let ret = read_jed(b"asdf\x02fdsa\x03");
-        assert_eq!(ret, Err("unexpected end of file - checksum"));
+        assert_eq!(ret, jed_err("unexpected end of file - checksum", 1, 11));
 
         let ret = read_jed(b"asdf\x02fdsa\x03AAA");
-        assert_eq!(ret, Err("unexpected end of file - checksum"));
+        assert_eq!(ret, jed_err("unexpected end of file - checksum", 1, 14));
     }
 
     #[test]
     fn read_bad_csum() {
         let ret = read_jed(b"asdf\x02fdsa\x03AAAA");
 
-        assert_eq!(ret, Err("invalid file checksum"));
+        assert_eq!(ret, jed_err("invalid file checksum", 1, 11));
     }
 
     #[test]
     fn read_malformed_csum() {
         let ret = read_jed(b"asdf\x02fdsa\x03AAAZ");
 
-        assert_eq!(ret, Err("invalid character encountered - file checksum"));
+        assert_eq!(ret, jed_err("invalid character encountered - file checksum", 1, 11));
     }
 
     #[test]
     fn read_no_f() {
         let ret = read_jed(b"\x02QF1*\x030000");
 
-        assert_eq!(ret, Err("missing F field"));
+        assert_eq!(ret, jed_err("missing F field", 1, 6));
     }
 
     #[test]
@@ -290,7 +721,7 @@ mod tests {
     fn read_bogus_f_command() {
         let ret = read_jed(b"\x02F2*\x030000");
 
-        assert_eq!(ret, Err("invalid character encountered - F field"));
+        assert_eq!(ret, jed_err("invalid character encountered - F field", 1, 2));
     }
 
     #[test]
@@ -304,7 +735,7 @@ mod tests {
     fn read_l_without_qf() {
         let ret = read_jed(b"\x02F0*L0 0*\x030000");
 
-        assert_eq!(ret, Err("missing QF field"));
+        assert_eq!(ret, jed_err("missing QF field", 1, 5));
     }
 
     #[test]
@@ -325,7 +756,7 @@ mod tests {
     fn read_one_fuse_csum_bad() {
         let ret = read_jed(b"\x02F0*QF1*L0 1*C0002*\x030000");
 
-        assert_eq!(ret, Err("invalid fuse checksum"));
+        assert_eq!(ret, jed_err("invalid fuse checksum", 1, 14));
     }
 
     #[test]
@@ -334,4 +765,129 @@ mod tests {
 
         assert_eq!(ret, Ok((vec![false, true], None)));
     }
+
+    #[test]
+    fn read_unterminated_field() {
+        let ret = read_jed(b"\x02F0*QF1\x030000");
+
+        assert_eq!(ret, jed_err("unterminated field", 1, 5));
+    }
+
+    #[test]
+    fn read_error_position() {
+        let ret = read_jed(b"header\r\n\x02F0*\r\n  F2*\x030000");
+        assert_eq!(ret, jed_err("invalid character encountered - F field", 3, 3));
+        assert_eq!(ret.unwrap_err().to_string(), "line 3, column 3: invalid character encountered - F field");
+
+        let ret = read_jed(b"\x02F0*QF1*\nL0 1*\nL1 1*\x030000");
+        assert_eq!(ret, jed_err("invalid fuse index out of range", 3, 1));
+
+        assert_eq!(JedFieldValue::from_field_text("\n QZ"), jed_err("unrecognized field", 2, 2));
+    }
+
+    // The following inputs were found by fuzzing (see the fuzz directory)
+
+    #[test]
+    fn read_empty() {
+        let ret = read_jed(b"");
+
+        assert_eq!(ret, jed_err("STX not found", 1, 1));
+    }
+
+    #[test]
+    fn read_non_ascii_field() {
+        let ret = read_jed("\x02F0*\u{e9}0*\x030000".as_bytes());
+
+        assert_eq!(ret, jed_err("unrecognized field", 1, 5));
+    }
+
+    #[test]
+    fn read_huge_fuse_count() {
+        let ret = read_jed(b"\x02QV2*F1*\nQF0486904869**\x030000");
+
+        assert_eq!(ret, jed_err("fuse count too large", 2, 1));
+    }
+
+    #[test]
+    fn read_duplicate_fuse_count() {
+        let ret = read_jed(b"\x02F0*QF16777216*\nQF16777216*QF16777216*\x030000");
+
+        assert_eq!(ret, jed_err("duplicate QF field", 2, 1));
+    }
+
+    #[test]
+    fn jed_file_all_fields() {
+        let mut in_bytes = b"header text\r\n\x02QF4*QP44*QV1*N DEVICE asdf*N another note*F0*G1*UAsig*\r\nJ0 0*X0*\
+            PA1 2 3*E1010*L0000 1 0*C0001*V0001 C01*\n\x030000\nfooter".to_vec();
+
+        // Fill in a real file checksum
+        let stx_pos = in_bytes.iter().position(|&x| x == STX).unwrap();
+        let etx_pos = in_bytes.iter().position(|&x| x == ETX).unwrap();
+        let file_csum = format!("{:04x}", jed_file_checksum(&in_bytes[stx_pos..etx_pos + 1]));
+        in_bytes[etx_pos + 1..etx_pos + 5].copy_from_slice(file_csum.as_bytes());
+
+        let jed_file = JedFile::from_file_contents(&in_bytes).unwrap();
+        assert_eq!(jed_file.header, b"header text\r\n");
+        assert_eq!(jed_file.footer, b"\nfooter");
+        assert_eq!(jed_file.trailer, "\n");
+        assert_eq!(jed_file.notes(), vec![" DEVICE asdf", " another note"]);
+        assert_eq!(jed_file.device(), Some(String::from("asdf")));
+        assert_eq!(jed_file.default_fuse(), Some(false));
+        assert_eq!(jed_file.fuse_count(), Some(4));
+        assert_eq!(jed_file.pin_count(), Some(44));
+        assert_eq!(jed_file.security_fuse(), Some(true));
+        assert_eq!(jed_file.user_signature(), Some("Asig"));
+        assert_eq!(jed_file.test_vectors(), vec![(1, "C01")]);
+        assert_eq!(jed_file.fuses(), Ok(vec![true, false, false, false]));
+
+        let mut out_bytes = Vec::new();
+        jed_file.write_to_writer(&mut out_bytes).unwrap();
+        assert_eq!(out_bytes, in_bytes);
+    }
+
+    #[test]
+    fn jed_file_modified() {
+        let in_bytes = b"\x02QF2*F0*L0 01*\n\x030000";
+        let mut jed_file = JedFile::from_file_contents(in_bytes).unwrap();
+
+        // A zero checksum stays zero
+        jed_file.fields[2] = JedField::new(JedFieldValue::Fuses(0, vec![true, true]));
+        let mut out_bytes = Vec::new();
+        jed_file.write_to_writer(&mut out_bytes).unwrap();
+        assert_eq!(out_bytes, b"\x02QF2*F0*\nL000000 11*\n\x030000".to_vec());
+
+        // A nonzero checksum gets recomputed
+        jed_file.file_checksum = String::from("FFFF");
+        jed_file.fields.push(JedField::new(JedFieldValue::FuseChecksum(3)));
+        let mut out_bytes = Vec::new();
+        jed_file.write_to_writer(&mut out_bytes).unwrap();
+        assert_eq!(read_jed(&out_bytes), Ok((vec![true, true], None)));
+        assert!(!out_bytes.ends_with(b"FFFF"));
+
+        // Changing the value of a field changes its text too
+        jed_file.fields[1].set_value(JedFieldValue::DefaultFuse(true));
+        assert_eq!(jed_file.fields[1].text(), "F1");
+        jed_file.fields[2].set_value(JedFieldValue::Fuses(1, vec![false]));
+        assert_eq!(jed_file.fields[2].text(), "\nL000001 0");
+        assert_eq!(jed_file.fields[2].value(), &JedFieldValue::Fuses(1, vec![false]));
+    }
+
+    #[test]
+    fn jed_fuse_writer() {
+        let mut out_bytes = Vec::new();
+        let fuses = {
+            let mut writer = JedFuseWriter::new(&mut out_bytes, 4);
+            writer.start_field(2).unwrap();
+            writer.fuse(true).unwrap();
+            writer.fuse(false).unwrap();
+            writer.end_field().unwrap();
+            writer.blank_line().unwrap();
+            writer.start_field(3).unwrap();
+            writer.fuse(true).unwrap();
+            assert!(writer.fuse(true).is_err());
+            writer.into_fuses()
+        };
+        assert_eq!(out_bytes, b"L000002 10*\n\nL000003 1".to_vec());
+        assert_eq!(fuses, vec![false, false, true, true]);
+    }
 }
@@ -42,6 +42,14 @@ OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 //! to IOBs.) As a special exception, the smallest 32-macrocell devices also have one single input-only pin that is
 //! connected directly into the global interconnect and does not have a corresponding macrocell.
 
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "serde")]
+extern crate serde_json;
+
 /// The number of inputs from the ZIA interconnect into the AND gate section of each PLA.
 /// This is an unchangeable property of the architecture of the CPLD.
 pub const INPUTS_PER_ANDTERM: usize = 40;
@@ -55,12 +63,30 @@ pub const MCS_PER_FB: usize = 16;
 mod bitstream;
 pub use bitstream::{XC2Bitstream, XC2BitstreamBits, XC2GlobalNets, XC2ClockDivRatio, XC2ClockDiv, process_jed};
 
+mod blif;
+pub use blif::{write_blif};
+
 mod crbit;
 pub use crbit::{FuseArray};
 
+mod diff;
+pub use diff::{diff_bitstreams};
+
 mod fb;
 pub use fb::{XC2BitstreamFB, CTC, CTR, CTS, CTE, get_pta, get_ptb, get_ptc};
 
+mod drc;
+pub use drc::{XC2DiagnosticSeverity, XC2Diagnostic, validate_bitstream, check_io_banks};
+
+mod error;
+pub use error::{XC2BitError};
+
+mod fitter;
+pub use fitter::{fit_netlist};
+
+mod human_readable;
+pub use human_readable::{read_human_readable};
+
 mod fusemap_logical;
 mod fusemap_physical;
 
@@ -72,17 +98,58 @@ mod mc;
 pub use mc::{XC2Macrocell, XC2MCRegClkSrc, XC2MCRegResetSrc, XC2MCRegSetSrc, XC2MCRegMode, XC2MCFeedbackMode,
              XC2MCXorMode};
 
+mod netlist;
+pub use netlist::{XC2Netlist, XC2NetlistCell, NET_ZERO, NET_ONE};
+#[cfg(feature = "serde")]
+pub use netlist::{read_yosys_json};
+
 mod partdb;
-pub use partdb::{XC2Device, XC2Speed, XC2Package, is_valid_part_combination, parse_part_name_string};
+pub use partdb::{XC2Device, XC2Speed, XC2Package, XC2TimingParams, is_valid_part_combination, parse_part_name_string};
 
 mod pla;
 pub use pla::{XC2PLAAndTerm, XC2PLAOrTerm};
 
+#[cfg(feature = "serde")]
+mod serde_array;
+
 mod zia;
-pub use zia::{XC2ZIARowPiece, XC2ZIAInput, ZIA_MAP_32, ZIA_MAP_64, ZIA_MAP_128, ZIA_MAP_256, ZIA_MAP_384, ZIA_MAP_512};
+pub use zia::{XC2ZIARowPiece, XC2ZIAInput, ZIA_MAP_32, ZIA_MAP_64, ZIA_MAP_128, ZIA_MAP_256, ZIA_MAP_384, ZIA_MAP_512,
+              zia_table_get_row, zia_find_rows_for_input, zia_route_inputs, zia_input_name};
 
 mod jed;
-pub use jed::{read_jed};
+pub use jed::{read_jed, jed_fuse_checksum, jed_file_checksum, JedFile, JedField, JedFieldValue, JedFuseWriter,
+              MAX_JED_FUSE_COUNT};
+
+mod isc;
+pub use isc::{isc_rows, isc_row_address, isc_row_from_address};
+
+mod svf;
+pub use svf::{SVFChain, write_svf};
+
+mod verilog;
+pub use verilog::{write_verilog};
+
+mod pinout;
+pub use pinout::{pinout_known, iob_to_pin_name, pin_name_to_iob, iob_is_bonded, num_banks, iob_to_bank};
+
+mod power;
+pub use power::{XC2PowerParams, XC2ClockActivity, XC2PowerConditions, XC2PowerEstimate, estimate_power};
+
+mod sim;
+pub use sim::{XC2Simulator};
+
+mod sta;
+pub use sta::{XC2ClockDomain, XC2PathDelay, XC2OutputEnableDelay, XC2SetupHold, XC2ClockPeriod, XC2TimingReport,
+              analyze_timing};
+
+mod ucf;
+pub use ucf::{XC2IOStandard, XC2UCFTermination, XC2UCFNet, read_ucf, ucf_loc_to_iob, check_ucf, apply_ucf};
+
+mod timing_sim;
+pub use timing_sim::{XC2SimDelays, XC2TimingSimulator};
+
+mod xsvf;
+pub use xsvf::{read_xsvf};
 
 #[cfg(test)]
 mod tests {
@@ -113,6 +180,12 @@ mod tests {
                     .read_to_end(&mut txt_data).expect("failed to read txt file");
 
                 let (parsed_jed_data, device_name) = read_jed(&jed_data).expect("failed to read jed");
+
+                // Reading and writing a JedFile must not change anything
+                let jed_file = JedFile::from_file_contents(&jed_data).expect("failed to read jed");
+                let mut jed_file_data = Vec::new();
+                jed_file.write_to_writer(&mut jed_file_data).expect("failed to write jed");
+                assert_eq!(jed_data, jed_file_data);
                 let device_name = device_name.expect("missing device name in jed");
 
                 let parsed_bitstream_data = process_jed(&parsed_jed_data, &device_name)
@@ -123,10 +196,71 @@ mod tests {
                 parsed_bitstream_data.write_jed(&mut new_jed).expect("failed to write jed");
                 assert_eq!(jed_data, new_jed);
 
+                // Our own output must pass strict verification, so both checksums must be present and not zero
+                let stx_pos = new_jed.iter().position(|&x| x == 0x02).expect("missing STX");
+                let etx_pos = new_jed.iter().position(|&x| x == 0x03).expect("missing ETX");
+                let file_csum_str = std::str::from_utf8(&new_jed[etx_pos + 1..etx_pos + 5]).unwrap();
+                let file_csum = u16::from_str_radix(file_csum_str, 16).expect("malformed file checksum");
+                assert_ne!(file_csum, 0);
+                assert_eq!(file_csum, jed_file_checksum(&new_jed[stx_pos..etx_pos + 1]));
+                let jed_body = std::str::from_utf8(&new_jed[stx_pos + 1..etx_pos]).unwrap();
+                let fuse_csum_field = jed_body.split('*').map(|x| x.trim()).find(|x| x.starts_with('C'))
+                    .expect("missing fuse checksum");
+                let fuse_csum = u16::from_str_radix(&fuse_csum_field[1..], 16).expect("malformed fuse checksum");
+                assert_eq!(fuse_csum, jed_fuse_checksum(&parsed_jed_data));
+
+                // Round-trip through JSON
+                #[cfg(feature = "serde")]
+                {
+                    let json_data = serde_json::to_vec(&parsed_bitstream_data).expect("failed to write json");
+                    let json_bitstream_data: XC2Bitstream = serde_json::from_slice(&json_data)
+                        .expect("failed to read json");
+                    let mut json_jed = Vec::new();
+                    json_bitstream_data.write_jed(&mut json_jed).expect("failed to write jed");
+                    assert_eq!(jed_data, json_jed);
+                }
+
+                // Bitstreams from the Xilinx tools must not have any design rule errors
+                assert!(parsed_bitstream_data.validate().iter()
+                    .all(|x| x.severity() != XC2DiagnosticSeverity::Error));
+                assert!(check_io_banks(&parsed_bitstream_data, &[]).iter()
+                    .all(|x| x.severity() != XC2DiagnosticSeverity::Error));
+
                 let mut human_readable_data = Vec::new();
                 parsed_bitstream_data.dump_human_readable(&mut human_readable_data)
                     .expect("failed to get human readable");
                 assert_eq!(txt_data, human_readable_data);
+
+                // Reading back the human-readable dump must give the same bitstream
+                let txt_bitstream_data = read_human_readable(&txt_data).expect("failed to read human readable");
+                let mut txt_jed = Vec::new();
+                txt_bitstream_data.write_jed(&mut txt_jed).expect("failed to write jed");
+                assert_eq!(jed_data, txt_jed);
+
+                // Round-trip through the physical crbit format
+                let mut crbit_data = Vec::new();
+                parsed_bitstream_data.to_crbit().expect("failed to convert to crbit")
+                    .write_to_writer(&mut crbit_data).expect("failed to write crbit");
+                let fuse_array = FuseArray::from_file_contents(&crbit_data).expect("failed to read crbit");
+                let crbit_bitstream_data = XC2Bitstream::from_crbit(&fuse_array).expect("failed to process crbit");
+
+                let mut new_crbit_data = Vec::new();
+                crbit_bitstream_data.to_crbit().expect("failed to convert to crbit")
+                    .write_to_writer(&mut new_crbit_data).expect("failed to write crbit");
+                assert_eq!(crbit_data, new_crbit_data);
+
+                // The clock divider is not stored in crbit format
+                let clock_div_is_default = match parsed_bitstream_data.bits.get_clock_div() {
+                    None => true,
+                    Some(clock_div) => !clock_div.enabled && !clock_div.delay &&
+                        clock_div.div_ratio == XC2ClockDivRatio::Div16,
+                };
+                if clock_div_is_default {
+                    let mut crbit_human_readable_data = Vec::new();
+                    crbit_bitstream_data.dump_human_readable(&mut crbit_human_readable_data)
+                        .expect("failed to get human readable");
+                    assert_eq!(txt_data, crbit_human_readable_data);
+                }
             }
         }
     }
@@ -34,6 +34,7 @@ use zia::{zia_get_row_width};
 
 /// Clock source for the register in a macrocell
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum XC2MCRegClkSrc {
     GCK0,
     GCK1,
@@ -44,6 +45,7 @@ pub enum XC2MCRegClkSrc {
 
 /// Reset source for the register in a macrocell
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum XC2MCRegResetSrc {
     Disabled,
     PTA,
@@ -53,6 +55,7 @@ pub enum XC2MCRegResetSrc {
 
 /// Set source for the register in a macrocell
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum XC2MCRegSetSrc {
     Disabled,
     PTA,
@@ -62,6 +65,7 @@ pub enum XC2MCRegSetSrc {
 
 /// Mode of the register in a macrocell.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum XC2MCRegMode {
     /// D-type flip-flop
     DFF,
@@ -76,6 +80,7 @@ pub enum XC2MCRegMode {
 /// Mux selection for the ZIA input from this macrocell. The ZIA input can be chosen to come from either the XOR gate
 /// or from the output of the register.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum XC2MCFeedbackMode {
     Disabled,
     COMB,
@@ -85,6 +90,7 @@ pub enum XC2MCFeedbackMode {
 /// Mux selection for the "not from OR gate" input to the XOR gate. The XOR gate in a macrocell contains two inputs,
 /// the output of the corresponding OR term from the PLA and a specific dedicated AND term from the PLA.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum XC2MCXorMode {
     /// A constant zero which results in this XOR outputting the value of the OR term
     ZERO,
@@ -98,6 +104,7 @@ pub enum XC2MCXorMode {
 
 /// Represents a macrocell.
 #[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct XC2Macrocell {
     /// Clock source for the register
     pub clk_src: XC2MCRegClkSrc,
@@ -454,6 +461,179 @@ impl XC2Macrocell {
         }
     }
 
+    /// Read the crbit representation of the macrocell `mc` in function block `fb` from the given `fuse_array`.
+    pub fn from_crbit(device: XC2Device, fb: u32, mc: u32, fuse_array: &FuseArray) -> XC2Macrocell {
+        let (x, y, mirror) = mc_block_loc(device, fb);
+        // direction
+        let x = x as i32;
+        let d = if !mirror {1} else {-1};
+        let aclk;
+        let clkop;
+        let clk;
+        let clkfreq;
+        let r;
+        let p;
+        let regmod;
+        let fb_bits;
+        let inreg;
+        let xorin;
+        let pu;
+        match device {
+            XC2Device::XC2C32 | XC2Device::XC2C32A => {
+                // The "32" variant
+                // each macrocell is 3 rows high
+                let y = y + (mc as usize) * 3;
+
+                aclk = fuse_array.get((x + d * 0) as usize, y + 0);
+                clkop = fuse_array.get((x + d * 1) as usize, y + 0);
+                clk = (fuse_array.get((x + d * 2) as usize, y + 0),
+                       fuse_array.get((x + d * 3) as usize, y + 0));
+                clkfreq = fuse_array.get((x + d * 4) as usize, y + 0);
+                r = (fuse_array.get((x + d * 5) as usize, y + 0),
+                     fuse_array.get((x + d * 6) as usize, y + 0));
+                p = (fuse_array.get((x + d * 7) as usize, y + 0),
+                     fuse_array.get((x + d * 8) as usize, y + 0));
+                regmod = (fuse_array.get((x + d * 0) as usize, y + 1),
+                          fuse_array.get((x + d * 1) as usize, y + 1));
+                fb_bits = (fuse_array.get((x + d * 4) as usize, y + 1),
+                           fuse_array.get((x + d * 5) as usize, y + 1));
+                inreg = fuse_array.get((x + d * 6) as usize, y + 1);
+                xorin = (fuse_array.get((x + d * 8) as usize, y + 1),
+                         fuse_array.get((x + d * 0) as usize, y + 2));
+                pu = fuse_array.get((x + d * 8) as usize, y + 2);
+            },
+            XC2Device::XC2C64 | XC2Device::XC2C64A => {
+                // The "64" variant
+                // each macrocell is 3 rows high
+                let y = y + (mc as usize) * 3;
+
+                aclk = fuse_array.get((x + d * 8) as usize, y + 0);
+                clkop = fuse_array.get((x + d * 7) as usize, y + 0);
+                clk = (fuse_array.get((x + d * 5) as usize, y + 0),
+                       fuse_array.get((x + d * 6) as usize, y + 0));
+                clkfreq = fuse_array.get((x + d * 4) as usize, y + 0);
+                r = (fuse_array.get((x + d * 2) as usize, y + 0),
+                     fuse_array.get((x + d * 3) as usize, y + 0));
+                p = (fuse_array.get((x + d * 0) as usize, y + 0),
+                     fuse_array.get((x + d * 1) as usize, y + 0));
+                regmod = (fuse_array.get((x + d * 7) as usize, y + 1),
+                          fuse_array.get((x + d * 8) as usize, y + 1));
+                fb_bits = (fuse_array.get((x + d * 3) as usize, y + 1),
+                           fuse_array.get((x + d * 4) as usize, y + 1));
+                inreg = fuse_array.get((x + d * 2) as usize, y + 1);
+                xorin = (fuse_array.get((x + d * 7) as usize, y + 2),
+                         fuse_array.get((x + d * 8) as usize, y + 2));
+                pu = fuse_array.get((x + d * 0) as usize, y + 2);
+            },
+            XC2Device::XC2C256 => {
+                // The "256" variant
+                // each macrocell is 3 rows high
+                let y = y + (mc as usize) * 3;
+
+                fb_bits = (fuse_array.get((x + d * 2) as usize, y + 0),
+                           fuse_array.get((x + d * 3) as usize, y + 0));
+                clkop = fuse_array.get((x + d * 5) as usize, y + 0);
+                clkfreq = fuse_array.get((x + d * 6) as usize, y + 0);
+                clk = (fuse_array.get((x + d * 7) as usize, y + 0),
+                       fuse_array.get((x + d * 8) as usize, y + 0));
+                aclk = fuse_array.get((x + d * 9) as usize, y + 0);
+                pu = fuse_array.get((x + d * 0) as usize, y + 1);
+                p = (fuse_array.get((x + d * 1) as usize, y + 1),
+                     fuse_array.get((x + d * 2) as usize, y + 1));
+                inreg = fuse_array.get((x + d * 9) as usize, y + 1);
+                xorin = (fuse_array.get((x + d * 0) as usize, y + 2),
+                         fuse_array.get((x + d * 1) as usize, y + 2));
+                r = (fuse_array.get((x + d * 4) as usize, y + 2),
+                     fuse_array.get((x + d * 5) as usize, y + 2));
+                regmod = (fuse_array.get((x + d * 6) as usize, y + 2),
+                          fuse_array.get((x + d * 7) as usize, y + 2));
+            },
+            XC2Device::XC2C128 | XC2Device::XC2C384 | XC2Device::XC2C512 => {
+                // The "common large macrocell" variant
+                // we need this funny lookup table, but otherwise macrocells are 2x15
+                let y = y + MC_TO_ROW_MAP_LARGE[mc as usize];
+
+                fb_bits = (fuse_array.get((x + d * 2) as usize, y + 0),
+                           fuse_array.get((x + d * 3) as usize, y + 0));
+                aclk = fuse_array.get((x + d * 8) as usize, y + 0);
+                clk = (fuse_array.get((x + d * 9) as usize, y + 0),
+                       fuse_array.get((x + d * 10) as usize, y + 0));
+                clkfreq = fuse_array.get((x + d * 11) as usize, y + 0);
+                clkop = fuse_array.get((x + d * 12) as usize, y + 0);
+                inreg = fuse_array.get((x + d * 13) as usize, y + 0);
+                pu = fuse_array.get((x + d * 14) as usize, y + 0);
+                xorin = (fuse_array.get((x + d * 0) as usize, y + 1),
+                         fuse_array.get((x + d * 1) as usize, y + 1));
+                regmod = (fuse_array.get((x + d * 9) as usize, y + 1),
+                          fuse_array.get((x + d * 10) as usize, y + 1));
+                r = (fuse_array.get((x + d * 11) as usize, y + 1),
+                     fuse_array.get((x + d * 12) as usize, y + 1));
+                p = (fuse_array.get((x + d * 13) as usize, y + 1),
+                     fuse_array.get((x + d * 14) as usize, y + 1));
+            }
+        }
+
+        let clk_src = match clk {
+            (false, false) => XC2MCRegClkSrc::GCK0,
+            (false, true)  => XC2MCRegClkSrc::GCK2,
+            (true, false)  => XC2MCRegClkSrc::GCK1,
+            (true, true)   => match aclk {
+                true => XC2MCRegClkSrc::CTC,
+                false => XC2MCRegClkSrc::PTC,
+            },
+        };
+
+        let reset_mode = match r {
+            (false, false) => XC2MCRegResetSrc::PTA,
+            (false, true)  => XC2MCRegResetSrc::GSR,
+            (true, false)  => XC2MCRegResetSrc::CTR,
+            (true, true)   => XC2MCRegResetSrc::Disabled,
+        };
+
+        let set_mode = match p {
+            (false, false) => XC2MCRegSetSrc::PTA,
+            (false, true)  => XC2MCRegSetSrc::GSR,
+            (true, false)  => XC2MCRegSetSrc::CTS,
+            (true, true)   => XC2MCRegSetSrc::Disabled,
+        };
+
+        let reg_mode = match regmod {
+            (false, false) => XC2MCRegMode::DFF,
+            (false, true)  => XC2MCRegMode::LATCH,
+            (true, false)  => XC2MCRegMode::TFF,
+            (true, true)   => XC2MCRegMode::DFFCE,
+        };
+
+        let fb_mode = match fb_bits {
+            (false, false) => XC2MCFeedbackMode::COMB,
+            (true, false)  => XC2MCFeedbackMode::REG,
+            (_, true)      => XC2MCFeedbackMode::Disabled,
+        };
+
+        let xormode = match xorin {
+            (false, false) => XC2MCXorMode::ZERO,
+            (false, true)  => XC2MCXorMode::PTCB,
+            (true, false)  => XC2MCXorMode::PTC,
+            (true, true)   => XC2MCXorMode::ONE,
+        };
+
+        // Buried macrocells do not have an IOB direct path
+        let is_buried = fb_ff_num_to_iob_num(device, fb, mc).is_none();
+
+        XC2Macrocell {
+            clk_src: clk_src,
+            clk_invert_pol: clkop,
+            is_ddr: clkfreq,
+            r_src: reset_mode,
+            s_src: set_mode,
+            init_state: pu,
+            reg_mode: reg_mode,
+            fb_mode: fb_mode,
+            ff_in_ibuf: !is_buried && !inreg,
+            xor_mode: xormode,
+        }
+    }
+
     /// encodes the Aclk bit
     pub fn aclk(&self) -> bool {
         match self.clk_src {
@@ -765,89 +945,96 @@ pub fn read_large_buried_ff_logical(fuses: &[bool], fuse_idx: usize) -> XC2Macro
 }
 
 /// Helper that prints the IOB and macrocell configuration on the "small" parts
-pub fn write_small_mc_to_jed(writer: &mut Write, device: XC2Device, fb: &XC2BitstreamFB, iobs: &[XC2MCSmallIOB],
-    fb_i: usize, fuse_base: usize) -> Result<(), io::Error> {
+pub fn write_small_mc_to_jed(writer: &mut JedFuseWriter, device: XC2Device, fb: &XC2BitstreamFB,
+    iobs: &[XC2MCSmallIOB], fb_i: usize, fuse_base: usize) -> Result<(), io::Error> {
 
     let zia_row_width = zia_get_row_width(device);
 
     for i in 0..MCS_PER_FB {
-        write!(writer, "L{:06} ",
-            fuse_base + zia_row_width * INPUTS_PER_ANDTERM +
+        writer.start_field(fuse_base + zia_row_width * INPUTS_PER_ANDTERM +
             ANDTERMS_PER_FB * INPUTS_PER_ANDTERM * 2 + ANDTERMS_PER_FB * MCS_PER_FB + i * 27)?;
 
         let iob = fb_ff_num_to_iob_num(device, fb_i as u32, i as u32).unwrap() as usize;
 
         // aclk
-        write!(writer, "{}", if fb.ffs[i].aclk() {"1"} else {"0"})?;
+        writer.fuse(fb.ffs[i].aclk())?;
 
         // clkop
-        write!(writer, "{}", if fb.ffs[i].clk_invert_pol {"1"} else {"0"})?;
+        writer.fuse(fb.ffs[i].clk_invert_pol)?;
 
         // clk
         let clk = fb.ffs[i].clk();
-        write!(writer, "{}{}", if clk.0 {"1"} else {"0"}, if clk.1 {"1"} else {"0"})?;
+        writer.fuse(clk.0)?;
+        writer.fuse(clk.1)?;
 
         // clkfreq
-        write!(writer, "{}", if fb.ffs[i].is_ddr {"1"} else {"0"})?;
+        writer.fuse(fb.ffs[i].is_ddr)?;
 
         // r
         let r = fb.ffs[i].r();
-        write!(writer, "{}{}", if r.0 {"1"} else {"0"}, if r.1 {"1"} else {"0"})?;
+        writer.fuse(r.0)?;
+        writer.fuse(r.1)?;
 
         // p
         let p = fb.ffs[i].p();
-        write!(writer, "{}{}", if p.0 {"1"} else {"0"}, if p.1 {"1"} else {"0"})?;
+        writer.fuse(p.0)?;
+        writer.fuse(p.1)?;
 
         // regmod
         let regmod = fb.ffs[i].regmod();
-        write!(writer, "{}{}", if regmod.0 {"1"} else {"0"}, if regmod.1 {"1"} else {"0"})?;
+        writer.fuse(regmod.0)?;
+        writer.fuse(regmod.1)?;
 
         // inz
         let inz = iobs[iob].inz();
-        write!(writer, "{}{}", if inz.0 {"1"} else {"0"}, if inz.1 {"1"} else {"0"})?;
+        writer.fuse(inz.0)?;
+        writer.fuse(inz.1)?;
 
         // fb
         let fb_bits = fb.ffs[i].fb();
-        write!(writer, "{}{}", if fb_bits.0 {"1"} else {"0"}, if fb_bits.1 {"1"} else {"0"})?;
+        writer.fuse(fb_bits.0)?;
+        writer.fuse(fb_bits.1)?;
 
         // inreg
-        write!(writer, "{}", if fb.ffs[i].ff_in_ibuf {"0"} else {"1"})?;
+        writer.fuse(!fb.ffs[i].ff_in_ibuf)?;
 
         // st
-        write!(writer, "{}", if iobs[iob].schmitt_trigger {"1"} else {"0"})?;
+        writer.fuse(iobs[iob].schmitt_trigger)?;
 
         // xorin
         let xorin = fb.ffs[i].xorin();
-        write!(writer, "{}{}", if xorin.0 {"1"} else {"0"}, if xorin.1 {"1"} else {"0"})?;
+        writer.fuse(xorin.0)?;
+        writer.fuse(xorin.1)?;
 
         // regcom
-        write!(writer, "{}", if iobs[iob].obuf_uses_ff {"0"} else {"1"})?;
+        writer.fuse(!iobs[iob].obuf_uses_ff)?;
 
         // oe
         let oe = iobs[iob].oe();
-        write!(writer, "{}{}{}{}",
-            if oe.0 {"1"} else {"0"}, if oe.1 {"1"} else {"0"},
-            if oe.2 {"1"} else {"0"}, if oe.3 {"1"} else {"0"})?;
+        writer.fuse(oe.0)?;
+        writer.fuse(oe.1)?;
+        writer.fuse(oe.2)?;
+        writer.fuse(oe.3)?;
 
         // tm
-        write!(writer, "{}", if iobs[iob].termination_enabled {"1"} else {"0"})?;
+        writer.fuse(iobs[iob].termination_enabled)?;
 
         // slw
-        write!(writer, "{}", if iobs[iob].slew_is_fast {"0"} else {"1"})?;
+        writer.fuse(!iobs[iob].slew_is_fast)?;
 
         // pu
-        write!(writer, "{}", if fb.ffs[i].init_state {"0"} else {"1"})?;
+        writer.fuse(!fb.ffs[i].init_state)?;
 
-        write!(writer, "*\n")?;
+        writer.end_field()?;
     }
-    write!(writer, "\n")?;
+    writer.blank_line()?;
 
     Ok(())
 }
 
 /// Helper that prints the IOB and macrocell configuration on the "large" parts
-pub fn write_large_mc_to_jed(writer: &mut Write, device: XC2Device, fb: &XC2BitstreamFB, iobs: &[XC2MCLargeIOB],
-    fb_i: usize, fuse_base: usize) -> Result<(), io::Error> {
+pub fn write_large_mc_to_jed(writer: &mut JedFuseWriter, device: XC2Device, fb: &XC2BitstreamFB,
+    iobs: &[XC2MCLargeIOB], fb_i: usize, fuse_base: usize) -> Result<(), io::Error> {
 
     let zia_row_width = zia_get_row_width(device);
 
@@ -855,89 +1042,98 @@ pub fn write_large_mc_to_jed(writer: &mut Write, device: XC2Device, fb: &XC2Bits
         ANDTERMS_PER_FB * INPUTS_PER_ANDTERM * 2 + ANDTERMS_PER_FB * MCS_PER_FB;
 
     for i in 0..MCS_PER_FB {
-        write!(writer, "L{:06} ", current_fuse_offset)?;
+        writer.start_field(current_fuse_offset)?;
 
         let iob = fb_ff_num_to_iob_num(device, fb_i as u32, i as u32);
 
         // aclk
-        write!(writer, "{}", if fb.ffs[i].aclk() {"1"} else {"0"})?;
+        writer.fuse(fb.ffs[i].aclk())?;
 
         // clk
         let clk = fb.ffs[i].clk();
-        write!(writer, "{}{}", if clk.0 {"1"} else {"0"}, if clk.1 {"1"} else {"0"})?;
+        writer.fuse(clk.0)?;
+        writer.fuse(clk.1)?;
 
 
         // clkfreq
-        write!(writer, "{}", if fb.ffs[i].is_ddr {"1"} else {"0"})?;
+        writer.fuse(fb.ffs[i].is_ddr)?;
 
         // clkop
-        write!(writer, "{}", if fb.ffs[i].clk_invert_pol {"1"} else {"0"})?;
+        writer.fuse(fb.ffs[i].clk_invert_pol)?;
 
         // dg
         if iob.is_some() {
-            write!(writer, "{}", if iobs[iob.unwrap() as usize].uses_data_gate {"1"} else {"0"})?;
+            writer.fuse(iobs[iob.unwrap() as usize].uses_data_gate)?;
         }
 
         // fb
         let fb_bits = fb.ffs[i].fb();
-        write!(writer, "{}{}", if fb_bits.0 {"1"} else {"0"}, if fb_bits.1 {"1"} else {"0"})?;
+        writer.fuse(fb_bits.0)?;
+        writer.fuse(fb_bits.1)?;
 
         if iob.is_some() {
             let iob = iob.unwrap() as usize;
 
             // inmod
             let inmod = iobs[iob].inmod();
-            write!(writer, "{}{}", if inmod.0 {"1"} else {"0"}, if inmod.1 {"1"} else {"0"})?;
+            writer.fuse(inmod.0)?;
+            writer.fuse(inmod.1)?;
 
             // inreg
-            write!(writer, "{}", if fb.ffs[i].ff_in_ibuf {"0"} else {"1"})?;
+            writer.fuse(!fb.ffs[i].ff_in_ibuf)?;
 
             // inz
             let inz = iobs[iob].inz();
-            write!(writer, "{}{}", if inz.0 {"1"} else {"0"}, if inz.1 {"1"} else {"0"})?;
+            writer.fuse(inz.0)?;
+            writer.fuse(inz.1)?;
 
             // oe
             let oe = iobs[iob].oe();
-            write!(writer, "{}{}{}{}",
-                if oe.0 {"1"} else {"0"}, if oe.1 {"1"} else {"0"},
-                if oe.2 {"1"} else {"0"}, if oe.3 {"1"} else {"0"})?;
+            writer.fuse(oe.0)?;
+            writer.fuse(oe.1)?;
+            writer.fuse(oe.2)?;
+            writer.fuse(oe.3)?;
         }
 
         // p
         let p = fb.ffs[i].p();
-        write!(writer, "{}{}", if p.0 {"1"} else {"0"}, if p.1 {"1"} else {"0"})?;
+        writer.fuse(p.0)?;
+        writer.fuse(p.1)?;
 
         // pu
-        write!(writer, "{}", if fb.ffs[i].init_state {"0"} else {"1"})?;
+        writer.fuse(!fb.ffs[i].init_state)?;
 
         if iob.is_some() {
             // regcom
-            write!(writer, "{}", if iobs[iob.unwrap() as usize].obuf_uses_ff {"0"} else {"1"})?;
+            writer.fuse(!(iobs[iob.unwrap() as usize].obuf_uses_ff))?;
         }
 
         // regmod
         let regmod = fb.ffs[i].regmod();
-        write!(writer, "{}{}", if regmod.0 {"1"} else {"0"}, if regmod.1 {"1"} else {"0"})?;
+        writer.fuse(regmod.0)?;
+        writer.fuse(regmod.1)?;
 
         // r
         let r = fb.ffs[i].r();
-        write!(writer, "{}{}", if r.0 {"1"} else {"0"}, if r.1 {"1"} else {"0"})?;
+        writer.fuse(r.0)?;
+        writer.fuse(r.1)?;
 
         if iob.is_some() {
             let iob = iob.unwrap() as usize;
 
             // slw
-            write!(writer, "{}", if iobs[iob].slew_is_fast {"0"} else {"1"})?;
+            writer.fuse(!iobs[iob].slew_is_fast)?;
 
             // tm
-            write!(writer, "{}", if iobs[iob].termination_enabled {"1"} else {"0"})?;
+            writer.fuse(iobs[iob].termination_enabled)?;
         }
 
         // xorin
         let xorin = fb.ffs[i].xorin();
-        write!(writer, "{}{}", if xorin.0 {"1"} else {"0"}, if xorin.1 {"1"} else {"0"})?;
+        writer.fuse(xorin.0)?;
+        writer.fuse(xorin.1)?;
 
-        write!(writer, "*\n")?;
+        writer.end_field()?;
 
         if iob.is_some() {
             current_fuse_offset += 29;
@@ -945,7 +1141,7 @@ pub fn write_large_mc_to_jed(writer: &mut Write, device: XC2Device, fb: &XC2Bits
             current_fuse_offset += 16;
         }
     }
-    write!(writer, "\n")?;
+    writer.blank_line()?;
 
     Ok(())
 }
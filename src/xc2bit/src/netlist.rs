@@ -0,0 +1,256 @@
+/*
+Copyright (c) 2017, Robert Ou <rqou@robertou.com> and contributors
+All rights reserved.
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice,
+   this list of conditions and the following disclaimer.
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+//! Contains a simple representation of a netlist of Coolrunner-II primitives, as written by the Yosys `write_json`
+//! command after `synth_coolrunner2`.
+//!
+//! Nets are numbered the same way Yosys numbers them. Net 0 is a constant zero, net 1 is a constant one, and all other
+//! numbers are real nets. A port bit that is not connected (`"x"` or `"z"` in Yosys) is `None`.
+
+use std::collections::HashMap;
+
+#[cfg(feature="serde")]
+use XC2BitError;
+
+/// Net number of the constant zero net
+pub const NET_ZERO: usize = 0;
+/// Net number of the constant one net
+pub const NET_ONE: usize = 1;
+
+/// Represents one instance of a primitive in a netlist
+#[derive(Clone, Debug)]
+pub struct XC2NetlistCell {
+    /// Name of this instance
+    pub name: String,
+    /// Name of the primitive, such as `ANDTERM` or `FDCP`
+    pub cell_type: String,
+    /// Integer parameters of the instance
+    pub parameters: HashMap<String, u64>,
+    /// Attributes of the instance, such as `LOC`
+    pub attributes: HashMap<String, String>,
+    /// Nets connected to each bit of each port of the instance
+    pub connections: HashMap<String, Vec<Option<usize>>>,
+}
+
+impl XC2NetlistCell {
+    /// Returns the net connected to a single-bit port, or `None` if the port is missing or not connected
+    pub fn port(&self, port: &str) -> Option<usize> {
+        self.connections.get(port).and_then(|x| x.first().cloned()).and_then(|x| x)
+    }
+
+    /// Returns the nets connected to a multi-bit port. Missing ports have no bits.
+    pub fn port_bits(&self, port: &str) -> &[Option<usize>] {
+        match self.connections.get(port) {
+            Some(x) => x,
+            None => &[],
+        }
+    }
+
+    /// Returns an integer parameter, or `default` if it is not set
+    pub fn parameter(&self, name: &str, default: u64) -> u64 {
+        *self.parameters.get(name).unwrap_or(&default)
+    }
+}
+
+/// Represents a flattened netlist of Coolrunner-II primitives
+#[derive(Clone, Debug, Default)]
+pub struct XC2Netlist {
+    pub cells: Vec<XC2NetlistCell>,
+}
+
+/// Internal helper that converts a Yosys parameter or attribute value into an integer. Yosys writes these either as
+/// JSON numbers or as strings of binary digits.
+#[cfg(feature = "serde")]
+fn yosys_value_to_int(value: &::serde_json::Value) -> Option<u64> {
+    match value {
+        &::serde_json::Value::Number(ref x) => x.as_u64(),
+        &::serde_json::Value::String(ref x) => {
+            if !x.is_empty() && x.len() <= 64 && x.chars().all(|c| c == '0' || c == '1') {
+                u64::from_str_radix(x, 2).ok()
+            } else {
+                None
+            }
+        },
+        _ => None,
+    }
+}
+
+/// Internal helper that converts a Yosys attribute value into a string
+#[cfg(feature = "serde")]
+fn yosys_value_to_string(value: &::serde_json::Value) -> String {
+    match value {
+        // Yosys adds a trailing space to strings that would otherwise look like binary numbers
+        &::serde_json::Value::String(ref x) => x.trim_end().to_owned(),
+        x => x.to_string(),
+    }
+}
+
+#[cfg(feature = "serde")]
+fn netlist_error(msg: String) -> XC2BitError {
+    XC2BitError::NetlistError{msg}
+}
+
+/// Reads a netlist written by the Yosys `write_json` command. The netlist must be flattened. If there is more than
+/// one module, the one with the `top` attribute is used.
+#[cfg(feature = "serde")]
+pub fn read_yosys_json(in_bytes: &[u8]) -> Result<XC2Netlist, XC2BitError> {
+    use serde_json::Value;
+
+    let json: Value = match ::serde_json::from_slice(in_bytes) {
+        Ok(x) => x,
+        Err(e) => return Err(netlist_error(format!("invalid json: {}", e))),
+    };
+
+    let modules = match json.get("modules").and_then(|x| x.as_object()) {
+        Some(x) => x,
+        None => return Err(netlist_error(String::from("missing modules in json"))),
+    };
+
+    let top_module = if modules.len() == 1 {
+        modules.values().next().unwrap()
+    } else {
+        let mut top_modules = modules.values().filter(|m| {
+            m.get("attributes").and_then(|x| x.get("top")).and_then(yosys_value_to_int).unwrap_or(0) != 0
+        });
+        match (top_modules.next(), top_modules.next()) {
+            (Some(x), None) => x,
+            _ => return Err(netlist_error(String::from("cannot find the top module"))),
+        }
+    };
+
+    let mut netlist = XC2Netlist::default();
+
+    if let Some(cells) = top_module.get("cells").and_then(|x| x.as_object()) {
+        for (cell_name, cell) in cells {
+            let cell_type = match cell.get("type").and_then(|x| x.as_str()) {
+                Some(x) => x.to_owned(),
+                None => return Err(netlist_error(format!("cell {} is missing its type", cell_name))),
+            };
+
+            let mut parameters = HashMap::new();
+            if let Some(params) = cell.get("parameters").and_then(|x| x.as_object()) {
+                for (k, v) in params {
+                    match yosys_value_to_int(v) {
+                        Some(x) => {parameters.insert(k.to_owned(), x);},
+                        None => return Err(netlist_error(
+                            format!("cell {} has a non-integer parameter {}", cell_name, k))),
+                    }
+                }
+            }
+
+            let mut attributes = HashMap::new();
+            if let Some(attrs) = cell.get("attributes").and_then(|x| x.as_object()) {
+                for (k, v) in attrs {
+                    attributes.insert(k.to_owned(), yosys_value_to_string(v));
+                }
+            }
+
+            let mut connections = HashMap::new();
+            if let Some(conns) = cell.get("connections").and_then(|x| x.as_object()) {
+                for (port, bits) in conns {
+                    let bits = match bits.as_array() {
+                        Some(x) => x,
+                        None => return Err(netlist_error(format!("cell {} port {} is not an array", cell_name, port))),
+                    };
+
+                    let mut port_bits = Vec::new();
+                    for bit in bits {
+                        port_bits.push(match bit {
+                            &Value::Number(ref x) => match x.as_u64() {
+                                Some(x) => Some(x as usize),
+                                None => return Err(netlist_error(
+                                    format!("cell {} port {} has a bad net", cell_name, port))),
+                            },
+                            &Value::String(ref x) if x == "0" => Some(NET_ZERO),
+                            &Value::String(ref x) if x == "1" => Some(NET_ONE),
+                            &Value::String(ref x) if x == "x" || x == "z" => None,
+                            _ => return Err(netlist_error(format!("cell {} port {} has a bad net", cell_name, port))),
+                        });
+                    }
+                    connections.insert(port.to_owned(), port_bits);
+                }
+            }
+
+            netlist.cells.push(XC2NetlistCell {
+                name: cell_name.to_owned(),
+                cell_type,
+                parameters,
+                attributes,
+                connections,
+            });
+        }
+    }
+
+    Ok(netlist)
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_json() {
+        let json = br#"{
+            "creator": "Yosys",
+            "modules": {
+                "sub": {
+                    "cells": {}
+                },
+                "top": {
+                    "attributes": {"top": "00000000000000000000000000000001"},
+                    "ports": {"a": {"direction": "input", "bits": [2]}},
+                    "cells": {
+                        "$and": {
+                            "type": "ANDTERM",
+                            "parameters": {"TRUE_INP": "00000000000000000000000000000001", "COMP_INP": 1},
+                            "attributes": {"LOC": "FB1_1 "},
+                            "connections": {"IN": [2], "IN_B": ["1"], "OUT": [3], "X": ["x"]}
+                        }
+                    }
+                }
+            }
+        }"#;
+
+        let netlist = read_yosys_json(json).unwrap();
+        assert_eq!(netlist.cells.len(), 1);
+        let cell = &netlist.cells[0];
+        assert_eq!(cell.name, "$and");
+        assert_eq!(cell.cell_type, "ANDTERM");
+        assert_eq!(cell.parameter("TRUE_INP", 0), 1);
+        assert_eq!(cell.parameter("COMP_INP", 0), 1);
+        assert_eq!(cell.parameter("MISSING", 5), 5);
+        assert_eq!(cell.attributes["LOC"], "FB1_1");
+        assert_eq!(cell.port("IN"), Some(2));
+        assert_eq!(cell.port("IN_B"), Some(NET_ONE));
+        assert_eq!(cell.port("X"), None);
+        assert_eq!(cell.port_bits("OUT"), &[Some(3)]);
+    }
+
+    #[test]
+    fn read_json_no_top() {
+        let json = br#"{"modules": {"a": {}, "b": {}}}"#;
+        assert_eq!(read_yosys_json(json).err().unwrap(), netlist_error(String::from("cannot find the top module")));
+    }
+}
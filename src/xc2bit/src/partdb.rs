@@ -25,11 +25,13 @@ OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 //! Miscellaneous stuff related to possible part combinations
 
-use std::ascii::AsciiExt;
 use std::fmt;
 
+use fusemap_physical::{fuse_array_dims};
+
 /// Coolrunner-II devices
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum XC2Device {
     XC2C32,
     XC2C32A,
@@ -71,10 +73,55 @@ impl XC2Device {
             XC2Device::XC2C512 => 270,
         }
     }
+
+    /// Returns the length of the JTAG instruction register for the device type
+    pub fn ir_length(&self) -> u32 {
+        8
+    }
+
+    /// Returns the JTAG IDCODE for the device type. The revision and package bits are set to zero; `idcode_mask`
+    /// should be used to ignore them when checking the IDCODE of a real device.
+    pub fn idcode(&self) -> u32 {
+        match *self {
+            XC2Device::XC2C32 => 0x06C18093,
+            XC2Device::XC2C32A => 0x06E18093,
+            XC2Device::XC2C64 => 0x06C58093,
+            XC2Device::XC2C64A => 0x06E58093,
+            XC2Device::XC2C128 => 0x06D88093,
+            XC2Device::XC2C256 => 0x06D48093,
+            XC2Device::XC2C384 => 0x06D58093,
+            XC2Device::XC2C512 => 0x06D78093,
+        }
+    }
+
+    /// Returns the mask of the JTAG IDCODE bits that identify the device type
+    pub fn idcode_mask(&self) -> u32 {
+        0x0FFF8FFF
+    }
+
+    /// Returns the number of rows that are programmed during in-system configuration
+    pub fn isc_num_rows(&self) -> usize {
+        fuse_array_dims(*self).1
+    }
+
+    /// Returns the number of fuse data bits in each row that is programmed during in-system configuration
+    pub fn isc_data_width(&self) -> usize {
+        fuse_array_dims(*self).0
+    }
+
+    /// Returns the number of row address bits used during in-system configuration
+    pub fn isc_address_width(&self) -> usize {
+        let mut bits = 0;
+        while (1 << bits) < self.isc_num_rows() {
+            bits += 1;
+        }
+        bits
+    }
 }
 
 /// Possible speed grades
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum XC2Speed {
     Speed4,
     Speed5,
@@ -97,6 +144,7 @@ impl fmt::Display for XC2Speed {
 
 /// Possible physical packages
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum XC2Package {
     PC44,
     QFG32,
@@ -216,6 +264,52 @@ pub fn is_valid_part_combination(device: XC2Device, speed: XC2Speed, package: XC
     }
 }
 
+/// Timing parameters of a device and speed grade, in picoseconds. These are not built into this library and must be
+/// copied from the data sheet of the device.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct XC2TimingParams {
+    /// Input pin to output pin through one product term (TPD1)
+    pub tpd1: u32,
+    /// Input pin to output pin through an OR of more than one product term (TPD2)
+    pub tpd2: u32,
+    /// Setup time of an input pin through one product term before a global clock (TSU1)
+    pub tsu1: u32,
+    /// Setup time of an input pin through an OR of more than one product term before a global clock (TSU2)
+    pub tsu2: u32,
+    /// Hold time of an input pin after a global clock (TH)
+    pub th: u32,
+    /// Global clock to output pin from a register (TCO)
+    pub tco: u32,
+    /// Added for each pass back through the ZIA and PLA from a macrocell (TF)
+    pub tf: u32,
+    /// Global tri-state pin to output enabled (TOE)
+    pub toe: u32,
+    /// Global tri-state pin to output disabled (TOD)
+    pub tod: u32,
+    /// Input pin through a product term to output enabled (TPOE)
+    pub tpoe: u32,
+    /// Input pin through a product term to output disabled (TPOD)
+    pub tpod: u32,
+    /// Setup time of an input pin that goes directly into a register (`ff_in_ibuf`) before a global clock
+    pub tsuin: u32,
+    /// Hold time of an input pin that goes directly into a register (`ff_in_ibuf`) after a global clock
+    pub thin: u32,
+    /// Added to the clock path when a register is clocked by a product term instead of a global clock
+    pub tptck: u32,
+    /// Added to every path to an output pin with fast slew
+    pub tslew_fast: u32,
+    /// Added to every path to an output pin with slow slew
+    pub tslew_slow: u32,
+    /// Added to the clock path when GCK2 goes through the clock divider
+    pub tclkdiv: u32,
+    /// Minimum clock period for a register feeding a register through one product term (1/fSYSTEM1)
+    pub tsys1: u32,
+    /// Minimum clock period for a register feeding a register through an OR of more than one product term
+    /// (1/fSYSTEM2)
+    pub tsys2: u32,
+}
+
 /// Parses the given string in <device>-<speed>-<package> format and returns the parsed result if it is a legal
 /// combination. Returns `None` if the part name string does not represent a valid device.
 pub fn parse_part_name_string(part_name: &str) -> Option<(XC2Device, XC2Speed, XC2Package)> {
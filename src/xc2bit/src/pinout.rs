@@ -0,0 +1,204 @@
+/*
+Copyright (c) 2017, Robert Ou <rqou@robertou.com> and contributors
+All rights reserved.
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice,
+   this list of conditions and the following disclaimer.
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+//! Contains the mapping between I/O pins, package pins, and I/O banks.
+//!
+//! The pin tables come from the XC2C32A datasheet (DS310). The XC2C32 is pin-compatible with the XC2C32A and uses
+//! the same tables. Pinouts of the other devices and packages have not been entered yet, so lookups for them return
+//! `None` and `pinout_known` returns `false`. The same is true of the I/O banks of the XC2C64A and larger devices.
+//! The missing tables need to be entered from the Xilinx package files.
+
+use *;
+
+/// XC2C32A in the QFG32 package, indexed by I/O pin number. Empty strings are I/O pins that are not bonded out.
+static PINS_32_QFG32: [&str; 32] = [
+    "",   "",   "",   "P3", "P2", "P1", "P32", "P31", "P30", "P29", "P28", "P24", "",    "P23", "",    "",
+    "P5", "",   "",   "",   "P6", "P7", "P8",  "P9",  "P10", "",    "",    "P13", "P17", "P18", "P19", "",
+];
+
+/// XC2C32A in the VQ44 package, indexed by I/O pin number
+static PINS_32_VQ44: [&str; 32] = [
+    "P38", "P37", "P36", "P34", "P33", "P32", "P31", "P30", "P29", "P28", "P27", "P23", "P22", "P21", "P20", "P19",
+    "P39", "P40", "P41", "P42", "P43", "P44", "P1",  "P2",  "P3",  "P5",  "P6",  "P8",  "P12", "P13", "P14", "P16",
+];
+
+/// XC2C32A in the CP56 package, indexed by I/O pin number
+static PINS_32_CP56: [&str; 32] = [
+    "F1", "E3", "E1", "D1", "C1", "A3", "A2", "B1", "A1", "C4", "C5", "C8", "A10", "B10", "C10", "E8",
+    "G1", "F3", "H1", "G3", "J1", "K1", "K2", "K3", "H3", "K5", "H5", "H8", "K8",  "H10", "G10", "F10",
+];
+
+/// Returns the pin table for a device and package, if it is known
+fn pin_table(device: XC2Device, package: XC2Package) -> Option<&'static [&'static str]> {
+    match (device, package) {
+        (XC2Device::XC2C32, XC2Package::VQ44) |
+        (XC2Device::XC2C32A, XC2Package::VQ44) => Some(&PINS_32_VQ44),
+        (XC2Device::XC2C32, XC2Package::CP56) |
+        (XC2Device::XC2C32A, XC2Package::CP56) => Some(&PINS_32_CP56),
+        (XC2Device::XC2C32A, XC2Package::QFG32) => Some(&PINS_32_QFG32),
+        _ => None,
+    }
+}
+
+/// Returns whether the package pins of the given device and package are known
+pub fn pinout_known(device: XC2Device, package: XC2Package) -> bool {
+    pin_table(device, package).is_some()
+}
+
+/// Returns the name of the package pin that the given I/O pin (in the internal numbering scheme) is bonded to. Returns
+/// `None` if the I/O pin is not bonded out or if the pinout is not known.
+pub fn iob_to_pin_name(device: XC2Device, package: XC2Package, iob: u32) -> Option<&'static str> {
+    match pin_table(device, package).and_then(|table| table.get(iob as usize)) {
+        Some(&"") | None => None,
+        Some(&name) => Some(name),
+    }
+}
+
+/// Returns the I/O pin (in the internal numbering scheme) that is bonded to the given package pin. Pin names are not
+/// case-sensitive. Returns `None` if the package pin is not an I/O pin or if the pinout is not known.
+pub fn pin_name_to_iob(device: XC2Device, package: XC2Package, name: &str) -> Option<u32> {
+    pin_table(device, package).and_then(|table| {
+        table.iter().position(|x| !x.is_empty() && x.eq_ignore_ascii_case(name)).map(|x| x as u32)
+    })
+}
+
+/// Returns whether the given I/O pin is bonded out in the given package, or `None` if the pinout is not known
+pub fn iob_is_bonded(device: XC2Device, package: XC2Package, iob: u32) -> Option<bool> {
+    pin_table(device, package).and_then(|table| table.get(iob as usize)).map(|x| !x.is_empty())
+}
+
+/// Returns the number of I/O banks in a device. These are numbered the same way as the `ivoltage` and `ovoltage`
+/// fields in the bitstream.
+pub fn num_banks(device: XC2Device) -> usize {
+    match device {
+        XC2Device::XC2C32 | XC2Device::XC2C64 => 1,
+        XC2Device::XC2C32A | XC2Device::XC2C64A | XC2Device::XC2C128 | XC2Device::XC2C256 => 2,
+        XC2Device::XC2C384 | XC2Device::XC2C512 => 4,
+    }
+}
+
+/// Returns the I/O bank that the given I/O pin belongs to, or `None` if this is not known
+pub fn iob_to_bank(device: XC2Device, iob: u32) -> Option<u32> {
+    if iob as usize >= device.num_iobs() {
+        return None;
+    }
+
+    match device {
+        XC2Device::XC2C32 | XC2Device::XC2C64 => Some(0),
+        // FB1 is in bank 2 and FB2 is in bank 1 (DS310)
+        XC2Device::XC2C32A => Some(if iob < MCS_PER_FB as u32 {1} else {0}),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pinout_lookups() {
+        assert_eq!(iob_to_pin_name(XC2Device::XC2C32A, XC2Package::VQ44, 0), Some("P38"));
+        assert_eq!(iob_to_pin_name(XC2Device::XC2C32A, XC2Package::VQ44, 31), Some("P16"));
+        assert_eq!(iob_to_pin_name(XC2Device::XC2C32A, XC2Package::VQ44, 32), None);
+        assert_eq!(pin_name_to_iob(XC2Device::XC2C32A, XC2Package::CP56, "h10"), Some(29));
+        assert_eq!(pin_name_to_iob(XC2Device::XC2C32A, XC2Package::VQ44, "P4"), None);
+        assert_eq!(iob_is_bonded(XC2Device::XC2C32A, XC2Package::QFG32, 0), Some(false));
+        assert_eq!(iob_is_bonded(XC2Device::XC2C32A, XC2Package::QFG32, 3), Some(true));
+        assert_eq!(iob_is_bonded(XC2Device::XC2C256, XC2Package::TQ144, 0), None);
+        assert!(!pinout_known(XC2Device::XC2C32A, XC2Package::PC44));
+    }
+
+    #[test]
+    fn pinout_tables_consistent() {
+        for &(device, package) in &[(XC2Device::XC2C32A, XC2Package::QFG32), (XC2Device::XC2C32A, XC2Package::VQ44),
+                                    (XC2Device::XC2C32A, XC2Package::CP56)] {
+            let mut bonded = 0;
+            for iob in 0..device.num_iobs() as u32 {
+                if let Some(name) = iob_to_pin_name(device, package, iob) {
+                    assert_eq!(pin_name_to_iob(device, package, name), Some(iob));
+                    bonded += 1;
+                }
+            }
+            assert_eq!(bonded, match package {
+                XC2Package::QFG32 => 20,
+                _ => 32,
+            });
+        }
+    }
+
+    #[test]
+    fn pinout_all_packages() {
+        let devices = [XC2Device::XC2C32, XC2Device::XC2C32A, XC2Device::XC2C64, XC2Device::XC2C64A,
+                       XC2Device::XC2C128, XC2Device::XC2C256, XC2Device::XC2C384, XC2Device::XC2C512];
+        let packages = [XC2Package::PC44, XC2Package::QFG32, XC2Package::VQ44, XC2Package::QFG48, XC2Package::CP56,
+                        XC2Package::VQ100, XC2Package::CP132, XC2Package::TQ144, XC2Package::PQ208, XC2Package::FT256,
+                        XC2Package::FG324];
+        let speeds = [XC2Speed::Speed4, XC2Speed::Speed5, XC2Speed::Speed6, XC2Speed::Speed7, XC2Speed::Speed10];
+
+        for &device in &devices {
+            for &package in &packages {
+                let valid = speeds.iter().any(|&speed| is_valid_part_combination(device, speed, package));
+                let table = match pin_table(device, package) {
+                    Some(x) => x,
+                    None => {
+                        // Without a table, every lookup has to say that the pinout is not known
+                        assert!(!pinout_known(device, package));
+                        for iob in 0..device.num_iobs() as u32 {
+                            assert_eq!(iob_to_pin_name(device, package, iob), None);
+                            assert_eq!(iob_is_bonded(device, package, iob), None);
+                        }
+                        continue;
+                    }
+                };
+
+                assert!(valid, "pin table for {} {} which does not exist", device, package);
+                assert_eq!(table.len(), device.num_iobs(), "{} {}", device, package);
+                for (iob, &name) in table.iter().enumerate() {
+                    if name.is_empty() {
+                        assert_eq!(iob_is_bonded(device, package, iob as u32), Some(false));
+                    } else {
+                        assert_eq!(pin_name_to_iob(device, package, name), Some(iob as u32),
+                            "{} {} pin {} is not unique", device, package, name);
+                    }
+                }
+            }
+
+            // Bank assignments are either known for every pin or for none of them
+            let banks = (0..device.num_iobs() as u32).map(|iob| iob_to_bank(device, iob)).collect::<Vec<_>>();
+            assert!(banks.iter().all(|x| x.is_some()) || banks.iter().all(|x| x.is_none()), "{}", device);
+            assert!(banks.iter().all(|x| x.map_or(true, |bank| (bank as usize) < num_banks(device))), "{}", device);
+        }
+    }
+
+    #[test]
+    fn pinout_banks() {
+        assert_eq!(iob_to_bank(XC2Device::XC2C32A, 0), Some(1));
+        assert_eq!(iob_to_bank(XC2Device::XC2C32A, 16), Some(0));
+        assert_eq!(iob_to_bank(XC2Device::XC2C32A, 32), None);
+        assert_eq!(iob_to_bank(XC2Device::XC2C64, 63), Some(0));
+        assert_eq!(iob_to_bank(XC2Device::XC2C256, 0), None);
+        assert_eq!(num_banks(XC2Device::XC2C512), 4);
+    }
+}
@@ -30,14 +30,17 @@ use *;
 /// Represents one single AND term in the PLA. Each AND term can perform an AND function on any subset of its inputs
 /// and the complement of those inputs. The index for each input is the corresponding ZIA row.
 #[derive(Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct XC2PLAAndTerm {
     /// Indicates whether a particular ZIA row output is a part of this AND term.
     ///
     /// `true` = part of and, `false` = not part of and
+    #[cfg_attr(feature = "serde", serde(with = "serde_array"))]
     pub input: [bool; INPUTS_PER_ANDTERM],
     /// Indicates whether the complement of a particular ZIA row output is a part of this AND term.
     ///
     /// `true` = part of and, `false` = not part of and
+    #[cfg_attr(feature = "serde", serde(with = "serde_array"))]
     pub input_b: [bool; INPUTS_PER_ANDTERM],
 }
 
@@ -58,10 +61,12 @@ impl Default for XC2PLAAndTerm {
 /// Represents one single OR term in the PLA. Each OR term can perform an OR function on any subset of its inputs.
 /// The index for each input is the index of the corresponding AND term in the same PLA.
 #[derive(Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct XC2PLAOrTerm {
     /// Indicates whether a particular PLA AND term is a part of this OR term.
     ///
     /// `true` = part of or, `false` = not part of or
+    #[cfg_attr(feature = "serde", serde(with = "serde_array"))]
     pub input: [bool; ANDTERMS_PER_FB],
 }
 
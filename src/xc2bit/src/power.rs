@@ -0,0 +1,472 @@
+/*
+Copyright (c) 2017, Robert Ou <rqou@robertou.com> and contributors
+All rights reserved.
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice,
+   this list of conditions and the following disclaimer.
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+//! Contains a power estimator for a configured device.
+//!
+//! The estimate is built from a small number of per-resource current coefficients in `XC2PowerParams`. Every signal
+//! is assumed to toggle at a single frequency: signals from a register toggle at the frequency of its clock times the
+//! toggle rate of that clock, and everything else toggles at the toggle frequency given for the input pins.
+
+use std::io;
+use std::io::Write;
+
+use *;
+
+/// Current coefficients used by the power estimator. Static currents are in µA, and dynamic currents are in µA per MHz
+/// that the signal toggles at. These are not built into this library and must be worked out for the device from its
+/// data sheet or from XPower.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct XC2PowerParams {
+    /// Standby current of the device with nothing toggling
+    pub standby: f64,
+    /// Each product term that has at least one input
+    pub product_term: f64,
+    /// Each macrocell whose OR term has at least one product term
+    pub macrocell: f64,
+    /// Each register that is used, per MHz of its clock
+    pub register: f64,
+    /// Each global clock that is enabled, per MHz of its clock
+    pub global_clock: f64,
+    /// The GCK2 clock divider while it is enabled, per MHz of its input clock
+    pub clock_divider: f64,
+    /// Each input buffer that is used
+    pub input: f64,
+    /// Extra current for each input buffer that uses the Schmitt trigger
+    pub schmitt_trigger: f64,
+    /// Static current of each input buffer that uses or provides VREF
+    pub vref: f64,
+    /// Static current of the DataGate circuit while it is enabled
+    pub data_gate: f64,
+    /// Extra load capacitance of each output with fast slew, in pF
+    pub fast_slew_pf: f64,
+}
+
+/// The frequency and toggle rate of one clock
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct XC2ClockActivity {
+    pub clock: XC2ClockDomain,
+    /// Frequency of the clock in MHz. For GCK2 this is the frequency on the pin, before the clock divider.
+    pub freq_mhz: f64,
+    /// Fraction of clock edges at which the registers on this clock change, from 0 to 1
+    pub toggle_rate: f64,
+}
+
+/// Operating conditions for the power estimator
+#[derive(Clone, Debug, PartialEq)]
+pub struct XC2PowerConditions {
+    /// Activity of each clock. Registers on clocks that are not listed are assumed to never be clocked.
+    pub clocks: Vec<XC2ClockActivity>,
+    /// Frequency that the input pins and the combinational logic toggle at, in MHz
+    pub input_toggle_mhz: f64,
+    /// Whether DataGate is blocking its inputs. Input pins that use DataGate then do not toggle.
+    pub data_gate_closed: bool,
+    /// External load on each output pin, in pF
+    pub load_pf: f64,
+    /// Core supply voltage
+    pub vccint: f64,
+    /// I/O supply voltage. If this is `None`, 3.3 V is used if any bank is configured for high voltage outputs and
+    /// 1.8 V is used otherwise.
+    pub vccio: Option<f64>,
+}
+
+impl Default for XC2PowerConditions {
+    fn default() -> Self {
+        XC2PowerConditions {
+            clocks: Vec::new(),
+            input_toggle_mhz: 0.0,
+            data_gate_closed: false,
+            load_pf: 10.0,
+            vccint: 1.8,
+            vccio: None,
+        }
+    }
+}
+
+/// Result of power estimation. Currents are in µA.
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct XC2PowerEstimate {
+    /// Standby current, including VREF and DataGate
+    pub standby: f64,
+    /// Product terms and macrocells
+    pub logic: f64,
+    /// Global clocks, the clock divider, and registers
+    pub clocks: f64,
+    /// Input buffers
+    pub inputs: f64,
+    /// Output buffers, drawn from VCCIO
+    pub outputs: f64,
+    /// Core supply voltage that was used
+    pub vccint: f64,
+    /// I/O supply voltage that was used
+    pub vccio: f64,
+}
+
+impl XC2PowerEstimate {
+    /// Returns the current drawn from VCCINT in µA
+    pub fn vccint_current(&self) -> f64 {
+        self.standby + self.logic + self.clocks + self.inputs
+    }
+
+    /// Returns the total power in mW
+    pub fn total_power_mw(&self) -> f64 {
+        (self.vccint_current() * self.vccint + self.outputs * self.vccio) / 1000.0
+    }
+
+    /// Write a human-readable version of this estimate to the given `writer` object
+    pub fn write_report(&self, writer: &mut Write) -> Result<(), io::Error> {
+        write!(writer, "standby: {:.1} uA\n", self.standby)?;
+        write!(writer, "logic: {:.1} uA\n", self.logic)?;
+        write!(writer, "clocks: {:.1} uA\n", self.clocks)?;
+        write!(writer, "inputs: {:.1} uA\n", self.inputs)?;
+        write!(writer, "VCCINT ({:.2} V): {:.1} uA\n", self.vccint, self.vccint_current())?;
+        write!(writer, "VCCIO ({:.2} V): {:.1} uA\n", self.vccio, self.outputs)?;
+        write!(writer, "total: {:.3} mW\n", self.total_power_mw())?;
+
+        Ok(())
+    }
+}
+
+/// Returns whether any I/O bank is configured for high voltage outputs
+fn any_bank_high_voltage(bits: &XC2BitstreamBits) -> bool {
+    match bits {
+        &XC2BitstreamBits::XC2C32 {ovoltage, ..} |
+        &XC2BitstreamBits::XC2C64 {ovoltage, ..} => ovoltage,
+        &XC2BitstreamBits::XC2C32A {ref ovoltage, ..} |
+        &XC2BitstreamBits::XC2C64A {ref ovoltage, ..} |
+        &XC2BitstreamBits::XC2C128 {ref ovoltage, ..} |
+        &XC2BitstreamBits::XC2C256 {ref ovoltage, ..} => ovoltage.iter().any(|&x| x),
+        &XC2BitstreamBits::XC2C384 {ref ovoltage, ..} |
+        &XC2BitstreamBits::XC2C512 {ref ovoltage, ..} => ovoltage.iter().any(|&x| x),
+    }
+}
+
+/// Returns whether the DataGate circuit is enabled
+fn data_gate_enabled(bits: &XC2BitstreamBits) -> bool {
+    match bits {
+        &XC2BitstreamBits::XC2C128 {data_gate, ..} |
+        &XC2BitstreamBits::XC2C256 {data_gate, ..} |
+        &XC2BitstreamBits::XC2C384 {data_gate, ..} |
+        &XC2BitstreamBits::XC2C512 {data_gate, ..} => data_gate,
+        _ => false,
+    }
+}
+
+/// Estimates the current drawn by a bitstream under the given conditions
+pub fn estimate_power(bitstream: &XC2Bitstream, params: &XC2PowerParams, conditions: &XC2PowerConditions)
+    -> XC2PowerEstimate {
+
+    let bits = &bitstream.bits;
+    let device = bits.device_type();
+    let input_freq = conditions.input_toggle_mhz;
+
+    let clock_activity = |domain: XC2ClockDomain| {
+        conditions.clocks.iter().find(|x| x.clock == domain).map(|x| (x.freq_mhz, x.toggle_rate)).unwrap_or((0.0, 0.0))
+    };
+    let clock_div = bits.get_clock_div().cloned().filter(|x| x.enabled);
+    let clock_div_ratio = clock_div.map(|x| match x.div_ratio {
+        XC2ClockDivRatio::Div2 => 2.0,
+        XC2ClockDivRatio::Div4 => 4.0,
+        XC2ClockDivRatio::Div6 => 6.0,
+        XC2ClockDivRatio::Div8 => 8.0,
+        XC2ClockDivRatio::Div10 => 10.0,
+        XC2ClockDivRatio::Div12 => 12.0,
+        XC2ClockDivRatio::Div14 => 14.0,
+        XC2ClockDivRatio::Div16 => 16.0,
+    }).unwrap_or(1.0);
+
+    // (zia_mode, obuf_uses_ff, obuf_mode, slew_is_fast, schmitt_trigger, uses_vref, uses_data_gate) of each I/O pin
+    let mut iob_settings = Vec::new();
+    for i in 0..device.num_iobs() {
+        iob_settings.push(if let Some(iob) = bits.get_small_iob(i) {
+            (iob.zia_mode, iob.obuf_uses_ff, iob.obuf_mode, iob.slew_is_fast, iob.schmitt_trigger, false, false)
+        } else {
+            let iob = bits.get_large_iob(i).unwrap();
+            let (schmitt_trigger, uses_vref) = match iob.ibuf_mode {
+                XC2IOBIbufMode::NoVrefNoSt => (false, false),
+                XC2IOBIbufMode::NoVrefSt => (true, false),
+                XC2IOBIbufMode::UsesVref | XC2IOBIbufMode::IsVref => (false, true),
+            };
+            (iob.zia_mode, iob.obuf_uses_ff, iob.obuf_mode, iob.slew_is_fast, schmitt_trigger, uses_vref,
+                iob.uses_data_gate)
+        });
+    }
+
+    let mut estimate = XC2PowerEstimate {
+        standby: params.standby,
+        vccint: conditions.vccint,
+        vccio: conditions.vccio.unwrap_or(if any_bank_high_voltage(bits) {3.3} else {1.8}),
+        ..Default::default()
+    };
+
+    // Global clocks and the clock divider
+    let global_nets = bits.get_global_nets();
+    for i in 0..3 {
+        if global_nets.gck_enable[i] {
+            let (freq, _) = clock_activity(XC2ClockDomain::GCK(i as u32));
+            estimate.clocks += params.global_clock * freq;
+            if i == 2 && clock_div.is_some() {
+                estimate.clocks += params.clock_divider * freq;
+            }
+        }
+    }
+    if data_gate_enabled(bits) {
+        estimate.standby += params.data_gate;
+    }
+
+    // Logic and registers. The toggle frequency of each macrocell output is kept for the output buffers.
+    let mut reg_freq = vec![[0.0; MCS_PER_FB]; device.num_fbs()];
+    let mut xor_freq = vec![[input_freq; MCS_PER_FB]; device.num_fbs()];
+    for fb in 0..device.num_fbs() {
+        let fb_bits = &bits.get_fb()[fb];
+        for mc in 0..MCS_PER_FB {
+            let ff = &fb_bits.ffs[mc];
+            let iob = fb_ff_num_to_iob_num(device, fb as u32, mc as u32).map(|x| iob_settings[x as usize]);
+            let reg_used = ff.fb_mode == XC2MCFeedbackMode::REG || match iob {
+                Some((zia_mode, obuf_uses_ff, obuf_mode, ..)) => zia_mode == XC2IOBZIAMode::REG ||
+                    (obuf_uses_ff && obuf_mode != XC2IOBOBufMode::Disabled && obuf_mode != XC2IOBOBufMode::CGND),
+                None => false,
+            };
+            if !reg_used {
+                continue;
+            }
+
+            let domain = match ff.clk_src {
+                XC2MCRegClkSrc::GCK0 => XC2ClockDomain::GCK(0),
+                XC2MCRegClkSrc::GCK1 => XC2ClockDomain::GCK(1),
+                XC2MCRegClkSrc::GCK2 => XC2ClockDomain::GCK(2),
+                XC2MCRegClkSrc::CTC => XC2ClockDomain::CTC{fb: fb as u32},
+                XC2MCRegClkSrc::PTC => XC2ClockDomain::PTC{fb: fb as u32, mc: mc as u32},
+            };
+            let (mut freq, toggle_rate) = clock_activity(domain);
+            if domain == XC2ClockDomain::GCK(2) {
+                freq /= clock_div_ratio;
+            }
+            if ff.is_ddr {
+                freq *= 2.0;
+            }
+            estimate.clocks += params.register * freq;
+            reg_freq[fb][mc] = freq * toggle_rate;
+        }
+
+        for mc in 0..MCS_PER_FB {
+            if fb_bits.or_terms[mc].input.iter().any(|&x| x) {
+                estimate.logic += params.macrocell * input_freq;
+            } else {
+                xor_freq[fb][mc] = 0.0;
+            }
+        }
+        for pt in 0..ANDTERMS_PER_FB {
+            let and_term = &fb_bits.and_terms[pt];
+            if and_term.input.iter().chain(and_term.input_b.iter()).any(|&x| x) {
+                estimate.logic += params.product_term * input_freq;
+            }
+        }
+    }
+
+    // Input buffers
+    let data_gate = data_gate_enabled(bits);
+    for &(zia_mode, _, _, _, schmitt_trigger, uses_vref, uses_data_gate) in &iob_settings {
+        if uses_vref {
+            estimate.standby += params.vref;
+        }
+        if zia_mode == XC2IOBZIAMode::Disabled || (data_gate && uses_data_gate && conditions.data_gate_closed) {
+            continue;
+        }
+        estimate.inputs += params.input * input_freq;
+        if schmitt_trigger {
+            estimate.inputs += params.schmitt_trigger * input_freq;
+        }
+    }
+    let inpin_used = bits.get_fb().iter()
+        .any(|fb| fb.zia_bits.iter().any(|x| x.selected == XC2ZIAInput::DedicatedInput));
+    if let (Some(inpin), true) = (bits.get_inpin(), inpin_used) {
+        estimate.inputs += params.input * input_freq;
+        if inpin.schmitt_trigger {
+            estimate.inputs += params.schmitt_trigger * input_freq;
+        }
+    }
+
+    // Output buffers
+    for i in 0..device.num_iobs() {
+        let (_, obuf_uses_ff, obuf_mode, slew_is_fast, ..) = iob_settings[i];
+        if obuf_mode == XC2IOBOBufMode::Disabled || obuf_mode == XC2IOBOBufMode::CGND {
+            continue;
+        }
+        let (fb, mc) = iob_num_to_fb_ff_num(device, i as u32).unwrap();
+        let freq = if obuf_uses_ff {reg_freq[fb as usize][mc as usize]} else {xor_freq[fb as usize][mc as usize]};
+        let load = conditions.load_pf + if slew_is_fast {params.fast_slew_pf} else {0.0};
+        estimate.outputs += load * estimate.vccio * freq;
+    }
+
+    estimate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blank(device: XC2Device) -> XC2Bitstream {
+        let (speed, package) = match device {
+            XC2Device::XC2C32A => (XC2Speed::Speed6, XC2Package::VQ44),
+            XC2Device::XC2C128 => (XC2Speed::Speed7, XC2Package::VQ100),
+            _ => unreachable!(),
+        };
+        XC2Bitstream::blank_bitstream(device, speed, package).unwrap()
+    }
+
+    /// A register on GCK0 that drives pin 3 and toggles based on pin 0
+    fn counter(mut bitstream: XC2Bitstream) -> XC2Bitstream {
+        let device = bitstream.bits.device_type();
+        let (_, mc) = iob_num_to_fb_ff_num(device, 3).unwrap();
+        let mc = mc as usize;
+        match bitstream.bits {
+            XC2BitstreamBits::XC2C32A{ref mut iobs, ..} => {
+                iobs[0].zia_mode = XC2IOBZIAMode::PAD;
+                iobs[0].schmitt_trigger = false;
+                iobs[3].obuf_mode = XC2IOBOBufMode::PushPull;
+                iobs[3].obuf_uses_ff = true;
+                iobs[3].slew_is_fast = false;
+            },
+            XC2BitstreamBits::XC2C128{ref mut iobs, ..} => {
+                iobs[0].zia_mode = XC2IOBZIAMode::PAD;
+                iobs[3].obuf_mode = XC2IOBOBufMode::PushPull;
+                iobs[3].obuf_uses_ff = true;
+                iobs[3].slew_is_fast = false;
+            },
+            _ => unreachable!(),
+        }
+        bitstream.bits.get_global_nets_mut().gck_enable[0] = true;
+        bitstream.bits.get_global_nets_mut().gck_enable[2] = true;
+        let fb = &mut bitstream.bits.get_fb_mut()[0];
+        fb.zia_bits[0].selected = XC2ZIAInput::IBuf{ibuf: 0};
+        fb.and_terms[20].input[0] = true;
+        fb.or_terms[mc].input[20] = true;
+        fb.ffs[mc].reg_mode = XC2MCRegMode::TFF;
+        fb.ffs[mc].clk_src = XC2MCRegClkSrc::GCK0;
+        bitstream
+    }
+
+    /// These are not real coefficients
+    fn params() -> XC2PowerParams {
+        XC2PowerParams {
+            standby: 20.0,
+            product_term: 0.5,
+            macrocell: 2.0,
+            register: 1.0,
+            global_clock: 5.0,
+            clock_divider: 10.0,
+            input: 1.0,
+            schmitt_trigger: 0.5,
+            vref: 200.0,
+            data_gate: 5.0,
+            fast_slew_pf: 2.0,
+        }
+    }
+
+    fn conditions(clock: u32) -> XC2PowerConditions {
+        XC2PowerConditions {
+            clocks: vec![XC2ClockActivity{clock: XC2ClockDomain::GCK(clock), freq_mhz: 50.0, toggle_rate: 0.5}],
+            input_toggle_mhz: 10.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn power_blank() {
+        let bitstream = blank(XC2Device::XC2C32A);
+        let params = params();
+        let estimate = estimate_power(&bitstream, &params, &conditions(0));
+        assert_eq!(estimate.vccint_current(), params.standby);
+        assert_eq!(estimate.outputs, 0.0);
+        assert_eq!(estimate.vccio, 1.8);
+    }
+
+    #[test]
+    fn power_counter() {
+        let params = params();
+        let mut bitstream = counter(blank(XC2Device::XC2C32A));
+        let estimate = estimate_power(&bitstream, &params, &conditions(0));
+        assert_eq!(estimate.logic, (params.product_term + params.macrocell) * 10.0);
+        assert_eq!(estimate.clocks, (params.global_clock + params.register) * 50.0);
+        assert_eq!(estimate.inputs, params.input * 10.0);
+        assert_eq!(estimate.outputs, 10.0 * 1.8 * 25.0);
+
+        // No activity on the clock that is used
+        let idle = estimate_power(&bitstream, &params, &conditions(1));
+        assert_eq!(idle.clocks, 0.0);
+        assert_eq!(idle.outputs, 0.0);
+
+        // Fast slew and high voltage outputs cost more
+        match bitstream.bits {
+            XC2BitstreamBits::XC2C32A{ref mut iobs, ref mut ovoltage, ..} => {
+                iobs[3].slew_is_fast = true;
+                ovoltage[1] = true;
+            },
+            _ => unreachable!(),
+        }
+        let fast = estimate_power(&bitstream, &params, &conditions(0));
+        assert_eq!(fast.vccio, 3.3);
+        assert!(fast.outputs > estimate.outputs);
+        assert!(fast.total_power_mw() > estimate.total_power_mw());
+    }
+
+    #[test]
+    fn power_large_device_features() {
+        let params = params();
+        let mut bitstream = counter(blank(XC2Device::XC2C128));
+        let (_, mc) = iob_num_to_fb_ff_num(XC2Device::XC2C128, 3).unwrap();
+        bitstream.bits.get_fb_mut()[0].ffs[mc as usize].clk_src = XC2MCRegClkSrc::GCK2;
+        let undivided = estimate_power(&bitstream, &params, &conditions(2));
+
+        // The clock divider slows the register down but costs some current itself
+        {
+            let clock_div = bitstream.bits.get_clock_div_mut().unwrap();
+            clock_div.enabled = true;
+            clock_div.div_ratio = XC2ClockDivRatio::Div4;
+        }
+        let divided = estimate_power(&bitstream, &params, &conditions(2));
+        assert_eq!(divided.clocks, undivided.clocks - params.register * 50.0 * 3.0 / 4.0 +
+            params.clock_divider * 50.0);
+        assert_eq!(divided.outputs, undivided.outputs / 4.0);
+
+        // VREF, Schmitt triggers, and DataGate
+        match bitstream.bits {
+            XC2BitstreamBits::XC2C128{ref mut iobs, ref mut data_gate, ..} => {
+                iobs[0].ibuf_mode = XC2IOBIbufMode::NoVrefSt;
+                iobs[0].uses_data_gate = true;
+                iobs[1].ibuf_mode = XC2IOBIbufMode::IsVref;
+                *data_gate = true;
+            },
+            _ => unreachable!(),
+        }
+        let mut conds = conditions(2);
+        let features = estimate_power(&bitstream, &params, &conds);
+        assert_eq!(features.standby, params.standby + params.vref + params.data_gate);
+        assert_eq!(features.inputs, (params.input + params.schmitt_trigger) * 10.0);
+        conds.data_gate_closed = true;
+        assert_eq!(estimate_power(&bitstream, &params, &conds).inputs, 0.0);
+    }
+}
@@ -0,0 +1,594 @@
+/*
+Copyright (c) 2017, Robert Ou <rqou@robertou.com> and contributors
+All rights reserved.
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice,
+   this list of conditions and the following disclaimer.
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+//! Contains a cycle-based functional simulator for a configured device.
+//!
+//! The simulator evaluates the logic described by a bitstream. Pins are driven with `set_pin` and sampled with
+//! `get_pin`. The global clock, set/reset, and tri-state nets are separate inputs (`set_gck`, `set_gsr`, and
+//! `set_gts`) because the pins they share are not known. After changing any inputs, call `step` to let the device
+//! react. All of the changes made before a call to `step` happen at the same time.
+//!
+//! Within a step, the combinational logic, latches, and asynchronous set/reset are first evaluated until they stop
+//! changing. Every register that sees an active clock edge then samples its input, and all registers are updated at
+//! once. This repeats until no more clock edges happen, which allows clocks to come from the outputs of other
+//! registers.
+//!
+//! The same assumptions that are made by the Verilog export are made here:
+//!
+//! * An AND term with no inputs is a constant one, and an OR term with no inputs is a constant zero.
+//! * A ZIA input from a disabled feedback path or a disabled input pin is a constant zero.
+//! * A disabled GTS net enables all outputs that it controls.
+//! * Reset has priority over set, and a register ignores its clock while it is being set or reset.
+//! * The clock divider, DataGate, Schmitt trigger, termination, slew rate, and voltage settings are ignored.
+
+use *;
+
+/// Maximum number of times the logic is evaluated while waiting for it to settle
+const MAX_SETTLE_PASSES: usize = 1000;
+/// Maximum number of rounds of clock edges in one step
+const MAX_CLOCK_ROUNDS: usize = 100;
+
+/// Simulates the logic configured by a bitstream
+pub struct XC2Simulator<'a> {
+    bitstream: &'a XC2Bitstream,
+    device: XC2Device,
+    /// Settings of each I/O pin
+    iob_settings: Vec<(XC2IOBZIAMode, bool, XC2IOBOBufMode)>,
+    /// (fb, mc) of each I/O pin
+    iob_mc: Vec<(usize, usize)>,
+
+    pin_inputs: Vec<bool>,
+    dedicated_input: bool,
+    gck: [bool; 3],
+    gsr: bool,
+    gts: [bool; 4],
+
+    zia: Vec<[bool; INPUTS_PER_ANDTERM]>,
+    pt: Vec<[bool; ANDTERMS_PER_FB]>,
+    xor: Vec<[bool; MCS_PER_FB]>,
+    q: Vec<[bool; MCS_PER_FB]>,
+    /// Value of the clock of each register (after the optional inversion) the last time it was checked
+    clk: Vec<[bool; MCS_PER_FB]>,
+    /// Value driven onto each I/O pin by the device, or `None` if the output is not driving
+    pin_outputs: Vec<Option<bool>>,
+}
+
+impl<'a> XC2Simulator<'a> {
+    /// Creates a new simulator for the given bitstream. All inputs start out low, and all registers start out in the
+    /// state given by their `init_state`.
+    pub fn new(bitstream: &'a XC2Bitstream) -> Result<XC2Simulator<'a>, XC2BitError> {
+        let bits = &bitstream.bits;
+        let device = bits.device_type();
+        let num_fbs = device.num_fbs();
+
+        let mut iob_settings = Vec::new();
+        let mut iob_mc = Vec::new();
+        for i in 0..device.num_iobs() {
+            iob_settings.push(if let Some(iob) = bits.get_small_iob(i) {
+                (iob.zia_mode, iob.obuf_uses_ff, iob.obuf_mode)
+            } else {
+                let iob = bits.get_large_iob(i).unwrap();
+                (iob.zia_mode, iob.obuf_uses_ff, iob.obuf_mode)
+            });
+            let (fb, mc) = iob_num_to_fb_ff_num(device, i as u32).unwrap();
+            iob_mc.push((fb as usize, mc as usize));
+        }
+
+        let mut q = vec![[false; MCS_PER_FB]; num_fbs];
+        for fb in 0..num_fbs {
+            for mc in 0..MCS_PER_FB {
+                q[fb][mc] = bits.get_fb()[fb].ffs[mc].init_state;
+            }
+        }
+
+        let mut sim = XC2Simulator {
+            bitstream,
+            device,
+            iob_settings,
+            iob_mc,
+            pin_inputs: vec![false; device.num_iobs()],
+            dedicated_input: false,
+            gck: [false; 3],
+            gsr: false,
+            gts: [false; 4],
+            zia: vec![[false; INPUTS_PER_ANDTERM]; num_fbs],
+            pt: vec![[false; ANDTERMS_PER_FB]; num_fbs],
+            xor: vec![[false; MCS_PER_FB]; num_fbs],
+            q,
+            clk: vec![[false; MCS_PER_FB]; num_fbs],
+            pin_outputs: vec![None; device.num_iobs()],
+        };
+
+        // Starting up must not be treated as a clock edge
+        sim.settle()?;
+        for fb in 0..num_fbs {
+            for mc in 0..MCS_PER_FB {
+                sim.clk[fb][mc] = sim.mc_clk(fb, mc);
+            }
+        }
+
+        Ok(sim)
+    }
+
+    /// Sets the value that is being driven onto the given I/O pin from outside the device. This value is only seen
+    /// by the device while its own output buffer for the pin is not driving.
+    pub fn set_pin(&mut self, iob: usize, value: bool) {
+        self.pin_inputs[iob] = value;
+    }
+
+    /// Sets the value of the dedicated input pin on 32-macrocell devices
+    pub fn set_dedicated_input(&mut self, value: bool) {
+        self.dedicated_input = value;
+    }
+
+    /// Sets the value of one of the global clock pins
+    pub fn set_gck(&mut self, idx: usize, value: bool) {
+        self.gck[idx] = value;
+    }
+
+    /// Sets the value of the global set/reset pin
+    pub fn set_gsr(&mut self, value: bool) {
+        self.gsr = value;
+    }
+
+    /// Sets the value of one of the global tri-state pins
+    pub fn set_gts(&mut self, idx: usize, value: bool) {
+        self.gts[idx] = value;
+    }
+
+    /// Returns the value that the device is driving onto the given I/O pin, or `None` if it is not driving the pin
+    pub fn get_pin(&self, iob: usize) -> Option<bool> {
+        self.pin_outputs[iob]
+    }
+
+    /// Returns the output of the register in the given macrocell
+    pub fn get_mc_q(&self, fb: usize, mc: usize) -> bool {
+        self.q[fb][mc]
+    }
+
+    /// Returns the output of the XOR gate in the given macrocell
+    pub fn get_mc_xor(&self, fb: usize, mc: usize) -> bool {
+        self.xor[fb][mc]
+    }
+
+    /// Returns the output of the given product term
+    pub fn get_pt(&self, fb: usize, pt: usize) -> bool {
+        self.pt[fb][pt]
+    }
+
+    /// Lets the device react to all of the input changes made since the last step. Fails if the logic does not stop
+    /// changing, which can happen if there is a combinational loop.
+    pub fn step(&mut self) -> Result<(), XC2BitError> {
+        for _ in 0..MAX_CLOCK_ROUNDS {
+            self.settle()?;
+
+            // Find all of the registers that see a clock edge. They all sample their inputs before any of them change.
+            let mut updates = Vec::new();
+            for fb in 0..self.device.num_fbs() {
+                for mc in 0..MCS_PER_FB {
+                    let mc_bits = &self.bitstream.bits.get_fb()[fb].ffs[mc];
+                    let old_clk = self.clk[fb][mc];
+                    let new_clk = self.mc_clk(fb, mc);
+                    self.clk[fb][mc] = new_clk;
+
+                    let is_edge = (new_clk && !old_clk) || (mc_bits.is_ddr && !new_clk && old_clk);
+                    let (set, reset) = self.mc_set_reset(fb, mc);
+                    if !is_edge || set || reset || mc_bits.reg_mode == XC2MCRegMode::LATCH {
+                        continue;
+                    }
+
+                    let d = self.mc_d(fb, mc);
+                    let new_q = match mc_bits.reg_mode {
+                        XC2MCRegMode::TFF => self.q[fb][mc] ^ d,
+                        XC2MCRegMode::DFFCE if !self.pt[fb][get_ptc(mc as u32) as usize] => self.q[fb][mc],
+                        _ => d,
+                    };
+                    updates.push((fb, mc, new_q));
+                }
+            }
+
+            if updates.is_empty() {
+                return Ok(());
+            }
+            for (fb, mc, new_q) in updates {
+                self.q[fb][mc] = new_q;
+            }
+        }
+
+        Err(XC2BitError::SimulationDidNotSettle{msg: "registers did not stop clocking each other"})
+    }
+
+    /// Toggles one of the global clock pins high and then low, stepping after each change
+    pub fn pulse_gck(&mut self, idx: usize) -> Result<(), XC2BitError> {
+        self.set_gck(idx, true);
+        self.step()?;
+        self.set_gck(idx, false);
+        self.step()
+    }
+
+    /// Internal helper that returns the value seen on an I/O pin by the input buffer
+    fn pad_in(&self, iob: usize) -> bool {
+        self.pin_outputs[iob].unwrap_or(self.pin_inputs[iob])
+    }
+
+    /// Internal helper that returns the input of the register in the given macrocell
+    fn mc_d(&self, fb: usize, mc: usize) -> bool {
+        if self.bitstream.bits.get_fb()[fb].ffs[mc].ff_in_ibuf {
+            match fb_ff_num_to_iob_num(self.device, fb as u32, mc as u32) {
+                Some(iob) => self.pad_in(iob as usize),
+                None => false,
+            }
+        } else {
+            self.xor[fb][mc]
+        }
+    }
+
+    /// Internal helper that returns the clock of the register in the given macrocell, after the optional inversion
+    fn mc_clk(&self, fb: usize, mc: usize) -> bool {
+        let mc_bits = &self.bitstream.bits.get_fb()[fb].ffs[mc];
+        let clk = match mc_bits.clk_src {
+            XC2MCRegClkSrc::GCK0 => self.gck[0] && self.bitstream.bits.get_global_nets().gck_enable[0],
+            XC2MCRegClkSrc::GCK1 => self.gck[1] && self.bitstream.bits.get_global_nets().gck_enable[1],
+            XC2MCRegClkSrc::GCK2 => self.gck[2] && self.bitstream.bits.get_global_nets().gck_enable[2],
+            XC2MCRegClkSrc::PTC => self.pt[fb][get_ptc(mc as u32) as usize],
+            XC2MCRegClkSrc::CTC => self.pt[fb][CTC as usize],
+        };
+        clk ^ mc_bits.clk_invert_pol
+    }
+
+    /// Internal helper that returns the value of the global set/reset net inside the device
+    fn gsr_net(&self) -> bool {
+        let global_nets = self.bitstream.bits.get_global_nets();
+        global_nets.gsr_enable && (self.gsr == global_nets.gsr_invert)
+    }
+
+    /// Internal helper that returns the output enable coming from a global tri-state net
+    fn gts_oe(&self, idx: usize) -> bool {
+        let global_nets = self.bitstream.bits.get_global_nets();
+        !global_nets.gts_enable[idx] || (self.gts[idx] == global_nets.gts_invert[idx])
+    }
+
+    /// Internal helper that returns the (set, reset) inputs of the register in the given macrocell
+    fn mc_set_reset(&self, fb: usize, mc: usize) -> (bool, bool) {
+        let mc_bits = &self.bitstream.bits.get_fb()[fb].ffs[mc];
+        let set = match mc_bits.s_src {
+            XC2MCRegSetSrc::Disabled => false,
+            XC2MCRegSetSrc::PTA => self.pt[fb][get_pta(mc as u32) as usize],
+            XC2MCRegSetSrc::GSR => self.gsr_net(),
+            XC2MCRegSetSrc::CTS => self.pt[fb][CTS as usize],
+        };
+        let reset = match mc_bits.r_src {
+            XC2MCRegResetSrc::Disabled => false,
+            XC2MCRegResetSrc::PTA => self.pt[fb][get_pta(mc as u32) as usize],
+            XC2MCRegResetSrc::GSR => self.gsr_net(),
+            XC2MCRegResetSrc::CTR => self.pt[fb][CTR as usize],
+        };
+        (set, reset)
+    }
+
+    /// Internal helper that returns the value of a ZIA input
+    fn zia_input(&self, input: XC2ZIAInput) -> bool {
+        match input {
+            XC2ZIAInput::Zero => false,
+            XC2ZIAInput::One => true,
+            XC2ZIAInput::Macrocell{fb, ff} => {
+                let (fb, ff) = (fb as usize, ff as usize);
+                match self.bitstream.bits.get_fb()[fb].ffs[ff].fb_mode {
+                    XC2MCFeedbackMode::Disabled => false,
+                    XC2MCFeedbackMode::COMB => self.xor[fb][ff],
+                    XC2MCFeedbackMode::REG => self.q[fb][ff],
+                }
+            },
+            XC2ZIAInput::IBuf{ibuf} => {
+                let ibuf = ibuf as usize;
+                match self.iob_settings[ibuf].0 {
+                    XC2IOBZIAMode::Disabled => false,
+                    XC2IOBZIAMode::PAD => self.pad_in(ibuf),
+                    XC2IOBZIAMode::REG => {
+                        let (fb, mc) = self.iob_mc[ibuf];
+                        self.q[fb][mc]
+                    },
+                }
+            },
+            XC2ZIAInput::DedicatedInput => self.dedicated_input,
+        }
+    }
+
+    /// Internal helper that evaluates everything except clock edges once. Returns whether anything changed.
+    fn eval_pass(&mut self) -> bool {
+        let mut changed = false;
+        let bits = &self.bitstream.bits;
+
+        for fb in 0..self.device.num_fbs() {
+            let fb_bits = &bits.get_fb()[fb];
+
+            for row in 0..INPUTS_PER_ANDTERM {
+                let val = self.zia_input(fb_bits.zia_bits[row].selected);
+                changed |= self.zia[fb][row] != val;
+                self.zia[fb][row] = val;
+            }
+
+            for i in 0..ANDTERMS_PER_FB {
+                let mut val = true;
+                for row in 0..INPUTS_PER_ANDTERM {
+                    if (fb_bits.and_terms[i].input[row] && !self.zia[fb][row]) ||
+                        (fb_bits.and_terms[i].input_b[row] && self.zia[fb][row]) {
+
+                        val = false;
+                    }
+                }
+                changed |= self.pt[fb][i] != val;
+                self.pt[fb][i] = val;
+            }
+
+            for mc in 0..MCS_PER_FB {
+                let mut or = false;
+                for i in 0..ANDTERMS_PER_FB {
+                    if fb_bits.or_terms[mc].input[i] && self.pt[fb][i] {
+                        or = true;
+                    }
+                }
+                let ptc = self.pt[fb][get_ptc(mc as u32) as usize];
+                let val = or ^ match fb_bits.ffs[mc].xor_mode {
+                    XC2MCXorMode::ZERO => false,
+                    XC2MCXorMode::ONE => true,
+                    XC2MCXorMode::PTC => ptc,
+                    XC2MCXorMode::PTCB => !ptc,
+                };
+                changed |= self.xor[fb][mc] != val;
+                self.xor[fb][mc] = val;
+            }
+
+            // Asynchronous set/reset and transparent latches
+            for mc in 0..MCS_PER_FB {
+                let (set, reset) = self.mc_set_reset(fb, mc);
+                let val = if reset {
+                    false
+                } else if set {
+                    true
+                } else if fb_bits.ffs[mc].reg_mode == XC2MCRegMode::LATCH && self.mc_clk(fb, mc) {
+                    self.mc_d(fb, mc)
+                } else {
+                    self.q[fb][mc]
+                };
+                changed |= self.q[fb][mc] != val;
+                self.q[fb][mc] = val;
+            }
+        }
+
+        for iob in 0..self.device.num_iobs() {
+            let (fb, mc) = self.iob_mc[iob];
+            let (_, obuf_uses_ff, obuf_mode) = self.iob_settings[iob];
+            let out = if obuf_uses_ff {self.q[fb][mc]} else {self.xor[fb][mc]};
+            let val = match obuf_mode {
+                XC2IOBOBufMode::Disabled => None,
+                XC2IOBOBufMode::PushPull => Some(out),
+                XC2IOBOBufMode::OpenDrain => if out {None} else {Some(false)},
+                XC2IOBOBufMode::TriStateGTS0 => if self.gts_oe(0) {Some(out)} else {None},
+                XC2IOBOBufMode::TriStateGTS1 => if self.gts_oe(1) {Some(out)} else {None},
+                XC2IOBOBufMode::TriStateGTS2 => if self.gts_oe(2) {Some(out)} else {None},
+                XC2IOBOBufMode::TriStateGTS3 => if self.gts_oe(3) {Some(out)} else {None},
+                XC2IOBOBufMode::TriStatePTB => if self.pt[fb][get_ptb(mc as u32) as usize] {Some(out)} else {None},
+                XC2IOBOBufMode::TriStateCTE => if self.pt[fb][CTE as usize] {Some(out)} else {None},
+                XC2IOBOBufMode::CGND => Some(false),
+            };
+            changed |= self.pin_outputs[iob] != val;
+            self.pin_outputs[iob] = val;
+        }
+
+        changed
+    }
+
+    /// Internal helper that evaluates everything except clock edges until nothing changes
+    fn settle(&mut self) -> Result<(), XC2BitError> {
+        for _ in 0..MAX_SETTLE_PASSES {
+            if !self.eval_pass() {
+                return Ok(());
+            }
+        }
+
+        Err(XC2BitError::SimulationDidNotSettle{msg: "logic did not settle"})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blank() -> XC2Bitstream {
+        XC2Bitstream::blank_bitstream(XC2Device::XC2C32A, XC2Speed::Speed6, XC2Package::VQ44).unwrap()
+    }
+
+    fn iobs_mut(bitstream: &mut XC2Bitstream) -> &mut [XC2MCSmallIOB] {
+        match bitstream.bits {
+            XC2BitstreamBits::XC2C32A{ref mut iobs, ..} => iobs,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn sim_combinatorial() {
+        // Pin 2 outputs the inverse of pin 0
+        let mut bitstream = blank();
+        iobs_mut(&mut bitstream)[0].zia_mode = XC2IOBZIAMode::PAD;
+        iobs_mut(&mut bitstream)[2].obuf_mode = XC2IOBOBufMode::PushPull;
+        let (_, mc) = iob_num_to_fb_ff_num(XC2Device::XC2C32A, 2).unwrap();
+        let mc = mc as usize;
+        {
+            let fb = &mut bitstream.bits.get_fb_mut()[0];
+            fb.zia_bits[0].selected = XC2ZIAInput::IBuf{ibuf: 0};
+            fb.and_terms[20].input[0] = true;
+            fb.or_terms[mc].input[20] = true;
+            // PTC is an empty AND term, so it is always one
+            fb.ffs[mc].xor_mode = XC2MCXorMode::PTC;
+        }
+
+        let mut sim = XC2Simulator::new(&bitstream).unwrap();
+        assert_eq!(sim.get_pin(2), Some(true));
+        assert_eq!(sim.get_pin(1), None);
+        sim.set_pin(0, true);
+        sim.step().unwrap();
+        assert_eq!(sim.get_pin(2), Some(false));
+        sim.set_pin(0, false);
+        sim.step().unwrap();
+        assert_eq!(sim.get_pin(2), Some(true));
+    }
+
+    #[test]
+    fn sim_registers() {
+        let mut bitstream = blank();
+        iobs_mut(&mut bitstream)[3].obuf_mode = XC2IOBOBufMode::TriStateGTS0;
+        iobs_mut(&mut bitstream)[3].obuf_uses_ff = true;
+        {
+            let global_nets = bitstream.bits.get_global_nets_mut();
+            global_nets.gck_enable[0] = true;
+            global_nets.gsr_enable = true;
+            global_nets.gsr_invert = true;
+            global_nets.gts_enable[0] = true;
+            global_nets.gts_invert[0] = false;
+        }
+        let (_, mc) = iob_num_to_fb_ff_num(XC2Device::XC2C32A, 3).unwrap();
+        let mc = mc as usize;
+        {
+            let mc_bits = &mut bitstream.bits.get_fb_mut()[0].ffs[mc];
+            mc_bits.reg_mode = XC2MCRegMode::TFF;
+            mc_bits.clk_src = XC2MCRegClkSrc::GCK0;
+            mc_bits.xor_mode = XC2MCXorMode::ONE;
+            mc_bits.r_src = XC2MCRegResetSrc::GSR;
+            mc_bits.init_state = true;
+        }
+
+        // Toggles on every rising edge
+        let mut sim = XC2Simulator::new(&bitstream).unwrap();
+        assert_eq!(sim.get_pin(3), Some(true));
+        sim.pulse_gck(0).unwrap();
+        assert_eq!(sim.get_pin(3), Some(false));
+        sim.pulse_gck(0).unwrap();
+        assert_eq!(sim.get_pin(3), Some(true));
+
+        // Held in reset by the GSR
+        sim.set_gsr(true);
+        sim.step().unwrap();
+        assert_eq!(sim.get_pin(3), Some(false));
+        sim.pulse_gck(0).unwrap();
+        assert_eq!(sim.get_pin(3), Some(false));
+        sim.set_gsr(false);
+        sim.pulse_gck(0).unwrap();
+        assert_eq!(sim.get_pin(3), Some(true));
+
+        // The GTS is active high here
+        sim.set_gts(0, true);
+        sim.step().unwrap();
+        assert_eq!(sim.get_pin(3), None);
+        assert!(sim.get_mc_q(0, mc));
+
+        // Falling edges only
+        bitstream.bits.get_fb_mut()[0].ffs[mc].clk_invert_pol = true;
+        let mut sim = XC2Simulator::new(&bitstream).unwrap();
+        sim.set_gck(0, true);
+        sim.step().unwrap();
+        assert!(sim.get_mc_q(0, mc));
+        sim.set_gck(0, false);
+        sim.step().unwrap();
+        assert!(!sim.get_mc_q(0, mc));
+
+        // Both edges
+        bitstream.bits.get_fb_mut()[0].ffs[mc].is_ddr = true;
+        let mut sim = XC2Simulator::new(&bitstream).unwrap();
+        sim.set_gck(0, true);
+        sim.step().unwrap();
+        assert!(!sim.get_mc_q(0, mc));
+        sim.set_gck(0, false);
+        sim.step().unwrap();
+        assert!(sim.get_mc_q(0, mc));
+    }
+
+    #[test]
+    fn sim_latch_and_loop() {
+        // A latch that is open while PTC is true, which it always is
+        let mut bitstream = blank();
+        {
+            let mc_bits = &mut bitstream.bits.get_fb_mut()[1].ffs[0];
+            mc_bits.reg_mode = XC2MCRegMode::LATCH;
+            mc_bits.clk_src = XC2MCRegClkSrc::PTC;
+            mc_bits.xor_mode = XC2MCXorMode::ONE;
+        }
+        let sim = XC2Simulator::new(&bitstream).unwrap();
+        assert!(sim.get_mc_q(1, 0));
+
+        // A macrocell that feeds back its own inverse never settles
+        let row = zia_find_rows_for_input(XC2Device::XC2C32A, XC2ZIAInput::Macrocell{fb: 0, ff: 0})[0];
+        {
+            let fb = &mut bitstream.bits.get_fb_mut()[0];
+            fb.zia_bits[row].selected = XC2ZIAInput::Macrocell{fb: 0, ff: 0};
+            fb.and_terms[20].input_b[row] = true;
+            fb.or_terms[0].input[20] = true;
+            fb.ffs[0].fb_mode = XC2MCFeedbackMode::COMB;
+        }
+        assert_eq!(XC2Simulator::new(&bitstream).err().unwrap(),
+            XC2BitError::SimulationDidNotSettle{msg: "logic did not settle"});
+    }
+
+    #[test]
+    fn sim_fitted_design() {
+        fn cell(name: &str, cell_type: &str, ports: &[(&str, usize)]) -> XC2NetlistCell {
+            XC2NetlistCell {
+                name: name.to_owned(),
+                cell_type: cell_type.to_owned(),
+                parameters: ::std::collections::HashMap::new(),
+                attributes: ::std::collections::HashMap::new(),
+                connections: ports.iter().map(|&(port, net)| (port.to_owned(), vec![Some(net)])).collect(),
+            }
+        }
+
+        // y = a & ~b
+        let mut netlist = XC2Netlist::default();
+        netlist.cells.push(cell("a", "IBUF", &[("I", 100), ("O", 2)]));
+        netlist.cells.push(cell("b", "IBUF", &[("I", 101), ("O", 3)]));
+        netlist.cells.push(cell("pt", "ANDTERM", &[("IN", 2), ("IN_B", 3), ("OUT", 4)]));
+        netlist.cells.push(cell("xor", "MACROCELL_XOR", &[("IN_ORTERM", 4), ("OUT", 5)]));
+        netlist.cells.push(cell("y", "IOBUFE", &[("I", 5), ("E", NET_ONE), ("IO", 102)]));
+        let bitstream = fit_netlist(&netlist, XC2Device::XC2C32A, XC2Speed::Speed6, XC2Package::VQ44).unwrap();
+
+        let mut iobs = Vec::new();
+        for i in 0..XC2Device::XC2C32A.num_iobs() {
+            if let Some(iob) = bitstream.bits.get_small_iob(i) {
+                if iob.zia_mode == XC2IOBZIAMode::PAD || iob.obuf_mode == XC2IOBOBufMode::PushPull {
+                    iobs.push(i);
+                }
+            }
+        }
+        // The fitter places the output first
+        let (y, a, b) = (iobs[0], iobs[1], iobs[2]);
+
+        let mut sim = XC2Simulator::new(&bitstream).unwrap();
+        for &(a_val, b_val) in &[(false, false), (false, true), (true, false), (true, true)] {
+            sim.set_pin(a, a_val);
+            sim.set_pin(b, b_val);
+            sim.step().unwrap();
+            assert_eq!(sim.get_pin(y), Some(a_val && !b_val));
+        }
+    }
+}
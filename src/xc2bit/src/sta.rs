@@ -0,0 +1,652 @@
+/*
+Copyright (c) 2017, Robert Ou <rqou@robertou.com> and contributors
+All rights reserved.
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice,
+   this list of conditions and the following disclaimer.
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+//! Contains a static timing analyzer for a configured device.
+//!
+//! Paths are built out of the timing parameters in `XC2TimingParams` as follows:
+//!
+//! * Input pin through a chain of `k` macrocells to an output pin: `tpd1` or `tpd2` (chosen by the number of product
+//!   terms in the OR term of the last macrocell) plus `(k - 1) * tf`.
+//! * Clock to an output pin: `tco` when the pin comes straight from the register, or `tco + k * tf` when it goes
+//!   through a chain of `k` macrocells.
+//! * Input pin through a chain of `k` macrocells to a register: setup `tsu1` or `tsu2` plus `(k - 1) * tf`, and hold
+//!   `th`. Input registers (`ff_in_ibuf`) use `tsuin` and `thin` instead.
+//! * Register through a chain of `k` macrocells to a register on the same clock: `tsys1` or `tsys2` plus
+//!   `(k - 1) * tf`. Paths between different clocks are not analyzed.
+//! * Clocks from a product term add `tptck` to the clock path, and GCK2 adds `tclkdiv` while the clock divider is
+//!   enabled. The delay through the product term itself is not included. A later clock increases clock-to-output
+//!   delays and hold times and decreases setup times.
+//! * Output enables add `toe`/`tod` from a GTS pin, or `tpoe`/`tpod` plus `k * tf` from an input pin through a
+//!   product term that uses a chain of `k` macrocells. Enables that come from registers are not analyzed.
+//! * Every path to an output pin adds `tslew_fast` or `tslew_slow`.
+//!
+//! When there is more than one path between two points, the slowest one is reported.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io;
+use std::io::Write;
+
+use *;
+
+/// The clock of a register
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum XC2ClockDomain {
+    /// One of the global clocks, GCK0 to GCK2
+    GCK(u32),
+    /// The control term clock of a function block
+    CTC {
+        fb: u32,
+    },
+    /// The product term clock of one macrocell
+    PTC {
+        fb: u32,
+        mc: u32,
+    },
+}
+
+impl fmt::Display for XC2ClockDomain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            XC2ClockDomain::GCK(i) => write!(f, "GCK{}", i),
+            XC2ClockDomain::CTC{fb} => write!(f, "FB{} CTC", fb + 1),
+            XC2ClockDomain::PTC{fb, mc} => write!(f, "FB{}_{} PTC", fb + 1, mc + 1),
+        }
+    }
+}
+
+/// The delay of the slowest path between two points, in picoseconds
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct XC2PathDelay {
+    pub from: String,
+    pub to: String,
+    pub delay: u32,
+}
+
+/// The delays from a signal to an output pin being enabled and disabled, in picoseconds
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct XC2OutputEnableDelay {
+    pub from: String,
+    pub to: String,
+    pub enable: u32,
+    pub disable: u32,
+}
+
+/// The setup and hold times of an input pin relative to a clock, in picoseconds. These can be negative.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct XC2SetupHold {
+    pub pin: String,
+    pub clock: XC2ClockDomain,
+    pub setup: i32,
+    pub hold: i32,
+}
+
+/// The minimum period of a clock, in picoseconds
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct XC2ClockPeriod {
+    pub clock: XC2ClockDomain,
+    pub min_period: u32,
+}
+
+impl XC2ClockPeriod {
+    /// Returns the maximum frequency of the clock in MHz
+    pub fn fmax_mhz(&self) -> f64 {
+        1000000.0 / self.min_period as f64
+    }
+}
+
+/// Result of static timing analysis. Pins are named after the macrocell they belong to (for example `FB1_2`).
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct XC2TimingReport {
+    /// Combinational delays from input pins to output pins
+    pub pin_to_pin: Vec<XC2PathDelay>,
+    /// Delays from clocks to output pins
+    pub clock_to_out: Vec<XC2PathDelay>,
+    /// Delays from GTS pins and input pins to output pins being enabled or disabled
+    pub output_enable: Vec<XC2OutputEnableDelay>,
+    /// Setup and hold times of input pins relative to each clock that they are captured by
+    pub setup_hold: Vec<XC2SetupHold>,
+    /// Minimum period of each clock that has paths from a register to a register
+    pub min_period: Vec<XC2ClockPeriod>,
+}
+
+impl XC2TimingReport {
+    /// Write a human-readable version of this report to the given `writer` object
+    pub fn write_report(&self, writer: &mut Write) -> Result<(), io::Error> {
+        write!(writer, "Pin to pin delays\n")?;
+        for path in &self.pin_to_pin {
+            write!(writer, "{} -> {}: {} ps\n", path.from, path.to, path.delay)?;
+        }
+
+        write!(writer, "\nClock to output delays\n")?;
+        for path in &self.clock_to_out {
+            write!(writer, "{} -> {}: {} ps\n", path.from, path.to, path.delay)?;
+        }
+
+        write!(writer, "\nOutput enable delays\n")?;
+        for path in &self.output_enable {
+            write!(writer, "{} -> {}: enable {} ps, disable {} ps\n", path.from, path.to, path.enable, path.disable)?;
+        }
+
+        write!(writer, "\nSetup and hold times\n")?;
+        for x in &self.setup_hold {
+            write!(writer, "{} -> {}: setup {} ps, hold {} ps\n", x.pin, x.clock, x.setup, x.hold)?;
+        }
+
+        write!(writer, "\nMaximum clock frequencies\n")?;
+        for x in &self.min_period {
+            write!(writer, "{}: {} ps ({:.2} MHz)\n", x.clock, x.min_period, x.fmax_mhz())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Where a combinational path starts
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+enum PathStart {
+    Pad(usize),
+    DedicatedInput,
+    Reg(usize, usize),
+}
+
+/// Internal state of the analyzer
+struct Analyzer<'a> {
+    bits: &'a XC2BitstreamBits,
+    device: XC2Device,
+    params: &'a XC2TimingParams,
+    /// (zia_mode, obuf_uses_ff, obuf_mode, slew_is_fast) of each I/O pin
+    iob_settings: Vec<(XC2IOBZIAMode, bool, XC2IOBOBufMode, bool)>,
+    /// (fb, mc) of each I/O pin
+    iob_mc: Vec<(usize, usize)>,
+    /// Result of `mc_sources` for each macrocell that has already been visited
+    mc_sources_cache: HashMap<(usize, usize), HashMap<PathStart, u32>>,
+}
+
+impl<'a> Analyzer<'a> {
+    fn pin_name(&self, start: PathStart) -> String {
+        match start {
+            PathStart::Pad(iob) => {
+                let (fb, mc) = self.iob_mc[iob];
+                format!("FB{}_{}", fb + 1, mc + 1)
+            },
+            PathStart::DedicatedInput => String::from("dedicated_input"),
+            PathStart::Reg(fb, mc) => format!("FB{}_{} FF", fb + 1, mc + 1),
+        }
+    }
+
+    fn slew(&self, iob: usize) -> u32 {
+        if self.iob_settings[iob].3 {self.params.tslew_fast} else {self.params.tslew_slow}
+    }
+
+    /// Returns the number of product terms in the OR term of a macrocell
+    fn or_pt_count(&self, fb: usize, mc: usize) -> usize {
+        self.bits.get_fb()[fb].or_terms[mc].input.iter().filter(|&&x| x).count()
+    }
+
+    fn clock_domain(&self, fb: usize, mc: usize) -> XC2ClockDomain {
+        match self.bits.get_fb()[fb].ffs[mc].clk_src {
+            XC2MCRegClkSrc::GCK0 => XC2ClockDomain::GCK(0),
+            XC2MCRegClkSrc::GCK1 => XC2ClockDomain::GCK(1),
+            XC2MCRegClkSrc::GCK2 => XC2ClockDomain::GCK(2),
+            XC2MCRegClkSrc::CTC => XC2ClockDomain::CTC{fb: fb as u32},
+            XC2MCRegClkSrc::PTC => XC2ClockDomain::PTC{fb: fb as u32, mc: mc as u32},
+        }
+    }
+
+    /// Returns the extra delay of the clock path of a register
+    fn clock_adder(&self, fb: usize, mc: usize) -> u32 {
+        match self.bits.get_fb()[fb].ffs[mc].clk_src {
+            XC2MCRegClkSrc::GCK0 | XC2MCRegClkSrc::GCK1 => 0,
+            XC2MCRegClkSrc::GCK2 => match self.bits.get_clock_div() {
+                Some(clock_div) if clock_div.enabled => self.params.tclkdiv,
+                _ => 0,
+            },
+            XC2MCRegClkSrc::CTC | XC2MCRegClkSrc::PTC => self.params.tptck,
+        }
+    }
+
+    /// Returns whether the output of the register in a macrocell goes anywhere
+    fn reg_is_used(&self, fb: usize, mc: usize) -> bool {
+        if self.bits.get_fb()[fb].ffs[mc].fb_mode == XC2MCFeedbackMode::REG {
+            return true;
+        }
+        match fb_ff_num_to_iob_num(self.device, fb as u32, mc as u32) {
+            Some(iob) => {
+                let (zia_mode, obuf_uses_ff, obuf_mode, _) = self.iob_settings[iob as usize];
+                zia_mode == XC2IOBZIAMode::REG || (obuf_uses_ff && obuf_mode != XC2IOBOBufMode::Disabled &&
+                    obuf_mode != XC2IOBOBufMode::CGND)
+            },
+            None => false,
+        }
+    }
+
+    /// Returns the start of every path into the given product terms along with the largest number of macrocells that
+    /// the path goes through before reaching them
+    fn pt_sources(&mut self, fb: usize, pts: &[usize], stack: &mut Vec<(usize, usize)>) -> HashMap<PathStart, u32> {
+        let mut ret = HashMap::new();
+        let add = |ret: &mut HashMap<PathStart, u32>, start: PathStart, k: u32| {
+            let entry = ret.entry(start).or_insert(k);
+            if *entry < k {
+                *entry = k;
+            }
+        };
+
+        for row in 0..INPUTS_PER_ANDTERM {
+            let used = pts.iter().any(|&pt| {
+                let and_term = &self.bits.get_fb()[fb].and_terms[pt];
+                and_term.input[row] || and_term.input_b[row]
+            });
+            if !used {
+                continue;
+            }
+
+            match self.bits.get_fb()[fb].zia_bits[row].selected {
+                XC2ZIAInput::Zero | XC2ZIAInput::One => {},
+                XC2ZIAInput::DedicatedInput => add(&mut ret, PathStart::DedicatedInput, 0),
+                XC2ZIAInput::IBuf{ibuf} => {
+                    let ibuf = ibuf as usize;
+                    match self.iob_settings[ibuf].0 {
+                        XC2IOBZIAMode::Disabled => {},
+                        XC2IOBZIAMode::PAD => add(&mut ret, PathStart::Pad(ibuf), 0),
+                        XC2IOBZIAMode::REG => {
+                            let (src_fb, src_mc) = self.iob_mc[ibuf];
+                            add(&mut ret, PathStart::Reg(src_fb, src_mc), 0);
+                        },
+                    }
+                },
+                XC2ZIAInput::Macrocell{fb: src_fb, ff: src_mc} => {
+                    let (src_fb, src_mc) = (src_fb as usize, src_mc as usize);
+                    match self.bits.get_fb()[src_fb].ffs[src_mc].fb_mode {
+                        XC2MCFeedbackMode::Disabled => {},
+                        XC2MCFeedbackMode::REG => add(&mut ret, PathStart::Reg(src_fb, src_mc), 0),
+                        XC2MCFeedbackMode::COMB => {
+                            for (start, k) in self.mc_sources(src_fb, src_mc, stack) {
+                                add(&mut ret, start, k);
+                            }
+                        },
+                    }
+                },
+            }
+        }
+
+        ret
+    }
+
+    /// Returns the start of every path into the XOR gate of a macrocell along with the largest number of macrocells
+    /// that the path goes through, including this one. Combinational loops are cut.
+    fn mc_sources(&mut self, fb: usize, mc: usize, stack: &mut Vec<(usize, usize)>) -> HashMap<PathStart, u32> {
+        if let Some(x) = self.mc_sources_cache.get(&(fb, mc)) {
+            return x.clone();
+        }
+        if stack.contains(&(fb, mc)) {
+            return HashMap::new();
+        }
+
+        let fb_bits = &self.bits.get_fb()[fb];
+        let mut pts = (0..ANDTERMS_PER_FB).filter(|&pt| fb_bits.or_terms[mc].input[pt]).collect::<Vec<_>>();
+        if fb_bits.ffs[mc].xor_mode == XC2MCXorMode::PTC || fb_bits.ffs[mc].xor_mode == XC2MCXorMode::PTCB {
+            pts.push(get_ptc(mc as u32) as usize);
+        }
+
+        stack.push((fb, mc));
+        let ret = self.pt_sources(fb, &pts, stack).into_iter().map(|(start, k)| (start, k + 1)).collect();
+        stack.pop();
+
+        self.mc_sources_cache.insert((fb, mc), ret);
+        self.mc_sources_cache[&(fb, mc)].clone()
+    }
+}
+
+/// Keeps the largest delay seen for each key
+fn keep_max<K: Ord>(map: &mut BTreeMap<K, u32>, key: K, delay: u32) {
+    let entry = map.entry(key).or_insert(delay);
+    if *entry < delay {
+        *entry = delay;
+    }
+}
+
+/// Performs static timing analysis of a bitstream using the given timing parameters
+pub fn analyze_timing(bitstream: &XC2Bitstream, params: &XC2TimingParams) -> XC2TimingReport {
+    let bits = &bitstream.bits;
+    let device = bits.device_type();
+
+    let mut iob_settings = Vec::new();
+    let mut iob_mc = Vec::new();
+    for i in 0..device.num_iobs() {
+        iob_settings.push(if let Some(iob) = bits.get_small_iob(i) {
+            (iob.zia_mode, iob.obuf_uses_ff, iob.obuf_mode, iob.slew_is_fast)
+        } else {
+            let iob = bits.get_large_iob(i).unwrap();
+            (iob.zia_mode, iob.obuf_uses_ff, iob.obuf_mode, iob.slew_is_fast)
+        });
+        let (fb, mc) = iob_num_to_fb_ff_num(device, i as u32).unwrap();
+        iob_mc.push((fb as usize, mc as usize));
+    }
+
+    let mut analyzer = Analyzer {
+        bits,
+        device,
+        params,
+        iob_settings,
+        iob_mc,
+        mc_sources_cache: HashMap::new(),
+    };
+
+    let tpd = |n: usize| if n <= 1 {params.tpd1} else {params.tpd2};
+    let tsu = |n: usize| if n <= 1 {params.tsu1} else {params.tsu2};
+    let tsys = |n: usize| if n <= 1 {params.tsys1} else {params.tsys2};
+
+    let mut pin_to_pin = BTreeMap::new();
+    let mut clock_to_out = BTreeMap::new();
+    let mut output_enable = BTreeMap::new();
+    let mut setup_hold = BTreeMap::new();
+    let mut min_period = BTreeMap::new();
+
+    // Paths to output pins
+    for iob in 0..device.num_iobs() {
+        let (fb, mc) = analyzer.iob_mc[iob];
+        let (_, obuf_uses_ff, obuf_mode, _) = analyzer.iob_settings[iob];
+        if obuf_mode == XC2IOBOBufMode::Disabled || obuf_mode == XC2IOBOBufMode::CGND {
+            continue;
+        }
+        let slew = analyzer.slew(iob);
+
+        if obuf_uses_ff {
+            keep_max(&mut clock_to_out, (iob, analyzer.clock_domain(fb, mc)),
+                params.tco + analyzer.clock_adder(fb, mc) + slew);
+        } else {
+            let n = analyzer.or_pt_count(fb, mc);
+            for (start, k) in analyzer.mc_sources(fb, mc, &mut Vec::new()) {
+                match start {
+                    PathStart::Reg(src_fb, src_mc) => {
+                        keep_max(&mut clock_to_out, (iob, analyzer.clock_domain(src_fb, src_mc)),
+                            params.tco + analyzer.clock_adder(src_fb, src_mc) + k * params.tf + slew);
+                    },
+                    _ => keep_max(&mut pin_to_pin, (iob, start), tpd(n) + (k - 1) * params.tf + slew),
+                }
+            }
+        }
+
+        let gts = match obuf_mode {
+            XC2IOBOBufMode::TriStateGTS0 => Some(0),
+            XC2IOBOBufMode::TriStateGTS1 => Some(1),
+            XC2IOBOBufMode::TriStateGTS2 => Some(2),
+            XC2IOBOBufMode::TriStateGTS3 => Some(3),
+            _ => None,
+        };
+        if let Some(gts) = gts {
+            output_enable.insert((iob, format!("GTS{}", gts)), (params.toe + slew, params.tod));
+        }
+        let oe_pt = match obuf_mode {
+            XC2IOBOBufMode::TriStatePTB => Some(get_ptb(mc as u32) as usize),
+            XC2IOBOBufMode::TriStateCTE => Some(CTE as usize),
+            _ => None,
+        };
+        if let Some(oe_pt) = oe_pt {
+            for (start, k) in analyzer.pt_sources(fb, &[oe_pt], &mut Vec::new()) {
+                if let PathStart::Reg(..) = start {
+                    continue;
+                }
+                output_enable.insert((iob, analyzer.pin_name(start)),
+                    (params.tpoe + k * params.tf + slew, params.tpod + k * params.tf));
+            }
+        }
+    }
+
+    // Paths to registers
+    for fb in 0..device.num_fbs() {
+        for mc in 0..MCS_PER_FB {
+            if !analyzer.reg_is_used(fb, mc) {
+                continue;
+            }
+            let domain = analyzer.clock_domain(fb, mc);
+            let adder = analyzer.clock_adder(fb, mc) as i32;
+            let mut update_setup_hold = |start: PathStart, setup: i32, hold: i32| {
+                let entry = setup_hold.entry((start, domain)).or_insert((setup, hold));
+                entry.0 = entry.0.max(setup);
+                entry.1 = entry.1.max(hold);
+            };
+
+            if bits.get_fb()[fb].ffs[mc].ff_in_ibuf {
+                if let Some(iob) = fb_ff_num_to_iob_num(device, fb as u32, mc as u32) {
+                    update_setup_hold(PathStart::Pad(iob as usize), params.tsuin as i32 - adder,
+                        params.thin as i32 + adder);
+                }
+                continue;
+            }
+
+            let n = analyzer.or_pt_count(fb, mc);
+            for (start, k) in analyzer.mc_sources(fb, mc, &mut Vec::new()) {
+                match start {
+                    PathStart::Reg(src_fb, src_mc) => {
+                        if analyzer.clock_domain(src_fb, src_mc) == domain {
+                            keep_max(&mut min_period, domain, tsys(n) + (k - 1) * params.tf);
+                        }
+                    },
+                    _ => update_setup_hold(start, (tsu(n) + (k - 1) * params.tf) as i32 - adder,
+                        params.th as i32 + adder),
+                }
+            }
+        }
+    }
+
+    let mut report = XC2TimingReport::default();
+    for ((iob, start), delay) in pin_to_pin {
+        report.pin_to_pin.push(XC2PathDelay {
+            from: analyzer.pin_name(start),
+            to: analyzer.pin_name(PathStart::Pad(iob)),
+            delay,
+        });
+    }
+    for ((iob, domain), delay) in clock_to_out {
+        report.clock_to_out.push(XC2PathDelay {
+            from: domain.to_string(),
+            to: analyzer.pin_name(PathStart::Pad(iob)),
+            delay,
+        });
+    }
+    for ((iob, from), (enable, disable)) in output_enable {
+        report.output_enable.push(XC2OutputEnableDelay {
+            from,
+            to: analyzer.pin_name(PathStart::Pad(iob)),
+            enable,
+            disable,
+        });
+    }
+    for ((start, clock), (setup, hold)) in setup_hold {
+        report.setup_hold.push(XC2SetupHold {
+            pin: analyzer.pin_name(start),
+            clock,
+            setup,
+            hold,
+        });
+    }
+    for (clock, min_period) in min_period {
+        report.min_period.push(XC2ClockPeriod {
+            clock,
+            min_period,
+        });
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blank() -> XC2Bitstream {
+        XC2Bitstream::blank_bitstream(XC2Device::XC2C32A, XC2Speed::Speed6, XC2Package::VQ44).unwrap()
+    }
+
+    /// Every parameter is different so that a path using the wrong one is noticed. These are not real delays.
+    fn params() -> XC2TimingParams {
+        XC2TimingParams {
+            tpd1: 5000,
+            tpd2: 5100,
+            tsu1: 2000,
+            tsu2: 2100,
+            th: 300,
+            tco: 4000,
+            tf: 1500,
+            toe: 6000,
+            tod: 6100,
+            tpoe: 7000,
+            tpod: 7100,
+            tsuin: 1000,
+            thin: 400,
+            tptck: 800,
+            tslew_fast: 50,
+            tslew_slow: 900,
+            tclkdiv: 1200,
+            tsys1: 3000,
+            tsys2: 3100,
+        }
+    }
+
+    fn iobs_mut(bitstream: &mut XC2Bitstream) -> &mut [XC2MCSmallIOB] {
+        match bitstream.bits {
+            XC2BitstreamBits::XC2C32A{ref mut iobs, ..} => iobs,
+            _ => unreachable!(),
+        }
+    }
+
+    fn mc_of(iob: u32) -> usize {
+        iob_num_to_fb_ff_num(XC2Device::XC2C32A, iob).unwrap().1 as usize
+    }
+
+    #[test]
+    fn sta_pin_to_pin() {
+        // Pin 2 is the inverse of pin 0. Pin 3 is pin 2 fed back through the ZIA and ANDed with pin 1.
+        let mut bitstream = blank();
+        iobs_mut(&mut bitstream)[0].zia_mode = XC2IOBZIAMode::PAD;
+        iobs_mut(&mut bitstream)[1].zia_mode = XC2IOBZIAMode::PAD;
+        iobs_mut(&mut bitstream)[2].obuf_mode = XC2IOBOBufMode::PushPull;
+        iobs_mut(&mut bitstream)[2].slew_is_fast = true;
+        iobs_mut(&mut bitstream)[3].obuf_mode = XC2IOBOBufMode::PushPull;
+        iobs_mut(&mut bitstream)[3].slew_is_fast = false;
+        let (mc2, mc3) = (mc_of(2), mc_of(3));
+        {
+            let fb = &mut bitstream.bits.get_fb_mut()[0];
+            fb.zia_bits[0].selected = XC2ZIAInput::IBuf{ibuf: 0};
+            fb.zia_bits[1].selected = XC2ZIAInput::IBuf{ibuf: 1};
+            fb.zia_bits[2].selected = XC2ZIAInput::Macrocell{fb: 0, ff: mc2 as u32};
+            fb.and_terms[20].input_b[0] = true;
+            fb.or_terms[mc2].input[20] = true;
+            fb.ffs[mc2].fb_mode = XC2MCFeedbackMode::COMB;
+            fb.and_terms[21].input[1] = true;
+            fb.and_terms[21].input[2] = true;
+            fb.and_terms[22].input[1] = true;
+            fb.or_terms[mc3].input[21] = true;
+            fb.or_terms[mc3].input[22] = true;
+        }
+
+        let p = params();
+        let report = analyze_timing(&bitstream, &p);
+        assert_eq!(report.pin_to_pin, vec![
+            XC2PathDelay{from: "FB1_1".to_owned(), to: format!("FB1_{}", mc2 + 1), delay: p.tpd1 + p.tslew_fast},
+            XC2PathDelay{from: "FB1_1".to_owned(), to: format!("FB1_{}", mc3 + 1),
+                delay: p.tpd2 + p.tf + p.tslew_slow},
+            XC2PathDelay{from: format!("FB1_{}", mc_of(1) + 1), to: format!("FB1_{}", mc3 + 1),
+                delay: p.tpd2 + p.tslew_slow},
+        ]);
+        assert!(report.clock_to_out.is_empty());
+        assert!(report.setup_hold.is_empty());
+        assert!(report.min_period.is_empty());
+    }
+
+    #[test]
+    fn sta_registers() {
+        // A toggle flip-flop on GCK0 enabled by pin 0 (through the PTC clock enable) and driving pin 3
+        let mut bitstream = blank();
+        iobs_mut(&mut bitstream)[0].zia_mode = XC2IOBZIAMode::PAD;
+        iobs_mut(&mut bitstream)[3].obuf_mode = XC2IOBOBufMode::TriStateGTS1;
+        iobs_mut(&mut bitstream)[3].obuf_uses_ff = true;
+        iobs_mut(&mut bitstream)[3].slew_is_fast = true;
+        bitstream.bits.get_global_nets_mut().gck_enable[0] = true;
+        let mc = mc_of(3);
+        {
+            let fb = &mut bitstream.bits.get_fb_mut()[0];
+            fb.zia_bits[0].selected = XC2ZIAInput::IBuf{ibuf: 0};
+            fb.zia_bits[1].selected = XC2ZIAInput::Macrocell{fb: 0, ff: mc as u32};
+            fb.and_terms[20].input[0] = true;
+            fb.and_terms[20].input[1] = true;
+            fb.or_terms[mc].input[20] = true;
+            fb.ffs[mc].reg_mode = XC2MCRegMode::DFF;
+            fb.ffs[mc].clk_src = XC2MCRegClkSrc::GCK0;
+            fb.ffs[mc].fb_mode = XC2MCFeedbackMode::REG;
+            fb.ffs[mc].xor_mode = XC2MCXorMode::ZERO;
+        }
+
+        let p = params();
+        let report = analyze_timing(&bitstream, &p);
+        let name = format!("FB1_{}", mc + 1);
+        assert!(report.pin_to_pin.is_empty());
+        assert_eq!(report.clock_to_out, vec![
+            XC2PathDelay{from: "GCK0".to_owned(), to: name.clone(), delay: p.tco + p.tslew_fast},
+        ]);
+        assert_eq!(report.output_enable, vec![
+            XC2OutputEnableDelay{from: "GTS1".to_owned(), to: name.clone(), enable: p.toe + p.tslew_fast,
+                disable: p.tod},
+        ]);
+        assert_eq!(report.setup_hold, vec![
+            XC2SetupHold{pin: "FB1_1".to_owned(), clock: XC2ClockDomain::GCK(0), setup: p.tsu1 as i32,
+                hold: p.th as i32},
+        ]);
+        assert_eq!(report.min_period, vec![
+            XC2ClockPeriod{clock: XC2ClockDomain::GCK(0), min_period: p.tsys1},
+        ]);
+
+        // A product term clock arrives later
+        bitstream.bits.get_fb_mut()[0].ffs[mc].clk_src = XC2MCRegClkSrc::PTC;
+        let report = analyze_timing(&bitstream, &p);
+        assert_eq!(report.clock_to_out[0].delay, p.tco + p.tptck + p.tslew_fast);
+        assert_eq!(report.setup_hold[0].setup, p.tsu1 as i32 - p.tptck as i32);
+        assert_eq!(report.setup_hold[0].hold, (p.th + p.tptck) as i32);
+        assert_eq!(report.min_period[0].clock, XC2ClockDomain::PTC{fb: 0, mc: mc as u32});
+    }
+
+    #[test]
+    fn sta_combinatorial_loop() {
+        // Two macrocells that feed each other must not hang the analyzer
+        let mut bitstream = blank();
+        iobs_mut(&mut bitstream)[0].obuf_mode = XC2IOBOBufMode::PushPull;
+        {
+            let fb = &mut bitstream.bits.get_fb_mut()[0];
+            fb.zia_bits[0].selected = XC2ZIAInput::Macrocell{fb: 0, ff: 0};
+            fb.zia_bits[1].selected = XC2ZIAInput::Macrocell{fb: 0, ff: 1};
+            fb.and_terms[20].input[1] = true;
+            fb.and_terms[21].input[0] = true;
+            fb.or_terms[0].input[20] = true;
+            fb.or_terms[1].input[21] = true;
+            fb.ffs[0].fb_mode = XC2MCFeedbackMode::COMB;
+            fb.ffs[1].fb_mode = XC2MCFeedbackMode::COMB;
+        }
+
+        let report = analyze_timing(&bitstream, &params());
+        assert!(report.pin_to_pin.is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+/*
+Copyright (c) 2017, Robert Ou <rqou@robertou.com> and contributors
+All rights reserved.
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice,
+   this list of conditions and the following disclaimer.
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+//! Contains routines for generating SVF files for programming devices over JTAG.
+//!
+//! The generated SVF runs the entire ISC flow: the IDCODE is checked, ISC mode is enabled, the device is erased, each
+//! row of the physical fuse array is programmed and then verified, and finally ISC mode is disabled. The rows are
+//! shifted in the order given by `isc_rows`.
+
+use std::io;
+use std::io::Write;
+
+use *;
+use isc::{INSTR_BYPASS, INSTR_IDCODE, INSTR_ISC_ENABLE, INSTR_ISC_ERASE, INSTR_ISC_PROGRAM, INSTR_ISC_READ,
+          INSTR_ISC_INIT, INSTR_ISC_DISABLE, isc_rows};
+
+/// Number of TCK cycles to wait after each step (assuming TCK runs at no more than 1 MHz)
+const ENABLE_TCKS: u32 = 800;
+const ERASE_TCKS: u32 = 100000;
+const PROGRAM_TCKS: u32 = 10000;
+const READ_TCKS: u32 = 20;
+const INIT_TCKS: u32 = 20;
+const DISABLE_TCKS: u32 = 100;
+
+/// Describes the other devices in the JTAG chain. All of these devices are placed into BYPASS while the
+/// Coolrunner-II is being programmed.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SVFChain {
+    /// Instruction register lengths of the devices between TDI and the Coolrunner-II, starting from TDI
+    pub before: Vec<u32>,
+    /// Instruction register lengths of the devices between the Coolrunner-II and TDO, starting from the Coolrunner-II
+    pub after: Vec<u32>,
+}
+
+/// Formats bits as an SVF hex string. The first bit is the one that is shifted first (the least significant bit).
+fn bits_to_hex(bits: &[bool]) -> String {
+    let num_digits = (bits.len() + 3) / 4;
+    let mut ret = String::with_capacity(num_digits);
+    for digit_i in (0..num_digits).rev() {
+        let mut digit = 0;
+        for bit_i in 0..4 {
+            let i = digit_i * 4 + bit_i;
+            if i < bits.len() && bits[i] {
+                digit |= 1 << bit_i;
+            }
+        }
+        ret.push_str(&format!("{:X}", digit));
+    }
+    ret
+}
+
+/// Converts the low `len` bits of `val` into bits, least significant bit first
+fn int_to_bits(val: u32, len: u32) -> Vec<bool> {
+    (0..len).map(|i| val & (1 << i) != 0).collect()
+}
+
+fn write_sir(writer: &mut Write, device: XC2Device, instr: u8) -> Result<(), io::Error> {
+    let bits = int_to_bits(instr as u32, device.ir_length());
+    write!(writer, "SIR {} TDI ({});\n", bits.len(), bits_to_hex(&bits))
+}
+
+fn write_runtest(writer: &mut Write, tcks: u32) -> Result<(), io::Error> {
+    write!(writer, "RUNTEST {} TCK;\n", tcks)
+}
+
+/// Writes an SVF file that programs the given fuse array into a device of type `device`
+pub fn write_svf(device: XC2Device, fuse_array: &FuseArray, chain: &SVFChain, writer: &mut Write)
+    -> Result<(), io::Error> {
+
+    let rows = isc_rows(device, fuse_array)?;
+
+    write!(writer, "// SVF file written by xc2bit\n")?;
+    write!(writer, "// https://github.com/azonenberg/openfpga\n")?;
+    if let Some(ref dev_name_str) = fuse_array.dev_name_str {
+        write!(writer, "// DEVICE {}\n", dev_name_str)?;
+    }
+    write!(writer, "\n")?;
+
+    write!(writer, "TRST OFF;\n")?;
+    write!(writer, "ENDIR IDLE;\n")?;
+    write!(writer, "ENDDR IDLE;\n")?;
+    write!(writer, "STATE RESET;\n")?;
+    write!(writer, "STATE IDLE;\n")?;
+    write!(writer, "FREQUENCY 1E6 HZ;\n")?;
+
+    // The header is shifted first and so ends up in the devices closest to TDO. All the other devices get all ones
+    // (BYPASS) in their instruction registers, and their bypass registers are one bit each.
+    let hir_len = chain.after.iter().sum::<u32>();
+    let tir_len = chain.before.iter().sum::<u32>();
+    let hdr_len = chain.after.len();
+    let tdr_len = chain.before.len();
+    if hir_len > 0 {
+        write!(writer, "HIR {} TDI ({});\n", hir_len, bits_to_hex(&vec![true; hir_len as usize]))?;
+    } else {
+        write!(writer, "HIR 0;\n")?;
+    }
+    if tir_len > 0 {
+        write!(writer, "TIR {} TDI ({});\n", tir_len, bits_to_hex(&vec![true; tir_len as usize]))?;
+    } else {
+        write!(writer, "TIR 0;\n")?;
+    }
+    if hdr_len > 0 {
+        write!(writer, "HDR {} TDI ({});\n", hdr_len, bits_to_hex(&vec![false; hdr_len]))?;
+    } else {
+        write!(writer, "HDR 0;\n")?;
+    }
+    if tdr_len > 0 {
+        write!(writer, "TDR {} TDI ({});\n", tdr_len, bits_to_hex(&vec![false; tdr_len]))?;
+    } else {
+        write!(writer, "TDR 0;\n")?;
+    }
+
+    // Check the IDCODE
+    write!(writer, "\n// Check IDCODE\n")?;
+    write_sir(writer, device, INSTR_IDCODE)?;
+    write!(writer, "SDR 32 TDI (00000000) TDO ({:08X}) MASK ({:08X});\n", device.idcode(), device.idcode_mask())?;
+
+    // Enable ISC mode
+    write!(writer, "\n// Enable ISC\n")?;
+    write_sir(writer, device, INSTR_ISC_ENABLE)?;
+    write_runtest(writer, ENABLE_TCKS)?;
+
+    // Erase
+    write!(writer, "\n// Erase\n")?;
+    write_sir(writer, device, INSTR_ISC_ERASE)?;
+    write_runtest(writer, ERASE_TCKS)?;
+
+    // Program each row
+    write!(writer, "\n// Program\n")?;
+    write_sir(writer, device, INSTR_ISC_PROGRAM)?;
+    for &(ref address, ref data) in &rows {
+        let mut bits = data.clone();
+        bits.extend_from_slice(address);
+
+        write!(writer, "SDR {} TDI ({});\n", bits.len(), bits_to_hex(&bits))?;
+        write_runtest(writer, PROGRAM_TCKS)?;
+    }
+
+    // Verify each row
+    write!(writer, "\n// Verify\n")?;
+    write_sir(writer, device, INSTR_ISC_READ)?;
+    for &(ref address, ref data) in &rows {
+        write!(writer, "SDR {} TDI ({});\n", address.len(), bits_to_hex(address))?;
+        write_runtest(writer, READ_TCKS)?;
+
+        write!(writer, "SDR {} TDI ({}) TDO ({}) MASK ({});\n", data.len(),
+            bits_to_hex(&vec![false; data.len()]), bits_to_hex(data), bits_to_hex(&vec![true; data.len()]))?;
+    }
+
+    // Exit ISC mode
+    write!(writer, "\n// Disable ISC\n")?;
+    write_sir(writer, device, INSTR_ISC_INIT)?;
+    write_runtest(writer, INIT_TCKS)?;
+    write_sir(writer, device, INSTR_ISC_DISABLE)?;
+    write_runtest(writer, DISABLE_TCKS)?;
+    write_sir(writer, device, INSTR_BYPASS)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_formatting() {
+        assert_eq!(bits_to_hex(&[true]), "1");
+        assert_eq!(bits_to_hex(&[false, true, true, true, true]), "1E");
+        assert_eq!(bits_to_hex(&int_to_bits(INSTR_ISC_ENABLE as u32, 8)), "E8");
+    }
+
+    #[test]
+    fn chain_padding() {
+        let bitstream = XC2Bitstream::blank_bitstream(XC2Device::XC2C32A, XC2Speed::Speed4, XC2Package::VQ44).unwrap();
+        let chain = SVFChain {
+            before: vec![6],
+            after: vec![8, 4],
+        };
+        let mut svf = Vec::new();
+        bitstream.write_svf(&chain, &mut svf).unwrap();
+        let svf = String::from_utf8(svf).unwrap();
+
+        assert!(svf.contains("HIR 12 TDI (FFF);\n"));
+        assert!(svf.contains("TIR 6 TDI (3F);\n"));
+        assert!(svf.contains("HDR 2 TDI (0);\n"));
+        assert!(svf.contains("TDR 1 TDI (0);\n"));
+        assert!(svf.contains("SDR 32 TDI (00000000) TDO (06E18093) MASK (0FFF8FFF);\n"));
+        assert_eq!(svf.matches("SDR 266 TDI").count(), 50);
+    }
+}
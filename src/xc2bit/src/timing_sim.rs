@@ -0,0 +1,931 @@
+/*
+Copyright (c) 2017, Robert Ou <rqou@robertou.com> and contributors
+All rights reserved.
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice,
+   this list of conditions and the following disclaimer.
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+//! Contains an event-driven timing simulator for a configured device that writes VCD waveforms.
+//!
+//! Unlike the cycle-based simulator in `sim`, every net has a delay, so glitches on combinational outputs show up in
+//! the waveforms. Delays are transport delays: every change of an input produces an event on the output after the
+//! delay of the output, even if the pulse is shorter than the delay. The delays are normally made from the same
+//! `XC2TimingParams` that `analyze_timing` uses, with `XC2SimDelays::from_timing_params`.
+//!
+//! Signals take the path pad → input buffer → ZIA → AND term → OR/XOR → register → output buffer → pad. The global
+//! clock, set/reset, and tri-state nets are separate inputs named `GCK0`, `GSR`, `GTS0`, etc. because the pins they
+//! share are not known. The logic is evaluated with the same assumptions as the cycle-based simulator.
+//!
+//! Stimulus can be scheduled using the API or read from a text file with one change per line in the form
+//! `<time in ps> <signal> <0 or 1>`, where `signal` is a pin name such as `FB1_2`, `dedicated_input`, `GCK0`, `GSR`,
+//! or `GTS0`. Blank lines and lines starting with `#` are ignored.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::io;
+use std::io::Write;
+
+use *;
+use human_readable::{parse_fb_ff_name};
+
+/// Maximum number of events that can happen at the same time before the logic is considered to be oscillating
+const MAX_EVENTS_PER_TIMESTEP: usize = 100000;
+
+/// A change of a net as (time, sequence number, net, value). The sequence number keeps events that happen at the
+/// same time in the order they were scheduled.
+type Event = Reverse<(u64, u64, usize, Option<bool>)>;
+
+/// Delays used by the timing simulator, in picoseconds
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct XC2SimDelays {
+    /// Pad to the output of the input buffer
+    pub input: u64,
+    /// Through the ZIA
+    pub zia: u64,
+    /// Through an AND term
+    pub and_term: u64,
+    /// Through an OR term and the macrocell XOR gate
+    pub or_xor: u64,
+    /// Macrocell to pad, including output enable changes
+    pub output: u64,
+    /// Global clock pin to the clock input of a register
+    pub global_clock: u64,
+    /// Clock, set, or reset of a register to its output
+    pub clk_to_q: u64,
+}
+
+impl XC2SimDelays {
+    /// Splits the delays in `params` over the stages of the simulator, so that the simulator gives the same delays as
+    /// `analyze_timing` for pin-to-pin paths, clock-to-output paths, setup times, and each extra pass through the ZIA.
+    /// The simulator does not model OR terms of more than one product term or slow slew, so the delays for one product
+    /// term and fast slew are used everywhere. All of `tf` is put in the ZIA stage.
+    ///
+    /// Returns `None` if the parameters cannot be split without making a stage negative.
+    pub fn from_timing_params(params: &XC2TimingParams) -> Option<XC2SimDelays> {
+        let tf = params.tf as u64;
+        let tpd = params.tpd1 as u64 + params.tslew_fast as u64;
+        let tco = params.tco as u64 + params.tslew_fast as u64;
+        let tsu = params.tsu1 as u64;
+
+        // Setup time is input + tf - global_clock
+        let input = tsu.saturating_sub(tf);
+        let global_clock = input + tf - tsu;
+        let output = tpd.checked_sub(input + tf)?;
+        let clk_to_q = tco.checked_sub(global_clock + output)?;
+
+        Some(XC2SimDelays {
+            input,
+            zia: tf,
+            and_term: 0,
+            or_xor: 0,
+            output,
+            global_clock,
+            clk_to_q,
+        })
+    }
+}
+
+/// Every net that is simulated
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+enum Net {
+    /// Value driven onto a pin from outside the device
+    PinInput(usize),
+    /// Value actually on a pin
+    Pad(usize),
+    /// Output of an input buffer
+    IBuf(usize),
+    /// Output of an output buffer, or `None` if it is not driving
+    OBuf(usize),
+    DedicatedInput,
+    Gck(usize),
+    Gsr,
+    Gts(usize),
+    Zia(usize, usize),
+    Pt(usize, usize),
+    Xor(usize, usize),
+    /// Clock input of a register after the optional inversion
+    RegClk(usize, usize),
+    Q(usize, usize),
+}
+
+/// Simulates the logic configured by a bitstream with delays
+pub struct XC2TimingSimulator<'a> {
+    bitstream: &'a XC2Bitstream,
+    device: XC2Device,
+    /// Settings of each I/O pin
+    iob_settings: Vec<(XC2IOBZIAMode, bool, XC2IOBOBufMode)>,
+    /// (fb, mc) of each I/O pin
+    iob_mc: Vec<(usize, usize)>,
+
+    nets: Vec<Net>,
+    net_idx: HashMap<Net, usize>,
+    delays: Vec<u64>,
+    fanout: Vec<Vec<usize>>,
+    values: Vec<Option<bool>>,
+
+    /// Internal state of each register
+    reg_state: Vec<[bool; MCS_PER_FB]>,
+    /// Clock of each register the last time it was evaluated
+    reg_last_clk: Vec<[bool; MCS_PER_FB]>,
+
+    time: u64,
+    events: BinaryHeap<Event>,
+    next_seq: u64,
+    /// All changes that have happened so far as (time, net, value)
+    changes: Vec<(u64, usize, Option<bool>)>,
+    initial_values: Vec<Option<bool>>,
+}
+
+/// Returns an error for the given zero-based line of a stimulus
+fn stimulus_error(line_num: usize, msg: String) -> XC2BitError {
+    XC2BitError::StimulusParseError{msg, line: line_num + 1}
+}
+
+impl<'a> XC2TimingSimulator<'a> {
+    /// Creates a new simulator for the given bitstream. At time 0, all inputs are low and all registers are in the
+    /// state given by their `init_state`. Fails if the logic does not settle.
+    pub fn new(bitstream: &'a XC2Bitstream, sim_delays: XC2SimDelays) -> Result<XC2TimingSimulator<'a>, XC2BitError> {
+        let bits = &bitstream.bits;
+        let device = bits.device_type();
+        let num_fbs = device.num_fbs();
+
+        let mut iob_settings = Vec::new();
+        let mut iob_mc = Vec::new();
+        for i in 0..device.num_iobs() {
+            iob_settings.push(if let Some(iob) = bits.get_small_iob(i) {
+                (iob.zia_mode, iob.obuf_uses_ff, iob.obuf_mode)
+            } else {
+                let iob = bits.get_large_iob(i).unwrap();
+                (iob.zia_mode, iob.obuf_uses_ff, iob.obuf_mode)
+            });
+            let (fb, mc) = iob_num_to_fb_ff_num(device, i as u32).unwrap();
+            iob_mc.push((fb as usize, mc as usize));
+        }
+
+        let mut nets = Vec::new();
+        for i in 0..device.num_iobs() {
+            nets.push(Net::PinInput(i));
+            nets.push(Net::Pad(i));
+            nets.push(Net::IBuf(i));
+            nets.push(Net::OBuf(i));
+        }
+        nets.push(Net::DedicatedInput);
+        for i in 0..3 {
+            nets.push(Net::Gck(i));
+        }
+        nets.push(Net::Gsr);
+        for i in 0..4 {
+            nets.push(Net::Gts(i));
+        }
+        for fb in 0..num_fbs {
+            for row in 0..INPUTS_PER_ANDTERM {
+                nets.push(Net::Zia(fb, row));
+            }
+            for pt in 0..ANDTERMS_PER_FB {
+                nets.push(Net::Pt(fb, pt));
+            }
+            for mc in 0..MCS_PER_FB {
+                nets.push(Net::Xor(fb, mc));
+                nets.push(Net::RegClk(fb, mc));
+                nets.push(Net::Q(fb, mc));
+            }
+        }
+
+        let mut net_idx = HashMap::new();
+        for (i, &net) in nets.iter().enumerate() {
+            net_idx.insert(net, i);
+        }
+
+        let mut reg_state = vec![[false; MCS_PER_FB]; num_fbs];
+        for fb in 0..num_fbs {
+            for mc in 0..MCS_PER_FB {
+                reg_state[fb][mc] = bits.get_fb()[fb].ffs[mc].init_state;
+            }
+        }
+
+        let mut sim = XC2TimingSimulator {
+            bitstream,
+            device,
+            iob_settings,
+            iob_mc,
+            delays: Vec::new(),
+            fanout: vec![Vec::new(); nets.len()],
+            values: vec![Some(false); nets.len()],
+            nets,
+            net_idx,
+            reg_state,
+            reg_last_clk: vec![[false; MCS_PER_FB]; num_fbs],
+            time: 0,
+            events: BinaryHeap::new(),
+            next_seq: 0,
+            changes: Vec::new(),
+            initial_values: Vec::new(),
+        };
+
+        for i in 0..sim.nets.len() {
+            let net = sim.nets[i];
+            let delay = sim.net_delay(net, &sim_delays);
+            sim.delays.push(delay);
+            for input in sim.net_inputs(net) {
+                let input_idx = sim.net_idx[&input];
+                sim.fanout[input_idx].push(i);
+            }
+        }
+
+        // Settle the initial state without delays. Starting up must not be treated as a clock edge.
+        let mut settled = false;
+        for _ in 0..sim.nets.len() {
+            let mut changed = false;
+            for i in 0..sim.nets.len() {
+                if let Net::Q(fb, mc) = sim.nets[i] {
+                    sim.reg_last_clk[fb][mc] = sim.value(Net::RegClk(fb, mc));
+                }
+                let val = sim.eval(i);
+                if val != sim.values[i] {
+                    sim.values[i] = val;
+                    changed = true;
+                }
+            }
+            if !changed {
+                settled = true;
+                break;
+            }
+        }
+        if !settled {
+            return Err(XC2BitError::SimulationDidNotSettle{msg: "logic did not settle"});
+        }
+        for fb in 0..num_fbs {
+            for mc in 0..MCS_PER_FB {
+                sim.reg_last_clk[fb][mc] = sim.value(Net::RegClk(fb, mc));
+            }
+        }
+        sim.initial_values = sim.values.clone();
+
+        Ok(sim)
+    }
+
+    /// Schedules the value driven onto an I/O pin from outside the device to change at the given time. This value is
+    /// only seen by the device while its own output buffer for the pin is not driving.
+    pub fn schedule_pin(&mut self, time: u64, iob: usize, value: bool) {
+        self.schedule(time, Net::PinInput(iob), value);
+    }
+
+    /// Schedules the dedicated input pin on 32-macrocell devices to change at the given time
+    pub fn schedule_dedicated_input(&mut self, time: u64, value: bool) {
+        self.schedule(time, Net::DedicatedInput, value);
+    }
+
+    /// Schedules one of the global clock pins to change at the given time
+    pub fn schedule_gck(&mut self, time: u64, idx: usize, value: bool) {
+        self.schedule(time, Net::Gck(idx), value);
+    }
+
+    /// Schedules the global set/reset pin to change at the given time
+    pub fn schedule_gsr(&mut self, time: u64, value: bool) {
+        self.schedule(time, Net::Gsr, value);
+    }
+
+    /// Schedules one of the global tri-state pins to change at the given time
+    pub fn schedule_gts(&mut self, time: u64, idx: usize, value: bool) {
+        self.schedule(time, Net::Gts(idx), value);
+    }
+
+    /// Schedules all of the changes in the given stimulus text. See the module documentation for the format.
+    pub fn schedule_stimulus(&mut self, stimulus: &str) -> Result<(), XC2BitError> {
+        for (line_num, line) in stimulus.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.len() != 3 {
+                return Err(stimulus_error(line_num, String::from("expected \"<time> <signal> <value>\"")));
+            }
+            let time = match fields[0].parse::<u64>() {
+                Ok(x) => x,
+                Err(_) => return Err(stimulus_error(line_num, format!("invalid time \"{}\"", fields[0]))),
+            };
+            let value = match fields[2] {
+                "0" => false,
+                "1" => true,
+                x => return Err(stimulus_error(line_num, format!("invalid value \"{}\"", x))),
+            };
+            let net = match self.stimulus_net(fields[1]) {
+                Some(x) => x,
+                None => return Err(stimulus_error(line_num, format!("unknown signal \"{}\"", fields[1]))),
+            };
+            self.schedule(time, net, value);
+        }
+
+        Ok(())
+    }
+
+    /// Processes all events up to and including the given time. Fails if the logic oscillates without any delay.
+    pub fn run_until(&mut self, end_time: u64) -> Result<(), XC2BitError> {
+        let mut events_this_timestep = 0;
+
+        while let Some(&Reverse((time, _, net, value))) = self.events.peek() {
+            if time > end_time {
+                break;
+            }
+            self.events.pop();
+
+            if time != self.time {
+                self.time = time;
+                events_this_timestep = 0;
+            }
+            events_this_timestep += 1;
+            if events_this_timestep > MAX_EVENTS_PER_TIMESTEP {
+                return Err(XC2BitError::SimulationDidNotSettle{msg: "logic did not settle"});
+            }
+
+            if self.values[net] == value {
+                continue;
+            }
+            self.values[net] = value;
+            self.changes.push((time, net, value));
+
+            for i in 0..self.fanout[net].len() {
+                let dest = self.fanout[net][i];
+                let new_value = self.eval(dest);
+                let seq = self.next_seq;
+                self.next_seq += 1;
+                self.events.push(Reverse((time + self.delays[dest], seq, dest, new_value)));
+            }
+        }
+
+        self.time = end_time;
+        Ok(())
+    }
+
+    /// Returns the current simulation time in picoseconds
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// Returns the value that the device is driving onto the given I/O pin, or `None` if it is not driving the pin
+    pub fn get_pin(&self, iob: usize) -> Option<bool> {
+        self.values[self.net_idx[&Net::OBuf(iob)]]
+    }
+
+    /// Returns the output of the register in the given macrocell
+    pub fn get_mc_q(&self, fb: usize, mc: usize) -> bool {
+        self.value(Net::Q(fb, mc))
+    }
+
+    /// Returns the output of the XOR gate in the given macrocell
+    pub fn get_mc_xor(&self, fb: usize, mc: usize) -> bool {
+        self.value(Net::Xor(fb, mc))
+    }
+
+    /// Writes everything that has been simulated so far to the given `writer` object as a VCD file. Pins and the
+    /// outputs driving them (`FB1_2_out`, which is `z` while not driving) are at the top level, and the ZIA rows, AND
+    /// terms, XOR gates, and registers of each FB are in a scope named after the FB.
+    pub fn write_vcd(&self, module_name: &str, writer: &mut Write) -> Result<(), io::Error> {
+        let mut names = vec![None; self.nets.len()];
+        for (i, &net) in self.nets.iter().enumerate() {
+            names[i] = match net {
+                Net::Pad(iob) => {
+                    let (fb, mc) = self.iob_mc[iob];
+                    Some(format!("FB{}_{}", fb + 1, mc + 1))
+                },
+                Net::OBuf(iob) => {
+                    let (fb, mc) = self.iob_mc[iob];
+                    Some(format!("FB{}_{}_out", fb + 1, mc + 1))
+                },
+                Net::DedicatedInput if self.bitstream.bits.get_inpin().is_some() =>
+                    Some(String::from("dedicated_input")),
+                Net::Gck(i) => Some(format!("GCK{}", i)),
+                Net::Gsr => Some(String::from("GSR")),
+                Net::Gts(i) => Some(format!("GTS{}", i)),
+                Net::Zia(_, row) => Some(format!("zia{}", row)),
+                Net::Pt(_, pt) => Some(format!("pt{}", pt)),
+                Net::Xor(_, mc) => Some(format!("mc{}_xor", mc + 1)),
+                Net::Q(_, mc) => Some(format!("mc{}_q", mc + 1)),
+                _ => None,
+            };
+        }
+
+        let vcd_id = |mut i: usize| {
+            let mut ret = String::new();
+            loop {
+                ret.push((33 + (i % 94)) as u8 as char);
+                i /= 94;
+                if i == 0 {
+                    break;
+                }
+            }
+            ret
+        };
+        let vcd_value = |value: Option<bool>| match value {
+            None => 'z',
+            Some(false) => '0',
+            Some(true) => '1',
+        };
+
+        write!(writer, "$version xc2bit $end\n")?;
+        write!(writer, "$timescale 1ps $end\n")?;
+        write!(writer, "$scope module {} $end\n", module_name)?;
+        let mut current_fb = None;
+        for (i, &net) in self.nets.iter().enumerate() {
+            if let Some(ref name) = names[i] {
+                let fb = match net {
+                    Net::Zia(fb, _) | Net::Pt(fb, _) | Net::Xor(fb, _) | Net::Q(fb, _) => Some(fb),
+                    _ => None,
+                };
+                if fb != current_fb {
+                    if current_fb.is_some() {
+                        write!(writer, "$upscope $end\n")?;
+                    }
+                    if let Some(fb) = fb {
+                        write!(writer, "$scope module FB{} $end\n", fb + 1)?;
+                    }
+                    current_fb = fb;
+                }
+                write!(writer, "$var wire 1 {} {} $end\n", vcd_id(i), name)?;
+            }
+        }
+        if current_fb.is_some() {
+            write!(writer, "$upscope $end\n")?;
+        }
+        write!(writer, "$upscope $end\n")?;
+        write!(writer, "$enddefinitions $end\n")?;
+
+        write!(writer, "#0\n$dumpvars\n")?;
+        for i in 0..self.nets.len() {
+            if names[i].is_some() {
+                write!(writer, "{}{}\n", vcd_value(self.initial_values[i]), vcd_id(i))?;
+            }
+        }
+        write!(writer, "$end\n")?;
+
+        let mut last_time = 0;
+        for &(time, net, value) in &self.changes {
+            if names[net].is_none() {
+                continue;
+            }
+            if time != last_time {
+                write!(writer, "#{}\n", time)?;
+                last_time = time;
+            }
+            write!(writer, "{}{}\n", vcd_value(value), vcd_id(net))?;
+        }
+        if self.time != last_time {
+            write!(writer, "#{}\n", self.time)?;
+        }
+
+        Ok(())
+    }
+
+    /// Internal helper that adds a stimulus event
+    fn schedule(&mut self, time: u64, net: Net, value: bool) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.events.push(Reverse((time, seq, self.net_idx[&net], Some(value))));
+    }
+
+    /// Internal helper that finds the net that a stimulus line refers to
+    fn stimulus_net(&self, name: &str) -> Option<Net> {
+        if name == "dedicated_input" {
+            return Some(Net::DedicatedInput);
+        }
+        if name == "GSR" {
+            return Some(Net::Gsr);
+        }
+        if let Some(num) = name.strip_prefix("GCK") {
+            return num.parse::<usize>().ok().and_then(|x| if x < 3 {Some(Net::Gck(x))} else {None});
+        }
+        if let Some(num) = name.strip_prefix("GTS") {
+            return num.parse::<usize>().ok().and_then(|x| if x < 4 {Some(Net::Gts(x))} else {None});
+        }
+        parse_fb_ff_name(name)
+            .and_then(|(fb, mc)| fb_ff_num_to_iob_num(self.device, fb, mc))
+            .map(|iob| Net::PinInput(iob as usize))
+    }
+
+    /// Internal helper that returns the current value of a net that is never high impedance
+    fn value(&self, net: Net) -> bool {
+        self.values[self.net_idx[&net]].unwrap_or(false)
+    }
+
+    /// Internal helper that returns the delay of a net
+    fn net_delay(&self, net: Net, sim_delays: &XC2SimDelays) -> u64 {
+        match net {
+            Net::IBuf(_) => sim_delays.input,
+            Net::OBuf(_) => sim_delays.output,
+            Net::Zia(..) => sim_delays.zia,
+            Net::Pt(..) => sim_delays.and_term,
+            Net::Xor(..) => sim_delays.or_xor,
+            Net::RegClk(fb, mc) => match self.bitstream.bits.get_fb()[fb].ffs[mc].clk_src {
+                XC2MCRegClkSrc::GCK0 | XC2MCRegClkSrc::GCK1 | XC2MCRegClkSrc::GCK2 => sim_delays.global_clock,
+                XC2MCRegClkSrc::PTC | XC2MCRegClkSrc::CTC => 0,
+            },
+            Net::Q(..) => sim_delays.clk_to_q,
+            _ => 0,
+        }
+    }
+
+    /// Internal helper that returns the nets that the value of a net depends on
+    fn net_inputs(&self, net: Net) -> Vec<Net> {
+        let bits = &self.bitstream.bits;
+        match net {
+            Net::PinInput(_) | Net::DedicatedInput | Net::Gck(_) | Net::Gsr | Net::Gts(_) => Vec::new(),
+            Net::Pad(iob) => vec![Net::OBuf(iob), Net::PinInput(iob)],
+            Net::IBuf(iob) => vec![Net::Pad(iob)],
+            Net::OBuf(iob) => {
+                let (fb, mc) = self.iob_mc[iob];
+                let mut ret = vec![Net::Xor(fb, mc), Net::Q(fb, mc)];
+                ret.extend((0..4).map(Net::Gts));
+                ret.push(Net::Pt(fb, get_ptb(mc as u32) as usize));
+                ret.push(Net::Pt(fb, CTE as usize));
+                ret
+            },
+            Net::Zia(fb, row) => match bits.get_fb()[fb].zia_bits[row].selected {
+                XC2ZIAInput::Zero | XC2ZIAInput::One => Vec::new(),
+                XC2ZIAInput::Macrocell{fb, ff} => {
+                    let (fb, ff) = (fb as usize, ff as usize);
+                    vec![Net::Xor(fb, ff), Net::Q(fb, ff)]
+                },
+                XC2ZIAInput::IBuf{ibuf} => {
+                    let (fb, mc) = self.iob_mc[ibuf as usize];
+                    vec![Net::IBuf(ibuf as usize), Net::Q(fb, mc)]
+                },
+                XC2ZIAInput::DedicatedInput => vec![Net::DedicatedInput],
+            },
+            Net::Pt(fb, _) => (0..INPUTS_PER_ANDTERM).map(|row| Net::Zia(fb, row)).collect(),
+            Net::Xor(fb, _) => (0..ANDTERMS_PER_FB).map(|pt| Net::Pt(fb, pt)).collect(),
+            Net::RegClk(fb, mc) => {
+                let mut ret = (0..3).map(Net::Gck).collect::<Vec<_>>();
+                ret.push(Net::Pt(fb, get_ptc(mc as u32) as usize));
+                ret.push(Net::Pt(fb, CTC as usize));
+                ret
+            },
+            Net::Q(fb, mc) => {
+                let mut ret = vec![Net::RegClk(fb, mc), Net::Xor(fb, mc), Net::Gsr];
+                if let Some(iob) = fb_ff_num_to_iob_num(self.device, fb as u32, mc as u32) {
+                    ret.push(Net::IBuf(iob as usize));
+                }
+                for &pt in &[get_pta(mc as u32), get_ptc(mc as u32), CTS, CTR] {
+                    ret.push(Net::Pt(fb, pt as usize));
+                }
+                ret
+            },
+        }
+    }
+
+    /// Internal helper that computes the new value of a net from the current values of its inputs. Evaluating a
+    /// register updates its internal state.
+    fn eval(&mut self, net_idx: usize) -> Option<bool> {
+        let bits = &self.bitstream.bits;
+        let global_nets = bits.get_global_nets();
+
+        match self.nets[net_idx] {
+            Net::PinInput(_) | Net::DedicatedInput | Net::Gck(_) | Net::Gsr | Net::Gts(_) => self.values[net_idx],
+            Net::Pad(iob) => {
+                let obuf = self.values[self.net_idx[&Net::OBuf(iob)]];
+                obuf.or(self.values[self.net_idx[&Net::PinInput(iob)]])
+            },
+            Net::IBuf(iob) => Some(self.value(Net::Pad(iob))),
+            Net::OBuf(iob) => {
+                let (fb, mc) = self.iob_mc[iob];
+                let (_, obuf_uses_ff, obuf_mode) = self.iob_settings[iob];
+                let out = if obuf_uses_ff {self.value(Net::Q(fb, mc))} else {self.value(Net::Xor(fb, mc))};
+                let gts_oe = |idx: usize| {
+                    !global_nets.gts_enable[idx] || (self.value(Net::Gts(idx)) == global_nets.gts_invert[idx])
+                };
+                let oe = match obuf_mode {
+                    XC2IOBOBufMode::Disabled => false,
+                    XC2IOBOBufMode::PushPull | XC2IOBOBufMode::CGND => true,
+                    XC2IOBOBufMode::OpenDrain => !out,
+                    XC2IOBOBufMode::TriStateGTS0 => gts_oe(0),
+                    XC2IOBOBufMode::TriStateGTS1 => gts_oe(1),
+                    XC2IOBOBufMode::TriStateGTS2 => gts_oe(2),
+                    XC2IOBOBufMode::TriStateGTS3 => gts_oe(3),
+                    XC2IOBOBufMode::TriStatePTB => self.value(Net::Pt(fb, get_ptb(mc as u32) as usize)),
+                    XC2IOBOBufMode::TriStateCTE => self.value(Net::Pt(fb, CTE as usize)),
+                };
+                if !oe {
+                    None
+                } else if obuf_mode == XC2IOBOBufMode::CGND {
+                    Some(false)
+                } else {
+                    Some(out)
+                }
+            },
+            Net::Zia(fb, row) => Some(match bits.get_fb()[fb].zia_bits[row].selected {
+                XC2ZIAInput::Zero => false,
+                XC2ZIAInput::One => true,
+                XC2ZIAInput::Macrocell{fb, ff} => {
+                    let (fb, ff) = (fb as usize, ff as usize);
+                    match bits.get_fb()[fb].ffs[ff].fb_mode {
+                        XC2MCFeedbackMode::Disabled => false,
+                        XC2MCFeedbackMode::COMB => self.value(Net::Xor(fb, ff)),
+                        XC2MCFeedbackMode::REG => self.value(Net::Q(fb, ff)),
+                    }
+                },
+                XC2ZIAInput::IBuf{ibuf} => {
+                    let ibuf = ibuf as usize;
+                    match self.iob_settings[ibuf].0 {
+                        XC2IOBZIAMode::Disabled => false,
+                        XC2IOBZIAMode::PAD => self.value(Net::IBuf(ibuf)),
+                        XC2IOBZIAMode::REG => {
+                            let (fb, mc) = self.iob_mc[ibuf];
+                            self.value(Net::Q(fb, mc))
+                        },
+                    }
+                },
+                XC2ZIAInput::DedicatedInput => self.value(Net::DedicatedInput),
+            }),
+            Net::Pt(fb, pt) => {
+                let and_term = &bits.get_fb()[fb].and_terms[pt];
+                let mut val = true;
+                for row in 0..INPUTS_PER_ANDTERM {
+                    let zia = self.value(Net::Zia(fb, row));
+                    if (and_term.input[row] && !zia) || (and_term.input_b[row] && zia) {
+                        val = false;
+                    }
+                }
+                Some(val)
+            },
+            Net::Xor(fb, mc) => {
+                let fb_bits = &bits.get_fb()[fb];
+                let mut or = false;
+                for pt in 0..ANDTERMS_PER_FB {
+                    if fb_bits.or_terms[mc].input[pt] && self.value(Net::Pt(fb, pt)) {
+                        or = true;
+                    }
+                }
+                let ptc = self.value(Net::Pt(fb, get_ptc(mc as u32) as usize));
+                Some(or ^ match fb_bits.ffs[mc].xor_mode {
+                    XC2MCXorMode::ZERO => false,
+                    XC2MCXorMode::ONE => true,
+                    XC2MCXorMode::PTC => ptc,
+                    XC2MCXorMode::PTCB => !ptc,
+                })
+            },
+            Net::RegClk(fb, mc) => {
+                let mc_bits = &bits.get_fb()[fb].ffs[mc];
+                let clk = match mc_bits.clk_src {
+                    XC2MCRegClkSrc::GCK0 => self.value(Net::Gck(0)) && global_nets.gck_enable[0],
+                    XC2MCRegClkSrc::GCK1 => self.value(Net::Gck(1)) && global_nets.gck_enable[1],
+                    XC2MCRegClkSrc::GCK2 => self.value(Net::Gck(2)) && global_nets.gck_enable[2],
+                    XC2MCRegClkSrc::PTC => self.value(Net::Pt(fb, get_ptc(mc as u32) as usize)),
+                    XC2MCRegClkSrc::CTC => self.value(Net::Pt(fb, CTC as usize)),
+                };
+                Some(clk ^ mc_bits.clk_invert_pol)
+            },
+            Net::Q(fb, mc) => {
+                let mc_bits = &bits.get_fb()[fb].ffs[mc];
+                let gsr = global_nets.gsr_enable && (self.value(Net::Gsr) == global_nets.gsr_invert);
+                let set = match mc_bits.s_src {
+                    XC2MCRegSetSrc::Disabled => false,
+                    XC2MCRegSetSrc::PTA => self.value(Net::Pt(fb, get_pta(mc as u32) as usize)),
+                    XC2MCRegSetSrc::GSR => gsr,
+                    XC2MCRegSetSrc::CTS => self.value(Net::Pt(fb, CTS as usize)),
+                };
+                let reset = match mc_bits.r_src {
+                    XC2MCRegResetSrc::Disabled => false,
+                    XC2MCRegResetSrc::PTA => self.value(Net::Pt(fb, get_pta(mc as u32) as usize)),
+                    XC2MCRegResetSrc::GSR => gsr,
+                    XC2MCRegResetSrc::CTR => self.value(Net::Pt(fb, CTR as usize)),
+                };
+                let d = if mc_bits.ff_in_ibuf {
+                    match fb_ff_num_to_iob_num(self.device, fb as u32, mc as u32) {
+                        Some(iob) => self.value(Net::IBuf(iob as usize)),
+                        None => false,
+                    }
+                } else {
+                    self.value(Net::Xor(fb, mc))
+                };
+
+                let clk = self.value(Net::RegClk(fb, mc));
+                let old_clk = self.reg_last_clk[fb][mc];
+                self.reg_last_clk[fb][mc] = clk;
+                let is_edge = (clk && !old_clk) || (mc_bits.is_ddr && !clk && old_clk);
+
+                let old_q = self.reg_state[fb][mc];
+                let new_q = if reset {
+                    false
+                } else if set {
+                    true
+                } else if mc_bits.reg_mode == XC2MCRegMode::LATCH {
+                    if clk {d} else {old_q}
+                } else if is_edge {
+                    match mc_bits.reg_mode {
+                        XC2MCRegMode::TFF => old_q ^ d,
+                        XC2MCRegMode::DFFCE if !self.value(Net::Pt(fb, get_ptc(mc as u32) as usize)) => old_q,
+                        _ => d,
+                    }
+                } else {
+                    old_q
+                };
+                self.reg_state[fb][mc] = new_q;
+                Some(new_q)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// These are not real delays, but setup is longer than `tf` so that every stage of the simulator is used
+    fn params() -> XC2TimingParams {
+        XC2TimingParams {
+            tpd1: 5000,
+            tpd2: 5100,
+            tsu1: 2000,
+            tsu2: 2100,
+            th: 300,
+            tco: 4000,
+            tf: 1500,
+            toe: 6000,
+            tod: 6100,
+            tpoe: 7000,
+            tpod: 7100,
+            tsuin: 1000,
+            thin: 400,
+            tptck: 800,
+            tslew_fast: 50,
+            tslew_slow: 900,
+            tclkdiv: 1200,
+            tsys1: 3000,
+            tsys2: 3100,
+        }
+    }
+
+    fn delays() -> XC2SimDelays {
+        XC2SimDelays::from_timing_params(&params()).unwrap()
+    }
+
+    /// Pin 2 outputs the inverse of pin 0
+    fn inverter() -> XC2Bitstream {
+        let mut bitstream = XC2Bitstream::blank_bitstream(XC2Device::XC2C32A, XC2Speed::Speed6, XC2Package::VQ44)
+            .unwrap();
+        match bitstream.bits {
+            XC2BitstreamBits::XC2C32A{ref mut iobs, ..} => {
+                iobs[0].zia_mode = XC2IOBZIAMode::PAD;
+                iobs[2].obuf_mode = XC2IOBOBufMode::PushPull;
+            },
+            _ => unreachable!(),
+        }
+        let (_, mc) = iob_num_to_fb_ff_num(XC2Device::XC2C32A, 2).unwrap();
+        let fb = &mut bitstream.bits.get_fb_mut()[0];
+        fb.zia_bits[0].selected = XC2ZIAInput::IBuf{ibuf: 0};
+        fb.and_terms[20].input[0] = true;
+        fb.or_terms[mc as usize].input[20] = true;
+        fb.ffs[mc as usize].xor_mode = XC2MCXorMode::PTC;
+        bitstream
+    }
+
+    #[test]
+    fn timing_sim_delay() {
+        let bitstream = inverter();
+        let delays = delays();
+        let tpd = delays.input + delays.zia + delays.and_term + delays.or_xor + delays.output;
+        assert_eq!(tpd, 5050);
+
+        let mut sim = XC2TimingSimulator::new(&bitstream, delays).unwrap();
+        assert_eq!(sim.get_pin(2), Some(true));
+        sim.schedule_pin(1000, 0, true);
+        sim.run_until(1000 + tpd - 1).unwrap();
+        assert_eq!(sim.get_pin(2), Some(true));
+        sim.run_until(1000 + tpd).unwrap();
+        assert_eq!(sim.get_pin(2), Some(false));
+        assert_eq!(sim.time(), 1000 + tpd);
+    }
+
+    #[test]
+    fn timing_sim_glitch() {
+        // Pin 2 is "a & ~a", but the inverted path goes through an extra macrocell, so it glitches when a rises
+        let mut bitstream = inverter();
+        let (_, out_mc) = iob_num_to_fb_ff_num(XC2Device::XC2C32A, 2).unwrap();
+        let out_mc = out_mc as usize;
+        let buried_mc = if out_mc == 15 {14} else {15};
+        let row = zia_find_rows_for_input(XC2Device::XC2C32A, XC2ZIAInput::Macrocell{fb: 0, ff: buried_mc as u32})
+            .into_iter().find(|&x| x != 0).unwrap();
+        {
+            let fb = &mut bitstream.bits.get_fb_mut()[0];
+            fb.ffs[out_mc].xor_mode = XC2MCXorMode::ZERO;
+            fb.and_terms[21].input[0] = true;
+            fb.or_terms[buried_mc].input[21] = true;
+            fb.ffs[buried_mc].fb_mode = XC2MCFeedbackMode::COMB;
+            fb.zia_bits[row].selected = XC2ZIAInput::Macrocell{fb: 0, ff: buried_mc as u32};
+            fb.and_terms[20].input_b[row] = true;
+        }
+
+        let delays = delays();
+        let mut sim = XC2TimingSimulator::new(&bitstream, delays).unwrap();
+        assert_eq!(sim.get_pin(2), Some(false));
+        sim.schedule_stimulus("# rising edge\n100 FB1_1 1\n").unwrap();
+
+        let tpd = delays.input + delays.zia + delays.and_term + delays.or_xor + delays.output;
+        sim.run_until(100 + tpd).unwrap();
+        assert_eq!(sim.get_pin(2), Some(true));
+        sim.run_until(100000).unwrap();
+        assert_eq!(sim.get_pin(2), Some(false));
+
+        let mut vcd = Vec::new();
+        sim.write_vcd("glitch", &mut vcd).unwrap();
+        let vcd = String::from_utf8(vcd).unwrap();
+        assert!(vcd.starts_with("$version xc2bit $end\n$timescale 1ps $end\n$scope module glitch $end\n"));
+        assert!(vcd.contains("$scope module FB1 $end\n"));
+        assert!(vcd.contains(&format!(" mc{}_xor $end\n", out_mc + 1)));
+        assert!(vcd.contains(&format!("#{}\n", 100 + tpd)));
+        assert!(vcd.ends_with("#100000\n"));
+    }
+
+    #[test]
+    fn timing_sim_register() {
+        let mut bitstream = XC2Bitstream::blank_bitstream(XC2Device::XC2C32A, XC2Speed::Speed4, XC2Package::VQ44)
+            .unwrap();
+        bitstream.bits.get_global_nets_mut().gck_enable[0] = true;
+        {
+            let mc_bits = &mut bitstream.bits.get_fb_mut()[1].ffs[3];
+            mc_bits.reg_mode = XC2MCRegMode::TFF;
+            mc_bits.clk_src = XC2MCRegClkSrc::GCK0;
+            mc_bits.xor_mode = XC2MCXorMode::ONE;
+            mc_bits.init_state = false;
+        }
+
+        let delays = delays();
+        let mut sim = XC2TimingSimulator::new(&bitstream, delays).unwrap();
+        sim.schedule_stimulus("1000 GCK0 1\n2000 GCK0 0\n3000 GCK0 1\n").unwrap();
+        let tco = delays.global_clock + delays.clk_to_q;
+        sim.run_until(1000 + tco - 1).unwrap();
+        assert!(!sim.get_mc_q(1, 3));
+        sim.run_until(1000 + tco).unwrap();
+        assert!(sim.get_mc_q(1, 3));
+        sim.run_until(3000 + tco - 1).unwrap();
+        assert!(sim.get_mc_q(1, 3));
+        sim.run_until(3000 + tco).unwrap();
+        assert!(!sim.get_mc_q(1, 3));
+    }
+
+    #[test]
+    fn timing_sim_matches_sta() {
+        let mut bitstream = inverter();
+        let out_iob = fb_ff_num_to_iob_num(XC2Device::XC2C32A, 1, 3).unwrap() as usize;
+        bitstream.bits.get_global_nets_mut().gck_enable[0] = true;
+        bitstream.bits.get_small_iob_mut(out_iob).unwrap().obuf_mode = XC2IOBOBufMode::PushPull;
+        bitstream.bits.get_small_iob_mut(out_iob).unwrap().obuf_uses_ff = true;
+        {
+            let mc_bits = &mut bitstream.bits.get_fb_mut()[1].ffs[3];
+            mc_bits.reg_mode = XC2MCRegMode::TFF;
+            mc_bits.clk_src = XC2MCRegClkSrc::GCK0;
+            mc_bits.xor_mode = XC2MCXorMode::ONE;
+            mc_bits.init_state = false;
+        }
+        let report = analyze_timing(&bitstream, &params());
+
+        let mut sim = XC2TimingSimulator::new(&bitstream, delays()).unwrap();
+        sim.schedule_stimulus("1000 FB1_1 1\n1000 GCK0 1\n").unwrap();
+        let clock_to_out = report.clock_to_out[0].delay as u64;
+        sim.run_until(1000 + clock_to_out - 1).unwrap();
+        assert_eq!(sim.get_pin(out_iob), Some(false));
+        sim.run_until(1000 + clock_to_out).unwrap();
+        assert_eq!(sim.get_pin(out_iob), Some(true));
+        let pin_to_pin = report.pin_to_pin[0].delay as u64;
+        sim.run_until(1000 + pin_to_pin - 1).unwrap();
+        assert_eq!(sim.get_pin(2), Some(true));
+        sim.run_until(1000 + pin_to_pin).unwrap();
+        assert_eq!(sim.get_pin(2), Some(false));
+
+        let mut bad_params = params();
+        bad_params.tco = 100;
+        assert_eq!(XC2SimDelays::from_timing_params(&bad_params), None);
+    }
+
+    #[test]
+    fn timing_sim_bad_stimulus() {
+        let bitstream = inverter();
+        let mut sim = XC2TimingSimulator::new(&bitstream, delays()).unwrap();
+        assert_eq!(sim.schedule_stimulus("\n10 FB1_1\n").err().unwrap().to_string(),
+            "line 2: expected \"<time> <signal> <value>\"");
+        assert_eq!(sim.schedule_stimulus("10 GCK3 1").err().unwrap(), XC2BitError::StimulusParseError{
+            msg: String::from("unknown signal \"GCK3\""),
+            line: 1,
+        });
+        assert_eq!(sim.schedule_stimulus("10 GSR x").err().unwrap().to_string(), "line 1: invalid value \"x\"");
+    }
+}
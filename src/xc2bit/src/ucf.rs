@@ -0,0 +1,742 @@
+/*
+Copyright (c) 2017, Robert Ou <rqou@robertou.com> and contributors
+All rights reserved.
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice,
+   this list of conditions and the following disclaimer.
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+//! Contains functions for reading UCF pin constraints and checking or applying them to a bitstream.
+//!
+//! Only `NET` statements are used. The supported attributes are `LOC`, `IOSTANDARD`, `SLEW`, `PULLUP`, `KEEPER`,
+//! `FLOAT`, and `SCHMITT_TRIGGER`. Other statements and attributes (for example timing constraints) are ignored.
+//! `LOC` can be either a package pin name or a macrocell name such as `FB1_2`. Package pin names can only be used for
+//! parts whose pinout is known (see `pinout_known`), and `IOSTANDARD` can only be used on pins whose I/O bank is known
+//! (see `iob_to_bank`).
+
+use std::fmt;
+
+use *;
+use human_readable::{parse_fb_ff_name};
+
+/// I/O standards that can be selected with `IOSTANDARD`
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum XC2IOStandard {
+    LVTTL,
+    LVCMOS33,
+    LVCMOS25,
+    LVCMOS18,
+    LVCMOS15,
+    SSTL2I,
+    SSTL3I,
+    HSTLI,
+}
+
+impl XC2IOStandard {
+    /// Parses the name used for an I/O standard in a UCF file. The name is not case-sensitive.
+    pub fn from_ucf_name(name: &str) -> Option<XC2IOStandard> {
+        match &name.to_ascii_uppercase()[..] {
+            "LVTTL" => Some(XC2IOStandard::LVTTL),
+            "LVCMOS33" => Some(XC2IOStandard::LVCMOS33),
+            "LVCMOS25" => Some(XC2IOStandard::LVCMOS25),
+            "LVCMOS18" => Some(XC2IOStandard::LVCMOS18),
+            "LVCMOS15" => Some(XC2IOStandard::LVCMOS15),
+            "SSTL2_I" => Some(XC2IOStandard::SSTL2I),
+            "SSTL3_I" => Some(XC2IOStandard::SSTL3I),
+            "HSTL_I" => Some(XC2IOStandard::HSTLI),
+            _ => None,
+        }
+    }
+
+    /// Returns whether this I/O standard needs the high voltage range (2.5 V and 3.3 V) rather than the low voltage
+    /// range (1.5 V and 1.8 V)
+    pub fn is_high_voltage(self) -> bool {
+        match self {
+            XC2IOStandard::LVTTL | XC2IOStandard::LVCMOS33 | XC2IOStandard::LVCMOS25 |
+            XC2IOStandard::SSTL2I | XC2IOStandard::SSTL3I => true,
+            XC2IOStandard::LVCMOS18 | XC2IOStandard::LVCMOS15 | XC2IOStandard::HSTLI => false,
+        }
+    }
+
+    /// Returns the I/O bank supply voltage (VCCIO) that this I/O standard needs, in millivolts. All the pins in a bank
+    /// must use I/O standards with the same VCCIO.
+    pub fn vccio_mv(self) -> u32 {
+        match self {
+            XC2IOStandard::LVTTL | XC2IOStandard::LVCMOS33 | XC2IOStandard::SSTL3I => 3300,
+            XC2IOStandard::LVCMOS25 | XC2IOStandard::SSTL2I => 2500,
+            XC2IOStandard::LVCMOS18 => 1800,
+            XC2IOStandard::LVCMOS15 | XC2IOStandard::HSTLI => 1500,
+        }
+    }
+
+    /// Returns whether this I/O standard uses a VREF input
+    pub fn uses_vref(self) -> bool {
+        match self {
+            XC2IOStandard::SSTL2I | XC2IOStandard::SSTL3I | XC2IOStandard::HSTLI => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for XC2IOStandard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match *self {
+            XC2IOStandard::LVTTL => "LVTTL",
+            XC2IOStandard::LVCMOS33 => "LVCMOS33",
+            XC2IOStandard::LVCMOS25 => "LVCMOS25",
+            XC2IOStandard::LVCMOS18 => "LVCMOS18",
+            XC2IOStandard::LVCMOS15 => "LVCMOS15",
+            XC2IOStandard::SSTL2I => "SSTL2_I",
+            XC2IOStandard::SSTL3I => "SSTL3_I",
+            XC2IOStandard::HSTLI => "HSTL_I",
+        })
+    }
+}
+
+/// Termination selected with `PULLUP`, `KEEPER`, or `FLOAT`
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum XC2UCFTermination {
+    PullUp,
+    Keeper,
+    Float,
+}
+
+/// Constraints on one net from a UCF file. Attributes that were not given are `None`.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct XC2UCFNet {
+    pub name: String,
+    /// Line of the first `NET` statement for this net
+    pub line: usize,
+    pub loc: Option<String>,
+    pub iostandard: Option<XC2IOStandard>,
+    pub slew_is_fast: Option<bool>,
+    pub termination: Option<XC2UCFTermination>,
+    pub schmitt_trigger: Option<bool>,
+}
+
+/// Splits `s` at every `sep` that is not inside double quotes
+fn split_unquoted(s: &str, sep: char) -> Vec<&str> {
+    let mut ret = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        } else if c == sep && !in_quotes {
+            ret.push(&s[start..i]);
+            start = i + 1;
+        }
+    }
+    ret.push(&s[start..]);
+    ret
+}
+
+fn unquote(s: &str) -> &str {
+    let s = s.trim();
+    if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+        &s[1..s.len() - 1]
+    } else {
+        s
+    }
+}
+
+fn parse_error(line: usize, msg: String) -> XC2BitError {
+    XC2BitError::UCFParseError {
+        msg,
+        line,
+    }
+}
+
+fn bad_constraint(net: &XC2UCFNet, msg: String) -> XC2BitError {
+    XC2BitError::UCFBadConstraint {
+        net: net.name.clone(),
+        msg,
+    }
+}
+
+/// Sets an attribute that may only be given once per net
+fn set_attr<T: PartialEq + fmt::Debug>(attr: &mut Option<T>, value: T, net: &str, what: &str, line_num: usize)
+    -> Result<(), XC2BitError> {
+
+    if let Some(ref old) = *attr {
+        if *old != value {
+            return Err(parse_error(line_num, format!("conflicting {} for net \"{}\"", what, net)));
+        }
+    }
+    *attr = Some(value);
+    Ok(())
+}
+
+/// Reads the `NET` constraints from the text of a UCF file. Each net is returned once, in the order in which it first
+/// appears.
+pub fn read_ucf(text: &str) -> Result<Vec<XC2UCFNet>, XC2BitError> {
+    // Remove comments and split into statements, keeping the line each statement starts on
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut current_line = None;
+    for (line_idx, l) in text.lines().enumerate() {
+        let mut in_quotes = false;
+        for c in l.chars() {
+            if c == '"' {
+                in_quotes = !in_quotes;
+            } else if c == '#' && !in_quotes {
+                break;
+            }
+
+            if c == ';' && !in_quotes {
+                if let Some(line) = current_line.take() {
+                    statements.push((line, current.trim().to_owned()));
+                }
+                current.clear();
+            } else {
+                if current_line.is_none() && !c.is_whitespace() {
+                    current_line = Some(line_idx + 1);
+                }
+                current.push(c);
+            }
+        }
+        current.push(' ');
+    }
+    if let Some(line) = current_line {
+        return Err(parse_error(line, String::from("missing ';' at end of statement")));
+    }
+
+    let mut nets: Vec<XC2UCFNet> = Vec::new();
+    for (line_num, statement) in statements {
+        let mut attrs = split_unquoted(&statement, '|').into_iter();
+        let head = attrs.next().unwrap().trim();
+
+        // The first attribute shares a piece with the keyword and the net name
+        let (keyword, rest) = match head.find(char::is_whitespace) {
+            Some(pos) => (&head[..pos], head[pos..].trim_start()),
+            None => (head, ""),
+        };
+        if !keyword.eq_ignore_ascii_case("NET") {
+            continue;
+        }
+        let (name, first_attr) = if let Some(quoted) = rest.strip_prefix('"') {
+            match quoted.find('"') {
+                Some(pos) => (&quoted[..pos], &quoted[pos + 1..]),
+                None => return Err(parse_error(line_num, String::from("unterminated net name"))),
+            }
+        } else {
+            match rest.find(char::is_whitespace) {
+                Some(pos) => (&rest[..pos], &rest[pos..]),
+                None => (rest, ""),
+            }
+        };
+        if name.is_empty() {
+            return Err(parse_error(line_num, String::from("missing net name")));
+        }
+
+        let net_idx = match nets.iter().position(|x| x.name == name) {
+            Some(x) => x,
+            None => {
+                nets.push(XC2UCFNet {
+                    name: name.to_owned(),
+                    line: line_num,
+                    ..Default::default()
+                });
+                nets.len() - 1
+            }
+        };
+        let net = &mut nets[net_idx];
+
+        for attr in ::std::iter::once(first_attr).chain(attrs) {
+            let attr = attr.trim();
+            if attr.is_empty() {
+                continue;
+            }
+            let (key, value) = match attr.find('=') {
+                Some(pos) => (attr[..pos].trim().to_ascii_uppercase(), Some(unquote(&attr[pos + 1..]))),
+                None => (attr.to_ascii_uppercase(), None),
+            };
+
+            match (&key[..], value) {
+                ("LOC", Some(value)) => set_attr(&mut net.loc, value.to_owned(), name, "LOC", line_num)?,
+                ("IOSTANDARD", Some(value)) => {
+                    let iostandard = match XC2IOStandard::from_ucf_name(value) {
+                        Some(x) => x,
+                        None => return Err(parse_error(line_num, format!("unsupported IOSTANDARD \"{}\"", value))),
+                    };
+                    set_attr(&mut net.iostandard, iostandard, name, "IOSTANDARD", line_num)?;
+                },
+                ("SLEW", Some(value)) => {
+                    let fast = match &value.to_ascii_uppercase()[..] {
+                        "FAST" => true,
+                        "SLOW" => false,
+                        _ => return Err(parse_error(line_num, format!("invalid SLEW \"{}\"", value))),
+                    };
+                    set_attr(&mut net.slew_is_fast, fast, name, "SLEW", line_num)?;
+                },
+                ("PULLUP", None) => set_attr(&mut net.termination, XC2UCFTermination::PullUp, name, "termination",
+                    line_num)?,
+                ("KEEPER", None) => set_attr(&mut net.termination, XC2UCFTermination::Keeper, name, "termination",
+                    line_num)?,
+                ("FLOAT", None) => set_attr(&mut net.termination, XC2UCFTermination::Float, name, "termination",
+                    line_num)?,
+                ("PULLDOWN", None) => return Err(parse_error(line_num, String::from("PULLDOWN is not supported"))),
+                ("SCHMITT_TRIGGER", None) => set_attr(&mut net.schmitt_trigger, true, name, "SCHMITT_TRIGGER",
+                    line_num)?,
+                ("LOC", None) | ("IOSTANDARD", None) | ("SLEW", None) =>
+                    return Err(parse_error(line_num, format!("missing value for {}", key))),
+                _ => {},
+            }
+        }
+    }
+
+    Ok(nets)
+}
+
+/// Returns the I/O pin (in the internal numbering scheme) that a `LOC` value refers to. The value can be a package pin
+/// name or a macrocell name such as `FB1_2`.
+pub fn ucf_loc_to_iob(device: XC2Device, package: XC2Package, loc: &str) -> Option<u32> {
+    if let Some(iob) = pin_name_to_iob(device, package, loc) {
+        return Some(iob);
+    }
+    parse_fb_ff_name(&loc.to_ascii_uppercase()).and_then(|(fb, ff)| {
+        if (fb as usize) < device.num_fbs() && (ff as usize) < MCS_PER_FB {
+            fb_ff_num_to_iob_num(device, fb, ff)
+        } else {
+            None
+        }
+    })
+}
+
+/// A single setting required by a UCF constraint
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum UCFSetting {
+    SlewIsFast(bool),
+    TerminationEnabled(bool),
+    GlobalPullUp(bool),
+    SchmittTrigger(bool),
+    UsesVref(bool),
+    BankHighVoltage(usize, bool),
+}
+
+/// Works out the I/O pin that a net is constrained to and the settings that the constraints need
+fn ucf_net_settings(bitstream: &XC2Bitstream, net: &XC2UCFNet)
+    -> Result<Option<(u32, Vec<UCFSetting>)>, XC2BitError> {
+
+    let device = bitstream.bits.device_type();
+    let loc = match net.loc {
+        Some(ref x) => x,
+        None => return Ok(None),
+    };
+    let iob = match ucf_loc_to_iob(device, bitstream.package, loc) {
+        Some(x) => x,
+        None => return Err(bad_constraint(net, format!("LOC \"{}\" is not a known I/O pin of {}-{}", loc, device,
+            bitstream.package))),
+    };
+
+    // Only the larger devices have VREF
+    let has_vref = bitstream.bits.get_large_iob(0).is_some();
+
+    let mut settings = Vec::new();
+    if let Some(fast) = net.slew_is_fast {
+        settings.push(UCFSetting::SlewIsFast(fast));
+    }
+    match net.termination {
+        Some(XC2UCFTermination::PullUp) => {
+            settings.push(UCFSetting::TerminationEnabled(true));
+            settings.push(UCFSetting::GlobalPullUp(true));
+        },
+        Some(XC2UCFTermination::Keeper) => {
+            settings.push(UCFSetting::TerminationEnabled(true));
+            settings.push(UCFSetting::GlobalPullUp(false));
+        },
+        Some(XC2UCFTermination::Float) => settings.push(UCFSetting::TerminationEnabled(false)),
+        None => {},
+    }
+    if let Some(iostandard) = net.iostandard {
+        if iostandard.uses_vref() && !has_vref {
+            return Err(bad_constraint(net, format!("IOSTANDARD {} needs VREF, which {} does not have", iostandard,
+                device)));
+        }
+        if iostandard.uses_vref() && net.schmitt_trigger == Some(true) {
+            return Err(bad_constraint(net, format!("SCHMITT_TRIGGER cannot be used with IOSTANDARD {}", iostandard)));
+        }
+        if has_vref {
+            settings.push(UCFSetting::UsesVref(iostandard.uses_vref()));
+        }
+        match iob_to_bank(device, iob) {
+            Some(bank) => settings.push(UCFSetting::BankHighVoltage(bank as usize, iostandard.is_high_voltage())),
+            None => return Err(bad_constraint(net, format!("the I/O bank of LOC \"{}\" is not known, so IOSTANDARD \
+                cannot be used", loc))),
+        }
+    }
+    if let Some(schmitt_trigger) = net.schmitt_trigger {
+        settings.push(UCFSetting::SchmittTrigger(schmitt_trigger));
+    }
+
+    Ok(Some((iob, settings)))
+}
+
+/// Returns the current value of a setting in the bitstream, or an error if the bitstream does not have the I/O pin or
+/// bank that the setting is for
+fn get_ucf_setting(bits: &XC2BitstreamBits, net: &XC2UCFNet, iob: u32, setting: UCFSetting)
+    -> Result<bool, XC2BitError> {
+
+    let no_iob = || bad_constraint(net, format!("{} does not have I/O pin {}", bits.device_type(), iob));
+    let small_iob = bits.get_small_iob(iob as usize);
+    let large_iob = bits.get_large_iob(iob as usize);
+    match setting {
+        UCFSetting::SlewIsFast(_) => small_iob.map(|x| x.slew_is_fast).or(large_iob.map(|x| x.slew_is_fast))
+            .ok_or_else(no_iob),
+        UCFSetting::TerminationEnabled(_) => small_iob.map(|x| x.termination_enabled)
+            .or(large_iob.map(|x| x.termination_enabled)).ok_or_else(no_iob),
+        UCFSetting::GlobalPullUp(_) => Ok(bits.get_global_nets().global_pu),
+        UCFSetting::SchmittTrigger(_) => small_iob.map(|x| x.schmitt_trigger)
+            .or(large_iob.map(|x| x.ibuf_mode == XC2IOBIbufMode::NoVrefSt)).ok_or_else(no_iob),
+        UCFSetting::UsesVref(_) => large_iob.map(|x| x.ibuf_mode == XC2IOBIbufMode::UsesVref)
+            .ok_or_else(|| bad_constraint(net, format!("{} does not have VREF", bits.device_type()))),
+        UCFSetting::BankHighVoltage(bank, _) => match bits.get_bank_voltage(bank) {
+            Some((ivoltage, ovoltage)) => Ok(ivoltage && ovoltage),
+            None => Err(bad_constraint(net, format!("{} does not have I/O bank {}", bits.device_type(), bank))),
+        },
+    }
+}
+
+/// Returns the value that a setting needs
+fn ucf_setting_value(setting: UCFSetting) -> bool {
+    match setting {
+        UCFSetting::SlewIsFast(x) | UCFSetting::TerminationEnabled(x) | UCFSetting::GlobalPullUp(x) |
+        UCFSetting::SchmittTrigger(x) | UCFSetting::UsesVref(x) | UCFSetting::BankHighVoltage(_, x) => x,
+    }
+}
+
+/// Describes a setting for error messages
+fn describe_ucf_setting(setting: UCFSetting) -> String {
+    describe_ucf_setting_value(setting, ucf_setting_value(setting))
+}
+
+/// Describes a setting with the given value for error messages
+fn describe_ucf_setting_value(setting: UCFSetting, value: bool) -> String {
+    match setting {
+        UCFSetting::SlewIsFast(_) => format!("slew rate {}", if value {"fast"} else {"slow"}),
+        UCFSetting::TerminationEnabled(_) => format!("termination {}", if value {"enabled"} else {"disabled"}),
+        UCFSetting::GlobalPullUp(_) => format!("global termination {}", if value {"pull-up"} else {"keeper"}),
+        UCFSetting::SchmittTrigger(_) => format!("Schmitt trigger {}", if value {"enabled"} else {"disabled"}),
+        UCFSetting::UsesVref(_) => format!("VREF {}", if value {"used"} else {"not used"}),
+        UCFSetting::BankHighVoltage(bank, _) => {
+            format!("bank {} voltage range {}", bank, if value {"high"} else {"low"})
+        },
+    }
+}
+
+/// Checks whether a bitstream matches the constraints from a UCF file. Returns every mismatch and every constraint that
+/// cannot be checked, so an empty result means that the bitstream matches. Nets without a `LOC` are not checked.
+pub fn check_ucf(bitstream: &XC2Bitstream, nets: &[XC2UCFNet]) -> Vec<XC2BitError> {
+    let mut ret = Vec::new();
+
+    for net in nets {
+        let (iob, settings) = match ucf_net_settings(bitstream, net) {
+            Ok(Some(x)) => x,
+            Ok(None) => continue,
+            Err(e) => {
+                ret.push(e);
+                continue;
+            }
+        };
+
+        for setting in settings {
+            match get_ucf_setting(&bitstream.bits, net, iob, setting) {
+                Ok(actual) => if actual != ucf_setting_value(setting) {
+                    ret.push(XC2BitError::UCFMismatch {
+                        net: net.name.clone(),
+                        line: net.line,
+                        expected: describe_ucf_setting(setting),
+                        found: describe_ucf_setting_value(setting, actual),
+                    });
+                },
+                Err(e) => ret.push(e),
+            }
+        }
+    }
+
+    ret
+}
+
+/// Changes a bitstream so that it matches the constraints from a UCF file. Nets without a `LOC` are ignored. Returns an
+/// error if the constraints cannot be applied or conflict with each other, in which case the bitstream is not changed.
+///
+/// On the XC2C32A and XC2C64A, the legacy voltage range settings are also updated when any bank voltage range is
+/// changed. They are set to high only if all banks are high, which is the setting that the single bank of an XC2C32 or
+/// XC2C64 would need.
+pub fn apply_ucf(bitstream: &mut XC2Bitstream, nets: &[XC2UCFNet]) -> Result<(), XC2BitError> {
+    // Work out everything first so that the bitstream is not changed on error
+    let mut all_settings = Vec::new();
+    for net in nets {
+        if let Some((iob, settings)) = ucf_net_settings(bitstream, net)? {
+            for setting in settings {
+                get_ucf_setting(&bitstream.bits, net, iob, setting)?;
+                all_settings.push((net, iob, setting));
+            }
+        }
+    }
+    for (i, &(net_a, _, setting_a)) in all_settings.iter().enumerate() {
+        for &(net_b, _, setting_b) in &all_settings[..i] {
+            let conflict = match (setting_a, setting_b) {
+                (UCFSetting::GlobalPullUp(a), UCFSetting::GlobalPullUp(b)) => a != b,
+                (UCFSetting::BankHighVoltage(bank_a, a), UCFSetting::BankHighVoltage(bank_b, b)) =>
+                    bank_a == bank_b && a != b,
+                _ => false,
+            };
+            if conflict {
+                return Err(XC2BitError::UCFConflict {
+                    net_a: net_b.name.clone(),
+                    setting_a: describe_ucf_setting(setting_b),
+                    net_b: net_a.name.clone(),
+                    setting_b: describe_ucf_setting(setting_a),
+                });
+            }
+        }
+    }
+
+    let bits = &mut bitstream.bits;
+    let device = bits.device_type();
+    let mut bank_voltage_changed = false;
+    for (net, iob_idx, setting) in all_settings {
+        let iob = iob_idx as usize;
+        let no_iob = || bad_constraint(net, format!("{} does not have I/O pin {}", device, iob_idx));
+        match setting {
+            UCFSetting::SlewIsFast(x) => {
+                if let Some(iob) = bits.get_small_iob_mut(iob) {
+                    iob.slew_is_fast = x;
+                }
+                if let Some(iob) = bits.get_large_iob_mut(iob) {
+                    iob.slew_is_fast = x;
+                }
+            },
+            UCFSetting::TerminationEnabled(x) => {
+                if let Some(iob) = bits.get_small_iob_mut(iob) {
+                    iob.termination_enabled = x;
+                }
+                if let Some(iob) = bits.get_large_iob_mut(iob) {
+                    iob.termination_enabled = x;
+                }
+            },
+            UCFSetting::GlobalPullUp(x) => bits.get_global_nets_mut().global_pu = x,
+            UCFSetting::SchmittTrigger(x) => {
+                if let Some(iob) = bits.get_small_iob_mut(iob) {
+                    iob.schmitt_trigger = x;
+                }
+                if let Some(iob) = bits.get_large_iob_mut(iob) {
+                    if iob.ibuf_mode != XC2IOBIbufMode::UsesVref && iob.ibuf_mode != XC2IOBIbufMode::IsVref {
+                        iob.ibuf_mode = if x {XC2IOBIbufMode::NoVrefSt} else {XC2IOBIbufMode::NoVrefNoSt};
+                    }
+                }
+            },
+            UCFSetting::UsesVref(x) => {
+                {
+                    let iob = bits.get_large_iob_mut(iob).ok_or_else(no_iob)?;
+                    if x {
+                        iob.ibuf_mode = XC2IOBIbufMode::UsesVref;
+                    } else if iob.ibuf_mode == XC2IOBIbufMode::UsesVref {
+                        iob.ibuf_mode = XC2IOBIbufMode::NoVrefNoSt;
+                    }
+                }
+                if x {
+                    match bits {
+                        &mut XC2BitstreamBits::XC2C128{ref mut use_vref, ..} |
+                        &mut XC2BitstreamBits::XC2C256{ref mut use_vref, ..} |
+                        &mut XC2BitstreamBits::XC2C384{ref mut use_vref, ..} |
+                        &mut XC2BitstreamBits::XC2C512{ref mut use_vref, ..} => *use_vref = true,
+                        _ => return Err(bad_constraint(net, format!("{} does not have VREF", device))),
+                    }
+                }
+            },
+            UCFSetting::BankHighVoltage(bank, x) => {
+                match bits.get_bank_voltage_mut(bank) {
+                    Some((ivoltage, ovoltage)) => {
+                        *ivoltage = x;
+                        *ovoltage = x;
+                    },
+                    None => return Err(bad_constraint(net, format!("{} does not have I/O bank {}", device, bank))),
+                }
+                bank_voltage_changed = true;
+            },
+        }
+    }
+
+    if bank_voltage_changed {
+        match bits {
+            &mut XC2BitstreamBits::XC2C32A{ref ivoltage, ref ovoltage, ref mut legacy_ivoltage, ref mut legacy_ovoltage,
+                ..} |
+            &mut XC2BitstreamBits::XC2C64A{ref ivoltage, ref ovoltage, ref mut legacy_ivoltage, ref mut legacy_ovoltage,
+                ..} => {
+                *legacy_ivoltage = ivoltage.iter().all(|&x| x);
+                *legacy_ovoltage = ovoltage.iter().all(|&x| x);
+            },
+            _ => {},
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blank() -> XC2Bitstream {
+        XC2Bitstream::blank_bitstream(XC2Device::XC2C32A, XC2Speed::Speed6, XC2Package::VQ44).unwrap()
+    }
+
+    #[test]
+    fn ucf_parse() {
+        let nets = read_ucf("# Board pins\n\
+            NET \"clk\" LOC = \"P43\" | IOSTANDARD = LVCMOS33;  # clock\n\
+            NET led<0> LOC=P38 | SLEW = SLOW | PULLUP |\n    SCHMITT_TRIGGER;\n\
+            TIMESPEC TS_clk = PERIOD \"clk\" 20 ns;\n\
+            NET \"clk\" TNM_NET = \"clk\";\n\
+            net \"clk\" keeper;\n").unwrap();
+        assert_eq!(nets, vec![
+            XC2UCFNet {
+                name: "clk".to_owned(),
+                line: 2,
+                loc: Some("P43".to_owned()),
+                iostandard: Some(XC2IOStandard::LVCMOS33),
+                termination: Some(XC2UCFTermination::Keeper),
+                ..Default::default()
+            },
+            XC2UCFNet {
+                name: "led<0>".to_owned(),
+                line: 3,
+                loc: Some("P38".to_owned()),
+                slew_is_fast: Some(false),
+                termination: Some(XC2UCFTermination::PullUp),
+                schmitt_trigger: Some(true),
+                ..Default::default()
+            },
+        ]);
+    }
+
+    #[test]
+    fn ucf_parse_errors() {
+        assert_eq!(read_ucf("NET \"a\" LOC = P1;\nNET \"a\" LOC = P2;").err().unwrap(),
+            XC2BitError::UCFParseError{msg: String::from("conflicting LOC for net \"a\""), line: 2});
+        assert_eq!(read_ucf("NET \"a\" IOSTANDARD = GTL;").err().unwrap().to_string(),
+            "line 1: unsupported IOSTANDARD \"GTL\"");
+        assert_eq!(read_ucf("NET \"a\" LOC = P1").err().unwrap().to_string(),
+            "line 1: missing ';' at end of statement");
+        assert_eq!(read_ucf("NET \"a\" PULLDOWN;").err().unwrap().to_string(), "line 1: PULLDOWN is not supported");
+    }
+
+    #[test]
+    fn ucf_loc() {
+        assert_eq!(ucf_loc_to_iob(XC2Device::XC2C32A, XC2Package::VQ44, "p38"), Some(0));
+        assert_eq!(ucf_loc_to_iob(XC2Device::XC2C32A, XC2Package::VQ44, "FB2_1"), Some(16));
+        assert_eq!(ucf_loc_to_iob(XC2Device::XC2C32A, XC2Package::VQ44, "FB3_1"), None);
+        assert_eq!(ucf_loc_to_iob(XC2Device::XC2C32A, XC2Package::VQ44, "P4"), None);
+    }
+
+    #[test]
+    fn ucf_check_and_apply() {
+        let nets = read_ucf("NET \"a\" LOC = P38 | SLEW = FAST | IOSTANDARD = LVCMOS33 | FLOAT;\n\
+            NET \"b\" LOC = FB2_1 | SLEW = SLOW | KEEPER | IOSTANDARD = LVCMOS18;\n\
+            NET \"c\" SLEW = SLOW;").unwrap();
+        let mut bitstream = blank();
+        bitstream.bits.get_small_iob_mut(0).unwrap().slew_is_fast = false;
+        let errors = check_ucf(&bitstream, &nets).iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(errors, vec![
+            "net \"a\" (line 1): constraints need slew rate fast but the bitstream has slew rate slow",
+            "net \"a\" (line 1): constraints need termination disabled but the bitstream has termination enabled",
+            "net \"a\" (line 1): constraints need bank 1 voltage range high but the bitstream has bank 1 voltage \
+                range low",
+            "net \"b\" (line 2): constraints need slew rate slow but the bitstream has slew rate fast",
+            "net \"b\" (line 2): constraints need global termination keeper but the bitstream has global termination \
+                pull-up",
+        ]);
+
+        apply_ucf(&mut bitstream, &nets).unwrap();
+        assert!(check_ucf(&bitstream, &nets).is_empty());
+        assert!(bitstream.bits.get_small_iob(0).unwrap().slew_is_fast);
+        assert_eq!(bitstream.bits.get_bank_voltage(1), Some((true, true)));
+        assert_eq!(bitstream.bits.get_bank_voltage(0), Some((false, false)));
+    }
+
+    #[test]
+    fn ucf_apply_legacy_voltage() {
+        let mut bitstream = blank();
+        let nets = read_ucf("NET \"a\" LOC = P38 | IOSTANDARD = LVCMOS33;\n\
+            NET \"b\" LOC = FB2_1 | IOSTANDARD = LVCMOS25;").unwrap();
+        apply_ucf(&mut bitstream, &nets).unwrap();
+        match bitstream.bits {
+            XC2BitstreamBits::XC2C32A{legacy_ivoltage, legacy_ovoltage, ..} => {
+                assert!(legacy_ivoltage);
+                assert!(legacy_ovoltage);
+            },
+            _ => unreachable!(),
+        }
+
+        let nets = read_ucf("NET \"b\" LOC = FB2_1 | IOSTANDARD = LVCMOS18;").unwrap();
+        apply_ucf(&mut bitstream, &nets).unwrap();
+        assert_eq!(bitstream.bits.get_bank_voltage(1), Some((true, true)));
+        match bitstream.bits {
+            XC2BitstreamBits::XC2C32A{legacy_ivoltage, legacy_ovoltage, ..} => {
+                assert!(!legacy_ivoltage);
+                assert!(!legacy_ovoltage);
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn ucf_apply_other_devices() {
+        // Macrocell names can be used as LOC on any device
+        let mut bitstream = XC2Bitstream::blank_bitstream(XC2Device::XC2C256, XC2Speed::Speed7, XC2Package::TQ144)
+            .unwrap();
+        let nets = read_ucf("NET \"a\" LOC = FB3_2 | SLEW = SLOW | SCHMITT_TRIGGER;").unwrap();
+        apply_ucf(&mut bitstream, &nets).unwrap();
+        assert!(check_ucf(&bitstream, &nets).is_empty());
+        let iob = ucf_loc_to_iob(XC2Device::XC2C256, XC2Package::TQ144, "FB3_2").unwrap() as usize;
+        assert!(!bitstream.bits.get_large_iob(iob).unwrap().slew_is_fast);
+        assert_eq!(bitstream.bits.get_large_iob(iob).unwrap().ibuf_mode, XC2IOBIbufMode::NoVrefSt);
+
+        let mut bitstream = XC2Bitstream::blank_bitstream(XC2Device::XC2C64, XC2Speed::Speed7, XC2Package::VQ44)
+            .unwrap();
+        let nets = read_ucf("NET \"a\" LOC = FB4_16 | IOSTANDARD = LVCMOS33;").unwrap();
+        apply_ucf(&mut bitstream, &nets).unwrap();
+        assert_eq!(bitstream.bits.get_bank_voltage(0), Some((true, true)));
+    }
+
+    #[test]
+    fn ucf_apply_errors() {
+        let mut bitstream = blank();
+        let nets = read_ucf("NET \"a\" LOC = P38 | PULLUP;\nNET \"b\" LOC = P37 | KEEPER;").unwrap();
+        assert_eq!(apply_ucf(&mut bitstream, &nets).err().unwrap(), XC2BitError::UCFConflict {
+            net_a: String::from("a"),
+            setting_a: String::from("global termination pull-up"),
+            net_b: String::from("b"),
+            setting_b: String::from("global termination keeper"),
+        });
+
+        let nets = read_ucf("NET \"a\" LOC = P38 | IOSTANDARD = SSTL2_I;").unwrap();
+        assert_eq!(apply_ucf(&mut bitstream, &nets).err().unwrap().to_string(),
+            "net \"a\": IOSTANDARD SSTL2_I needs VREF, which XC2C32A does not have");
+
+        let nets = read_ucf("NET \"a\" LOC = P99;").unwrap();
+        assert_eq!(check_ucf(&bitstream, &nets), vec![XC2BitError::UCFBadConstraint {
+            net: String::from("a"),
+            msg: String::from("LOC \"P99\" is not a known I/O pin of XC2C32A-VQ44"),
+        }]);
+    }
+}
@@ -0,0 +1,413 @@
+/*
+Copyright (c) 2017, Robert Ou <rqou@robertou.com> and contributors
+All rights reserved.
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice,
+   this list of conditions and the following disclaimer.
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+//! Contains routines for exporting a bitstream as a structural Verilog netlist.
+//!
+//! The netlist instantiates one primitive for every AND term, OR term, XOR gate, register, and I/O buffer in the
+//! device, and the behavioral models of these primitives are written into the same file. I/O pins are named after the
+//! package pin they are bonded to (for example `P38`). If the pinout of the package is not known or the pin is not
+//! bonded out, they are named after the macrocell they belong to instead (for example `FB1_2`). The global clock,
+//! set/reset, and tri-state nets become separate input ports named `GCK0`, `GSR`, `GTS0`, etc. because the pins they
+//! share are not known.
+//!
+//! The following assumptions are made about the parts of the device that are not fully understood:
+//!
+//! * An AND term with no inputs is a constant one, and an OR term with no inputs is a constant zero.
+//! * A ZIA input from a disabled feedback path or a disabled input pin is a constant zero.
+//! * A disabled GTS net enables all outputs that it controls.
+//! * Reset has priority over set.
+//! * The Schmitt trigger, termination, slew rate, and voltage settings do not affect the logic and are ignored.
+
+use std::io;
+use std::io::Write;
+
+use *;
+
+/// Behavioral models of the primitives used in the netlist
+static PRIMITIVE_MODELS: &str = r#"
+// Behavioral models of the Coolrunner-II primitives used above
+
+module xc2_and #(parameter WIDTH = 1) (input [WIDTH-1:0] in, output out);
+    assign out = &in;
+endmodule
+
+module xc2_or #(parameter WIDTH = 1) (input [WIDTH-1:0] in, output out);
+    assign out = |in;
+endmodule
+
+module xc2_xor (input a, input b, output out);
+    assign out = a ^ b;
+endmodule
+
+// MODE is one of "DFF", "LATCH", "TFF", or "DFFCE". The clock enable is only used in "DFFCE" mode.
+module xc2_reg #(parameter MODE = "DFF", parameter CLK_INV = 0, parameter DDR = 0, parameter INIT = 0)
+    (input d, input ce, input clk, input set, input reset, output reg q);
+
+    wire c = clk ^ CLK_INV;
+
+    initial q = INIT;
+
+    generate
+        if (MODE == "LATCH") begin
+            always @(*) begin
+                if (reset)
+                    q = 1'b0;
+                else if (set)
+                    q = 1'b1;
+                else if (c)
+                    q = d;
+            end
+        end else begin
+            always @(posedge c or negedge c or posedge set or posedge reset) begin
+                if (reset)
+                    q <= 1'b0;
+                else if (set)
+                    q <= 1'b1;
+                else if (c || DDR) begin
+                    if (MODE == "TFF")
+                        q <= q ^ d;
+                    else if (MODE != "DFFCE" || ce)
+                        q <= d;
+                end
+            end
+        end
+    endgenerate
+endmodule
+
+// MODE is one of "DISABLED", "PUSHPULL", "OPENDRAIN", "TRISTATE", or "CGND"
+module xc2_iobuf #(parameter MODE = "DISABLED") (input i, input oe, output o, inout pad);
+    assign pad = (MODE == "DISABLED") ? 1'bz :
+                 (MODE == "CGND") ? 1'b0 :
+                 (MODE == "OPENDRAIN") ? (i ? 1'bz : 1'b0) :
+                 (MODE == "TRISTATE" && !oe) ? 1'bz :
+                 i;
+    assign o = pad;
+endmodule
+"#;
+
+/// Returns the settings that are common to the I/O pins of all device sizes
+fn iob_common(bits: &XC2BitstreamBits, iob: usize) -> (XC2IOBZIAMode, bool, XC2IOBOBufMode) {
+    if let Some(iob) = bits.get_small_iob(iob) {
+        (iob.zia_mode, iob.obuf_uses_ff, iob.obuf_mode)
+    } else {
+        let iob = bits.get_large_iob(iob).unwrap();
+        (iob.zia_mode, iob.obuf_uses_ff, iob.obuf_mode)
+    }
+}
+
+/// Returns the name of the net driven by the given ZIA input
+fn zia_input_net(device: XC2Device, input: XC2ZIAInput) -> String {
+    match input {
+        XC2ZIAInput::Zero => String::from("1'b0"),
+        XC2ZIAInput::One => String::from("1'b1"),
+        XC2ZIAInput::Macrocell{fb, ff} => format!("fb{}_fb[{}]", fb + 1, ff),
+        XC2ZIAInput::IBuf{ibuf} => {
+            let (fb, ff) = iob_num_to_fb_ff_num(device, ibuf).unwrap();
+            format!("fb{}_pad_zia[{}]", fb + 1, ff)
+        },
+        XC2ZIAInput::DedicatedInput => String::from("dedicated_input"),
+    }
+}
+
+/// Name of the port of the given I/O pin: the package pin if it is known, otherwise the macrocell
+fn iob_port_name(device: XC2Device, package: XC2Package, iob: u32) -> String {
+    match iob_to_pin_name(device, package, iob) {
+        Some(name) => name.to_owned(),
+        None => {
+            let (fb, ff) = iob_num_to_fb_ff_num(device, iob).unwrap();
+            format!("FB{}_{}", fb + 1, ff + 1)
+        }
+    }
+}
+
+/// Write a structural Verilog netlist of the given bitstream as a module called `module_name` to the given `writer`
+/// object. The behavioral models of the primitives are written after the netlist.
+pub fn write_verilog(bitstream: &XC2Bitstream, module_name: &str, writer: &mut Write) -> Result<(), io::Error> {
+    let bits = &bitstream.bits;
+    let device = bits.device_type();
+    let global_nets = bits.get_global_nets();
+
+    write!(writer, "// Structural netlist of a {}-{}-{} bitstream written by xc2bit\n\n",
+        device, bitstream.speed_grade, bitstream.package)?;
+
+    // Ports
+    let mut ports = Vec::new();
+    for i in 0..device.num_iobs() {
+        ports.push(iob_port_name(device, bitstream.package, i as u32));
+    }
+    if bits.get_inpin().is_some() {
+        ports.push(String::from("dedicated_input"));
+    }
+    for i in 0..3 {
+        if global_nets.gck_enable[i] {
+            ports.push(format!("GCK{}", i));
+        }
+    }
+    if global_nets.gsr_enable {
+        ports.push(String::from("GSR"));
+    }
+    for i in 0..4 {
+        if global_nets.gts_enable[i] {
+            ports.push(format!("GTS{}", i));
+        }
+    }
+
+    write!(writer, "module {}(\n", module_name)?;
+    for i in 0..ports.len() {
+        write!(writer, "    {}{}\n", ports[i], if i == ports.len() - 1 {""} else {","})?;
+    }
+    write!(writer, ");\n")?;
+
+    for i in 0..device.num_iobs() {
+        write!(writer, "    inout {};\n", ports[i])?;
+    }
+    for port in &ports[device.num_iobs()..] {
+        write!(writer, "    input {};\n", port)?;
+    }
+
+    // Global nets
+    write!(writer, "\n    // Global nets\n")?;
+    for i in 0..3 {
+        write!(writer, "    wire gck{} = {};\n", i,
+            if global_nets.gck_enable[i] {format!("GCK{}", i)} else {String::from("1'b0")})?;
+    }
+    write!(writer, "    wire gsr = {};\n", match (global_nets.gsr_enable, global_nets.gsr_invert) {
+        (false, _) => "1'b0",
+        (true, true) => "GSR",
+        (true, false) => "~GSR",
+    })?;
+    for i in 0..4 {
+        // This is the output enable, so it is the complement of the T input
+        write!(writer, "    wire gts{}_oe = {};\n", i, match (global_nets.gts_enable[i], global_nets.gts_invert[i]) {
+            (false, _) => String::from("1'b1"),
+            (true, true) => format!("GTS{}", i),
+            (true, false) => format!("~GTS{}", i),
+        })?;
+    }
+
+    // Declare all of the per-FB nets first because the ZIA can refer to any of them
+    for fb in 0..device.num_fbs() {
+        write!(writer, "\n")?;
+        write!(writer, "    wire [{}:0] fb{}_zia;\n", INPUTS_PER_ANDTERM - 1, fb + 1)?;
+        write!(writer, "    wire [{}:0] fb{}_pt;\n", ANDTERMS_PER_FB - 1, fb + 1)?;
+        for net in &["or", "xor", "q", "fb", "pad_in", "pad_zia"] {
+            write!(writer, "    wire [{}:0] fb{}_{};\n", MCS_PER_FB - 1, fb + 1, net)?;
+        }
+    }
+
+    // I/O pins
+    write!(writer, "\n    // I/O pins\n")?;
+    let mut has_iob = vec![[false; MCS_PER_FB]; device.num_fbs()];
+    for i in 0..device.num_iobs() {
+        let (fb, ff) = iob_num_to_fb_ff_num(device, i as u32).unwrap();
+        let (fb, ff) = (fb as usize, ff as usize);
+        has_iob[fb][ff] = true;
+        let (zia_mode, obuf_uses_ff, obuf_mode) = iob_common(bits, i);
+
+        let (mode, oe) = match obuf_mode {
+            XC2IOBOBufMode::Disabled => ("DISABLED", String::from("1'b0")),
+            XC2IOBOBufMode::PushPull => ("PUSHPULL", String::from("1'b1")),
+            XC2IOBOBufMode::OpenDrain => ("OPENDRAIN", String::from("1'b1")),
+            XC2IOBOBufMode::TriStateGTS0 => ("TRISTATE", String::from("gts0_oe")),
+            XC2IOBOBufMode::TriStateGTS1 => ("TRISTATE", String::from("gts1_oe")),
+            XC2IOBOBufMode::TriStateGTS2 => ("TRISTATE", String::from("gts2_oe")),
+            XC2IOBOBufMode::TriStateGTS3 => ("TRISTATE", String::from("gts3_oe")),
+            XC2IOBOBufMode::TriStatePTB => ("TRISTATE", format!("fb{}_pt[{}]", fb + 1, get_ptb(ff as u32))),
+            XC2IOBOBufMode::TriStateCTE => ("TRISTATE", format!("fb{}_pt[{}]", fb + 1, CTE)),
+            XC2IOBOBufMode::CGND => ("CGND", String::from("1'b0")),
+        };
+
+        write!(writer, "    xc2_iobuf #(.MODE(\"{}\")) fb{}_{}_iobuf (.i(fb{}_{}[{}]), .oe({}), .o(fb{}_pad_in[{}]), \
+                        .pad({}));\n",
+            mode, fb + 1, ff + 1, fb + 1, if obuf_uses_ff {"q"} else {"xor"}, ff, oe, fb + 1, ff,
+            iob_port_name(device, bitstream.package, i as u32))?;
+        write!(writer, "    assign fb{}_pad_zia[{}] = {};\n", fb + 1, ff, match zia_mode {
+            XC2IOBZIAMode::Disabled => String::from("1'b0"),
+            XC2IOBZIAMode::PAD => format!("fb{}_pad_in[{}]", fb + 1, ff),
+            XC2IOBZIAMode::REG => format!("fb{}_q[{}]", fb + 1, ff),
+        })?;
+    }
+    // Buried macrocells
+    for fb in 0..device.num_fbs() {
+        for ff in 0..MCS_PER_FB {
+            if !has_iob[fb][ff] {
+                write!(writer, "    assign fb{}_pad_in[{}] = 1'b0;\n", fb + 1, ff)?;
+                write!(writer, "    assign fb{}_pad_zia[{}] = 1'b0;\n", fb + 1, ff)?;
+            }
+        }
+    }
+
+    // FBs
+    for (fb, fb_bits) in bits.get_fb().iter().enumerate() {
+        write!(writer, "\n    // FB{}\n", fb + 1)?;
+
+        for i in 0..INPUTS_PER_ANDTERM {
+            write!(writer, "    assign fb{}_zia[{}] = {};\n",
+                fb + 1, i, zia_input_net(device, fb_bits.zia_bits[i].selected))?;
+        }
+
+        for i in 0..ANDTERMS_PER_FB {
+            let mut inputs = Vec::new();
+            for j in 0..INPUTS_PER_ANDTERM {
+                if fb_bits.and_terms[i].input[j] {
+                    inputs.push(format!("fb{}_zia[{}]", fb + 1, j));
+                }
+                if fb_bits.and_terms[i].input_b[j] {
+                    inputs.push(format!("~fb{}_zia[{}]", fb + 1, j));
+                }
+            }
+            if inputs.is_empty() {
+                inputs.push(String::from("1'b1"));
+            }
+            write!(writer, "    xc2_and #(.WIDTH({})) fb{}_and{} (.in({{{}}}), .out(fb{}_pt[{}]));\n",
+                inputs.len(), fb + 1, i, inputs.join(", "), fb + 1, i)?;
+        }
+
+        for i in 0..MCS_PER_FB {
+            let mut inputs = Vec::new();
+            for j in 0..ANDTERMS_PER_FB {
+                if fb_bits.or_terms[i].input[j] {
+                    inputs.push(format!("fb{}_pt[{}]", fb + 1, j));
+                }
+            }
+            if inputs.is_empty() {
+                inputs.push(String::from("1'b0"));
+            }
+            write!(writer, "    xc2_or #(.WIDTH({})) fb{}_or{} (.in({{{}}}), .out(fb{}_or[{}]));\n",
+                inputs.len(), fb + 1, i, inputs.join(", "), fb + 1, i)?;
+        }
+
+        for i in 0..MCS_PER_FB {
+            let mc = &fb_bits.ffs[i];
+            let ptc = format!("fb{}_pt[{}]", fb + 1, get_ptc(i as u32));
+
+            write!(writer, "    xc2_xor fb{}_{}_xor (.a(fb{}_or[{}]), .b({}), .out(fb{}_xor[{}]));\n",
+                fb + 1, i + 1, fb + 1, i, match mc.xor_mode {
+                    XC2MCXorMode::ZERO => String::from("1'b0"),
+                    XC2MCXorMode::ONE => String::from("1'b1"),
+                    XC2MCXorMode::PTC => ptc.clone(),
+                    XC2MCXorMode::PTCB => format!("~{}", ptc),
+                }, fb + 1, i)?;
+
+            let clk = match mc.clk_src {
+                XC2MCRegClkSrc::GCK0 => String::from("gck0"),
+                XC2MCRegClkSrc::GCK1 => String::from("gck1"),
+                XC2MCRegClkSrc::GCK2 => String::from("gck2"),
+                XC2MCRegClkSrc::PTC => ptc.clone(),
+                XC2MCRegClkSrc::CTC => format!("fb{}_pt[{}]", fb + 1, CTC),
+            };
+            let set = match mc.s_src {
+                XC2MCRegSetSrc::Disabled => String::from("1'b0"),
+                XC2MCRegSetSrc::PTA => format!("fb{}_pt[{}]", fb + 1, get_pta(i as u32)),
+                XC2MCRegSetSrc::GSR => String::from("gsr"),
+                XC2MCRegSetSrc::CTS => format!("fb{}_pt[{}]", fb + 1, CTS),
+            };
+            let reset = match mc.r_src {
+                XC2MCRegResetSrc::Disabled => String::from("1'b0"),
+                XC2MCRegResetSrc::PTA => format!("fb{}_pt[{}]", fb + 1, get_pta(i as u32)),
+                XC2MCRegResetSrc::GSR => String::from("gsr"),
+                XC2MCRegResetSrc::CTR => format!("fb{}_pt[{}]", fb + 1, CTR),
+            };
+            let mode = match mc.reg_mode {
+                XC2MCRegMode::DFF => "DFF",
+                XC2MCRegMode::LATCH => "LATCH",
+                XC2MCRegMode::TFF => "TFF",
+                XC2MCRegMode::DFFCE => "DFFCE",
+            };
+            let d = if mc.ff_in_ibuf {
+                format!("fb{}_pad_in[{}]", fb + 1, i)
+            } else {
+                format!("fb{}_xor[{}]", fb + 1, i)
+            };
+
+            write!(writer, "    xc2_reg #(.MODE(\"{}\"), .CLK_INV({}), .DDR({}), .INIT({})) fb{}_{}_reg \
+                            (.d({}), .ce({}), .clk({}), .set({}), .reset({}), .q(fb{}_q[{}]));\n",
+                mode, mc.clk_invert_pol as u8, mc.is_ddr as u8, mc.init_state as u8, fb + 1, i + 1, d, ptc,
+                clk, set, reset, fb + 1, i)?;
+
+            write!(writer, "    assign fb{}_fb[{}] = {};\n", fb + 1, i, match mc.fb_mode {
+                XC2MCFeedbackMode::Disabled => String::from("1'b0"),
+                XC2MCFeedbackMode::COMB => format!("fb{}_xor[{}]", fb + 1, i),
+                XC2MCFeedbackMode::REG => format!("fb{}_q[{}]", fb + 1, i),
+            })?;
+        }
+    }
+
+    write!(writer, "endmodule\n")?;
+    write!(writer, "{}", PRIMITIVE_MODELS)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn netlist_contents() {
+        let mut bitstream = XC2Bitstream::blank_bitstream(XC2Device::XC2C32A, XC2Speed::Speed6, XC2Package::VQ44)
+            .unwrap();
+        bitstream.bits.get_global_nets_mut().gck_enable[0] = true;
+        {
+            let fb = &mut bitstream.bits.get_fb_mut()[0];
+            fb.zia_bits[0].selected = XC2ZIAInput::IBuf{ibuf: 1};
+            fb.and_terms[20].input[0] = true;
+            fb.and_terms[20].input_b[3] = true;
+            fb.or_terms[0].input[20] = true;
+            fb.ffs[0].xor_mode = XC2MCXorMode::PTCB;
+            fb.ffs[0].fb_mode = XC2MCFeedbackMode::REG;
+        }
+
+        let mut netlist = Vec::new();
+        bitstream.write_verilog("test", &mut netlist).unwrap();
+        let netlist = String::from_utf8(netlist).unwrap();
+
+        assert!(netlist.starts_with("// Structural netlist of a XC2C32A-6-VQ44 bitstream written by xc2bit\n"));
+        assert!(netlist.contains("module test(\n    P38,\n"));
+        assert!(netlist.contains(".o(fb1_pad_in[0]), .pad(P38));\n"));
+        assert!(netlist.contains("    dedicated_input,\n    GCK0\n);\n"));
+        assert!(netlist.contains("    wire gck0 = GCK0;\n    wire gck1 = 1'b0;\n"));
+        assert!(netlist.contains("    assign fb1_zia[0] = fb1_pad_zia[1];\n"));
+        assert!(netlist.contains(
+            "    xc2_and #(.WIDTH(2)) fb1_and20 (.in({fb1_zia[0], ~fb1_zia[3]}), .out(fb1_pt[20]));\n"));
+        assert!(netlist.contains("    xc2_and #(.WIDTH(1)) fb1_and21 (.in({1'b1}), .out(fb1_pt[21]));\n"));
+        assert!(netlist.contains("    xc2_or #(.WIDTH(1)) fb1_or0 (.in({fb1_pt[20]}), .out(fb1_or[0]));\n"));
+        assert!(netlist.contains("    xc2_xor fb1_1_xor (.a(fb1_or[0]), .b(~fb1_pt[10]), .out(fb1_xor[0]));\n"));
+        assert!(netlist.contains("    assign fb1_fb[0] = fb1_q[0];\n"));
+        assert!(netlist.contains("module xc2_reg"));
+    }
+
+    #[test]
+    fn unknown_pinout_port_names() {
+        let bitstream = XC2Bitstream::blank_bitstream(XC2Device::XC2C64A, XC2Speed::Speed5, XC2Package::VQ44).unwrap();
+
+        let mut netlist = Vec::new();
+        bitstream.write_verilog("test", &mut netlist).unwrap();
+        let netlist = String::from_utf8(netlist).unwrap();
+
+        assert!(netlist.contains("module test(\n    FB1_1,\n"));
+        assert!(netlist.contains(".o(fb1_pad_in[0]), .pad(FB1_1));\n"));
+    }
+}
@@ -0,0 +1,263 @@
+/*
+Copyright (c) 2017, Robert Ou <rqou@robertou.com> and contributors
+All rights reserved.
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice,
+   this list of conditions and the following disclaimer.
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS" AND
+ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE IMPLIED
+WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+//! Contains routines for recovering the fuses programmed by an XSVF file.
+//!
+//! An XSVF file is a compact binary version of an SVF file. Only the data that is shifted while the ISC_PROGRAM
+//! instruction is loaded is used, and it must have the same layout as the rows returned by `isc_rows` (the fuse data
+//! followed by the Gray-coded row address).
+
+use *;
+use isc::{INSTR_ISC_PROGRAM, isc_row_from_address};
+
+// XSVF commands
+const XCOMPLETE: u8 = 0x00;
+const XTDOMASK: u8 = 0x01;
+const XSIR: u8 = 0x02;
+const XSDR: u8 = 0x03;
+const XRUNTEST: u8 = 0x04;
+const XREPEAT: u8 = 0x07;
+const XSDRSIZE: u8 = 0x08;
+const XSDRTDO: u8 = 0x09;
+const XSETSDRMASKS: u8 = 0x0A;
+const XSDRINC: u8 = 0x0B;
+const XSDRB: u8 = 0x0C;
+const XSDRC: u8 = 0x0D;
+const XSDRE: u8 = 0x0E;
+const XSDRTDOB: u8 = 0x0F;
+const XSDRTDOC: u8 = 0x10;
+const XSDRTDOE: u8 = 0x11;
+const XSTATE: u8 = 0x12;
+const XENDIR: u8 = 0x13;
+const XENDDR: u8 = 0x14;
+const XSIR2: u8 = 0x15;
+const XCOMMENT: u8 = 0x16;
+const XWAIT: u8 = 0x17;
+
+fn xsvf_error(msg: &'static str) -> XC2BitError {
+    XC2BitError::XSVFParseError{msg}
+}
+
+/// Helper for reading the fields of XSVF commands
+struct XSVFReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> XSVFReader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], XC2BitError> {
+        if self.bytes.len() - self.pos < len {
+            return Err(xsvf_error("unexpected end of file"));
+        }
+
+        let ret = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(ret)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, XC2BitError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, XC2BitError> {
+        let bytes = self.read_bytes(2)?;
+        Ok(((bytes[0] as u16) << 8) | (bytes[1] as u16))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, XC2BitError> {
+        let bytes = self.read_bytes(4)?;
+        Ok(((bytes[0] as u32) << 24) | ((bytes[1] as u32) << 16) | ((bytes[2] as u32) << 8) | (bytes[3] as u32))
+    }
+
+    /// Reads a vector of `len` bits. The bits are returned in the order they are shifted (least significant first).
+    fn read_bits(&mut self, len: u32) -> Result<Vec<bool>, XC2BitError> {
+        let num_bytes = ((len as usize) + 7) / 8;
+        let bytes = self.read_bytes(num_bytes)?;
+
+        Ok((0..len as usize).map(|i| bytes[num_bytes - 1 - i / 8] & (1 << (i % 8)) != 0).collect())
+    }
+}
+
+/// Converts bits (least significant first) into an integer
+fn bits_to_int(bits: &[bool]) -> u32 {
+    let mut ret = 0;
+    for i in 0..bits.len() {
+        if bits[i] {
+            ret |= 1 << i;
+        }
+    }
+    ret
+}
+
+/// Reads an XSVF file that programs a device of type `device` and returns the fuses that it programs. Rows that are
+/// not programmed by the file are left erased (all ones). The returned fuse array does not have a device name set.
+pub fn read_xsvf(in_bytes: &[u8], device: XC2Device) -> Result<FuseArray, XC2BitError> {
+    let w = device.isc_data_width();
+    let h = device.isc_num_rows();
+    let mut fuse_array = FuseArray::from_dim(w, h);
+    for y in 0..h {
+        for x in 0..w {
+            fuse_array.set(x, y, true);
+        }
+    }
+
+    let mut reader = XSVFReader {
+        bytes: in_bytes,
+        pos: 0,
+    };
+    let mut sdr_size = 0;
+    let mut instr = None;
+
+    while reader.pos < in_bytes.len() {
+        let mut shifted_data = None;
+
+        match reader.read_u8()? {
+            XCOMPLETE => break,
+            XTDOMASK => {
+                reader.read_bits(sdr_size)?;
+            },
+            XSIR => {
+                let len = reader.read_u8()? as u32;
+                let bits = reader.read_bits(len)?;
+                if len != device.ir_length() {
+                    return Err(xsvf_error("XSVF is not for a single Coolrunner-II device"));
+                }
+                instr = Some(bits_to_int(&bits));
+            },
+            XSIR2 => {
+                let len = reader.read_u16()? as u32;
+                let bits = reader.read_bits(len)?;
+                if len != device.ir_length() {
+                    return Err(xsvf_error("XSVF is not for a single Coolrunner-II device"));
+                }
+                instr = Some(bits_to_int(&bits));
+            },
+            XSDR => {
+                shifted_data = Some(reader.read_bits(sdr_size)?);
+            },
+            XSDRTDO => {
+                shifted_data = Some(reader.read_bits(sdr_size)?);
+                // Expected TDO
+                reader.read_bits(sdr_size)?;
+            },
+            XSDRB | XSDRC | XSDRE => {
+                reader.read_bits(sdr_size)?;
+            },
+            XSDRTDOB | XSDRTDOC | XSDRTDOE => {
+                reader.read_bits(sdr_size)?;
+                reader.read_bits(sdr_size)?;
+            },
+            XRUNTEST => {
+                reader.read_u32()?;
+            },
+            XREPEAT | XSTATE | XENDIR | XENDDR => {
+                reader.read_u8()?;
+            },
+            XSDRSIZE => {
+                sdr_size = reader.read_u32()?;
+            },
+            XSETSDRMASKS => {
+                // Address mask and data mask
+                reader.read_bits(sdr_size)?;
+                reader.read_bits(sdr_size)?;
+            },
+            XSDRINC => return Err(xsvf_error("unsupported XSVF command XSDRINC")),
+            XCOMMENT => {
+                while reader.read_u8()? != 0 {}
+            },
+            XWAIT => {
+                // Wait state, end state, and time
+                reader.read_u8()?;
+                reader.read_u8()?;
+                reader.read_u32()?;
+            },
+            _ => return Err(xsvf_error("unrecognized XSVF command")),
+        }
+
+        if let Some(bits) = shifted_data {
+            if instr == Some(INSTR_ISC_PROGRAM as u32) {
+                if bits.len() != w + device.isc_address_width() {
+                    return Err(xsvf_error("wrong ISC_PROGRAM data length"));
+                }
+
+                // Find the row with this Gray-coded address
+                let y = isc_row_from_address(device, &bits[w..]);
+                if y.is_none() {
+                    return Err(xsvf_error("ISC_PROGRAM row address out of range"));
+                }
+                let y = y.unwrap();
+
+                for x in 0..w {
+                    fuse_array.set(x, y, bits[x]);
+                }
+            }
+        }
+    }
+
+    Ok(fuse_array)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_truncated() {
+        let ret = read_xsvf(b"\x08\x00\x00", XC2Device::XC2C32A);
+
+        assert_eq!(ret.err(), Some(xsvf_error("unexpected end of file")));
+    }
+
+    #[test]
+    fn read_unknown_command() {
+        let ret = read_xsvf(b"\x42", XC2Device::XC2C32A);
+
+        assert_eq!(ret.err(), Some(xsvf_error("unrecognized XSVF command")));
+    }
+
+    #[test]
+    fn read_one_row() {
+        // Program row 2 (Gray code 3) of an XC2C32A with the fuse at x = 0 cleared
+        let mut xsvf = Vec::new();
+        xsvf.extend_from_slice(b"\x16comment\x00");
+        xsvf.extend_from_slice(&[XSIR, 8, INSTR_ISC_PROGRAM]);
+        xsvf.extend_from_slice(&[XSDRSIZE, 0, 0, 0x01, 0x0A]);
+        xsvf.push(XSDR);
+        // 266 bits in 34 bytes. The 6 address bits are the most significant bits.
+        let mut sdr = vec![0xFF; 34];
+        sdr[0] = 0x00;
+        sdr[1] = 0x3F;
+        sdr[33] = 0xFE;
+        xsvf.extend_from_slice(&sdr);
+        xsvf.extend_from_slice(&[XRUNTEST, 0, 0, 0x27, 0x10]);
+        xsvf.push(XCOMPLETE);
+
+        let fuse_array = read_xsvf(&xsvf, XC2Device::XC2C32A).unwrap();
+
+        assert_eq!(fuse_array.get(0, 2), false);
+        assert_eq!(fuse_array.get(1, 2), true);
+        assert_eq!(fuse_array.get(0, 1), true);
+    }
+}
@@ -30,13 +30,15 @@ use *;
 /// Represents one output of the ZIA. The ZIA is divided into rows, and each row can independently select a choice
 /// to connect to each function block. This represents one such output (as opposed to all outputs in a given row)
 #[derive(Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct XC2ZIARowPiece {
     pub selected: XC2ZIAInput,
 }
 
 /// Represents one input to the ZIA. The ZIA has inputs from every part of the chip and can additionally output a
 /// constant zero or one.
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum XC2ZIAInput {
     Macrocell {
         fb: u32,
@@ -9158,8 +9160,8 @@ const T: bool = true;
 const F: bool = false;
 
 /// Internal function that reads a piece of the ZIA corresponding to one FB and one row
-pub fn read_32_zia_fb_row_logical(fuses: &[bool], block_idx: usize, row_idx: usize)
-    -> Result<XC2ZIARowPiece, &'static str> {
+pub fn read_32_zia_fb_row_logical(fuses: &[bool], fb: u32, block_idx: usize, row_idx: usize)
+    -> Result<XC2ZIARowPiece, XC2BitError> {
 
     // This is an ugly workaround for the lack of stable slice patterns
     let zia_row_fuses = (
@@ -9182,7 +9184,11 @@ pub fn read_32_zia_fb_row_logical(fuses: &[bool], block_idx: usize, row_idx: usi
         (F, T, F, T, T, T, T, T) => ZIA_MAP_32[row_idx][5],
         (T, T, T, T, T, T, T, T) => XC2ZIAInput::One,
         (F, F, T, T, T, T, T, T) => XC2ZIAInput::Zero,
-        _ => return Err("unknown ZIA input choice"),
+        _ => return Err(XC2BitError::UnknownZIAChoice {
+            fb: fb,
+            row: row_idx as u32,
+            fuse_idx: block_idx + row_idx * 8,
+        }),
     };
 
     Ok(XC2ZIARowPiece {
@@ -9223,8 +9229,8 @@ pub fn encode_32_zia_choice(row: u32, choice: XC2ZIAInput) -> Option<[bool; 8]>
 }
 
 /// Internal function that reads a piece of the ZIA corresponding to one FB and one row
-pub fn read_64_zia_fb_row_logical(fuses: &[bool], block_idx: usize, row_idx: usize)
-    -> Result<XC2ZIARowPiece, &'static str> {
+pub fn read_64_zia_fb_row_logical(fuses: &[bool], fb: u32, block_idx: usize, row_idx: usize)
+    -> Result<XC2ZIARowPiece, XC2BitError> {
 
     // This is an ugly workaround for the lack of stable slice patterns
     let zia_row_fuses = (
@@ -9262,7 +9268,11 @@ pub fn read_64_zia_fb_row_logical(fuses: &[bool], block_idx: usize, row_idx: usi
         (T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T) => XC2ZIAInput::One,
         // TODO: This one isn't certain
         (T, T, T, T, T, T, T, F, F, T, T, T, T, T, T, T) => XC2ZIAInput::Zero,
-        _ => return Err("unknown ZIA input choice"),
+        _ => return Err(XC2BitError::UnknownZIAChoice {
+            fb: fb,
+            row: row_idx as u32,
+            fuse_idx: block_idx + row_idx * 16,
+        }),
     };
 
     Ok(XC2ZIARowPiece {
@@ -9310,8 +9320,8 @@ pub fn encode_64_zia_choice(row: u32, choice: XC2ZIAInput) -> Option<[bool; 16]>
 }
 
 /// Internal function that reads a piece of the ZIA corresponding to one FB and one row
-pub fn read_128_zia_fb_row_logical(fuses: &[bool], block_idx: usize, row_idx: usize)
-    -> Result<XC2ZIARowPiece, &'static str> {
+pub fn read_128_zia_fb_row_logical(fuses: &[bool], fb: u32, block_idx: usize, row_idx: usize)
+    -> Result<XC2ZIARowPiece, XC2BitError> {
 
     // This is an ugly workaround for the lack of stable slice patterns
     let zia_row_fuses = (
@@ -9371,7 +9381,11 @@ pub fn read_128_zia_fb_row_logical(fuses: &[bool], block_idx: usize, row_idx: us
         (T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T) => XC2ZIAInput::One,
         // TODO: This one isn't certain
         (T, T, T, T, T, T, T, T, F, F, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T) => XC2ZIAInput::Zero,
-        _ => return Err("unknown ZIA input choice"),
+        _ => return Err(XC2BitError::UnknownZIAChoice {
+            fb: fb,
+            row: row_idx as u32,
+            fuse_idx: block_idx + row_idx * 28,
+        }),
     };
 
     Ok(XC2ZIARowPiece {
@@ -9429,8 +9443,8 @@ pub fn encode_128_zia_choice(row: u32, choice: XC2ZIAInput) -> Option<[bool; 28]
 }
 
 /// Internal function that reads a piece of the ZIA corresponding to one FB and one row
-pub fn read_256_zia_fb_row_logical(fuses: &[bool], block_idx: usize, row_idx: usize)
-    -> Result<XC2ZIARowPiece, &'static str> {
+pub fn read_256_zia_fb_row_logical(fuses: &[bool], fb: u32, block_idx: usize, row_idx: usize)
+    -> Result<XC2ZIARowPiece, XC2BitError> {
 
     // This is an ugly workaround for the lack of stable slice patterns
     let zia_row_fuses = (
@@ -9528,7 +9542,11 @@ pub fn read_256_zia_fb_row_logical(fuses: &[bool], block_idx: usize, row_idx: us
         (T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T) => XC2ZIAInput::One,
         // TODO: This one isn't certain
         (T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, F, F, T, T, T, T, T, T) => XC2ZIAInput::Zero,
-        _ => return Err("unknown ZIA input choice"),
+        _ => return Err(XC2BitError::UnknownZIAChoice {
+            fb: fb,
+            row: row_idx as u32,
+            fuse_idx: block_idx + row_idx * 48,
+        }),
     };
 
     Ok(XC2ZIARowPiece {
@@ -9604,8 +9622,8 @@ pub fn encode_256_zia_choice(row: u32, choice: XC2ZIAInput) -> Option<[bool; 48]
 }
 
 /// Internal function that reads a piece of the ZIA corresponding to one FB and one row
-pub fn read_384_zia_fb_row_logical(fuses: &[bool], block_idx: usize, row_idx: usize)
-    -> Result<XC2ZIARowPiece, &'static str> {
+pub fn read_384_zia_fb_row_logical(fuses: &[bool], fb: u32, block_idx: usize, row_idx: usize)
+    -> Result<XC2ZIARowPiece, XC2BitError> {
 
     // This is an ugly workaround for the lack of stable slice patterns
     let zia_row_fuses = (
@@ -9751,7 +9769,11 @@ pub fn read_384_zia_fb_row_logical(fuses: &[bool], block_idx: usize, row_idx: us
         (T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T) => XC2ZIAInput::One,
         // TODO: This one isn't certain
         (T, F, F, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T) => XC2ZIAInput::Zero,
-        _ => return Err("unknown ZIA input choice"),
+        _ => return Err(XC2BitError::UnknownZIAChoice {
+            fb: fb,
+            row: row_idx as u32,
+            fuse_idx: block_idx + row_idx * 74,
+        }),
     };
 
     Ok(XC2ZIARowPiece {
@@ -9849,8 +9871,8 @@ pub fn encode_384_zia_choice(row: u32, choice: XC2ZIAInput) -> Option<[bool; 74]
 }
 
 /// Internal function that reads a piece of the ZIA corresponding to one FB and one row
-pub fn read_512_zia_fb_row_logical(fuses: &[bool], block_idx: usize, row_idx: usize)
-    -> Result<XC2ZIARowPiece, &'static str> {
+pub fn read_512_zia_fb_row_logical(fuses: &[bool], fb: u32, block_idx: usize, row_idx: usize)
+    -> Result<XC2ZIARowPiece, XC2BitError> {
 
     // This is an ugly workaround for the lack of stable slice patterns
     let zia_row_fuses = (
@@ -10026,7 +10048,11 @@ pub fn read_512_zia_fb_row_logical(fuses: &[bool], block_idx: usize, row_idx: us
         (T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T) => XC2ZIAInput::One,
         // TODO: This one isn't certain
         (T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, F, F, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T, T) => XC2ZIAInput::Zero,
-        _ => return Err("unknown ZIA input choice"),
+        _ => return Err(XC2BitError::UnknownZIAChoice {
+            fb: fb,
+            row: row_idx as u32,
+            fuse_idx: block_idx + row_idx * 88,
+        }),
     };
 
     Ok(XC2ZIARowPiece {
@@ -10150,3 +10176,163 @@ pub fn zia_get_row_width(device: XC2Device) -> usize {
         XC2Device::XC2C512 => 88,
     }
 }
+
+/// Returns the inputs that can be selected on the given row of the ZIA, not including the constants `Zero` and `One`
+/// that can be selected on every row
+pub fn zia_table_get_row(device: XC2Device, row: usize) -> &'static [XC2ZIAInput] {
+    match device {
+        XC2Device::XC2C32 | XC2Device::XC2C32A => &ZIA_MAP_32[row],
+        XC2Device::XC2C64 | XC2Device::XC2C64A => &ZIA_MAP_64[row],
+        XC2Device::XC2C128 => &ZIA_MAP_128[row],
+        XC2Device::XC2C256 => &ZIA_MAP_256[row],
+        XC2Device::XC2C384 => &ZIA_MAP_384[row],
+        XC2Device::XC2C512 => &ZIA_MAP_512[row],
+    }
+}
+
+/// Returns the rows of the ZIA that can carry the given input. Constants can be carried on every row.
+pub fn zia_find_rows_for_input(device: XC2Device, input: XC2ZIAInput) -> Vec<usize> {
+    (0..INPUTS_PER_ANDTERM).filter(|&row| zia_row_can_carry(device, row, input)).collect()
+}
+
+/// Internal helper that checks whether a row of the ZIA can carry the given input
+fn zia_row_can_carry(device: XC2Device, row: usize, input: XC2ZIAInput) -> bool {
+    input == XC2ZIAInput::Zero || input == XC2ZIAInput::One || zia_table_get_row(device, row).contains(&input)
+}
+
+/// Internal helper that finds a maximum matching between `inputs` and the rows of the ZIA. Returns the index into
+/// `inputs` assigned to each row along with the first input that could not be assigned, if any.
+fn zia_match_inputs(device: XC2Device, inputs: &[XC2ZIAInput]) -> ([Option<usize>; INPUTS_PER_ANDTERM], Option<usize>) {
+    // Standard augmenting path search
+    fn try_assign(device: XC2Device, inputs: &[XC2ZIAInput], input: usize, visited: &mut [bool],
+        row_assignment: &mut [Option<usize>]) -> bool {
+
+        for row in 0..INPUTS_PER_ANDTERM {
+            if visited[row] || !zia_row_can_carry(device, row, inputs[input]) {
+                continue;
+            }
+            visited[row] = true;
+
+            let other_input = row_assignment[row];
+            if other_input.is_none() || try_assign(device, inputs, other_input.unwrap(), visited, row_assignment) {
+                row_assignment[row] = Some(input);
+                return true;
+            }
+        }
+
+        false
+    }
+
+    let mut row_assignment = [None; INPUTS_PER_ANDTERM];
+    let mut first_failure = None;
+    for input in 0..inputs.len() {
+        let mut visited = [false; INPUTS_PER_ANDTERM];
+        if !try_assign(device, inputs, input, &mut visited, &mut row_assignment) && first_failure.is_none() {
+            first_failure = Some(input);
+        }
+    }
+
+    (row_assignment, first_failure)
+}
+
+/// Assigns each of the given inputs to a row of the ZIA for one function block. Duplicate inputs only use one row.
+/// On success, returns the input selected by each row, with `None` for rows that are not needed. On failure, returns
+/// a minimal set of inputs that cannot all be routed at the same time; removing any one of them makes the rest of
+/// the set routable.
+pub fn zia_route_inputs(device: XC2Device, inputs: &[XC2ZIAInput])
+    -> Result<[Option<XC2ZIAInput>; INPUTS_PER_ANDTERM], Vec<XC2ZIAInput>> {
+
+    let mut unique_inputs = Vec::new();
+    for &input in inputs {
+        if !unique_inputs.contains(&input) {
+            unique_inputs.push(input);
+        }
+    }
+
+    let (row_assignment, first_failure) = zia_match_inputs(device, &unique_inputs);
+    if first_failure.is_none() {
+        let mut ret = [None; INPUTS_PER_ANDTERM];
+        for row in 0..INPUTS_PER_ANDTERM {
+            ret[row] = row_assignment[row].map(|x| unique_inputs[x]);
+        }
+        return Ok(ret);
+    }
+
+    // Shrink the set of inputs by dropping every input that is not needed to cause the failure
+    let mut conflict = unique_inputs;
+    let mut i = 0;
+    while i < conflict.len() {
+        let mut without = conflict.clone();
+        without.remove(i);
+        if zia_match_inputs(device, &without).1.is_some() {
+            conflict = without;
+        } else {
+            i += 1;
+        }
+    }
+
+    Err(conflict)
+}
+
+/// Returns the name of a ZIA input in the same format that `dump_human_readable` uses
+pub fn zia_input_name(device: XC2Device, input: XC2ZIAInput) -> String {
+    match input {
+        XC2ZIAInput::Zero => String::from("0"),
+        XC2ZIAInput::One => String::from("1"),
+        XC2ZIAInput::Macrocell{fb, ff} => format!("FB{}_{} FF", fb + 1, ff + 1),
+        XC2ZIAInput::IBuf{ibuf} => match iob_num_to_fb_ff_num(device, ibuf) {
+            Some((fb, ff)) => format!("FB{}_{} pad", fb + 1, ff + 1),
+            None => format!("invalid pad {}", ibuf),
+        },
+        XC2ZIAInput::DedicatedInput => String::from("dedicated input"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zia_route_simple() {
+        let inputs = [ZIA_MAP_32[0][0], ZIA_MAP_32[0][1], XC2ZIAInput::One, ZIA_MAP_32[0][0]];
+        let rows = zia_route_inputs(XC2Device::XC2C32A, &inputs).ok().unwrap();
+
+        for &input in &inputs {
+            let row = rows.iter().position(|&x| x == Some(input)).unwrap();
+            assert!(zia_find_rows_for_input(XC2Device::XC2C32A, input).contains(&row));
+        }
+        assert_eq!(rows.iter().filter(|x| x.is_some()).count(), 3);
+    }
+
+    #[test]
+    fn zia_route_conflict() {
+        // Every input on the chip cannot fit in 40 rows
+        let device = XC2Device::XC2C32A;
+        let mut all_inputs = Vec::new();
+        for row in 0..INPUTS_PER_ANDTERM {
+            for &input in zia_table_get_row(device, row) {
+                if !all_inputs.contains(&input) {
+                    all_inputs.push(input);
+                }
+            }
+        }
+        all_inputs.push(XC2ZIAInput::Zero);
+        let conflict = zia_route_inputs(device, &all_inputs).err().unwrap();
+
+        // The conflict must be minimal
+        assert!(conflict.len() <= INPUTS_PER_ANDTERM + 1);
+        assert!(zia_match_inputs(device, &conflict).1.is_some());
+        for i in 0..conflict.len() {
+            let mut without = conflict.clone();
+            without.remove(i);
+            assert!(zia_route_inputs(device, &without).is_ok());
+        }
+    }
+
+    #[test]
+    fn zia_route_impossible_input() {
+        let conflict = zia_route_inputs(XC2Device::XC2C32A, &[XC2ZIAInput::Macrocell{fb: 5, ff: 0}]).err().unwrap();
+        assert!(conflict == vec![XC2ZIAInput::Macrocell{fb: 5, ff: 0}]);
+        assert_eq!(zia_input_name(XC2Device::XC2C32A, XC2ZIAInput::IBuf{ibuf: 100}), "invalid pad 100");
+    }
+}